@@ -0,0 +1,45 @@
+//! Compile-once evaluation against parsing on every call
+//!
+//! Run with `cargo bench --bench compiled`. There is no benchmarking
+//! dependency; each case reports the mean time per evaluation over a fixed
+//! number of iterations.
+
+use soba::{CompiledExpr, Soba};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20_000;
+
+const SOURCE: &str = "(1 + 2) * 3 - 4 / 5 < 10 && !(2 ** 8 == 256.5) || 7 * 7 >= 49; \
+                      (10 - 3) * (4 + 5) / 3 != 21 || -(1 + 2 + 3 + 4 + 5) < 0";
+
+fn time(name: &str, mut f: impl FnMut()) -> Duration {
+    // Warm up caches and the allocator
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+    println!("{name:<20} {per_iteration:>10.2?}/iter");
+    per_iteration
+}
+
+fn main() {
+    let soba = Soba::new();
+    let compiled = CompiledExpr::compile(SOURCE).unwrap();
+    assert_eq!(soba.eval(SOURCE).unwrap(), compiled.eval().unwrap());
+
+    let parse_each_time = time("Soba::eval", || {
+        black_box(soba.eval(black_box(SOURCE)).unwrap());
+    });
+    let compiled_once = time("CompiledExpr::eval", || {
+        black_box(compiled.eval().unwrap());
+    });
+    println!(
+        "compiling once is {:.1}x faster",
+        parse_each_time.as_secs_f64() / compiled_once.as_secs_f64()
+    );
+}
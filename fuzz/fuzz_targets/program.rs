@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Generated programs are valid, so this explores evaluation and printing
+// rather than parse errors
+fuzz_target!(|program: soba::Program| {
+    if let Err(err) = soba::ast::check_round_trip(&program) {
+        panic!("{err}");
+    }
+    let _ = soba::eval_program(&program);
+});
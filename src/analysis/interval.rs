@@ -0,0 +1,609 @@
+//! Interval (value range) analysis
+//!
+//! Propagates the range of values each expression can produce through the
+//! AST and reports problems that are guaranteed to happen at runtime:
+//! division by zero, overflow of constant values, and comparisons whose
+//! outcome never changes. Code that short-circuiting makes unreachable is
+//! not reported.
+
+use crate::ast::{BinaryOp, Expr, Program, Statement, UnaryOp};
+use crate::span::Span;
+use crate::value::FloatEq;
+use std::fmt;
+
+/// Closed range of numbers `[lo, hi]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Self {
+        if lo.is_nan() || hi.is_nan() {
+            Self::full()
+        } else {
+            Self { lo, hi }
+        }
+    }
+
+    /// Range containing exactly one value
+    pub fn point(value: f64) -> Self {
+        Self::new(value, value)
+    }
+
+    /// Range containing every number
+    pub fn full() -> Self {
+        Self {
+            lo: f64::NEG_INFINITY,
+            hi: f64::INFINITY,
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    /// Get the value if the range contains exactly one
+    pub fn as_point(&self) -> Option<f64> {
+        (self.lo == self.hi).then_some(self.lo)
+    }
+
+    fn from_bounds(candidates: [f64; 4]) -> Self {
+        if candidates.iter().any(|c| c.is_nan()) {
+            return Self::full();
+        }
+        let lo = candidates.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = candidates.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self::new(lo, hi)
+    }
+
+    fn add(self, other: Interval) -> Interval {
+        Interval::new(self.lo + other.lo, self.hi + other.hi)
+    }
+
+    fn sub(self, other: Interval) -> Interval {
+        Interval::new(self.lo - other.hi, self.hi - other.lo)
+    }
+
+    fn mul(self, other: Interval) -> Interval {
+        Interval::from_bounds([
+            self.lo * other.lo,
+            self.lo * other.hi,
+            self.hi * other.lo,
+            self.hi * other.hi,
+        ])
+    }
+
+    /// Divide by a range that does not contain zero
+    fn div(self, other: Interval) -> Interval {
+        Interval::from_bounds([
+            self.lo / other.lo,
+            self.lo / other.hi,
+            self.hi / other.lo,
+            self.hi / other.hi,
+        ])
+    }
+
+    fn is_finite(&self) -> bool {
+        self.lo.is_finite() && self.hi.is_finite()
+    }
+
+    /// Raise to a power, known exactly only for constants
+    fn pow(self, other: Interval) -> Interval {
+        match (self.as_point(), other.as_point()) {
+            (Some(base), Some(exponent)) if !base.powf(exponent).is_nan() => {
+                Interval::point(base.powf(exponent))
+            }
+            _ => Interval::full(),
+        }
+    }
+
+    /// Check whether every value in the range is infinite
+    fn is_infinite(&self) -> bool {
+        self.lo == f64::INFINITY || self.hi == f64::NEG_INFINITY
+    }
+}
+
+/// Set of boolean outcomes an expression can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Truth {
+    pub can_be_true: bool,
+    pub can_be_false: bool,
+}
+
+impl Truth {
+    pub fn exactly(value: bool) -> Self {
+        Self {
+            can_be_true: value,
+            can_be_false: !value,
+        }
+    }
+
+    pub fn unknown() -> Self {
+        Self {
+            can_be_true: true,
+            can_be_false: true,
+        }
+    }
+
+    /// Get the outcome if it never changes
+    pub fn as_constant(&self) -> Option<bool> {
+        match (self.can_be_true, self.can_be_false) {
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn not(self) -> Truth {
+        Truth {
+            can_be_true: self.can_be_false,
+            can_be_false: self.can_be_true,
+        }
+    }
+}
+
+/// Abstract value computed for an expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Range {
+    Int(Interval),
+    Float(Interval),
+    Bool(Truth),
+    /// Evaluation fails or the value is not tracked
+    Unknown,
+}
+
+impl Range {
+    /// Range of the value converted to `f64`, as arithmetic does
+    fn numeric(&self) -> Option<Interval> {
+        match self {
+            Range::Int(i) | Range::Float(i) => Some(*i),
+            Range::Bool(truth) => Some(Interval::new(
+                if truth.can_be_false { 0.0 } else { 1.0 },
+                if truth.can_be_true { 1.0 } else { 0.0 },
+            )),
+            Range::Unknown => None,
+        }
+    }
+
+    /// Possible truthiness of the value
+    fn truth(&self) -> Truth {
+        match self {
+            Range::Int(i) | Range::Float(i) => Truth {
+                can_be_true: i.as_point() != Some(0.0),
+                can_be_false: i.contains(0.0),
+            },
+            Range::Bool(truth) => *truth,
+            Range::Unknown => Truth::unknown(),
+        }
+    }
+}
+
+/// Kind of problem found by the analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeIssue {
+    /// A divisor is always zero
+    DivisionByZero,
+    /// A value always overflows its type
+    ConstantOverflow,
+    /// A comparison always evaluates to true
+    AlwaysTrue,
+    /// A comparison always evaluates to false
+    AlwaysFalse,
+}
+
+/// A problem found by the analysis, with the span of the offending expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeDiagnostic {
+    pub issue: RangeIssue,
+    pub span: Span,
+}
+
+impl fmt::Display for RangeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeIssue::DivisionByZero => write!(f, "division by zero"),
+            RangeIssue::ConstantOverflow => write!(f, "arithmetic overflow"),
+            RangeIssue::AlwaysTrue => write!(f, "comparison is always true"),
+            RangeIssue::AlwaysFalse => write!(f, "comparison is always false"),
+        }
+    }
+}
+
+impl fmt::Display for RangeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.issue, self.span)
+    }
+}
+
+/// Analyze every statement of a program
+pub fn analyze_program(program: &Program) -> Vec<RangeDiagnostic> {
+    analyze_program_with_float_eq(program, FloatEq::default())
+}
+
+/// Like `analyze_program`, for floats compared with `float_eq` at runtime
+pub fn analyze_program_with_float_eq(program: &Program, float_eq: FloatEq) -> Vec<RangeDiagnostic> {
+    let mut analyzer = Analyzer {
+        float_eq,
+        ..Analyzer::default()
+    };
+    for stmt in &program.statements {
+        match stmt {
+            Statement::ExprStatement { expr, .. } => {
+                analyzer.expr(expr);
+            }
+        }
+    }
+    analyzer.diagnostics
+}
+
+/// Analyze a single expression, returning its range and any problems found
+pub fn analyze_expr(expr: &Expr) -> (Range, Vec<RangeDiagnostic>) {
+    let mut analyzer = Analyzer::default();
+    let range = analyzer.expr(expr);
+    (range, analyzer.diagnostics)
+}
+
+#[derive(Default)]
+struct Analyzer {
+    diagnostics: Vec<RangeDiagnostic>,
+    float_eq: FloatEq,
+    /// Depth of operands that short-circuiting never evaluates
+    unreachable: usize,
+}
+
+impl Analyzer {
+    fn report(&mut self, issue: RangeIssue, span: Span) {
+        if self.unreachable == 0 {
+            self.diagnostics.push(RangeDiagnostic { issue, span });
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Range {
+        match expr {
+            Expr::Int { value, .. } => Range::Int(Interval::point(f64::from(*value))),
+            Expr::Float { value, .. } => Range::Float(Interval::point(*value)),
+            Expr::Bool { value, .. } => Range::Bool(Truth::exactly(*value)),
+            Expr::Grouped { inner, .. } => self.expr(inner),
+            Expr::UnaryExpr { op, operand, span } => {
+                let operand = self.expr(operand);
+                self.unary(*op, operand, *span)
+            }
+            Expr::PostfixExpr { operand, .. } => {
+                // Only custom operators are postfix so far
+                self.expr(operand);
+                Range::Unknown
+            }
+            Expr::InfixExpr {
+                left,
+                op,
+                right,
+                span,
+            } => match op {
+                BinaryOp::LogicalAnd | BinaryOp::LogicalOr => self.logical(*op, left, right),
+                _ => {
+                    let left = self.expr(left);
+                    let right = self.expr(right);
+                    self.binary(*op, left, right, *span)
+                }
+            },
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, operand: Range, span: Span) -> Range {
+        match op {
+            UnaryOp::Plus => operand,
+            // Nothing is known about what an embedder's handler returns
+            UnaryOp::Custom(_) => Range::Unknown,
+            UnaryOp::LogicalNot => match operand {
+                Range::Unknown => Range::Unknown,
+                _ => Range::Bool(operand.truth().not()),
+            },
+            UnaryOp::Minus => match operand {
+                Range::Int(i) => {
+                    let min = f64::from(i32::MIN);
+                    if i.as_point() == Some(min) {
+                        self.report(RangeIssue::ConstantOverflow, span);
+                        Range::Unknown
+                    } else if i.contains(min) {
+                        Range::Unknown
+                    } else {
+                        Range::Int(Interval::new(-i.hi, -i.lo))
+                    }
+                }
+                Range::Float(i) => Range::Float(Interval::new(-i.hi, -i.lo)),
+                Range::Bool(_) | Range::Unknown => Range::Unknown,
+            },
+        }
+    }
+
+    fn logical(&mut self, op: BinaryOp, left: &Expr, right: &Expr) -> Range {
+        let left = self.expr(left);
+        if left == Range::Unknown {
+            // The left operand may fail; keep analyzing the right side for reports
+            self.expr(right);
+            return Range::Bool(Truth::unknown());
+        }
+
+        let left = left.truth();
+        let short_circuits = match op {
+            BinaryOp::LogicalAnd => left.as_constant() == Some(false),
+            _ => left.as_constant() == Some(true),
+        };
+
+        if short_circuits {
+            self.unreachable += 1;
+            self.expr(right);
+            self.unreachable -= 1;
+            return Range::Bool(left);
+        }
+
+        let right = self.expr(right).truth();
+        let truth = match op {
+            BinaryOp::LogicalAnd => Truth {
+                can_be_true: left.can_be_true && right.can_be_true,
+                can_be_false: left.can_be_false || right.can_be_false,
+            },
+            _ => Truth {
+                can_be_true: left.can_be_true || right.can_be_true,
+                can_be_false: left.can_be_false && right.can_be_false,
+            },
+        };
+        Range::Bool(truth)
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: Range, right: Range, span: Span) -> Range {
+        if let BinaryOp::Custom(_) = op {
+            return Range::Unknown;
+        }
+        if left == Range::Unknown || right == Range::Unknown {
+            return match op {
+                BinaryOp::Plus
+                | BinaryOp::Minus
+                | BinaryOp::Multiply
+                | BinaryOp::Divide
+                | BinaryOp::Power => Range::Unknown,
+                _ => Range::Bool(Truth::unknown()),
+            };
+        }
+
+        match op {
+            BinaryOp::Plus
+            | BinaryOp::Minus
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Power => self.arithmetic(op, left, right, span),
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => unreachable!("handled by logical"),
+            BinaryOp::Custom(_) => unreachable!("handled above"),
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                let truth = equality(left, right, self.float_eq);
+                let truth = if op == BinaryOp::NotEqual {
+                    truth.not()
+                } else {
+                    truth
+                };
+                self.comparison_result(truth, span)
+            }
+            BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEqual | BinaryOp::GreaterEqual => {
+                if matches!(left, Range::Bool(_)) || matches!(right, Range::Bool(_)) {
+                    // Ordering booleans is a type error, not a range question
+                    return Range::Unknown;
+                }
+                let (a, b) = (left.numeric().unwrap(), right.numeric().unwrap());
+                let truth = match op {
+                    BinaryOp::Less => ordering(a.hi < b.lo, a.lo >= b.hi),
+                    BinaryOp::Greater => ordering(a.lo > b.hi, a.hi <= b.lo),
+                    BinaryOp::LessEqual => ordering(a.hi <= b.lo, a.lo > b.hi),
+                    _ => ordering(a.lo >= b.hi, a.hi < b.lo),
+                };
+                self.comparison_result(truth, span)
+            }
+        }
+    }
+
+    fn arithmetic(&mut self, op: BinaryOp, left: Range, right: Range, span: Span) -> Range {
+        let (a, b) = (left.numeric().unwrap(), right.numeric().unwrap());
+        let result = match op {
+            BinaryOp::Plus => a.add(b),
+            BinaryOp::Minus => a.sub(b),
+            BinaryOp::Multiply => a.mul(b),
+            BinaryOp::Power => a.pow(b),
+            _ => {
+                if b.as_point() == Some(0.0) {
+                    self.report(RangeIssue::DivisionByZero, span);
+                    return Range::Unknown;
+                }
+                if b.contains(0.0) {
+                    return Range::Float(Interval::full());
+                }
+                a.div(b)
+            }
+        };
+
+        if a.is_finite() && b.is_finite() && result.is_infinite() {
+            self.report(RangeIssue::ConstantOverflow, span);
+        }
+        Range::Float(result)
+    }
+
+    fn comparison_result(&mut self, truth: Truth, span: Span) -> Range {
+        match truth.as_constant() {
+            Some(true) => self.report(RangeIssue::AlwaysTrue, span),
+            Some(false) => self.report(RangeIssue::AlwaysFalse, span),
+            None => {}
+        }
+        Range::Bool(truth)
+    }
+}
+
+fn ordering(always: bool, never: bool) -> Truth {
+    Truth {
+        can_be_true: !never,
+        can_be_false: !always,
+    }
+}
+
+/// Possible outcomes of `==`, following `Value::equal_to_with`
+fn equality(left: Range, right: Range, float_eq: FloatEq) -> Truth {
+    match (left, right) {
+        (Range::Bool(a), Range::Bool(b)) => match (a.as_constant(), b.as_constant()) {
+            (Some(x), Some(y)) => Truth::exactly(x == y),
+            _ => Truth::unknown(),
+        },
+        (Range::Int(a), Range::Int(b)) => match (a.as_point(), b.as_point()) {
+            (Some(x), Some(y)) => Truth::exactly(x == y),
+            _ if a.hi < b.lo || b.hi < a.lo => Truth::exactly(false),
+            _ => Truth::unknown(),
+        },
+        (Range::Int(a) | Range::Float(a), Range::Int(b) | Range::Float(b)) => {
+            match (a.as_point(), b.as_point()) {
+                (Some(x), Some(y)) => Truth::exactly(float_eq.eq(x, y)),
+                _ if apart(a, b, float_eq) => Truth::exactly(false),
+                _ => Truth::unknown(),
+            }
+        }
+        // Different types are never equal
+        _ => Truth::exactly(false),
+    }
+}
+
+/// Whether no number in `a` equals one in `b` under `float_eq`
+///
+/// The closest pair straddles the gap between the ranges; every
+/// `FloatEq` strategy only gets stricter as numbers move further apart.
+fn apart(a: Interval, b: Interval, float_eq: FloatEq) -> bool {
+    let (below, above) = if a.hi < b.lo {
+        (a.hi, b.lo)
+    } else if b.hi < a.lo {
+        (b.hi, a.lo)
+    } else {
+        return false;
+    };
+    !float_eq.eq(below, above)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::SobaLexer;
+    use crate::parser::Parser;
+
+    fn issues(input: &str) -> Vec<RangeIssue> {
+        let lexer = SobaLexer::new(input.chars().collect());
+        let program = Parser::new(lexer).unwrap().parse_program().unwrap();
+        analyze_program(&program)
+            .into_iter()
+            .map(|d| d.issue)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_program_has_no_issues() {
+        assert!(issues("1 + 2 * 3; -(4 / 2); !true || false").is_empty());
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(issues("1 / 0"), vec![RangeIssue::DivisionByZero]);
+        assert_eq!(issues("1 / (2 - 2)"), vec![RangeIssue::DivisionByZero]);
+        assert_eq!(issues("1 / false"), vec![RangeIssue::DivisionByZero]);
+    }
+
+    #[test]
+    fn test_division_by_zero_span() {
+        let lexer = SobaLexer::new("3;\n8 / (1 - 1)".chars().collect());
+        let program = Parser::new(lexer).unwrap().parse_program().unwrap();
+        let diagnostics = analyze_program(&program);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Span::new(3, 14));
+    }
+
+    #[test]
+    fn test_constant_overflow() {
+        // 1e38 multiplied together nine times exceeds f64::MAX
+        let big = "100000000000000000000000000000000000000.0";
+        let input = [big; 9].join(" * ");
+        assert_eq!(issues(&input), vec![RangeIssue::ConstantOverflow]);
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(
+            Interval::point(2.0).pow(Interval::point(3.0)),
+            Interval::point(8.0)
+        );
+        assert_eq!(
+            Interval::new(1.0, 2.0).pow(Interval::point(2.0)),
+            Interval::full()
+        );
+        assert_eq!(issues("10.0 ** 400"), vec![RangeIssue::ConstantOverflow]);
+    }
+
+    #[test]
+    fn test_int_negation_overflow() {
+        let range = Range::Int(Interval::point(f64::from(i32::MIN)));
+        let mut analyzer = Analyzer::default();
+        let span = Span::single(0);
+        assert_eq!(analyzer.unary(UnaryOp::Minus, range, span), Range::Unknown);
+        assert_eq!(analyzer.diagnostics[0].issue, RangeIssue::ConstantOverflow);
+    }
+
+    #[test]
+    fn test_constant_comparisons() {
+        assert_eq!(issues("1 < 2"), vec![RangeIssue::AlwaysTrue]);
+        assert_eq!(issues("3 >= 4.5"), vec![RangeIssue::AlwaysFalse]);
+        assert_eq!(issues("2 == 2.0"), vec![RangeIssue::AlwaysTrue]);
+        assert_eq!(issues("1 != true"), vec![RangeIssue::AlwaysTrue]);
+    }
+
+    #[test]
+    fn test_float_eq() {
+        let analyze = |input: &str, float_eq| {
+            let lexer = SobaLexer::new(input.chars().collect());
+            let program = Parser::new(lexer).unwrap().parse_program().unwrap();
+            analyze_program_with_float_eq(&program, float_eq)
+                .into_iter()
+                .map(|d| d.issue)
+                .collect::<Vec<_>>()
+        };
+        let sum = "0.1 + 0.2 == 0.3";
+        assert_eq!(issues(sum), vec![RangeIssue::AlwaysTrue]);
+        assert_eq!(analyze(sum, FloatEq::Exact), vec![RangeIssue::AlwaysFalse]);
+
+        let low = Interval::new(0.0, 1.0);
+        let high = Interval::new(1.25, 2.0);
+        assert!(apart(low, high, FloatEq::Exact));
+        assert!(apart(high, low, FloatEq::Absolute(0.25)));
+        assert!(!apart(low, high, FloatEq::Absolute(0.5)));
+        assert!(!apart(low, Interval::new(0.5, 2.0), FloatEq::Exact));
+    }
+
+    #[test]
+    fn test_short_circuited_operand_is_not_reported() {
+        assert!(issues("false && 1 / 0").is_empty());
+        assert!(issues("true || 1 / 0").is_empty());
+        assert_eq!(issues("true && 1 / 0"), vec![RangeIssue::DivisionByZero]);
+    }
+
+    #[test]
+    fn test_interval_arithmetic() {
+        let a = Interval::new(-1.0, 2.0);
+        let b = Interval::new(3.0, 4.0);
+        assert_eq!(a.add(b), Interval::new(2.0, 6.0));
+        assert_eq!(a.sub(b), Interval::new(-5.0, -1.0));
+        assert_eq!(a.mul(b), Interval::new(-4.0, 8.0));
+        assert_eq!(b.div(Interval::new(1.0, 2.0)), Interval::new(1.5, 4.0));
+    }
+
+    #[test]
+    fn test_possible_but_not_guaranteed_zero_is_silent() {
+        let numerator = Range::Float(Interval::point(1.0));
+        let divisor = Range::Float(Interval::new(-1.0, 1.0));
+        let mut analyzer = Analyzer::default();
+        let span = Span::single(0);
+        let result = analyzer.binary(BinaryOp::Divide, numerator, divisor, span);
+        assert_eq!(result, Range::Float(Interval::full()));
+        assert!(analyzer.diagnostics.is_empty());
+    }
+}
@@ -0,0 +1,655 @@
+//! Lint rules over whole programs
+//!
+//! The linter collects the findings of the other passes, the parser's
+//! warnings, the type checker and the range analysis, under named rules
+//! that can be selected or ignored one by one. Each rule has a default
+//! severity: errors are code that fails whenever it runs, warnings are
+//! code that is likely not what was meant. `Linter::set_severity` changes
+//! it.
+//!
+//! Rules that only need to look at one statement or expression at a time
+//! implement `LintCheck`, which the linter runs over the AST with a
+//! `Visitor`. The built-in `constant-condition`, `unused-result` and
+//! `float-equality` rules are written that way, and embedders add their
+//! own with `Linter::add_check`.
+
+use super::interval::{analyze_program, RangeIssue};
+use crate::ast::visit::walk_expr;
+use crate::ast::{BinaryOp, Expr, Program, Statement, Visitor};
+use crate::error::{ParseResult, ParseWarning};
+use crate::parser::Parser;
+use crate::source::SourceFile;
+use crate::span::Span;
+use crate::typeck::check_program;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// How serious a lint is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A named check, as listed in `LINT_RULES`
+#[derive(Debug, PartialEq, Eq)]
+pub struct LintRule {
+    /// Name used to select or ignore the rule, e.g. `redundant-parentheses`
+    pub name: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+/// Every rule, in the order `soba lint` lists them
+pub const LINT_RULES: &[LintRule] = &[
+    LintRule {
+        name: "type-error",
+        severity: Severity::Error,
+        description: "An operator is applied to a type it does not accept",
+    },
+    LintRule {
+        name: "division-by-zero",
+        severity: Severity::Error,
+        description: "A divisor is always zero",
+    },
+    LintRule {
+        name: "constant-overflow",
+        severity: Severity::Error,
+        description: "A value always overflows its type",
+    },
+    LintRule {
+        name: "constant-comparison",
+        severity: Severity::Warning,
+        description: "A comparison has the same result whatever its operands",
+    },
+    LintRule {
+        name: "redundant-parentheses",
+        severity: Severity::Warning,
+        description: "Parentheses directly around another parenthesized expression",
+    },
+    LintRule {
+        name: "mixed-logical-operators",
+        severity: Severity::Warning,
+        description: "'&&' and '||' combined without parentheses",
+    },
+    CONSTANT_CONDITION,
+    UNUSED_RESULT,
+    FLOAT_EQUALITY,
+];
+
+const CONSTANT_CONDITION: LintRule = LintRule {
+    name: "constant-condition",
+    severity: Severity::Warning,
+    description: "An operand of '&&' or '||' is 'true' or 'false'",
+};
+
+const UNUSED_RESULT: LintRule = LintRule {
+    name: "unused-result",
+    severity: Severity::Warning,
+    description: "A statement other than the last one, whose value is thrown away",
+};
+
+const FLOAT_EQUALITY: LintRule = LintRule {
+    name: "float-equality",
+    severity: Severity::Warning,
+    description: "'==' or '!=' on a fraction or a quotient, which rounding can change",
+};
+
+/// A rule that looks at one statement or expression at a time
+///
+/// The linter visits every statement and every expression of a program,
+/// parents before children, and reports each message a check returns at
+/// the span of the expression it looked at.
+pub trait LintCheck {
+    /// The rule this check reports under
+    fn rule(&self) -> &'static LintRule;
+
+    /// Check a statement; `is_last` tells whether its value is the
+    /// program's
+    fn check_statement(&self, _stmt: &Statement, _is_last: bool) -> Option<String> {
+        None
+    }
+
+    /// Check an expression
+    fn check_expr(&self, _expr: &Expr) -> Option<String> {
+        None
+    }
+}
+
+/// `&&` or `||` with a literal operand
+struct ConstantCondition;
+
+impl LintCheck for ConstantCondition {
+    fn rule(&self) -> &'static LintRule {
+        &CONSTANT_CONDITION
+    }
+
+    fn check_expr(&self, expr: &Expr) -> Option<String> {
+        let Expr::InfixExpr {
+            left, op, right, ..
+        } = expr
+        else {
+            return None;
+        };
+        let absorbing = match op {
+            BinaryOp::LogicalAnd => false,
+            BinaryOp::LogicalOr => true,
+            _ => return None,
+        };
+        let value = [left, right]
+            .into_iter()
+            .find_map(|operand| match ungrouped(operand) {
+                Expr::Bool { value, .. } => Some(*value),
+                _ => None,
+            })?;
+        Some(if value == absorbing {
+            format!("The condition is always {value}")
+        } else {
+            format!("The '{value}' operand has no effect")
+        })
+    }
+}
+
+/// Statements whose value nothing reads
+struct UnusedResult;
+
+impl LintCheck for UnusedResult {
+    fn rule(&self) -> &'static LintRule {
+        &UNUSED_RESULT
+    }
+
+    fn check_statement(&self, _stmt: &Statement, is_last: bool) -> Option<String> {
+        (!is_last).then(|| "The value of this statement is never used".to_string())
+    }
+}
+
+/// Exact comparisons of values that are likely rounded
+struct FloatEquality;
+
+impl LintCheck for FloatEquality {
+    fn rule(&self) -> &'static LintRule {
+        &FLOAT_EQUALITY
+    }
+
+    fn check_expr(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::InfixExpr {
+                left,
+                op: op @ (BinaryOp::Equal | BinaryOp::NotEqual),
+                right,
+                ..
+            } if is_inexact(left) || is_inexact(right) => {
+                let op = if *op == BinaryOp::Equal { "==" } else { "!=" };
+                Some(format!(
+                    "Comparing floats with '{op}'; rounding can make equal values differ"
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The built-in checks, in the order of `LINT_RULES`
+const CHECKS: &[&(dyn LintCheck + Sync)] = &[&ConstantCondition, &UnusedResult, &FloatEquality];
+
+/// The expression inside any parentheses
+fn ungrouped(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Grouped { inner, .. } => ungrouped(inner),
+        _ => expr,
+    }
+}
+
+/// Whether `expr` has a fractional literal or a division, whose results
+/// are rounded
+fn is_inexact(expr: &Expr) -> bool {
+    match expr {
+        Expr::Float { value, .. } => value.fract() != 0.0,
+        Expr::Int { .. } | Expr::Bool { .. } => false,
+        Expr::InfixExpr {
+            op: BinaryOp::Divide,
+            ..
+        } => true,
+        Expr::InfixExpr { left, right, .. } => is_inexact(left) || is_inexact(right),
+        Expr::Grouped { inner, .. } => is_inexact(inner),
+        Expr::UnaryExpr { operand, .. } | Expr::PostfixExpr { operand, .. } => is_inexact(operand),
+    }
+}
+
+/// Runs checks over every statement and expression of a program
+struct CheckRunner<'a> {
+    checks: &'a [&'a dyn LintCheck],
+    /// The rule, message and span of each finding
+    found: Vec<(&'static LintRule, String, Span)>,
+}
+
+impl CheckRunner<'_> {
+    fn run(&mut self, program: &Program) {
+        let count = program.statements.len();
+        for (index, stmt) in program.statements.iter().enumerate() {
+            for check in self.checks {
+                if let Some(message) = check.check_statement(stmt, index + 1 == count) {
+                    let Statement::ExprStatement { expr, .. } = stmt;
+                    self.found.push((check.rule(), message, expr.span()));
+                }
+            }
+            self.visit_statement(stmt);
+        }
+    }
+}
+
+impl Visitor for CheckRunner<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        for check in self.checks {
+            if let Some(message) = check.check_expr(expr) {
+                self.found.push((check.rule(), message, expr.span()));
+            }
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Look up a rule by name
+pub fn lint_rule(name: &str) -> Option<&'static LintRule> {
+    LINT_RULES.iter().find(|rule| rule.name == name)
+}
+
+/// A finding of one rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub rule: &'static LintRule,
+    /// The rule's severity, or the one `Linter::set_severity` gave it
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+
+/// A rule name that is not in `LINT_RULES`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownRule {
+    pub name: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.pad("warning"),
+            Severity::Error => f.pad("error"),
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule.name, self.message)
+    }
+}
+
+impl fmt::Display for UnknownRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown lint rule '{}'", self.name)
+    }
+}
+
+impl std::error::Error for UnknownRule {}
+
+/// Runs the enabled rules over source files
+#[derive(Clone)]
+pub struct Linter {
+    enabled: HashSet<&'static str>,
+    /// Severities that differ from the rules' defaults
+    severities: HashMap<&'static str, Severity>,
+    /// Checks added with `add_check`
+    checks: Vec<Rc<dyn LintCheck>>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self {
+            enabled: LINT_RULES.iter().map(|rule| rule.name).collect(),
+            severities: HashMap::new(),
+            checks: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Linter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let checks: Vec<&str> = self.checks.iter().map(|check| check.rule().name).collect();
+        f.debug_struct("Linter")
+            .field("enabled", &self.enabled)
+            .field("severities", &self.severities)
+            .field("checks", &checks)
+            .finish()
+    }
+}
+
+impl Linter {
+    /// A linter with every rule enabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `check` as well, enabled; it replaces an added check with the
+    /// same rule name
+    pub fn add_check(&mut self, check: impl LintCheck + 'static) {
+        let name = check.rule().name;
+        self.checks.retain(|added| added.rule().name != name);
+        self.checks.push(Rc::new(check));
+        self.enabled.insert(name);
+    }
+
+    /// The built-in rules followed by those of added checks
+    pub fn rules(&self) -> impl Iterator<Item = &'static LintRule> + '_ {
+        LINT_RULES
+            .iter()
+            .chain(self.checks.iter().map(|check| check.rule()))
+    }
+
+    /// Look up a built-in or added rule by name
+    pub fn rule(&self, name: &str) -> Result<&'static LintRule, UnknownRule> {
+        self.rules()
+            .find(|rule| rule.name == name)
+            .ok_or_else(|| UnknownRule {
+                name: name.to_string(),
+            })
+    }
+
+    /// Run only the named rules
+    pub fn select(&mut self, names: &[&str]) -> Result<(), UnknownRule> {
+        let rules = names
+            .iter()
+            .map(|name| Ok(self.rule(name)?.name))
+            .collect::<Result<HashSet<_>, _>>()?;
+        self.enabled = rules;
+        Ok(())
+    }
+
+    /// Stop running the named rules
+    pub fn ignore(&mut self, names: &[&str]) -> Result<(), UnknownRule> {
+        for name in names {
+            let rule = self.rule(name)?;
+            self.enabled.remove(rule.name);
+        }
+        Ok(())
+    }
+
+    /// Report the named rules with `severity` instead of their default
+    pub fn set_severity(&mut self, names: &[&str], severity: Severity) -> Result<(), UnknownRule> {
+        for name in names {
+            let rule = self.rule(name)?;
+            self.severities.insert(rule.name, severity);
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.enabled.contains(name)
+    }
+
+    /// The severity lints of the rule are reported with
+    pub fn severity(&self, rule: &LintRule) -> Severity {
+        self.severities
+            .get(rule.name)
+            .copied()
+            .unwrap_or(rule.severity)
+    }
+
+    /// Lint `file`, returning its lints in source order
+    ///
+    /// Fails if the file does not parse; the other checks need a program.
+    pub fn lint(&self, file: &SourceFile) -> ParseResult<Vec<Lint>> {
+        let mut parser = Parser::new(file.lexer())?;
+        let program = parser.parse_program()?;
+        let mut lints = Vec::new();
+        let mut push = |rule: &'static LintRule, message: String, span: Span| {
+            if self.is_enabled(rule.name) {
+                lints.push(Lint {
+                    rule,
+                    severity: self.severity(rule),
+                    message,
+                    span,
+                });
+            }
+        };
+        let mut add = |name: &str, message: String, span: Span| {
+            push(
+                lint_rule(name).expect("rules are in LINT_RULES"),
+                message,
+                span,
+            );
+        };
+
+        for warning in parser.take_warnings() {
+            let name = match warning {
+                ParseWarning::RedundantParentheses { .. } => "redundant-parentheses",
+                ParseWarning::MixedLogicalOperators { .. } => "mixed-logical-operators",
+            };
+            add(name, warning.to_string(), warning.span());
+        }
+        if let Err(errors) = check_program(&program) {
+            for err in errors {
+                add("type-error", err.message, err.span);
+            }
+        }
+        for diagnostic in analyze_program(&program) {
+            let (name, message) = match diagnostic.issue {
+                RangeIssue::DivisionByZero => ("division-by-zero", "The divisor is always zero"),
+                RangeIssue::ConstantOverflow => ("constant-overflow", "The value always overflows"),
+                RangeIssue::AlwaysTrue => ("constant-comparison", "The comparison is always true"),
+                RangeIssue::AlwaysFalse => {
+                    ("constant-comparison", "The comparison is always false")
+                }
+            };
+            add(name, message.to_string(), diagnostic.span);
+        }
+
+        let checks: Vec<&dyn LintCheck> = CHECKS
+            .iter()
+            .map(|check| *check as &dyn LintCheck)
+            .chain(self.checks.iter().map(|check| check.as_ref()))
+            .filter(|check| self.is_enabled(check.rule().name))
+            .collect();
+        let mut runner = CheckRunner {
+            checks: &checks,
+            found: Vec::new(),
+        };
+        runner.run(&program);
+        for (rule, message, span) in runner.found {
+            push(rule, message, span);
+        }
+
+        lints.sort_by_key(|lint| (lint.span.start, lint.span.end));
+        Ok(lints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SourceMap;
+
+    fn lint(linter: &Linter, input: &str) -> Vec<(&'static str, String)> {
+        let mut sources = SourceMap::new();
+        let id = sources.add("test.soba", input);
+        linter
+            .lint(sources.get(id).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|lint| {
+                (
+                    lint.rule.name,
+                    sources.snippet(lint.span).unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let linter = Linter::new();
+        assert_eq!(
+            lint(&linter, "((1 + 2)); 1 / 0; 1 < 2"),
+            vec![
+                ("redundant-parentheses", "((1 + 2))".to_string()),
+                ("unused-result", "((1 + 2))".to_string()),
+                ("division-by-zero", "1 / 0".to_string()),
+                ("unused-result", "1 / 0".to_string()),
+                ("constant-comparison", "1 < 2".to_string()),
+            ]
+        );
+        assert_eq!(
+            lint(&linter, "-true || true && false"),
+            vec![
+                ("type-error", "-true".to_string()),
+                (
+                    "mixed-logical-operators",
+                    "-true || true && false".to_string()
+                ),
+                ("constant-condition", "true && false".to_string()),
+            ]
+        );
+        assert!(lint(&linter, "1 + 2 * 3").is_empty());
+    }
+
+    #[test]
+    fn test_checks() {
+        let linter = Linter::new();
+        assert_eq!(
+            lint(&linter, "1 < 3 || (true); 2 > 1 && false"),
+            vec![
+                ("constant-comparison", "1 < 3".to_string()),
+                ("unused-result", "1 < 3 || (true)".to_string()),
+                ("constant-condition", "1 < 3 || (true)".to_string()),
+                ("constant-comparison", "2 > 1".to_string()),
+                ("constant-condition", "2 > 1 && false".to_string()),
+            ]
+        );
+        let mut sources = SourceMap::new();
+        let id = sources.add("test.soba", "1 < 3 || true; 1 < 3 && true");
+        let messages: Vec<String> = linter
+            .lint(sources.get(id).unwrap())
+            .unwrap()
+            .into_iter()
+            .filter(|lint| lint.rule.name == "constant-condition")
+            .map(|lint| lint.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "The condition is always true",
+                "The 'true' operand has no effect"
+            ]
+        );
+
+        let mut floats = Linter::new();
+        floats.select(&["float-equality"]).unwrap();
+        assert_eq!(
+            lint(&floats, "0.1 + 0.2 == 0.3"),
+            vec![("float-equality", "0.1 + 0.2 == 0.3".to_string())]
+        );
+        assert_eq!(lint(&floats, "2 != (1 / 3) * 3").len(), 1);
+        // Integral values compare exactly
+        assert!(lint(&floats, "1 + 2.0 == 3").is_empty());
+    }
+
+    /// Flags every `**`, as an embedder's rule might
+    struct NoPower;
+
+    const NO_POWER: LintRule = LintRule {
+        name: "no-power",
+        severity: Severity::Error,
+        description: "'**' is not allowed",
+    };
+
+    impl LintCheck for NoPower {
+        fn rule(&self) -> &'static LintRule {
+            &NO_POWER
+        }
+
+        fn check_expr(&self, expr: &Expr) -> Option<String> {
+            matches!(
+                expr,
+                Expr::InfixExpr {
+                    op: BinaryOp::Power,
+                    ..
+                }
+            )
+            .then(|| "Use multiplication instead".to_string())
+        }
+    }
+
+    #[test]
+    fn test_added_checks_and_severities() {
+        let mut linter = Linter::new();
+        assert!(linter.rule("no-power").is_err());
+        linter.add_check(NoPower);
+        assert_eq!(
+            lint(&linter, "2 ** 3 ** 2"),
+            vec![
+                ("no-power", "2 ** 3 ** 2".to_string()),
+                ("no-power", "3 ** 2".to_string()),
+            ]
+        );
+        assert_eq!(linter.rules().last(), Some(&NO_POWER));
+
+        linter
+            .set_severity(&["no-power"], Severity::Warning)
+            .unwrap();
+        linter
+            .set_severity(&["unused-result"], Severity::Error)
+            .unwrap();
+        let mut sources = SourceMap::new();
+        let id = sources.add("test.soba", "2 ** 2; 1");
+        let severities: Vec<(&str, Severity)> = linter
+            .lint(sources.get(id).unwrap())
+            .unwrap()
+            .iter()
+            .map(|lint| (lint.rule.name, lint.severity))
+            .collect();
+        assert_eq!(
+            severities,
+            [
+                ("unused-result", Severity::Error),
+                ("no-power", Severity::Warning)
+            ]
+        );
+
+        linter.ignore(&["no-power"]).unwrap();
+        assert!(lint(&linter, "2 ** 2").is_empty());
+        assert!(linter.set_severity(&["nope"], Severity::Error).is_err());
+    }
+
+    #[test]
+    fn test_select_and_ignore() {
+        let mut linter = Linter::new();
+        linter.ignore(&["division-by-zero"]).unwrap();
+        assert_eq!(lint(&linter, "1 / 0").len(), 0);
+        assert!(linter.is_enabled("type-error"));
+
+        linter.select(&["type-error"]).unwrap();
+        assert_eq!(lint(&linter, "((-true)); 1 / 0").len(), 1);
+        assert!(!linter.is_enabled("redundant-parentheses"));
+
+        assert_eq!(
+            linter.ignore(&["no-such-rule"]).unwrap_err().to_string(),
+            "Unknown lint rule 'no-such-rule'"
+        );
+    }
+
+    #[test]
+    fn test_lint_display_and_severity() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("test.soba", "1 / 0");
+        let lints = Linter::new().lint(sources.get(id).unwrap()).unwrap();
+        assert_eq!(lints[0].severity, Severity::Error);
+        assert_eq!(
+            lints[0].to_string(),
+            "error[division-by-zero]: The divisor is always zero"
+        );
+
+        let id = sources.add("bad.soba", "1 +");
+        assert!(Linter::new().lint(sources.get(id).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Size and shape metrics of a program
+//!
+//! Metrics are cheap to compute, so an embedder can check them against
+//! limits before evaluating user-submitted input, or aggregate them over a
+//! corpus of programs.
+
+use crate::ast::visit::{walk_expr, Visitor};
+use crate::ast::{Expr, Program};
+use std::collections::BTreeMap;
+
+/// Counts describing the size and shape of a program's expressions
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AstMetrics {
+    /// Number of expression nodes, including groups
+    pub nodes: usize,
+    /// Nesting depth of the deepest expression; a lone literal has depth 1
+    pub max_depth: usize,
+    /// How often each infix operator is used, keyed by symbol
+    pub infix_operators: BTreeMap<String, usize>,
+    /// How often each prefix operator is used, keyed by symbol
+    pub prefix_operators: BTreeMap<String, usize>,
+    /// How often each postfix operator is used, keyed by symbol
+    pub postfix_operators: BTreeMap<String, usize>,
+    pub int_literals: usize,
+    pub float_literals: usize,
+    pub bool_literals: usize,
+}
+
+impl AstMetrics {
+    /// Metrics of a single expression
+    pub fn of_expr(expr: &Expr) -> Self {
+        let mut collector = MetricsCollector::default();
+        collector.visit_expr(expr);
+        collector.metrics
+    }
+
+    /// Total number of operator uses of any kind
+    pub fn operator_count(&self) -> usize {
+        [
+            &self.infix_operators,
+            &self.prefix_operators,
+            &self.postfix_operators,
+        ]
+        .iter()
+        .flat_map(|histogram| histogram.values())
+        .sum()
+    }
+
+    /// Total number of literals of any type
+    pub fn literal_count(&self) -> usize {
+        self.int_literals + self.float_literals + self.bool_literals
+    }
+}
+
+impl Program {
+    /// Compute size and shape metrics over every statement
+    pub fn metrics(&self) -> AstMetrics {
+        let mut collector = MetricsCollector::default();
+        collector.visit_program(self);
+        collector.metrics
+    }
+}
+
+#[derive(Default)]
+struct MetricsCollector {
+    metrics: AstMetrics,
+    depth: usize,
+}
+
+impl Visitor for MetricsCollector {
+    fn visit_expr(&mut self, expr: &Expr) {
+        let metrics = &mut self.metrics;
+        metrics.nodes += 1;
+        match expr {
+            Expr::Int { .. } => metrics.int_literals += 1,
+            Expr::Float { .. } => metrics.float_literals += 1,
+            Expr::Bool { .. } => metrics.bool_literals += 1,
+            Expr::InfixExpr { op, .. } => count(&mut metrics.infix_operators, op),
+            Expr::UnaryExpr { op, .. } => count(&mut metrics.prefix_operators, op),
+            Expr::PostfixExpr { op, .. } => count(&mut metrics.postfix_operators, op),
+            Expr::Grouped { .. } => {}
+        }
+
+        self.depth += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(self.depth);
+        walk_expr(self, expr);
+        self.depth -= 1;
+    }
+}
+
+fn count(histogram: &mut BTreeMap<String, usize>, op: &impl ToString) {
+    *histogram.entry(op.to_string()).or_default() += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_util::parse;
+
+    #[test]
+    fn test_program_metrics() {
+        let metrics = parse("1 + 2 * 3; -(4.5 - 1) < 2; !true").metrics();
+
+        assert_eq!(metrics.nodes, 14);
+        // <, -, (), -, 4.5
+        assert_eq!(metrics.max_depth, 5);
+        assert_eq!(
+            metrics.infix_operators,
+            BTreeMap::from([
+                ("*".to_string(), 1),
+                ("+".to_string(), 1),
+                ("-".to_string(), 1),
+                ("<".to_string(), 1),
+            ])
+        );
+        assert_eq!(
+            metrics.prefix_operators,
+            BTreeMap::from([("!".to_string(), 1), ("-".to_string(), 1)])
+        );
+        assert!(metrics.postfix_operators.is_empty());
+        assert_eq!(metrics.operator_count(), 6);
+        assert_eq!(
+            (
+                metrics.int_literals,
+                metrics.float_literals,
+                metrics.bool_literals
+            ),
+            (5, 1, 1)
+        );
+        assert_eq!(metrics.literal_count(), 7);
+    }
+
+    #[test]
+    fn test_empty_program_and_single_expr() {
+        assert_eq!(Program::empty().metrics(), AstMetrics::default());
+
+        let metrics = AstMetrics::of_expr(&Expr::int(1));
+        assert_eq!((metrics.nodes, metrics.max_depth), (1, 1));
+    }
+}
@@ -0,0 +1,16 @@
+//! Static analysis module
+//!
+//! This module contains passes that inspect programs before evaluation.
+
+pub mod interval;
+pub mod lint;
+pub mod metrics;
+
+pub use interval::{
+    analyze_expr, analyze_program, analyze_program_with_float_eq, Interval, Range, RangeDiagnostic,
+    RangeIssue,
+};
+pub use lint::{
+    lint_rule, Lint, LintCheck, LintRule, Linter, Severity, UnknownRule, LINT_RULES,
+};
+pub use metrics::AstMetrics;
@@ -0,0 +1,421 @@
+//! Arena-allocated expression storage
+//!
+//! `ExprArena` keeps expression nodes in one contiguous `Vec` and links
+//! children through `ExprId` indices instead of `Box`es. Large programs that
+//! are evaluated repeatedly benefit from the single allocation and the better
+//! cache locality, while the boxed `Expr` tree remains the primary AST.
+//!
+//! `ExprId` doubles as the node id for analyses: a `SideTable` stores
+//! per-node results such as types, constant values or coverage counts next
+//! to the tree instead of inside it. Spans are kept the same way, so the
+//! nodes themselves hold only what evaluation needs.
+
+use super::{BinaryOp, Expr, PostfixOp, Program, Statement, UnaryOp};
+use crate::prelude::*;
+use crate::span::Span;
+
+/// Index of an expression node inside an `ExprArena`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+impl ExprId {
+    /// Get the position of this node in the arena
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Expression node whose children are arena indices
+///
+/// Nodes carry no spans; the arena keeps those in a side table like any
+/// other per-node data.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ArenaExpr {
+    /// Integer literal
+    Int { value: i32 },
+    /// Floating-point literal
+    Float { value: f64 },
+    /// Boolean literal
+    Bool { value: bool },
+    /// Binary infix expression (e.g., 1 + 2)
+    InfixExpr {
+        left: ExprId,
+        op: BinaryOp,
+        right: ExprId,
+    },
+    /// Grouped expression (e.g., (1 + 2))
+    Grouped { inner: ExprId },
+    /// Unary expression (e.g., -1, +5)
+    UnaryExpr { op: UnaryOp, operand: ExprId },
+    /// Postfix expression (e.g., 5!)
+    PostfixExpr { op: PostfixOp, operand: ExprId },
+}
+
+/// Contiguous storage for expression nodes
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ExprArena {
+    nodes: Vec<ArenaExpr>,
+    /// Source span of every node
+    spans: SideTable<Span>,
+}
+
+impl ExprArena {
+    /// Create an empty arena
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty arena with room for `capacity` nodes
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+            spans: SideTable::with_capacity(capacity),
+        }
+    }
+
+    /// Store a node covering `span` and return its id
+    ///
+    /// Child ids referenced by `node` must already belong to this arena.
+    pub fn alloc(&mut self, node: ArenaExpr, span: Span) -> ExprId {
+        let id = ExprId(self.nodes.len() as u32);
+        self.nodes.push(node);
+        self.spans.insert(id, span);
+        id
+    }
+
+    /// Get the node for an id
+    pub fn get(&self, id: ExprId) -> &ArenaExpr {
+        &self.nodes[id.index()]
+    }
+
+    /// Get the source span of a node
+    pub fn span(&self, id: ExprId) -> Span {
+        self.spans[id]
+    }
+
+    /// The spans of all nodes, as a side table
+    pub fn spans(&self) -> &SideTable<Span> {
+        &self.spans
+    }
+
+    /// Number of nodes stored in the arena
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Iterate over all nodes in allocation order (children before parents)
+    pub fn iter(&self) -> impl Iterator<Item = (ExprId, &ArenaExpr)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (ExprId(index as u32), node))
+    }
+
+    /// Copy a boxed expression tree into the arena and return the id of its root
+    pub fn alloc_expr(&mut self, expr: &Expr) -> ExprId {
+        let node = match expr {
+            Expr::Int { value, .. } => ArenaExpr::Int { value: *value },
+            Expr::Float { value, .. } => ArenaExpr::Float { value: *value },
+            Expr::Bool { value, .. } => ArenaExpr::Bool { value: *value },
+            Expr::InfixExpr {
+                left, op, right, ..
+            } => {
+                let left = self.alloc_expr(left);
+                let right = self.alloc_expr(right);
+                ArenaExpr::InfixExpr {
+                    left,
+                    op: *op,
+                    right,
+                }
+            }
+            Expr::Grouped { inner, .. } => ArenaExpr::Grouped {
+                inner: self.alloc_expr(inner),
+            },
+            Expr::UnaryExpr { op, operand, .. } => ArenaExpr::UnaryExpr {
+                op: *op,
+                operand: self.alloc_expr(operand),
+            },
+            Expr::PostfixExpr { op, operand, .. } => ArenaExpr::PostfixExpr {
+                op: *op,
+                operand: self.alloc_expr(operand),
+            },
+        };
+        self.alloc(node, expr.span())
+    }
+
+    /// Rebuild the boxed expression tree rooted at `id`
+    pub fn to_expr(&self, id: ExprId) -> Expr {
+        let span = self.span(id);
+        match self.get(id) {
+            ArenaExpr::Int { value } => Expr::Int {
+                value: *value,
+                span,
+            },
+            ArenaExpr::Float { value } => Expr::Float {
+                value: *value,
+                span,
+            },
+            ArenaExpr::Bool { value } => Expr::Bool {
+                value: *value,
+                span,
+            },
+            ArenaExpr::InfixExpr { left, op, right } => Expr::InfixExpr {
+                left: Box::new(self.to_expr(*left)),
+                op: *op,
+                right: Box::new(self.to_expr(*right)),
+                span,
+            },
+            ArenaExpr::Grouped { inner } => Expr::Grouped {
+                inner: Box::new(self.to_expr(*inner)),
+                span,
+            },
+            ArenaExpr::UnaryExpr { op, operand } => Expr::UnaryExpr {
+                op: *op,
+                operand: Box::new(self.to_expr(*operand)),
+                span,
+            },
+            ArenaExpr::PostfixExpr { op, operand } => Expr::PostfixExpr {
+                op: *op,
+                operand: Box::new(self.to_expr(*operand)),
+                span,
+            },
+        }
+    }
+}
+
+impl core::ops::Index<ExprId> for ExprArena {
+    type Output = ArenaExpr;
+
+    fn index(&self, id: ExprId) -> &ArenaExpr {
+        self.get(id)
+    }
+}
+
+/// A program whose expressions are all stored in one arena
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArenaProgram {
+    pub arena: ExprArena,
+    /// Root expression of each statement, in order
+    pub statements: Vec<ExprId>,
+    pub span: Span,
+}
+
+impl ArenaProgram {
+    /// Copy a boxed program into a new arena
+    pub fn from_program(program: &Program) -> Self {
+        let mut arena = ExprArena::new();
+        let statements = program
+            .statements
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::ExprStatement { expr, .. } => arena.alloc_expr(expr),
+            })
+            .collect();
+        Self {
+            arena,
+            statements,
+            span: program.span,
+        }
+    }
+
+    /// Rebuild the boxed program
+    pub fn to_program(&self) -> Program {
+        let statements = self
+            .statements
+            .iter()
+            .map(|id| Statement::expr_statement(self.arena.to_expr(*id)))
+            .collect();
+        Program {
+            statements,
+            span: self.span,
+        }
+    }
+}
+
+/// Per-node data for the expressions of one arena
+///
+/// Entries are optional, so a pass can record results only for the nodes
+/// it cares about.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SideTable<T> {
+    entries: Vec<Option<T>>,
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SideTable<T> {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Create an empty table with room for `capacity` nodes
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Create an empty table with room for every node of `arena`
+    pub fn for_arena(arena: &ExprArena) -> Self {
+        let mut entries = Vec::with_capacity(arena.len());
+        entries.resize_with(arena.len(), || None);
+        Self { entries }
+    }
+
+    /// Set the entry for a node, returning the previous one
+    pub fn insert(&mut self, id: ExprId, value: T) -> Option<T> {
+        if id.index() >= self.entries.len() {
+            self.entries.resize_with(id.index() + 1, || None);
+        }
+        self.entries[id.index()].replace(value)
+    }
+
+    /// Get the entry for a node
+    pub fn get(&self, id: ExprId) -> Option<&T> {
+        self.entries.get(id.index()).and_then(Option::as_ref)
+    }
+
+    pub fn contains(&self, id: ExprId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Remove and return the entry for a node
+    pub fn remove(&mut self, id: ExprId) -> Option<T> {
+        self.entries.get_mut(id.index()).and_then(Option::take)
+    }
+
+    /// Number of nodes that have an entry
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the nodes that have an entry, in id order
+    pub fn iter(&self) -> impl Iterator<Item = (ExprId, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.as_ref().map(|value| (ExprId(index as u32), value)))
+    }
+}
+
+impl<T> core::ops::Index<ExprId> for SideTable<T> {
+    type Output = T;
+
+    fn index(&self, id: ExprId) -> &T {
+        self.get(id).expect("no side table entry for node")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_expr() -> Expr {
+        // -(1 + 2.5)
+        Expr::UnaryExpr {
+            op: UnaryOp::Minus,
+            operand: Box::new(Expr::Grouped {
+                inner: Box::new(Expr::InfixExpr {
+                    left: Box::new(Expr::int(1)),
+                    op: BinaryOp::Plus,
+                    right: Box::new(Expr::float(2.5)),
+                    span: Span::single(0),
+                }),
+                span: Span::single(0),
+            }),
+            span: Span::single(0),
+        }
+    }
+
+    #[test]
+    fn test_alloc_and_get() {
+        let mut arena = ExprArena::new();
+        let id = arena.alloc(ArenaExpr::Int { value: 7 }, Span::new(3, 4));
+        assert_eq!(arena.len(), 1);
+        assert!(matches!(arena[id], ArenaExpr::Int { value: 7 }));
+        assert_eq!(arena.span(id), Span::new(3, 4));
+        assert_eq!(arena.spans().len(), 1);
+    }
+
+    #[test]
+    fn test_alloc_expr_stores_children_first() {
+        let mut arena = ExprArena::new();
+        let root = arena.alloc_expr(&sample_expr());
+        assert_eq!(arena.len(), 5);
+        assert_eq!(root.index(), 4);
+
+        if let ArenaExpr::UnaryExpr { operand, .. } = arena[root] {
+            assert!(operand < root);
+            assert!(matches!(arena[operand], ArenaExpr::Grouped { .. }));
+        } else {
+            panic!("Expected unary expression");
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let expr = sample_expr();
+        let mut arena = ExprArena::new();
+        let root = arena.alloc_expr(&expr);
+        assert_eq!(arena.to_expr(root), expr);
+    }
+
+    #[test]
+    fn test_spans_live_in_a_side_table() {
+        let lexer = crate::lexer::SobaLexer::new("1;\n-(2 + 3)".chars().collect());
+        let program = crate::parser::Parser::new(lexer)
+            .unwrap()
+            .parse_program()
+            .unwrap();
+        let arena_program = ArenaProgram::from_program(&program);
+        let arena = &arena_program.arena;
+        let root = arena_program.statements[1];
+
+        assert_eq!(arena.span(root), Span::new(3, 11));
+        assert_eq!(arena.spans().len(), arena.len());
+        assert_eq!(arena_program.to_program(), program);
+    }
+
+    #[test]
+    fn test_arena_program_round_trip() {
+        let program = Program::new(vec![
+            Statement::expr_statement(sample_expr()),
+            Statement::expr_statement(Expr::bool(true)),
+        ]);
+        let arena_program = ArenaProgram::from_program(&program);
+        assert_eq!(arena_program.statements.len(), 2);
+        assert_eq!(arena_program.arena.len(), 6);
+        assert_eq!(arena_program.to_program(), program);
+    }
+
+    #[test]
+    fn test_side_table() {
+        let mut arena = ExprArena::new();
+        let root = arena.alloc_expr(&sample_expr());
+        let mut depths = SideTable::for_arena(&arena);
+        assert!(depths.is_empty());
+
+        assert_eq!(depths.insert(root, 0), None);
+        assert_eq!(depths.insert(root, 1), Some(0));
+        assert_eq!(depths[root], 1);
+        assert!(!depths.contains(ExprId(0)));
+        assert_eq!(depths.iter().collect::<Vec<_>>(), vec![(root, &1)]);
+        assert_eq!(depths.remove(root), Some(1));
+        assert!(depths.is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Debugging dumps of the AST
+//!
+//! Unlike the printer, these show the shape of the tree rather than soba
+//! source: `to_sexpr` writes one s-expression per statement, such as
+//! `(+ 1 (* 2 3))`, and `to_tree` writes one node per line, indented by
+//! depth and followed by its byte range.
+//!
+//! In s-expressions, a prefix operator has one operand, `(- 1)`, and a
+//! binary operator two, `(- 1 2)`; postfix operators are written
+//! `(postfix ! 5)` and parentheses `(group 1)`. Floats always have a
+//! decimal point, so `2.0` and `2` stay apart.
+
+use super::{Expr, Program, Statement};
+use crate::prelude::*;
+use core::fmt::Write;
+
+impl Expr {
+    /// The expression as an s-expression
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        write_sexpr(self, &mut out);
+        out
+    }
+
+    /// The expression as an indented tree, starting at `depth` levels
+    pub fn to_tree(&self, depth: usize) -> String {
+        let mut out = String::new();
+        write_tree(self, depth, &mut out);
+        out
+    }
+}
+
+impl Program {
+    /// Each statement as an s-expression on its own line
+    pub fn to_sexpr(&self) -> String {
+        self.statements
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::ExprStatement { expr, .. } => format!("{}\n", expr.to_sexpr()),
+            })
+            .collect()
+    }
+
+    /// The program as an indented tree, one node per line
+    pub fn to_tree(&self) -> String {
+        let mut out = format!("Program {}\n", self.span);
+        for stmt in &self.statements {
+            let Statement::ExprStatement { expr, span } = stmt;
+            let _ = writeln!(out, "  ExprStatement {span}");
+            write_tree(expr, 2, &mut out);
+        }
+        out
+    }
+}
+
+fn write_sexpr(expr: &Expr, out: &mut String) {
+    match expr {
+        Expr::Int { value, .. } => {
+            let _ = write!(out, "{value}");
+        }
+        Expr::Float { value, .. } => {
+            let _ = write!(out, "{value:?}");
+        }
+        Expr::Bool { value, .. } => {
+            let _ = write!(out, "{value}");
+        }
+        Expr::InfixExpr {
+            left, op, right, ..
+        } => {
+            let _ = write!(out, "({op} ");
+            write_sexpr(left, out);
+            out.push(' ');
+            write_sexpr(right, out);
+            out.push(')');
+        }
+        Expr::Grouped { inner, .. } => {
+            out.push_str("(group ");
+            write_sexpr(inner, out);
+            out.push(')');
+        }
+        Expr::UnaryExpr { op, operand, .. } => {
+            let _ = write!(out, "({op} ");
+            write_sexpr(operand, out);
+            out.push(')');
+        }
+        Expr::PostfixExpr { op, operand, .. } => {
+            let _ = write!(out, "(postfix {op} ");
+            write_sexpr(operand, out);
+            out.push(')');
+        }
+    }
+}
+
+fn write_tree(expr: &Expr, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let span = expr.span();
+    let _ = match expr {
+        Expr::Int { value, .. } => writeln!(out, "{indent}Int {value} {span}"),
+        Expr::Float { value, .. } => writeln!(out, "{indent}Float {value:?} {span}"),
+        Expr::Bool { value, .. } => writeln!(out, "{indent}Bool {value} {span}"),
+        Expr::InfixExpr { op, .. } => writeln!(out, "{indent}Infix {op} {span}"),
+        Expr::Grouped { .. } => writeln!(out, "{indent}Grouped {span}"),
+        Expr::UnaryExpr { op, .. } => writeln!(out, "{indent}Unary {op} {span}"),
+        Expr::PostfixExpr { op, .. } => writeln!(out, "{indent}Postfix {op} {span}"),
+    };
+    match expr {
+        Expr::Int { .. } | Expr::Float { .. } | Expr::Bool { .. } => {}
+        Expr::InfixExpr { left, right, .. } => {
+            write_tree(left, depth + 1, out);
+            write_tree(right, depth + 1, out);
+        }
+        Expr::Grouped { inner: operand, .. }
+        | Expr::UnaryExpr { operand, .. }
+        | Expr::PostfixExpr { operand, .. } => write_tree(operand, depth + 1, out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::SobaLexer;
+    use crate::operators::{Associativity, OperatorRegistry};
+    use crate::parser::test_util::parse;
+    use crate::parser::{Parser, Precedence};
+
+    #[test]
+    fn test_sexpr() {
+        assert_eq!(
+            parse("1 + 2 * 3; -(2.0 ** 2) < 1 || !true").to_sexpr(),
+            "(+ 1 (* 2 3))\n(|| (< (- (group (** 2.0 2))) 1) (! true))\n"
+        );
+        assert_eq!(parse("").to_sexpr(), "");
+
+        let mut operators = OperatorRegistry::new();
+        operators.register_postfix("!!", Ok).unwrap();
+        operators
+            .register_infix("<>", Precedence::Sum, Associativity::Left, |l, _| Ok(l))
+            .unwrap();
+        let lexer = SobaLexer::with_operators("1 <> 2!!".chars().collect(), &operators);
+        let program = Parser::with_operators(lexer, &operators)
+            .unwrap()
+            .parse_program()
+            .unwrap();
+        assert_eq!(program.to_sexpr(), "(<> 1 (postfix !! 2))\n");
+    }
+
+    #[test]
+    fn test_tree() {
+        assert_eq!(
+            parse("-(1 + 2.5)").to_tree(),
+            "Program 0..10\n\
+             \x20 ExprStatement 0..10\n\
+             \x20   Unary - 0..10\n\
+             \x20     Grouped 1..10\n\
+             \x20       Infix + 2..9\n\
+             \x20         Int 1 2..3\n\
+             \x20         Float 2.5 6..9\n"
+        );
+        assert_eq!(Expr::bool(true).to_tree(1), "  Bool true 0..0\n");
+    }
+}
@@ -1,9 +1,14 @@
 //! Abstract Syntax Tree expression definitions
 
+use crate::lexer::TokenKind;
+use crate::operators::CustomOp;
+use crate::parser::table::{lookup, BUILTIN_INFIX, BUILTIN_PREFIX};
+use crate::prelude::*;
 use crate::span::Span;
 
 /// AST node for expressions
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     /// Integer literal
     Int { value: i32, span: Span },
@@ -26,15 +31,23 @@ pub enum Expr {
         operand: Box<Expr>,
         span: Span,
     },
+    /// Postfix expression (e.g., 5!)
+    PostfixExpr {
+        op: PostfixOp,
+        operand: Box<Expr>,
+        span: Span,
+    },
 }
 
 /// Binary operators
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOp {
     Plus,
     Minus,
     Multiply,
     Divide,
+    Power,
     LogicalAnd,
     LogicalOr,
     Equal,
@@ -43,16 +56,28 @@ pub enum BinaryOp {
     Greater,
     LessEqual,
     GreaterEqual,
-    // Future: Modulo, etc.
+    /// An operator from an `OperatorRegistry`
+    Custom(CustomOp),
 }
 
 /// Unary operators
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOp {
     Plus,
     Minus,
     LogicalNot,
-    // Future: other unary operators
+    /// A prefix operator from an `OperatorRegistry`
+    Custom(CustomOp),
+}
+
+/// Postfix operators
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PostfixOp {
+    /// A postfix operator from an `OperatorRegistry`
+    Custom(CustomOp),
+    // Future: calls, indexing, member access
 }
 
 impl Expr {
@@ -64,15 +89,77 @@ impl Expr {
             | Expr::Bool { span, .. }
             | Expr::InfixExpr { span, .. }
             | Expr::Grouped { span, .. }
-            | Expr::UnaryExpr { span, .. } => *span,
+            | Expr::UnaryExpr { span, .. }
+            | Expr::PostfixExpr { span, .. } => *span,
+        }
+    }
+
+    /// Whether two expressions have the same structure, whatever their spans
+    pub fn eq_ignoring_spans(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (Expr::Int { value: a, .. }, Expr::Int { value: b, .. }) => a == b,
+            (Expr::Float { value: a, .. }, Expr::Float { value: b, .. }) => a == b,
+            (Expr::Bool { value: a, .. }, Expr::Bool { value: b, .. }) => a == b,
+            (
+                Expr::InfixExpr {
+                    left: l1,
+                    op: o1,
+                    right: r1,
+                    ..
+                },
+                Expr::InfixExpr {
+                    left: l2,
+                    op: o2,
+                    right: r2,
+                    ..
+                },
+            ) => o1 == o2 && l1.eq_ignoring_spans(l2) && r1.eq_ignoring_spans(r2),
+            (Expr::Grouped { inner: a, .. }, Expr::Grouped { inner: b, .. }) => {
+                a.eq_ignoring_spans(b)
+            }
+            (
+                Expr::UnaryExpr {
+                    op: o1, operand: a, ..
+                },
+                Expr::UnaryExpr {
+                    op: o2, operand: b, ..
+                },
+            ) => o1 == o2 && a.eq_ignoring_spans(b),
+            (
+                Expr::PostfixExpr {
+                    op: o1, operand: a, ..
+                },
+                Expr::PostfixExpr {
+                    op: o2, operand: b, ..
+                },
+            ) => o1 == o2 && a.eq_ignoring_spans(b),
+            _ => false,
+        }
+    }
+
+    /// The innermost expression whose span covers byte `offset`, if any
+    pub fn node_at(&self, offset: usize) -> Option<&Expr> {
+        if !self.span().contains(offset) {
+            return None;
         }
+        let child = match self {
+            Expr::Int { .. } | Expr::Float { .. } | Expr::Bool { .. } => None,
+            Expr::InfixExpr { left, right, .. } => {
+                left.node_at(offset).or_else(|| right.node_at(offset))
+            }
+            Expr::Grouped { inner, .. } => inner.node_at(offset),
+            Expr::UnaryExpr { operand, .. } | Expr::PostfixExpr { operand, .. } => {
+                operand.node_at(offset)
+            }
+        };
+        Some(child.unwrap_or(self))
     }
 
     /// Create a simple integer expression without span
     pub fn int(value: i32) -> Self {
         Expr::Int {
             value,
-            span: Span::single(crate::span::Position::start()),
+            span: Span::single(0),
         }
     }
 
@@ -80,7 +167,7 @@ impl Expr {
     pub fn float(value: f64) -> Self {
         Expr::Float {
             value,
-            span: Span::single(crate::span::Position::start()),
+            span: Span::single(0),
         }
     }
 
@@ -88,18 +175,33 @@ impl Expr {
     pub fn bool(value: bool) -> Self {
         Expr::Bool {
             value,
-            span: Span::single(crate::span::Position::start()),
+            span: Span::single(0),
         }
     }
 }
 
-impl std::fmt::Display for BinaryOp {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl BinaryOp {
+    /// Get the built-in binary operator a token stands for, if any
+    pub fn from_token(token: &TokenKind) -> Option<BinaryOp> {
+        lookup(BUILTIN_INFIX, token).map(|operator| operator.op)
+    }
+}
+
+impl UnaryOp {
+    /// Get the built-in prefix operator a token stands for, if any
+    pub fn from_token(token: &TokenKind) -> Option<UnaryOp> {
+        lookup(BUILTIN_PREFIX, token)
+    }
+}
+
+impl core::fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             BinaryOp::Plus => write!(f, "+"),
             BinaryOp::Minus => write!(f, "-"),
             BinaryOp::Multiply => write!(f, "*"),
             BinaryOp::Divide => write!(f, "/"),
+            BinaryOp::Power => write!(f, "**"),
             BinaryOp::LogicalAnd => write!(f, "&&"),
             BinaryOp::LogicalOr => write!(f, "||"),
             BinaryOp::Equal => write!(f, "=="),
@@ -108,16 +210,26 @@ impl std::fmt::Display for BinaryOp {
             BinaryOp::Greater => write!(f, ">"),
             BinaryOp::LessEqual => write!(f, "<="),
             BinaryOp::GreaterEqual => write!(f, ">="),
+            BinaryOp::Custom(op) => write!(f, "{op}"),
         }
     }
 }
 
-impl std::fmt::Display for UnaryOp {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             UnaryOp::Plus => write!(f, "+"),
             UnaryOp::Minus => write!(f, "-"),
             UnaryOp::LogicalNot => write!(f, "!"),
+            UnaryOp::Custom(op) => write!(f, "{op}"),
+        }
+    }
+}
+
+impl core::fmt::Display for PostfixOp {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PostfixOp::Custom(op) => write!(f, "{op}"),
         }
     }
 }
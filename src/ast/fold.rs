@@ -0,0 +1,124 @@
+//! AST rewriting
+//!
+//! `Folder` consumes a tree and rebuilds it, letting passes such as
+//! optimizations and desugarings replace any node. Like `Visitor`, each
+//! method defaults to a `walk_*` function that folds the children and
+//! keeps the node itself unchanged.
+
+use super::{Expr, Program, Statement};
+use crate::prelude::*;
+
+/// A transformation that rebuilds the AST
+pub trait Folder {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        walk_statement(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr(self, expr)
+    }
+}
+
+/// Fold every statement of a program
+pub fn walk_program<F: Folder + ?Sized>(folder: &mut F, program: Program) -> Program {
+    let statements = program
+        .statements
+        .into_iter()
+        .map(|stmt| folder.fold_statement(stmt))
+        .collect();
+    Program {
+        statements,
+        span: program.span,
+    }
+}
+
+/// Fold the expression of a statement
+pub fn walk_statement<F: Folder + ?Sized>(folder: &mut F, stmt: Statement) -> Statement {
+    match stmt {
+        Statement::ExprStatement { expr, span } => Statement::ExprStatement {
+            expr: folder.fold_expr(expr),
+            span,
+        },
+    }
+}
+
+/// Fold the direct children of an expression, keeping its operator and span
+pub fn walk_expr<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::Int { .. } | Expr::Float { .. } | Expr::Bool { .. } => expr,
+        Expr::InfixExpr {
+            left,
+            op,
+            right,
+            span,
+        } => Expr::InfixExpr {
+            left: Box::new(folder.fold_expr(*left)),
+            op,
+            right: Box::new(folder.fold_expr(*right)),
+            span,
+        },
+        Expr::Grouped { inner, span } => Expr::Grouped {
+            inner: Box::new(folder.fold_expr(*inner)),
+            span,
+        },
+        Expr::UnaryExpr { op, operand, span } => Expr::UnaryExpr {
+            op,
+            operand: Box::new(folder.fold_expr(*operand)),
+            span,
+        },
+        Expr::PostfixExpr { op, operand, span } => Expr::PostfixExpr {
+            op,
+            operand: Box::new(folder.fold_expr(*operand)),
+            span,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::UnaryOp;
+    use crate::parser::test_util::parse;
+
+    /// Drops parentheses and unary plus, which do not change a value
+    struct Simplify;
+
+    impl Folder for Simplify {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match walk_expr(self, expr) {
+                Expr::Grouped { inner, .. } => *inner,
+                Expr::UnaryExpr {
+                    op: UnaryOp::Plus,
+                    operand,
+                    ..
+                } => *operand,
+                other => other,
+            }
+        }
+    }
+
+    struct Identity;
+
+    impl Folder for Identity {}
+
+    #[test]
+    fn test_default_fold_is_identity() {
+        let program = parse("(1 + 2) * -3; !true");
+        assert_eq!(Identity.fold_program(program.clone()), program);
+    }
+
+    #[test]
+    fn test_fold_rewrites_nested_nodes() {
+        let folded = Simplify.fold_program(parse("+((1)) * (+(2 + 3))"));
+        let Statement::ExprStatement { expr, .. } = &folded.statements[0];
+        let Expr::InfixExpr { left, right, .. } = expr else {
+            panic!("Expected infix expression");
+        };
+        assert!(matches!(**left, Expr::Int { value: 1, .. }));
+        assert!(matches!(**right, Expr::InfixExpr { .. }));
+    }
+}
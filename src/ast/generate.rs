@@ -0,0 +1,157 @@
+//! Random programs for fuzzing and property tests
+//!
+//! With the `arbitrary` feature, `Expr` and `Program` implement
+//! `arbitrary::Arbitrary`, and so does `Value`. Generated trees are valid
+//! parser output: they use the built-in operators and literals the lexer
+//! can produce, and they are built by parsing the printed source of a
+//! random tree, so their spans and parentheses are real.
+
+use super::{BinaryOp, Expr, Program, Statement, UnaryOp};
+use crate::lexer::SobaLexer;
+use crate::parser::Parser;
+use crate::span::Span;
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+
+/// How deeply generated expressions nest
+const MAX_DEPTH: usize = 8;
+/// Most statements in a generated program
+const MAX_STATEMENTS: usize = 8;
+
+const BINARY_OPS: &[BinaryOp] = &[
+    BinaryOp::Plus,
+    BinaryOp::Minus,
+    BinaryOp::Multiply,
+    BinaryOp::Divide,
+    BinaryOp::Power,
+    BinaryOp::LogicalAnd,
+    BinaryOp::LogicalOr,
+    BinaryOp::Equal,
+    BinaryOp::NotEqual,
+    BinaryOp::Less,
+    BinaryOp::Greater,
+    BinaryOp::LessEqual,
+    BinaryOp::GreaterEqual,
+];
+
+const UNARY_OPS: &[UnaryOp] = &[UnaryOp::Plus, UnaryOp::Minus, UnaryOp::LogicalNot];
+
+impl<'a> Arbitrary<'a> for Expr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let tree = random_expr(u, MAX_DEPTH)?;
+        Parser::new(lexer(&tree.to_source()))
+            .and_then(|mut parser| parser.parse_standalone_expression())
+            .map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Program {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let count = u.int_in_range(0..=MAX_STATEMENTS)?;
+        let statements = (0..count)
+            .map(|_| {
+                let expr = random_expr(u, MAX_DEPTH)?;
+                let span = expr.span();
+                Ok(Statement::ExprStatement { expr, span })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let tree = Program::new(statements);
+        Parser::new(lexer(&tree.to_source()))
+            .and_then(|mut parser| parser.parse_program())
+            .map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+/// A random tree with placeholder spans, only fit for printing
+///
+/// Literals are never negative, since the printer writes a negative literal
+/// as a prefix `-` that parses back as a unary expression.
+fn random_expr(u: &mut Unstructured<'_>, depth: usize) -> Result<Expr> {
+    let span = Span::single(0);
+    let kinds = if depth == 0 || u.is_empty() { 3 } else { 6 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Expr::Int {
+            value: u.int_in_range(0..=i32::MAX)?,
+            span,
+        },
+        1 => {
+            let value = f64::arbitrary(u)?.abs();
+            Expr::Float {
+                value: if value.is_finite() { value } else { 0.0 },
+                span,
+            }
+        }
+        2 => Expr::Bool {
+            value: bool::arbitrary(u)?,
+            span,
+        },
+        3 => Expr::InfixExpr {
+            left: Box::new(random_expr(u, depth - 1)?),
+            op: *u.choose(BINARY_OPS)?,
+            right: Box::new(random_expr(u, depth - 1)?),
+            span,
+        },
+        4 => Expr::UnaryExpr {
+            op: *u.choose(UNARY_OPS)?,
+            operand: Box::new(random_expr(u, depth - 1)?),
+            span,
+        },
+        _ => Expr::Grouped {
+            inner: Box::new(random_expr(u, depth - 1)?),
+            span,
+        },
+    })
+}
+
+fn lexer(source: &str) -> SobaLexer {
+    SobaLexer::new(source.chars().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::check_round_trip;
+    use crate::evaluator::eval_program;
+    use crate::value::Value;
+
+    /// Deterministic pseudo-random input bytes
+    fn seeds() -> impl Iterator<Item = Vec<u8>> {
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        (0..200).map(move |_| {
+            (0..256)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_generated_programs_are_valid() {
+        let mut statements = 0;
+        for data in seeds() {
+            let program = Program::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            statements += program.statements.len();
+            check_round_trip(&program).unwrap();
+            let _ = eval_program(&program);
+        }
+        assert!(statements > 0, "the generator only made empty programs");
+
+        for data in seeds() {
+            let expr = Expr::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            assert_eq!(expr.span().start, 0);
+        }
+    }
+
+    #[test]
+    fn test_generated_values() {
+        let mut kinds = std::collections::HashSet::new();
+        for data in seeds() {
+            let value = Value::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            kinds.insert(value.type_name());
+        }
+        assert_eq!(kinds.len(), 4);
+    }
+}
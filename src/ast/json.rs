@@ -0,0 +1,722 @@
+//! Versioned JSON interchange format for the AST
+//!
+//! The format is independent of the in-memory AST, so it stays stable
+//! while the AST evolves. Any change that older readers could not handle
+//! bumps `FORMAT_VERSION`.
+//!
+//! # Format version 5
+//!
+//! Version 2 added custom operators, version 3 postfix expressions and
+//! version 4 the `**` operator and `power` precedence. Older documents are
+//! read the same way, since they simply contain none of these. Version 5
+//! writes span ends as plain byte offsets; the position objects of older
+//! versions are still read, keeping only their offset.
+//!
+//! A document is an object with the version and the program:
+//!
+//! ```json
+//! {
+//!   "format_version": 5,
+//!   "program": {
+//!     "statements": [{ "type": "expr", "expr": EXPR, "span": SPAN }],
+//!     "span": SPAN
+//!   }
+//! }
+//! ```
+//!
+//! `SPAN` is `{ "start": 0, "end": 3 }`: byte offsets, with the end
+//! exclusive. Lines and columns can be recovered from the source text with
+//! a `LineIndex`. Spans do not record their `SourceId`, which
+//! only means something within one `SourceMap`; they are read back with
+//! the default id.
+//!
+//! `EXPR` is an object tagged by `"type"`, always with a `"span"`:
+//!
+//! | `type`    | Other fields                                   |
+//! |-----------|------------------------------------------------|
+//! | `int`     | `value`: 32-bit signed integer                 |
+//! | `float`   | `value`: number                                |
+//! | `bool`    | `value`: boolean                               |
+//! | `infix`   | `op`, `left`: EXPR, `right`: EXPR              |
+//! | `grouped` | `inner`: EXPR                                  |
+//! | `unary`   | `op`, `operand`: EXPR                          |
+//! | `postfix` | `op`, `operand`: EXPR                          |
+//!
+//! Operators are written as their source symbols: `+ - * / ** && || == !=
+//! < > <= >=` for `infix`, and `+ - !` for `unary`.
+//!
+//! A custom operator is written as an object instead. For `infix` it is
+//! `{ "custom": { "symbol": "<=>", "precedence": PREC, "associativity":
+//! "left" } }`, where `PREC` is one of `logical_or`, `logical_and`,
+//! `comparison`, `sum`, `product`, `unary` or `power` and the
+//! associativity is `left` or `right`. For `unary` and `postfix` it is `{ "custom": {
+//! "symbol": "%%" } }`; only custom operators are postfix so far.
+
+use super::{BinaryOp, Expr, PostfixOp, Program, Statement, UnaryOp};
+use crate::operators::{Associativity, CustomOp};
+use crate::parser::Precedence;
+use crate::span::Span;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// Version written by `to_json` and the newest version `from_json` accepts
+pub const FORMAT_VERSION: u32 = 5;
+
+/// Errors from reading a JSON AST document
+#[derive(Debug)]
+pub enum JsonError {
+    /// The document is not valid JSON or does not match the schema
+    InvalidDocument(serde_json::Error),
+    /// The document was written by a newer, incompatible format version
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::InvalidDocument(err) => write!(f, "Invalid JSON AST: {err}"),
+            JsonError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported JSON AST format version {version} (newest supported is {FORMAT_VERSION})"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for JsonError {}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonError::InvalidDocument(err)
+    }
+}
+
+/// Serialize a program as a JSON document in the current format version
+pub fn to_json(program: &Program) -> String {
+    let document = Document {
+        format_version: FORMAT_VERSION,
+        program: WireProgram::from(program),
+    };
+    serde_json::to_string(&document).expect("AST documents always serialize")
+}
+
+/// Read a program from a JSON document
+pub fn from_json(input: &str) -> Result<Program, JsonError> {
+    // Check the version before the schema, so documents from newer
+    // releases are reported as such rather than as schema mismatches
+    let header: Header = serde_json::from_str(input)?;
+    if header.format_version == 0 || header.format_version > FORMAT_VERSION {
+        return Err(JsonError::UnsupportedVersion(header.format_version));
+    }
+
+    let document: Document = serde_json::from_str(input)?;
+    Ok(document.program.into())
+}
+
+#[derive(Deserialize)]
+struct Header {
+    format_version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    format_version: u32,
+    program: WireProgram,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireProgram {
+    statements: Vec<WireStatement>,
+    span: WireSpan,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireStatement {
+    Expr { expr: WireExpr, span: WireSpan },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireExpr {
+    Int {
+        value: i32,
+        span: WireSpan,
+    },
+    Float {
+        value: f64,
+        span: WireSpan,
+    },
+    Bool {
+        value: bool,
+        span: WireSpan,
+    },
+    Infix {
+        op: WireBinaryOp,
+        left: Box<WireExpr>,
+        right: Box<WireExpr>,
+        span: WireSpan,
+    },
+    Grouped {
+        inner: Box<WireExpr>,
+        span: WireSpan,
+    },
+    Unary {
+        op: WireUnaryOp,
+        operand: Box<WireExpr>,
+        span: WireSpan,
+    },
+    Postfix {
+        op: WirePostfixOp,
+        operand: Box<WireExpr>,
+        span: WireSpan,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum WireBinaryOp {
+    #[serde(rename = "+")]
+    Plus,
+    #[serde(rename = "-")]
+    Minus,
+    #[serde(rename = "*")]
+    Multiply,
+    #[serde(rename = "/")]
+    Divide,
+    #[serde(rename = "**")]
+    Power,
+    #[serde(rename = "&&")]
+    LogicalAnd,
+    #[serde(rename = "||")]
+    LogicalOr,
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = "<=")]
+    LessEqual,
+    #[serde(rename = ">=")]
+    GreaterEqual,
+    #[serde(rename = "custom")]
+    Custom(WireCustomInfix),
+}
+
+#[derive(Serialize, Deserialize)]
+enum WireUnaryOp {
+    #[serde(rename = "+")]
+    Plus,
+    #[serde(rename = "-")]
+    Minus,
+    #[serde(rename = "!")]
+    LogicalNot,
+    #[serde(rename = "custom")]
+    Custom(WireCustomUnary),
+}
+
+#[derive(Serialize, Deserialize)]
+enum WirePostfixOp {
+    #[serde(rename = "custom")]
+    Custom(WireCustomUnary),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WireCustomInfix {
+    symbol: String,
+    precedence: WirePrecedence,
+    associativity: WireAssociativity,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WireCustomUnary {
+    symbol: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WirePrecedence {
+    LogicalOr,
+    LogicalAnd,
+    Comparison,
+    Sum,
+    Product,
+    Unary,
+    Power,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WireAssociativity {
+    Left,
+    Right,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireSpan {
+    start: WireOffset,
+    end: WireOffset,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum WireOffset {
+    Offset(usize),
+    /// Written before version 5, with a line and column as well
+    Position {
+        offset: usize,
+    },
+}
+
+impl From<&Program> for WireProgram {
+    fn from(program: &Program) -> Self {
+        WireProgram {
+            statements: program.statements.iter().map(WireStatement::from).collect(),
+            span: program.span.into(),
+        }
+    }
+}
+
+impl From<WireProgram> for Program {
+    fn from(program: WireProgram) -> Self {
+        Program {
+            statements: program
+                .statements
+                .into_iter()
+                .map(Statement::from)
+                .collect(),
+            span: program.span.into(),
+        }
+    }
+}
+
+impl From<&Statement> for WireStatement {
+    fn from(stmt: &Statement) -> Self {
+        match stmt {
+            Statement::ExprStatement { expr, span } => WireStatement::Expr {
+                expr: expr.into(),
+                span: (*span).into(),
+            },
+        }
+    }
+}
+
+impl From<WireStatement> for Statement {
+    fn from(stmt: WireStatement) -> Self {
+        match stmt {
+            WireStatement::Expr { expr, span } => Statement::ExprStatement {
+                expr: expr.into(),
+                span: span.into(),
+            },
+        }
+    }
+}
+
+impl From<&Expr> for WireExpr {
+    fn from(expr: &Expr) -> Self {
+        match expr {
+            Expr::Int { value, span } => WireExpr::Int {
+                value: *value,
+                span: (*span).into(),
+            },
+            Expr::Float { value, span } => WireExpr::Float {
+                value: *value,
+                span: (*span).into(),
+            },
+            Expr::Bool { value, span } => WireExpr::Bool {
+                value: *value,
+                span: (*span).into(),
+            },
+            Expr::InfixExpr {
+                left,
+                op,
+                right,
+                span,
+            } => WireExpr::Infix {
+                op: (*op).into(),
+                left: Box::new(left.as_ref().into()),
+                right: Box::new(right.as_ref().into()),
+                span: (*span).into(),
+            },
+            Expr::Grouped { inner, span } => WireExpr::Grouped {
+                inner: Box::new(inner.as_ref().into()),
+                span: (*span).into(),
+            },
+            Expr::UnaryExpr { op, operand, span } => WireExpr::Unary {
+                op: (*op).into(),
+                operand: Box::new(operand.as_ref().into()),
+                span: (*span).into(),
+            },
+            Expr::PostfixExpr { op, operand, span } => WireExpr::Postfix {
+                op: (*op).into(),
+                operand: Box::new(operand.as_ref().into()),
+                span: (*span).into(),
+            },
+        }
+    }
+}
+
+impl From<WireExpr> for Expr {
+    fn from(expr: WireExpr) -> Self {
+        match expr {
+            WireExpr::Int { value, span } => Expr::Int {
+                value,
+                span: span.into(),
+            },
+            WireExpr::Float { value, span } => Expr::Float {
+                value,
+                span: span.into(),
+            },
+            WireExpr::Bool { value, span } => Expr::Bool {
+                value,
+                span: span.into(),
+            },
+            WireExpr::Infix {
+                op,
+                left,
+                right,
+                span,
+            } => Expr::InfixExpr {
+                left: Box::new((*left).into()),
+                op: op.into(),
+                right: Box::new((*right).into()),
+                span: span.into(),
+            },
+            WireExpr::Grouped { inner, span } => Expr::Grouped {
+                inner: Box::new((*inner).into()),
+                span: span.into(),
+            },
+            WireExpr::Unary { op, operand, span } => Expr::UnaryExpr {
+                op: op.into(),
+                operand: Box::new((*operand).into()),
+                span: span.into(),
+            },
+            WireExpr::Postfix { op, operand, span } => Expr::PostfixExpr {
+                op: op.into(),
+                operand: Box::new((*operand).into()),
+                span: span.into(),
+            },
+        }
+    }
+}
+
+impl From<BinaryOp> for WireBinaryOp {
+    fn from(op: BinaryOp) -> Self {
+        match op {
+            BinaryOp::Plus => WireBinaryOp::Plus,
+            BinaryOp::Minus => WireBinaryOp::Minus,
+            BinaryOp::Multiply => WireBinaryOp::Multiply,
+            BinaryOp::Divide => WireBinaryOp::Divide,
+            BinaryOp::Power => WireBinaryOp::Power,
+            BinaryOp::LogicalAnd => WireBinaryOp::LogicalAnd,
+            BinaryOp::LogicalOr => WireBinaryOp::LogicalOr,
+            BinaryOp::Equal => WireBinaryOp::Equal,
+            BinaryOp::NotEqual => WireBinaryOp::NotEqual,
+            BinaryOp::Less => WireBinaryOp::Less,
+            BinaryOp::Greater => WireBinaryOp::Greater,
+            BinaryOp::LessEqual => WireBinaryOp::LessEqual,
+            BinaryOp::GreaterEqual => WireBinaryOp::GreaterEqual,
+            BinaryOp::Custom(op) => WireBinaryOp::Custom(WireCustomInfix {
+                symbol: op.symbol.to_string(),
+                precedence: op.precedence.into(),
+                associativity: op.associativity.into(),
+            }),
+        }
+    }
+}
+
+impl From<WireBinaryOp> for BinaryOp {
+    fn from(op: WireBinaryOp) -> Self {
+        match op {
+            WireBinaryOp::Plus => BinaryOp::Plus,
+            WireBinaryOp::Minus => BinaryOp::Minus,
+            WireBinaryOp::Multiply => BinaryOp::Multiply,
+            WireBinaryOp::Divide => BinaryOp::Divide,
+            WireBinaryOp::Power => BinaryOp::Power,
+            WireBinaryOp::LogicalAnd => BinaryOp::LogicalAnd,
+            WireBinaryOp::LogicalOr => BinaryOp::LogicalOr,
+            WireBinaryOp::Equal => BinaryOp::Equal,
+            WireBinaryOp::NotEqual => BinaryOp::NotEqual,
+            WireBinaryOp::Less => BinaryOp::Less,
+            WireBinaryOp::Greater => BinaryOp::Greater,
+            WireBinaryOp::LessEqual => BinaryOp::LessEqual,
+            WireBinaryOp::GreaterEqual => BinaryOp::GreaterEqual,
+            WireBinaryOp::Custom(op) => BinaryOp::Custom(CustomOp::new(
+                &op.symbol,
+                op.precedence.into(),
+                op.associativity.into(),
+            )),
+        }
+    }
+}
+
+impl From<UnaryOp> for WireUnaryOp {
+    fn from(op: UnaryOp) -> Self {
+        match op {
+            UnaryOp::Plus => WireUnaryOp::Plus,
+            UnaryOp::Minus => WireUnaryOp::Minus,
+            UnaryOp::LogicalNot => WireUnaryOp::LogicalNot,
+            UnaryOp::Custom(op) => WireUnaryOp::Custom(WireCustomUnary {
+                symbol: op.symbol.to_string(),
+            }),
+        }
+    }
+}
+
+impl From<WireUnaryOp> for UnaryOp {
+    fn from(op: WireUnaryOp) -> Self {
+        match op {
+            WireUnaryOp::Plus => UnaryOp::Plus,
+            WireUnaryOp::Minus => UnaryOp::Minus,
+            WireUnaryOp::LogicalNot => UnaryOp::LogicalNot,
+            WireUnaryOp::Custom(op) => UnaryOp::Custom(CustomOp::new(
+                &op.symbol,
+                Precedence::Unary,
+                Associativity::Right,
+            )),
+        }
+    }
+}
+
+impl From<PostfixOp> for WirePostfixOp {
+    fn from(op: PostfixOp) -> Self {
+        match op {
+            PostfixOp::Custom(op) => WirePostfixOp::Custom(WireCustomUnary {
+                symbol: op.symbol.to_string(),
+            }),
+        }
+    }
+}
+
+impl From<WirePostfixOp> for PostfixOp {
+    fn from(op: WirePostfixOp) -> Self {
+        match op {
+            WirePostfixOp::Custom(op) => PostfixOp::Custom(CustomOp::new(
+                &op.symbol,
+                Precedence::Group,
+                Associativity::Left,
+            )),
+        }
+    }
+}
+
+impl From<Precedence> for WirePrecedence {
+    fn from(precedence: Precedence) -> Self {
+        match precedence {
+            Precedence::LogicalOr => WirePrecedence::LogicalOr,
+            Precedence::LogicalAnd => WirePrecedence::LogicalAnd,
+            Precedence::Comparison => WirePrecedence::Comparison,
+            Precedence::Sum => WirePrecedence::Sum,
+            Precedence::Product => WirePrecedence::Product,
+            Precedence::Power => WirePrecedence::Power,
+            // Registration rejects the rest for infix operators
+            Precedence::Unary | Precedence::Lowest | Precedence::Group => WirePrecedence::Unary,
+        }
+    }
+}
+
+impl From<WirePrecedence> for Precedence {
+    fn from(precedence: WirePrecedence) -> Self {
+        match precedence {
+            WirePrecedence::LogicalOr => Precedence::LogicalOr,
+            WirePrecedence::LogicalAnd => Precedence::LogicalAnd,
+            WirePrecedence::Comparison => Precedence::Comparison,
+            WirePrecedence::Sum => Precedence::Sum,
+            WirePrecedence::Product => Precedence::Product,
+            WirePrecedence::Unary => Precedence::Unary,
+            WirePrecedence::Power => Precedence::Power,
+        }
+    }
+}
+
+impl From<Associativity> for WireAssociativity {
+    fn from(associativity: Associativity) -> Self {
+        match associativity {
+            Associativity::Left => WireAssociativity::Left,
+            Associativity::Right => WireAssociativity::Right,
+        }
+    }
+}
+
+impl From<WireAssociativity> for Associativity {
+    fn from(associativity: WireAssociativity) -> Self {
+        match associativity {
+            WireAssociativity::Left => Associativity::Left,
+            WireAssociativity::Right => Associativity::Right,
+        }
+    }
+}
+
+impl From<Span> for WireSpan {
+    fn from(span: Span) -> Self {
+        WireSpan {
+            start: span.start.into(),
+            end: span.end.into(),
+        }
+    }
+}
+
+impl From<WireSpan> for Span {
+    fn from(span: WireSpan) -> Self {
+        Span::new(span.start.into(), span.end.into())
+    }
+}
+
+impl From<usize> for WireOffset {
+    fn from(offset: usize) -> Self {
+        WireOffset::Offset(offset)
+    }
+}
+
+impl From<WireOffset> for usize {
+    fn from(offset: WireOffset) -> Self {
+        match offset {
+            WireOffset::Offset(offset) | WireOffset::Position { offset } => offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_util::parse;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_round_trip() {
+        let program = parse("1 + 2.5 * 3; !(true || false); -4 <= 2 ** -1");
+        assert_eq!(from_json(&to_json(&program)).unwrap(), program);
+    }
+
+    #[test]
+    fn test_document_layout() {
+        let document: Value = serde_json::from_str(&to_json(&parse("-1 >= 2"))).unwrap();
+        let span = |start: usize, end: usize| json!({ "start": start, "end": end });
+
+        assert_eq!(
+            document,
+            json!({
+                "format_version": 5,
+                "program": {
+                    "statements": [{
+                        "type": "expr",
+                        "expr": {
+                            "type": "infix",
+                            "op": ">=",
+                            "left": {
+                                "type": "unary",
+                                "op": "-",
+                                "operand": { "type": "int", "value": 1, "span": span(1, 2) },
+                                "span": span(0, 2),
+                            },
+                            "right": { "type": "int", "value": 2, "span": span(6, 7) },
+                            "span": span(0, 7),
+                        },
+                        "span": span(0, 7),
+                    }],
+                    "span": span(0, 7),
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejects_newer_versions() {
+        let mut document: Value = serde_json::from_str(&to_json(&parse("1"))).unwrap();
+        document["format_version"] = json!(FORMAT_VERSION + 1);
+        assert!(matches!(
+            from_json(&document.to_string()),
+            Err(JsonError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_rejects_schema_mismatch() {
+        let input = r#"{"format_version": 1, "program": {"statements": [{"type": "loop"}]}}"#;
+        assert!(matches!(
+            from_json(input),
+            Err(JsonError::InvalidDocument(_))
+        ));
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let spaceship = CustomOp::new("<=>", Precedence::Comparison, Associativity::Left);
+        let percent = CustomOp::new("%%", Precedence::Unary, Associativity::Right);
+        let program = Program::new(vec![Statement::ExprStatement {
+            expr: Expr::InfixExpr {
+                left: Box::new(Expr::UnaryExpr {
+                    op: UnaryOp::Custom(percent),
+                    operand: Box::new(Expr::int(1)),
+                    span: Span::single(0),
+                }),
+                op: BinaryOp::Custom(spaceship),
+                right: Box::new(Expr::int(2)),
+                span: Span::single(0),
+            },
+            span: Span::single(0),
+        }]);
+
+        let json = to_json(&program);
+        let document: Value = serde_json::from_str(&json).unwrap();
+        let expr = &document["program"]["statements"][0]["expr"];
+        assert_eq!(
+            expr["op"],
+            json!({ "custom": { "symbol": "<=>", "precedence": "comparison", "associativity": "left" } })
+        );
+        assert_eq!(expr["left"]["op"], json!({ "custom": { "symbol": "%%" } }));
+        assert_eq!(from_json(&json).unwrap(), program);
+    }
+
+    #[test]
+    fn test_postfix_operators() {
+        let bang = CustomOp::new("!!", Precedence::Group, Associativity::Left);
+        let program = Program::new(vec![Statement::ExprStatement {
+            expr: Expr::PostfixExpr {
+                op: PostfixOp::Custom(bang),
+                operand: Box::new(Expr::int(3)),
+                span: Span::single(0),
+            },
+            span: Span::single(0),
+        }]);
+
+        let json = to_json(&program);
+        let document: Value = serde_json::from_str(&json).unwrap();
+        let expr = &document["program"]["statements"][0]["expr"];
+        assert_eq!(expr["type"], json!("postfix"));
+        assert_eq!(expr["op"], json!({ "custom": { "symbol": "!!" } }));
+        assert_eq!(from_json(&json).unwrap(), program);
+    }
+
+    #[test]
+    fn test_reads_version_1() {
+        let mut document: Value = serde_json::from_str(&to_json(&parse("1 + 2"))).unwrap();
+        document["format_version"] = json!(1);
+        assert_eq!(from_json(&document.to_string()).unwrap(), parse("1 + 2"));
+    }
+
+    #[test]
+    fn test_reads_position_objects() {
+        let span = json!({
+            "start": { "offset": 0, "line": 1, "column": 1 },
+            "end": { "offset": 1, "line": 1, "column": 2 },
+        });
+        let document = json!({
+            "format_version": 4,
+            "program": {
+                "statements": [{
+                    "type": "expr",
+                    "expr": { "type": "int", "value": 1, "span": span },
+                    "span": span,
+                }],
+                "span": span,
+            }
+        });
+        assert_eq!(from_json(&document.to_string()).unwrap(), parse("1"));
+    }
+}
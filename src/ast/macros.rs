@@ -0,0 +1,101 @@
+//! The `soba_ast!` macro for writing expressions in Rust code
+
+use super::fold::{walk_expr, Folder};
+use super::Expr;
+use crate::span::Span;
+
+/// Build an `Expr` from Soba source written inline
+///
+/// The tokens are parsed with the real parser, so precedence and
+/// associativity match the language exactly. Every span in the result is
+/// `Span::single(0)`, like those of `Expr::int`, so the tree compares equal
+/// to hand-built ones. Panics if the input is not a single expression.
+///
+/// ```
+/// use soba::{soba_ast, BinaryOp, Expr};
+///
+/// let expr = soba_ast!(1 + 2 * 3);
+/// assert!(matches!(expr, Expr::InfixExpr { op: BinaryOp::Plus, .. }));
+/// assert_eq!(soba_ast!(7), Expr::int(7));
+/// ```
+#[macro_export]
+macro_rules! soba_ast {
+    ($($tokens:tt)+) => {
+        $crate::ast::macros::parse_synthetic(stringify!($($tokens)+))
+    };
+}
+
+/// Parse `input` and replace every span with `Span::single(0)`
+///
+/// Support for `soba_ast!`; not meant to be called directly.
+#[doc(hidden)]
+pub fn parse_synthetic(input: &str) -> Expr {
+    let expr = match crate::parse_expr_string(input) {
+        Ok(expr) => expr,
+        Err(err) => panic!("soba_ast!({input}) is not a valid expression: {err}"),
+    };
+    SyntheticSpans.fold_expr(expr)
+}
+
+struct SyntheticSpans;
+
+impl Folder for SyntheticSpans {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let mut expr = walk_expr(self, expr);
+        match &mut expr {
+            Expr::Int { span, .. }
+            | Expr::Float { span, .. }
+            | Expr::Bool { span, .. }
+            | Expr::InfixExpr { span, .. }
+            | Expr::Grouped { span, .. }
+            | Expr::UnaryExpr { span, .. }
+            | Expr::PostfixExpr { span, .. } => *span = Span::single(0),
+        }
+        expr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{BinaryOp, Expr, UnaryOp};
+    use crate::span::Span;
+
+    #[test]
+    fn test_builds_expressions() {
+        assert_eq!(soba_ast!(42), Expr::int(42));
+        assert_eq!(soba_ast!(true), Expr::bool(true));
+        assert_eq!(
+            soba_ast!(1 + 2.5),
+            Expr::InfixExpr {
+                left: Box::new(Expr::int(1)),
+                op: BinaryOp::Plus,
+                right: Box::new(Expr::float(2.5)),
+                span: Span::single(0),
+            }
+        );
+        assert_eq!(
+            soba_ast!(-(7)),
+            Expr::UnaryExpr {
+                op: UnaryOp::Minus,
+                operand: Box::new(Expr::Grouped {
+                    inner: Box::new(Expr::int(7)),
+                    span: Span::single(0),
+                }),
+                span: Span::single(0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_operators_survive_stringify() {
+        let source = "1 ** 2 && 3 <= 4 || 5 != 6 == !false";
+        let parsed = crate::parse_expr_string(source).unwrap();
+        assert!(soba_ast!(1 ** 2 && 3 <= 4 || 5 != 6 == !false).eq_ignoring_spans(&parsed));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a valid expression")]
+    fn test_invalid_input_panics() {
+        soba_ast!(1 +);
+    }
+}
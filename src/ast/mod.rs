@@ -2,8 +2,22 @@
 //!
 //! This module contains all AST node definitions and related utilities.
 
+pub mod arena;
+pub mod dump;
 pub mod expr;
+pub mod fold;
+#[cfg(feature = "arbitrary")]
+pub mod generate;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod macros;
+pub mod printer;
 pub mod stmt;
+pub mod visit;
 
-pub use expr::{BinaryOp, Expr, UnaryOp};
+pub use arena::{ArenaExpr, ArenaProgram, ExprArena, ExprId, SideTable};
+pub use expr::{BinaryOp, Expr, PostfixOp, UnaryOp};
+pub use fold::Folder;
+pub use printer::{check_round_trip, check_round_trip_with_operators, RoundTripError};
 pub use stmt::{Program, Statement};
+pub use visit::Visitor;
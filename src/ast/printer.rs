@@ -0,0 +1,363 @@
+//! Printing the AST back to soba source
+//!
+//! The output is canonical: single spaces around binary operators, no space
+//! after prefix operators, one statement per line, and parentheses only
+//! where the precedence table requires them. Parsing the output yields an
+//! equivalent program, so `to_source` is idempotent.
+//!
+//! `check_round_trip` tests that guarantee for a given program, and the
+//! golden files in `testdata/printer` pin the exact output.
+
+use super::fold::{walk_expr, Folder};
+use super::{Expr, Program, Statement};
+use crate::error::ParseError;
+use crate::lexer::SobaLexer;
+use crate::operators::{Associativity, OperatorRegistry};
+use crate::parser::{InfixOperator, Parser, PostfixOperator, Precedence};
+use crate::prelude::*;
+use core::fmt;
+
+impl Expr {
+    /// Render the expression as canonical soba source
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        write_expr(self, Precedence::Lowest, &mut out);
+        out
+    }
+}
+
+impl Statement {
+    /// Render the statement as canonical soba source, including its `;`
+    pub fn to_source(&self) -> String {
+        match self {
+            Statement::ExprStatement { expr, .. } => format!("{};", expr.to_source()),
+        }
+    }
+}
+
+impl Program {
+    /// Render the program as canonical soba source
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        for stmt in &self.statements {
+            out.push_str(&stmt.to_source());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Why a program did not survive printing and parsing again
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoundTripError {
+    /// The printed source does not parse
+    Unparsable { source: String, error: ParseError },
+    /// The printed source parses into a different program
+    Changed { source: String, reparsed: Program },
+}
+
+impl fmt::Display for RoundTripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundTripError::Unparsable { source, error } => {
+                write!(f, "Printed source does not parse ({error}):\n{source}")
+            }
+            RoundTripError::Changed { source, .. } => {
+                write!(
+                    f,
+                    "Printed source parses into a different program:\n{source}"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for RoundTripError {}
+
+/// Check that parsing `program.to_source()` gives back `program`
+///
+/// Spans are ignored, and so are parentheses, which the printer re-adds
+/// only where precedence needs them. Programs from the parser always pass;
+/// a failure for one built or rewritten in code means it has no source
+/// form, such as a negative `Expr::Int` other than `i32::MIN`.
+pub fn check_round_trip(program: &Program) -> Result<(), RoundTripError> {
+    check_round_trip_with_operators(program, &OperatorRegistry::new())
+}
+
+/// Like `check_round_trip`, parsing with the custom operators in `operators`
+pub fn check_round_trip_with_operators(
+    program: &Program,
+    operators: &OperatorRegistry,
+) -> Result<(), RoundTripError> {
+    let source = program.to_source();
+    let lexer = SobaLexer::with_operators(source.chars().collect(), operators);
+    let reparsed = match Parser::with_operators(lexer, operators)
+        .and_then(|mut parser| parser.parse_program())
+    {
+        Ok(reparsed) => reparsed,
+        Err(error) => return Err(RoundTripError::Unparsable { source, error }),
+    };
+
+    if without_groups(program.clone()).eq_ignoring_spans(&without_groups(reparsed.clone())) {
+        Ok(())
+    } else {
+        Err(RoundTripError::Changed { source, reparsed })
+    }
+}
+
+fn without_groups(program: Program) -> Program {
+    struct Ungroup;
+
+    impl Folder for Ungroup {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            match expr {
+                Expr::Grouped { inner, .. } => self.fold_expr(*inner),
+                expr => walk_expr(self, expr),
+            }
+        }
+    }
+
+    Ungroup.fold_program(program)
+}
+
+/// Write `expr`, parenthesizing it if it binds looser than `min_precedence`
+fn write_expr(expr: &Expr, min_precedence: Precedence, out: &mut String) {
+    match expr {
+        // Only `i32::MIN` comes negative from the parser, which reads
+        // `-2147483648` as one literal; like a prefix minus, it needs
+        // parentheses under a postfix operator or on the left of `**`
+        Expr::Int { value, .. } if *value < 0 && Precedence::Unary < min_precedence => {
+            out.push_str(&format!("({value})"))
+        }
+        Expr::Int { value, .. } => out.push_str(&value.to_string()),
+        Expr::Float { value, .. } => out.push_str(&float_source(*value)),
+        Expr::Bool { value, .. } => out.push_str(&value.to_string()),
+        // Source parentheses are dropped; they are re-added only where needed
+        Expr::Grouped { inner, .. } => write_expr(inner, min_precedence, out),
+        Expr::UnaryExpr { op, operand, .. } => {
+            // Only a postfix operand needs these: `(-x)!` is not `-x!`
+            let needs_parens = Precedence::Unary < min_precedence;
+            if needs_parens {
+                out.push('(');
+            }
+            out.push_str(&op.to_string());
+            write_expr(operand, Precedence::Unary, out);
+            if needs_parens {
+                out.push(')');
+            }
+        }
+        Expr::PostfixExpr { op, operand, .. } => {
+            // Postfix operators bind tightest, so never need parentheses
+            write_expr(operand, PostfixOperator::of(*op).precedence, out);
+            out.push_str(&op.to_string());
+        }
+        Expr::InfixExpr {
+            left, op, right, ..
+        } => {
+            let operator = InfixOperator::of(*op);
+            let precedence = operator.precedence;
+            let needs_parens = precedence < min_precedence;
+            if needs_parens {
+                out.push('(');
+            }
+            // The operand on the side the operator does not group to must
+            // keep its parentheses at equal precedence
+            let (left_min, right_min) = match operator.associativity {
+                Associativity::Left => (precedence, precedence.next_tighter()),
+                Associativity::Right => (precedence.next_tighter(), precedence),
+            };
+            write_expr(left, left_min, out);
+            out.push_str(&format!(" {op} "));
+            write_expr(right, right_min, out);
+            if needs_parens {
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// Format a float so that it lexes back as a float literal
+fn float_source(value: f64) -> String {
+    // `Display` for f64 never uses exponent notation, but drops `.0`
+    let mut text = value.to_string();
+    if !text.contains('.') {
+        text.push_str(".0");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BinaryOp, Program};
+    use crate::evaluator::eval_program;
+    use crate::lexer::SobaLexer;
+    use crate::parser::test_util::parse;
+    use crate::parser::Parser;
+
+    fn canonical(input: &str) -> String {
+        parse(input).to_source()
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        assert_eq!(canonical("(1 + 2) * 3"), "(1 + 2) * 3;\n");
+        assert_eq!(canonical("1 + (2 * 3)"), "1 + 2 * 3;\n");
+        assert_eq!(canonical("(1 - 2) - 3"), "1 - 2 - 3;\n");
+        assert_eq!(canonical("1 - (2 - 3)"), "1 - (2 - 3);\n");
+        assert_eq!(canonical("((true))"), "true;\n");
+        assert_eq!(
+            canonical("true || (false && true)"),
+            "true || false && true;\n"
+        );
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        assert_eq!(canonical("- (1 + 2)"), "-(1 + 2);\n");
+        assert_eq!(canonical("-(-(5))"), "--5;\n");
+        assert_eq!(canonical("!(1 < 2)"), "!(1 < 2);\n");
+        assert_eq!(canonical("(-1) * 2"), "-1 * 2;\n");
+        assert_eq!(canonical("(-2) ** 2"), "(-2) ** 2;\n");
+        assert_eq!(canonical("-(2 ** 2)"), "-2 ** 2;\n");
+        assert_eq!(canonical("2 ** (3 ** 2)"), "2 ** 3 ** 2;\n");
+        assert_eq!(canonical("(2 ** 3) ** 2"), "(2 ** 3) ** 2;\n");
+        assert_eq!(canonical("-2147483648"), "-2147483648;\n");
+        assert_eq!(canonical("(-2147483648) ** 2"), "(-2147483648) ** 2;\n");
+    }
+
+    #[test]
+    fn test_literals_and_statements() {
+        assert_eq!(canonical("2.0;  .5; 10"), "2.0;\n0.5;\n10;\n");
+        assert_eq!(canonical(""), "");
+    }
+
+    #[test]
+    fn test_round_trip_preserves_meaning() {
+        for input in [
+            "1 + 2 * 3 - 4 / 2",
+            "(1 < 2) == (3 >= 4)",
+            "-(2 - 7) * -(1.5)",
+            "!false && (true || false)",
+            "8 / (4 / 2)",
+            "(-2) ** 3 ** (4 / 2)",
+        ] {
+            let program = parse(input);
+            let source = program.to_source();
+            let reparsed = parse(&source);
+            assert_eq!(reparsed.to_source(), source, "input: {input}");
+            assert_eq!(
+                eval_program(&reparsed).unwrap(),
+                eval_program(&program).unwrap(),
+                "input: {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_round_trip() {
+        for input in [
+            "1 + 2 * 3 - 4 / 2; (1 < 2) == (3 >= 4)",
+            "-(2 - 7) * -(1.5); !!(((true)))",
+            "(-2) ** 3 ** (4 / 2); 1.0 / 3.0",
+            "-2147483648; 1 - -2147483648; (-2147483648) ** 2",
+        ] {
+            assert_eq!(check_round_trip(&parse(input)), Ok(()), "input: {input}");
+        }
+
+        // A negative literal prints as a prefix minus
+        let negative = Program::new(vec![Statement::expr_statement(Expr::int(-1))]);
+        assert!(matches!(
+            check_round_trip(&negative),
+            Err(RoundTripError::Changed { source, .. }) if source == "-1;\n"
+        ));
+
+        // So does a negative float, inside a power where that changes meaning
+        let power = Program::new(vec![Statement::expr_statement(Expr::InfixExpr {
+            left: Box::new(Expr::float(-2.0)),
+            op: BinaryOp::Power,
+            right: Box::new(Expr::int(2)),
+            span: crate::span::Span::single(0),
+        })]);
+        assert!(matches!(
+            check_round_trip(&power),
+            Err(RoundTripError::Changed { .. })
+        ));
+    }
+
+    /// Each `testdata/printer/<name>.soba` must print exactly as
+    /// `<name>.printed`; set `SOBA_BLESS=1` to rewrite the expected files
+    #[test]
+    fn test_golden_files() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/printer");
+        let bless = std::env::var_os("SOBA_BLESS").is_some();
+        let mut checked = 0;
+
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "soba") {
+                continue;
+            }
+            let program = parse(&std::fs::read_to_string(&path).unwrap());
+            let printed = program.to_source();
+            let expected_path = path.with_extension("printed");
+
+            if bless {
+                std::fs::write(&expected_path, &printed).unwrap();
+            } else {
+                let expected = std::fs::read_to_string(&expected_path).unwrap_or_default();
+                assert_eq!(printed, expected, "{}", path.display());
+            }
+            assert_eq!(check_round_trip(&program), Ok(()), "{}", path.display());
+            checked += 1;
+        }
+        assert!(checked > 0, "no golden files in {}", dir.display());
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        use crate::operators::{Associativity, OperatorRegistry};
+        use crate::parser::Precedence;
+
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix("^^", Precedence::Product, Associativity::Right, |l, _| {
+                Ok(l)
+            })
+            .unwrap();
+        operators
+            .register_infix("<>", Precedence::Sum, Associativity::Left, |l, _| Ok(l))
+            .unwrap();
+        operators.register_postfix("!!", Ok).unwrap();
+        let canonical = |input: &str| {
+            let lexer = SobaLexer::with_operators(input.chars().collect(), &operators);
+            Parser::with_operators(lexer, &operators)
+                .unwrap()
+                .parse_program()
+                .unwrap()
+                .to_source()
+        };
+
+        assert_eq!(canonical("1 ^^ (2 ^^ 3)"), "1 ^^ 2 ^^ 3;\n");
+        assert_eq!(canonical("(1 ^^ 2) ^^ 3"), "(1 ^^ 2) ^^ 3;\n");
+        assert_eq!(canonical("(1 <> 2) <> 3"), "1 <> 2 <> 3;\n");
+        assert_eq!(canonical("1 <> (2 + 3)"), "1 <> (2 + 3);\n");
+        assert_eq!(canonical("-(2!!)"), "-2!!;\n");
+        assert_eq!(canonical("(-2)!!"), "(-2)!!;\n");
+        assert_eq!(canonical("(1 ^^ 2)!!"), "(1 ^^ 2)!!;\n");
+
+        let lexer = SobaLexer::with_operators("(1 <> 2)!! ^^ 3".chars().collect(), &operators);
+        let program = Parser::with_operators(lexer, &operators)
+            .unwrap()
+            .parse_program()
+            .unwrap();
+        assert_eq!(
+            check_round_trip_with_operators(&program, &operators),
+            Ok(())
+        );
+        assert!(matches!(
+            check_round_trip(&program),
+            Err(RoundTripError::Unparsable { .. })
+        ));
+    }
+}
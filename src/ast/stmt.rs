@@ -1,10 +1,12 @@
 //! Statement and Program AST definitions
 
 use crate::ast::Expr;
+use crate::prelude::*;
 use crate::span::Span;
 
 /// A statement in the program
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Statement {
     /// Expression statement (expression followed by semicolon)
     ExprStatement { expr: Expr, span: Span },
@@ -12,6 +14,7 @@ pub enum Statement {
 
 /// A program is a sequence of statements
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
     pub statements: Vec<Statement>,
     pub span: Span,
@@ -25,6 +28,16 @@ impl Statement {
         }
     }
 
+    /// Whether two statements have the same structure, whatever their spans
+    pub fn eq_ignoring_spans(&self, other: &Statement) -> bool {
+        match (self, other) {
+            (
+                Statement::ExprStatement { expr: a, .. },
+                Statement::ExprStatement { expr: b, .. },
+            ) => a.eq_ignoring_spans(b),
+        }
+    }
+
     /// Create a simple expression statement without span
     pub fn expr_statement(expr: Expr) -> Self {
         Statement::ExprStatement {
@@ -38,21 +51,41 @@ impl Program {
     /// Create a new program with statements
     pub fn new(statements: Vec<Statement>) -> Self {
         let span = if statements.is_empty() {
-            Span::single(crate::span::Position::start())
+            Span::single(0)
         } else {
-            let start = statements.first().unwrap().span().start;
-            let end = statements.last().unwrap().span().end;
-            Span::new(start, end)
+            let first = statements.first().unwrap().span();
+            first.merge(statements.last().unwrap().span())
         };
 
         Program { statements, span }
     }
 
+    /// Whether two programs have the same statements, whatever their spans
+    pub fn eq_ignoring_spans(&self, other: &Program) -> bool {
+        self.statements.len() == other.statements.len()
+            && self
+                .statements
+                .iter()
+                .zip(&other.statements)
+                .all(|(a, b)| a.eq_ignoring_spans(b))
+    }
+
+    /// The innermost expression whose span covers byte `offset`, if any
+    ///
+    /// Offsets in whitespace, comments or semicolons between expressions
+    /// are not covered by any node; an offset on an operator gives the
+    /// expression the operator builds.
+    pub fn node_at(&self, offset: usize) -> Option<&Expr> {
+        self.statements.iter().find_map(|stmt| match stmt {
+            Statement::ExprStatement { expr, .. } => expr.node_at(offset),
+        })
+    }
+
     /// Create an empty program
     pub fn empty() -> Self {
         Program {
             statements: Vec::new(),
-            span: Span::single(crate::span::Position::start()),
+            span: Span::single(0),
         }
     }
 }
@@ -90,4 +123,73 @@ mod tests {
         let program = Program::empty();
         assert_eq!(program.statements.len(), 0);
     }
+
+    #[test]
+    fn test_eq_ignoring_spans() {
+        use crate::ast::BinaryOp;
+        use crate::lexer::SobaLexer;
+        use crate::parser::Parser;
+
+        let parse = |input: &str| {
+            let lexer = SobaLexer::new(input.chars().collect());
+            Parser::new(lexer).unwrap().parse_program().unwrap()
+        };
+
+        let program = parse("1 + 2; true");
+        let spaced = parse("1+2;\n\n  true");
+        assert_ne!(program, spaced);
+        assert!(program.eq_ignoring_spans(&spaced));
+        assert!(!program.eq_ignoring_spans(&parse("1 + 2")));
+        assert!(!program.eq_ignoring_spans(&parse("1 - 2; true")));
+        assert!(!program.eq_ignoring_spans(&parse("(1 + 2); true")));
+
+        let built = Program::new(vec![
+            Statement::expr_statement(Expr::InfixExpr {
+                left: Box::new(Expr::int(1)),
+                op: BinaryOp::Plus,
+                right: Box::new(Expr::int(2)),
+                span: Span::single(0),
+            }),
+            Statement::expr_statement(Expr::bool(true)),
+        ]);
+        assert!(built.eq_ignoring_spans(&program));
+    }
+
+    #[test]
+    fn test_node_at() {
+        use crate::lexer::SobaLexer;
+        use crate::parser::Parser;
+
+        let source = "1 + 2;\n-(3 * 45)";
+        let lexer = SobaLexer::new(source.chars().collect());
+        let program = Parser::new(lexer).unwrap().parse_program().unwrap();
+        let text_at = |offset| {
+            let span = program.node_at(offset)?.span();
+            Some(&source[span.start..span.end])
+        };
+
+        assert_eq!(text_at(0), Some("1"));
+        assert_eq!(text_at(2), Some("1 + 2"));
+        assert_eq!(text_at(5), None);
+        assert_eq!(text_at(7), Some("-(3 * 45)"));
+        assert_eq!(text_at(8), Some("(3 * 45)"));
+        assert_eq!(text_at(11), Some("3 * 45"));
+        assert_eq!(text_at(14), Some("45"));
+        assert_eq!(text_at(15), Some("(3 * 45)"));
+        assert_eq!(text_at(16), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::lexer::SobaLexer;
+        use crate::parser::Parser;
+
+        let lexer = SobaLexer::new("1 + 2.5; -(true)".chars().collect());
+        let program = Parser::new(lexer).unwrap().parse_program().unwrap();
+
+        let json = serde_json::to_string(&program).unwrap();
+        let decoded: Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, program);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,85 @@
+//! Read-only AST traversal
+//!
+//! Implement `Visitor` and override the methods for the nodes you care
+//! about; call the matching `walk_*` function from an override to keep
+//! descending into children.
+
+use super::{Expr, Program, Statement};
+
+/// A read-only traversal over the AST
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+/// Visit every statement of a program
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for stmt in &program.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+/// Visit the expression of a statement
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::ExprStatement { expr, .. } => visitor.visit_expr(expr),
+    }
+}
+
+/// Visit the direct children of an expression, left to right
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Int { .. } | Expr::Float { .. } | Expr::Bool { .. } => {}
+        Expr::InfixExpr { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Grouped { inner, .. } => visitor.visit_expr(inner),
+        Expr::UnaryExpr { operand, .. } | Expr::PostfixExpr { operand, .. } => {
+            visitor.visit_expr(operand)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOp;
+    use crate::parser::test_util::parse;
+
+    #[derive(Default)]
+    struct Counter {
+        exprs: usize,
+        additions: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_expr(&mut self, expr: &Expr) {
+            self.exprs += 1;
+            if let Expr::InfixExpr {
+                op: BinaryOp::Plus, ..
+            } = expr
+            {
+                self.additions += 1;
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn test_visits_every_expression() {
+        let mut counter = Counter::default();
+        counter.visit_program(&parse("1 + 2; -(3 + 4.5) * 2"));
+        assert_eq!(counter.exprs, 10);
+        assert_eq!(counter.additions, 2);
+    }
+}
@@ -0,0 +1,7 @@
+//! Code generation module
+//!
+//! This module contains backends that compile programs ahead of time.
+
+pub mod wasm;
+
+pub use wasm::{compile_program, compile_program_with_float_eq, CompileError, WasmModule};
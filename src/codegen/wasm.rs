@@ -0,0 +1,753 @@
+//! WebAssembly backend
+//!
+//! Compiles a program into a standalone `.wasm` module that exports a single
+//! `main` function taking no parameters and returning the value of the last
+//! statement. Every soba expression has a type that is known before
+//! evaluation, so values map directly onto wasm types: ints and bools become
+//! `i32` (bools as 0/1) and floats become `f64`. Strings and host objects
+//! only come from the host and no expression has their types.
+//!
+//! Runtime errors (division by zero, integer overflow on negation, type
+//! errors such as ordering booleans) execute `unreachable` and trap. They
+//! are raised at the same point the interpreter would raise them, so
+//! short-circuited operands never trap.
+//!
+//! wasm has no power instruction and the module imports nothing, so `**`
+//! and custom operators, whose handlers live in the host, cannot be
+//! compiled. `compile_program` rejects them with a `CompileError` instead
+//! of emitting a module.
+
+use crate::ast::visit::{walk_expr, Visitor};
+use crate::ast::{BinaryOp, Expr, PostfixOp, Program, Statement, UnaryOp};
+use crate::span::Span;
+use crate::typeck::infer_type;
+use crate::value::{FloatEq, Type};
+use std::fmt;
+
+/// A compiled WebAssembly module
+#[derive(Debug, PartialEq, Clone)]
+pub struct WasmModule {
+    /// Binary module contents, ready to be written to a `.wasm` file
+    pub bytes: Vec<u8>,
+    /// Type of the value returned by the exported `main` function
+    pub result_type: Type,
+}
+
+/// A construct the backend cannot compile
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompileError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot compile to wasm at {}: {}",
+            self.span, self.message
+        )
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Name of the exported entry point
+pub const ENTRY_POINT: &str = "main";
+
+// Value types
+const I32: u8 = 0x7F;
+const I64: u8 = 0x7E;
+const F64: u8 = 0x7C;
+
+// Instructions
+const UNREACHABLE: u8 = 0x00;
+const IF: u8 = 0x04;
+const ELSE: u8 = 0x05;
+const END: u8 = 0x0B;
+const DROP: u8 = 0x1A;
+const SELECT: u8 = 0x1B;
+const LOCAL_GET: u8 = 0x20;
+const LOCAL_SET: u8 = 0x21;
+const LOCAL_TEE: u8 = 0x22;
+const I32_CONST: u8 = 0x41;
+const I64_CONST: u8 = 0x42;
+const F64_CONST: u8 = 0x44;
+const I32_EQZ: u8 = 0x45;
+const I32_EQ: u8 = 0x46;
+const I32_NE: u8 = 0x47;
+const I32_LT_S: u8 = 0x48;
+const I32_GT_S: u8 = 0x4A;
+const I32_LE_S: u8 = 0x4C;
+const I32_GE_S: u8 = 0x4E;
+const I64_LE_U: u8 = 0x58;
+const I64_GE_S: u8 = 0x59;
+const F64_EQ: u8 = 0x61;
+const F64_NE: u8 = 0x62;
+const F64_LT: u8 = 0x63;
+const F64_GT: u8 = 0x64;
+const F64_LE: u8 = 0x65;
+const F64_GE: u8 = 0x66;
+const I32_SUB: u8 = 0x6B;
+const I32_AND: u8 = 0x71;
+const I32_OR: u8 = 0x72;
+const I64_SUB: u8 = 0x7D;
+const I64_XOR: u8 = 0x85;
+const F64_ABS: u8 = 0x99;
+const F64_NEG: u8 = 0x9A;
+const F64_ADD: u8 = 0xA0;
+const F64_SUB: u8 = 0xA1;
+const F64_MUL: u8 = 0xA2;
+const F64_DIV: u8 = 0xA3;
+const F64_CONVERT_I32_S: u8 = 0xB7;
+const I64_REINTERPRET_F64: u8 = 0xBD;
+const BLOCK_EMPTY: u8 = 0x40;
+
+/// Compile a program into a WebAssembly module
+///
+/// Fails on the first construct the backend has no code for, in source
+/// order; everything else compiles, with runtime errors becoming traps.
+/// Floats compare with `FloatEq::default()`.
+pub fn compile_program(program: &Program) -> Result<WasmModule, CompileError> {
+    compile_program_with_float_eq(program, FloatEq::default())
+}
+
+/// Compile a program whose float `==` and `!=` use `float_eq`
+pub fn compile_program_with_float_eq(
+    program: &Program,
+    float_eq: FloatEq,
+) -> Result<WasmModule, CompileError> {
+    let mut unsupported = Unsupported(None);
+    unsupported.visit_program(program);
+    if let Some(err) = unsupported.0 {
+        return Err(err);
+    }
+
+    let mut body = FunctionBody {
+        float_eq,
+        ..FunctionBody::default()
+    };
+    let mut result_type = Type::Int;
+
+    if program.statements.is_empty() {
+        body.code.push(I32_CONST);
+        write_sleb(&mut body.code, 0);
+    }
+
+    let last = program.statements.len().saturating_sub(1);
+    for (index, stmt) in program.statements.iter().enumerate() {
+        let Statement::ExprStatement { expr, .. } = stmt;
+        let ty = body.expr(expr);
+        if index == last {
+            result_type = ty;
+        } else {
+            body.code.push(DROP);
+        }
+    }
+    body.code.push(END);
+
+    Ok(WasmModule {
+        bytes: encode_module(&body, wasm_type(result_type)),
+        result_type,
+    })
+}
+
+/// Finds the first expression `FunctionBody` cannot lower
+struct Unsupported(Option<CompileError>);
+
+impl Visitor for Unsupported {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.0.is_some() {
+            return;
+        }
+        let message = match expr {
+            Expr::InfixExpr {
+                op: BinaryOp::Power,
+                ..
+            } => Some("`**` has no wasm instruction".to_string()),
+            Expr::InfixExpr {
+                op: op @ BinaryOp::Custom(_),
+                ..
+            } => Some(format!("custom operator `{op}` runs in the host")),
+            Expr::UnaryExpr {
+                op: op @ UnaryOp::Custom(_),
+                ..
+            } => Some(format!("custom operator `{op}` runs in the host")),
+            Expr::PostfixExpr {
+                op: op @ PostfixOp::Custom(_),
+                ..
+            } => Some(format!("custom operator `{op}` runs in the host")),
+            _ => None,
+        };
+        match message {
+            Some(message) => {
+                self.0 = Some(CompileError {
+                    message,
+                    span: expr.span(),
+                })
+            }
+            None => walk_expr(self, expr),
+        }
+    }
+}
+
+fn wasm_type(ty: Type) -> u8 {
+    match ty {
+        Type::Int | Type::Bool => I32,
+        Type::Float => F64,
+        Type::Str | Type::Host => unreachable!("no expression has a host-only type"),
+    }
+}
+
+/// Instructions and locals of the `main` function
+#[derive(Default)]
+struct FunctionBody {
+    code: Vec<u8>,
+    /// Local types in declaration order
+    locals: Vec<u8>,
+    float_eq: FloatEq,
+}
+
+impl FunctionBody {
+    fn new_local(&mut self, ty: u8) -> u32 {
+        self.locals.push(ty);
+        (self.locals.len() - 1) as u32
+    }
+
+    fn local_op(&mut self, op: u8, local: u32) {
+        self.code.push(op);
+        write_uleb(&mut self.code, local);
+    }
+
+    /// Emit code for an expression, leaving its value on the stack
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Int { value, .. } => {
+                self.code.push(I32_CONST);
+                write_sleb(&mut self.code, i64::from(*value));
+                Type::Int
+            }
+            Expr::Float { value, .. } => {
+                self.f64_const(*value);
+                Type::Float
+            }
+            Expr::Bool { value, .. } => {
+                self.code.push(I32_CONST);
+                write_sleb(&mut self.code, i64::from(*value));
+                Type::Bool
+            }
+            Expr::Grouped { inner, .. } => self.expr(inner),
+            Expr::UnaryExpr { op, operand, .. } => self.unary(*op, operand),
+            Expr::PostfixExpr { .. } => unreachable!("rejected by `Unsupported`"),
+            Expr::InfixExpr {
+                left, op, right, ..
+            } => self.binary(*op, left, right),
+        }
+    }
+
+    fn f64_const(&mut self, value: f64) {
+        self.code.push(F64_CONST);
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Emit code for an operand converted to `f64`, as `Value::as_f64` does
+    fn expr_as_f64(&mut self, expr: &Expr) {
+        if self.expr(expr) != Type::Float {
+            self.code.push(F64_CONVERT_I32_S);
+        }
+    }
+
+    /// Emit code for an operand converted to a 0/1 truthiness flag
+    fn expr_truthy(&mut self, expr: &Expr) {
+        match self.expr(expr) {
+            Type::Bool => {}
+            Type::Int => {
+                self.code.push(I32_CONST);
+                write_sleb(&mut self.code, 0);
+                self.code.push(I32_NE);
+            }
+            Type::Float => {
+                self.f64_const(0.0);
+                self.code.push(F64_NE);
+            }
+            Type::Str | Type::Host => unreachable!("no expression has a host-only type"),
+        }
+    }
+
+    /// Evaluate both operands for their traps, discard them, then trap
+    fn type_error(&mut self, operands: &[&Expr]) {
+        for operand in operands {
+            self.expr(operand);
+            self.code.push(DROP);
+        }
+        self.code.push(UNREACHABLE);
+    }
+
+    fn trap_if_set(&mut self) {
+        self.code
+            .extend_from_slice(&[IF, BLOCK_EMPTY, UNREACHABLE, END]);
+    }
+
+    fn unary(&mut self, op: UnaryOp, operand: &Expr) -> Type {
+        match op {
+            UnaryOp::Plus => self.expr(operand),
+            UnaryOp::Custom(_) => unreachable!("rejected by `Unsupported`"),
+            UnaryOp::LogicalNot => {
+                self.expr_truthy(operand);
+                self.code.push(I32_EQZ);
+                Type::Bool
+            }
+            UnaryOp::Minus => match self.expr(operand) {
+                Type::Int => {
+                    // Negating i32::MIN overflows
+                    let local = self.new_local(I32);
+                    self.local_op(LOCAL_TEE, local);
+                    self.code.push(I32_CONST);
+                    write_sleb(&mut self.code, i64::from(i32::MIN));
+                    self.code.push(I32_EQ);
+                    self.trap_if_set();
+                    self.code.push(I32_CONST);
+                    write_sleb(&mut self.code, 0);
+                    self.local_op(LOCAL_GET, local);
+                    self.code.push(I32_SUB);
+                    Type::Int
+                }
+                Type::Float => {
+                    self.code.push(F64_NEG);
+                    Type::Float
+                }
+                Type::Bool => {
+                    // Cannot negate boolean value
+                    self.code.extend_from_slice(&[DROP, UNREACHABLE]);
+                    Type::Int
+                }
+                Type::Str | Type::Host => unreachable!("no expression has a host-only type"),
+            },
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr) -> Type {
+        match op {
+            BinaryOp::Custom(_) | BinaryOp::Power => unreachable!("rejected by `Unsupported`"),
+            BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Multiply => {
+                self.expr_as_f64(left);
+                self.expr_as_f64(right);
+                self.code.push(match op {
+                    BinaryOp::Plus => F64_ADD,
+                    BinaryOp::Minus => F64_SUB,
+                    _ => F64_MUL,
+                });
+                Type::Float
+            }
+            BinaryOp::Divide => {
+                self.expr_as_f64(left);
+                self.expr_as_f64(right);
+                let divisor = self.new_local(F64);
+                self.local_op(LOCAL_TEE, divisor);
+                self.f64_const(0.0);
+                self.code.push(F64_EQ);
+                self.trap_if_set();
+                self.local_op(LOCAL_GET, divisor);
+                self.code.push(F64_DIV);
+                Type::Float
+            }
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => {
+                self.expr_truthy(left);
+                self.code.extend_from_slice(&[IF, I32]);
+                if op == BinaryOp::LogicalAnd {
+                    self.expr_truthy(right);
+                    self.code.push(ELSE);
+                    self.code.push(I32_CONST);
+                    write_sleb(&mut self.code, 0);
+                } else {
+                    self.code.push(I32_CONST);
+                    write_sleb(&mut self.code, 1);
+                    self.code.push(ELSE);
+                    self.expr_truthy(right);
+                }
+                self.code.push(END);
+                Type::Bool
+            }
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                self.equality(left, right);
+                if op == BinaryOp::NotEqual {
+                    self.code.push(I32_EQZ);
+                }
+                Type::Bool
+            }
+            BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEqual | BinaryOp::GreaterEqual => {
+                self.ordering(op, left, right);
+                Type::Bool
+            }
+        }
+    }
+
+    /// Emit `left == right` with the semantics of `Value::equal_to_with`
+    fn equality(&mut self, left: &Expr, right: &Expr) {
+        match (infer_type(left), infer_type(right)) {
+            (Type::Int, Type::Int) | (Type::Bool, Type::Bool) => {
+                self.expr(left);
+                self.expr(right);
+                self.code.push(I32_EQ);
+            }
+            (Type::Float, Type::Float) | (Type::Int, Type::Float) | (Type::Float, Type::Int) => {
+                self.expr_as_f64(left);
+                self.expr_as_f64(right);
+                self.float_equality();
+            }
+            // Different types are not equal
+            _ => {
+                for operand in [left, right] {
+                    self.expr(operand);
+                    self.code.push(DROP);
+                }
+                self.code.push(I32_CONST);
+                write_sleb(&mut self.code, 0);
+            }
+        }
+    }
+
+    /// Compare the two `f64`s on top of the stack with `FloatEq::eq`
+    fn float_equality(&mut self) {
+        match self.float_eq {
+            FloatEq::Exact => self.code.push(F64_EQ),
+            FloatEq::Absolute(tolerance) => {
+                // |a - b| < tolerance
+                self.code.extend_from_slice(&[F64_SUB, F64_ABS]);
+                self.f64_const(tolerance);
+                self.code.push(F64_LT);
+            }
+            FloatEq::Ulps(max) => {
+                let [a, b] = [self.new_local(F64), self.new_local(F64)];
+                let [bits_a, bits_b, diff] = [
+                    self.new_local(I64),
+                    self.new_local(I64),
+                    self.new_local(I64),
+                ];
+                self.local_op(LOCAL_SET, b);
+                self.local_op(LOCAL_SET, a);
+
+                // a == b | (a == a & b == b & same sign & |bits a - bits b| <= max)
+                self.local_op(LOCAL_GET, a);
+                self.local_op(LOCAL_GET, b);
+                self.code.push(F64_EQ);
+                for local in [a, b] {
+                    self.local_op(LOCAL_GET, local);
+                    self.local_op(LOCAL_GET, local);
+                    self.code.push(F64_EQ);
+                }
+                self.code.push(I32_AND);
+                for (local, bits) in [(a, bits_a), (b, bits_b)] {
+                    self.local_op(LOCAL_GET, local);
+                    self.code.push(I64_REINTERPRET_F64);
+                    self.local_op(LOCAL_TEE, bits);
+                }
+                self.code.push(I64_XOR);
+                self.i64_const(0);
+                self.code.extend_from_slice(&[I64_GE_S, I32_AND]);
+
+                // Same-sign bit patterns are at most 2^63 apart, so the
+                // signed difference cannot overflow
+                self.local_op(LOCAL_GET, bits_a);
+                self.local_op(LOCAL_GET, bits_b);
+                self.code.push(I64_SUB);
+                self.local_op(LOCAL_TEE, diff);
+                self.i64_const(0);
+                self.local_op(LOCAL_GET, diff);
+                self.code.push(I64_SUB);
+                self.local_op(LOCAL_GET, diff);
+                self.i64_const(0);
+                self.code.extend_from_slice(&[I64_GE_S, SELECT]);
+                self.i64_const(max as i64);
+                self.code.extend_from_slice(&[I64_LE_U, I32_AND, I32_OR]);
+            }
+        }
+    }
+
+    fn i64_const(&mut self, value: i64) {
+        self.code.push(I64_CONST);
+        write_sleb(&mut self.code, value);
+    }
+
+    /// Emit an ordering comparison with the semantics of `Value::less_than` and friends
+    fn ordering(&mut self, op: BinaryOp, left: &Expr, right: &Expr) {
+        let instruction = |int: bool| match (op, int) {
+            (BinaryOp::Less, true) => I32_LT_S,
+            (BinaryOp::Greater, true) => I32_GT_S,
+            (BinaryOp::LessEqual, true) => I32_LE_S,
+            (BinaryOp::GreaterEqual, true) => I32_GE_S,
+            (BinaryOp::Less, false) => F64_LT,
+            (BinaryOp::Greater, false) => F64_GT,
+            (BinaryOp::LessEqual, false) => F64_LE,
+            _ => F64_GE,
+        };
+
+        match (infer_type(left), infer_type(right)) {
+            (Type::Int, Type::Int) => {
+                self.expr(left);
+                self.expr(right);
+                self.code.push(instruction(true));
+            }
+            (Type::Bool, _) | (_, Type::Bool) => self.type_error(&[left, right]),
+            _ => {
+                self.expr_as_f64(left);
+                self.expr_as_f64(right);
+                self.code.push(instruction(false));
+            }
+        }
+    }
+}
+
+fn encode_module(body: &FunctionBody, result: u8) -> Vec<u8> {
+    let mut module = Vec::new();
+    module.extend_from_slice(b"\0asm");
+    module.extend_from_slice(&1u32.to_le_bytes());
+
+    // Type section: one function type () -> result
+    write_section(&mut module, 1, &[0x01, 0x60, 0x00, 0x01, result]);
+
+    // Function section: one function using type 0
+    write_section(&mut module, 3, &[0x01, 0x00]);
+
+    // Export section: export function 0 as the entry point
+    let mut exports = vec![0x01];
+    write_name(&mut exports, ENTRY_POINT);
+    exports.extend_from_slice(&[0x00, 0x00]);
+    write_section(&mut module, 7, &exports);
+
+    // Code section: locals are declared one per group to keep their indices stable
+    let mut function = Vec::new();
+    write_uleb(&mut function, body.locals.len() as u32);
+    for ty in &body.locals {
+        function.extend_from_slice(&[0x01, *ty]);
+    }
+    function.extend_from_slice(&body.code);
+
+    let mut code = vec![0x01];
+    write_uleb(&mut code, function.len() as u32);
+    code.extend_from_slice(&function);
+    write_section(&mut module, 10, &code);
+
+    module
+}
+
+fn write_section(module: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    module.push(id);
+    write_uleb(module, contents.len() as u32);
+    module.extend_from_slice(contents);
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_uleb(out, name.len() as u32);
+    out.extend_from_slice(name.as_bytes());
+}
+
+fn write_uleb(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::{eval_program, eval_program_with_float_eq};
+    use crate::lexer::SobaLexer;
+    use crate::operators::OperatorRegistry;
+    use crate::parser::test_util::parse;
+    use crate::parser::Parser;
+    use crate::value::Value;
+    use wasmi::{Engine, Linker, Module, Store};
+
+    /// Compile and run a program, returning `None` if the module trapped
+    fn run(input: &str) -> Option<Value> {
+        run_program(&parse(input))
+    }
+
+    fn run_program(program: &Program) -> Option<Value> {
+        run_compiled(compile_program(program).unwrap())
+    }
+
+    fn run_compiled(compiled: WasmModule) -> Option<Value> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, &compiled.bytes).expect("module should validate");
+        let mut store = Store::new(&engine, ());
+        let instance = Linker::<()>::new(&engine)
+            .instantiate_and_start(&mut store, &module)
+            .unwrap();
+
+        match compiled.result_type {
+            Type::Float => {
+                let main = instance
+                    .get_typed_func::<(), f64>(&store, ENTRY_POINT)
+                    .unwrap();
+                main.call(&mut store, ()).ok().map(Value::Float)
+            }
+            Type::Int | Type::Bool => {
+                let main = instance
+                    .get_typed_func::<(), i32>(&store, ENTRY_POINT)
+                    .unwrap();
+                let raw = main.call(&mut store, ()).ok()?;
+                Some(match compiled.result_type {
+                    Type::Bool => Value::Bool(raw != 0),
+                    _ => Value::Int(raw),
+                })
+            }
+            Type::Str | Type::Host => unreachable!("no expression has a host-only type"),
+        }
+    }
+
+    fn assert_matches_interpreter(input: &str) {
+        let expected = eval_program(&parse(input)).ok();
+        assert_eq!(run(input), expected, "input: {input}");
+    }
+
+    #[test]
+    fn test_module_header() {
+        let compiled = compile_program(&parse("1")).unwrap();
+        assert_eq!(&compiled.bytes[..8], b"\0asm\x01\0\0\0");
+        assert_eq!(compiled.result_type, Type::Int);
+    }
+
+    #[test]
+    fn test_literals_and_empty_program() {
+        assert_matches_interpreter("");
+        assert_matches_interpreter("42");
+        assert_matches_interpreter("2.5");
+        assert_matches_interpreter("true");
+        assert_matches_interpreter("1; 2; false");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_matches_interpreter("2 + 3 * 4");
+        assert_matches_interpreter("(10 - 2) / 4");
+        assert_matches_interpreter("true + 1.5");
+        assert_matches_interpreter("-5; -2.5; +7; -(3 - 10)");
+    }
+
+    #[test]
+    fn test_logical_and_comparison() {
+        assert_matches_interpreter("!0 && 2.5 || false");
+        assert_matches_interpreter("0 || 0.0");
+        assert_matches_interpreter("1 < 2 && 3.5 >= 3 && 2 != 2.5");
+        assert_matches_interpreter("5 == 5.0; true == true; 1 == true");
+        assert_matches_interpreter("0.1 + 0.2 == 0.3");
+    }
+
+    #[test]
+    fn test_float_eq() {
+        let inputs = [
+            "0.1 + 0.2 == 0.3",
+            "1000.1 + 0.2 == 1000.3",
+            "1000.1 + 0.2 != 1000.3",
+            "1 == 1.0",
+            "0.0 == -0.0",
+            "-1.5 == -1.5000000000000004",
+            "0.5 == 0.5000000000000001",
+            "1.0 == -1.0",
+            "-0.1 - 0.2 == -0.3",
+        ];
+        let strategies = [
+            FloatEq::default(),
+            FloatEq::Exact,
+            FloatEq::Absolute(0.5),
+            FloatEq::Ulps(0),
+            FloatEq::Ulps(4),
+            FloatEq::Ulps(u64::MAX),
+        ];
+        for float_eq in strategies {
+            for input in inputs {
+                let program = parse(input);
+                let expected =
+                    eval_program_with_float_eq(&program, &OperatorRegistry::new(), float_eq).ok();
+                let compiled = compile_program_with_float_eq(&program, float_eq).unwrap();
+                assert_eq!(
+                    run_compiled(compiled),
+                    expected,
+                    "input: {input}, float_eq: {float_eq:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors_trap() {
+        assert_eq!(run("1 / 0"), None);
+        assert_eq!(run("-true"), None);
+        assert_eq!(run("true < false"), None);
+        assert_eq!(run("1 / 0 == true"), None);
+    }
+
+    #[test]
+    fn test_unsupported_constructs_are_rejected() {
+        use crate::operators::{Associativity, OperatorRegistry};
+        use crate::parser::Precedence;
+
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix("<>", Precedence::Sum, Associativity::Left, |l, _| Ok(l))
+            .unwrap();
+        let lexer = SobaLexer::with_operators("1 <> 2 + 3".chars().collect(), &operators);
+        let program = Parser::with_operators(lexer, &operators)
+            .unwrap()
+            .parse_program()
+            .unwrap();
+
+        assert_eq!(
+            compile_program(&program),
+            Err(CompileError {
+                message: "custom operator `<>` runs in the host".to_string(),
+                span: Span::new(0, 6),
+            })
+        );
+
+        // Even where evaluation would never reach it
+        let err = compile_program(&parse("false && 2 ** 3")).unwrap_err();
+        assert_eq!(err.span, Span::new(9, 15));
+        assert_eq!(
+            err.to_string(),
+            "Cannot compile to wasm at 9..15: `**` has no wasm instruction"
+        );
+    }
+
+    #[test]
+    fn test_short_circuit_avoids_traps() {
+        assert_matches_interpreter("false && 1 / 0 == 1");
+        assert_matches_interpreter("true || true < false");
+    }
+
+    #[test]
+    fn test_leb128_encoding() {
+        let mut out = Vec::new();
+        write_sleb(&mut out, i64::from(i32::MIN));
+        assert_eq!(out, [0x80, 0x80, 0x80, 0x80, 0x78]);
+
+        out.clear();
+        write_sleb(&mut out, -1);
+        assert_eq!(out, [0x7F]);
+
+        out.clear();
+        write_uleb(&mut out, 624485);
+        assert_eq!(out, [0xE5, 0x8E, 0x26]);
+    }
+}
@@ -0,0 +1,408 @@
+//! REPL settings
+//!
+//! Settings start from the defaults, are overridden by the config file at
+//! `$XDG_CONFIG_HOME/soba/config.toml` (or `~/.config/soba/config.toml`)
+//! and then by command line flags.
+//!
+//! The history lives in `$XDG_DATA_HOME/soba/history` (or
+//! `~/.local/share/soba/history`) unless `--history-file` or the
+//! `SOBA_HISTORY` environment variable names another file.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::PathBuf;
+
+/// How the REPL looks and behaves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplConfig {
+    /// Prompt shown before each entry; continuation lines get as many dots
+    pub prompt: String,
+    /// Number of entries kept in the history file
+    pub history_size: usize,
+    /// History file given with `--history-file`, instead of the default
+    pub history_file: Option<PathBuf>,
+    /// Show errors in color when writing to a terminal
+    pub color: bool,
+    /// Type check each entry and reject it on errors the evaluator would
+    /// let through, such as `false && -true`
+    pub strict: bool,
+    /// Show the type after each result, as in `5 : int`
+    pub types: bool,
+}
+
+impl Default for ReplConfig {
+    fn default() -> Self {
+        Self {
+            prompt: ">> ".to_string(),
+            history_size: 1000,
+            history_file: None,
+            color: true,
+            strict: false,
+            types: false,
+        }
+    }
+}
+
+/// Flags that override the config file, with their descriptions
+pub const FLAGS: &[(&str, &str)] = &[
+    ("--prompt <text>", "Prompt shown before each entry"),
+    (
+        "--history-size <n>",
+        "Number of entries kept in the history",
+    ),
+    (
+        "--history-file <path>",
+        "File the history is kept in, also set by SOBA_HISTORY",
+    ),
+    ("--color, --no-color", "Show errors in color or not"),
+    (
+        "--strict, --no-strict",
+        "Type check entries before evaluating",
+    ),
+    ("--types, --no-types", "Show the type after each result"),
+];
+
+/// Whether `flag` is one of `FLAGS` that is followed by a value
+pub fn flag_takes_value(flag: &str) -> bool {
+    FLAGS.iter().any(|(usage, _)| {
+        usage
+            .strip_prefix(flag)
+            .is_some_and(|rest| rest.starts_with(" <"))
+    })
+}
+
+/// Settings that `:set <setting> on|off` changes in a running REPL
+pub const SWITCHES: &[&str] = &["color", "strict", "types"];
+
+/// Why settings could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The config file is not valid TOML
+    Syntax { message: String },
+    /// The config file has a key this version does not know
+    UnknownKey { key: String },
+    /// A key or flag has a value of the wrong kind
+    InvalidValue { key: String, expected: &'static str },
+    /// A command line flag is not a REPL flag
+    UnknownFlag { flag: String },
+    /// `:set` names a setting that is not in `SWITCHES`
+    UnknownSwitch { name: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Syntax { message } => write!(f, "{}", message.trim_end()),
+            ConfigError::UnknownKey { key } => write!(f, "Unknown setting '{key}'"),
+            ConfigError::InvalidValue { key, expected } => {
+                write!(f, "'{key}' must be {expected}")
+            }
+            ConfigError::UnknownFlag { flag } => write!(f, "Unknown flag '{flag}'"),
+            ConfigError::UnknownSwitch { name } => {
+                write!(
+                    f,
+                    "Unknown setting '{name}', try one of {}",
+                    SWITCHES.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ReplConfig {
+    /// Where the config file lives, if there is a home or config directory
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("soba").join("config.toml"))
+    }
+
+    /// Where the REPL history is kept, if there is a home or data directory
+    pub fn history_path(&self) -> Option<PathBuf> {
+        self.history_path_in(|name| std::env::var_os(name))
+    }
+
+    /// `history_path` with environment variables looked up by `var`
+    fn history_path_in(&self, var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+        let var = |name| var(name).filter(|value| !value.is_empty());
+        if let Some(path) = self
+            .history_file
+            .clone()
+            .or_else(|| var("SOBA_HISTORY").map(PathBuf::from))
+        {
+            return Some(path);
+        }
+        let data_dir = var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+        Some(data_dir.join("soba").join("history"))
+    }
+
+    /// Apply the settings in the text of a config file
+    pub fn merge_toml(&mut self, text: &str) -> Result<(), ConfigError> {
+        let table: toml::Table =
+            text.parse()
+                .map_err(|err: toml::de::Error| ConfigError::Syntax {
+                    message: err.to_string(),
+                })?;
+        for (key, value) in &table {
+            let invalid = |expected| ConfigError::InvalidValue {
+                key: key.clone(),
+                expected,
+            };
+            match key.as_str() {
+                "prompt" => {
+                    self.prompt = value.as_str().ok_or(invalid("a string"))?.to_string();
+                }
+                "history_size" => {
+                    self.history_size = value
+                        .as_integer()
+                        .and_then(|size| usize::try_from(size).ok())
+                        .ok_or(invalid("a non-negative integer"))?;
+                }
+                "color" => self.color = value.as_bool().ok_or(invalid("true or false"))?,
+                "strict" => self.strict = value.as_bool().ok_or(invalid("true or false"))?,
+                "types" => self.types = value.as_bool().ok_or(invalid("true or false"))?,
+                _ => return Err(ConfigError::UnknownKey { key: key.clone() }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply command line flags, as listed in `FLAGS`
+    pub fn merge_args(&mut self, args: &[String]) -> Result<(), ConfigError> {
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = |expected| {
+                args.next().ok_or(ConfigError::InvalidValue {
+                    key: flag.clone(),
+                    expected,
+                })
+            };
+            match flag.as_str() {
+                "--prompt" => self.prompt = value("followed by the prompt")?.clone(),
+                "--history-size" => {
+                    let expected = "followed by a non-negative integer";
+                    self.history_size =
+                        value(expected)?
+                            .parse()
+                            .map_err(|_| ConfigError::InvalidValue {
+                                key: flag.clone(),
+                                expected,
+                            })?;
+                }
+                "--history-file" => {
+                    self.history_file = Some(PathBuf::from(value("followed by a path")?));
+                }
+                "--color" => self.color = true,
+                "--no-color" => self.color = false,
+                "--strict" => self.strict = true,
+                "--no-strict" => self.strict = false,
+                "--types" => self.types = true,
+                "--no-types" => self.types = false,
+                _ => return Err(ConfigError::UnknownFlag { flag: flag.clone() }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn the setting `name`, one of `SWITCHES`, `on` or `off`
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        let switch = match name {
+            "color" => &mut self.color,
+            "strict" => &mut self.strict,
+            "types" => &mut self.types,
+            _ => {
+                return Err(ConfigError::UnknownSwitch {
+                    name: name.to_string(),
+                })
+            }
+        };
+        *switch = match value {
+            "on" => true,
+            "off" => false,
+            _ => {
+                return Err(ConfigError::InvalidValue {
+                    key: name.to_string(),
+                    expected: "on or off",
+                })
+            }
+        };
+        Ok(())
+    }
+
+    /// Prompt for the lines after the first of an unfinished entry, as wide
+    /// as the main prompt
+    pub fn continuation_prompt(&self) -> String {
+        let content = self.prompt.trim_end();
+        let padding = &self.prompt[content.len()..];
+        format!("{}{padding}", ".".repeat(content.chars().count()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_merge_toml() {
+        let mut config = ReplConfig::default();
+        config
+            .merge_toml("prompt = \"soba> \"\nhistory_size = 50\ncolor = false\nstrict = true\n")
+            .unwrap();
+        assert_eq!(
+            config,
+            ReplConfig {
+                prompt: "soba> ".to_string(),
+                history_size: 50,
+                history_file: None,
+                color: false,
+                strict: true,
+                types: false,
+            }
+        );
+
+        // Keys that are left out keep their values
+        config.merge_toml("color = true").unwrap();
+        assert!(config.color);
+        assert_eq!(config.history_size, 50);
+    }
+
+    #[test]
+    fn test_merge_toml_errors() {
+        let mut config = ReplConfig::default();
+        assert_eq!(
+            config.merge_toml("colour = false"),
+            Err(ConfigError::UnknownKey {
+                key: "colour".to_string()
+            })
+        );
+        assert_eq!(
+            config
+                .merge_toml("history_size = -1")
+                .unwrap_err()
+                .to_string(),
+            "'history_size' must be a non-negative integer"
+        );
+        assert!(matches!(
+            config.merge_toml("prompt = "),
+            Err(ConfigError::Syntax { .. })
+        ));
+        assert_eq!(config, ReplConfig::default());
+    }
+
+    #[test]
+    fn test_flags_override_the_file() {
+        let mut config = ReplConfig::default();
+        config.merge_toml("strict = true\nprompt = \"> \"").unwrap();
+        config
+            .merge_args(&args(&[
+                "--no-strict",
+                "--history-size",
+                "10",
+                "--no-color",
+            ]))
+            .unwrap();
+        assert!(!config.strict);
+        assert!(!config.color);
+        assert_eq!(config.history_size, 10);
+        assert_eq!(config.prompt, "> ");
+
+        assert_eq!(
+            config.merge_args(&args(&["--history-size", "many"])),
+            Err(ConfigError::InvalidValue {
+                key: "--history-size".to_string(),
+                expected: "followed by a non-negative integer",
+            })
+        );
+        assert!(config.merge_args(&args(&["--prompt"])).is_err());
+        assert_eq!(
+            config
+                .merge_args(&args(&["--colour"]))
+                .unwrap_err()
+                .to_string(),
+            "Unknown flag '--colour'"
+        );
+    }
+
+    #[test]
+    fn test_flag_takes_value() {
+        assert!(flag_takes_value("--prompt"));
+        assert!(flag_takes_value("--history-size"));
+        assert!(!flag_takes_value("--history"));
+        assert!(!flag_takes_value("--no-color"));
+        assert!(!flag_takes_value("--quiet"));
+    }
+
+    #[test]
+    fn test_history_path() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+        let mut config = ReplConfig::default();
+        assert_eq!(
+            config.history_path_in(env(&[("HOME", "/home/a")])),
+            Some(PathBuf::from("/home/a/.local/share/soba/history"))
+        );
+        assert_eq!(
+            config.history_path_in(env(&[("HOME", "/home/a"), ("XDG_DATA_HOME", "/data")])),
+            Some(PathBuf::from("/data/soba/history"))
+        );
+        assert_eq!(
+            config.history_path_in(env(&[("XDG_DATA_HOME", ""), ("SOBA_HISTORY", "/tmp/h")])),
+            Some(PathBuf::from("/tmp/h"))
+        );
+        assert_eq!(config.history_path_in(env(&[])), None);
+
+        // The flag wins over the environment
+        config
+            .merge_args(&args(&["--history-file", "here.txt"]))
+            .unwrap();
+        assert_eq!(
+            config.history_path_in(env(&[("SOBA_HISTORY", "/tmp/h")])),
+            Some(PathBuf::from("here.txt"))
+        );
+    }
+
+    #[test]
+    fn test_set() {
+        let mut config = ReplConfig::default();
+        config.set("types", "on").unwrap();
+        assert!(config.types);
+        config.set("types", "off").unwrap();
+        assert!(!config.types);
+        config.set("strict", "on").unwrap();
+        assert!(config.strict);
+
+        assert_eq!(
+            config.set("types", "yes").unwrap_err().to_string(),
+            "'types' must be on or off"
+        );
+        assert_eq!(
+            config.set("prompt", "on").unwrap_err().to_string(),
+            "Unknown setting 'prompt', try one of color, strict, types"
+        );
+    }
+
+    #[test]
+    fn test_continuation_prompt() {
+        assert_eq!(ReplConfig::default().continuation_prompt(), ".. ");
+        let config = ReplConfig {
+            prompt: "soba>  ".to_string(),
+            ..ReplConfig::default()
+        };
+        assert_eq!(config.continuation_prompt(), ".....  ");
+    }
+}
@@ -0,0 +1,238 @@
+//! Building the concrete syntax tree from trivia tokens
+
+use super::tree::{SyntaxElement, SyntaxKind, SyntaxNode};
+use crate::error::{ParseError, ParseResult};
+use crate::lexer::{SobaLexer, TokenKind, TriviaToken};
+use crate::parser::pratt::{
+    folds_into_min_int, int_literal, EXPRESSION_START_TOKENS, INFIX_OPERATOR_TOKENS,
+};
+use crate::parser::{OperatorTable, Precedence, DEFAULT_MAX_DEPTH};
+
+/// Parse source text into a lossless syntax tree
+///
+/// The grammar, precedence rules and nesting limit are the same as
+/// `Parser`'s. Every statement except the last must end with `;`.
+pub fn parse_cst(input: &str) -> ParseResult<SyntaxNode> {
+    parse_cst_at(input, 0)
+}
+
+/// Parse source text that starts `start` bytes into a file, so spans count
+/// from the start of the file
+pub fn parse_cst_at(input: &str, start: usize) -> ParseResult<SyntaxNode> {
+    let tokens = SobaLexer::starting_at(input.chars().collect(), start).tokenize_with_trivia()?;
+    CstBuilder {
+        tokens,
+        index: 0,
+        operators: OperatorTable::builtin(),
+        depth: 0,
+    }
+    .program()
+}
+
+struct CstBuilder {
+    tokens: Vec<TriviaToken>,
+    index: usize,
+    operators: OperatorTable,
+    depth: usize,
+}
+
+impl CstBuilder {
+    fn current(&self) -> &TriviaToken {
+        // The stream always ends with `Eof`, which is never consumed by `bump`
+        &self.tokens[self.index]
+    }
+
+    fn current_kind(&self) -> &TokenKind {
+        &self.current().token.kind
+    }
+
+    fn bump(&mut self) -> SyntaxElement {
+        let token = self.tokens[self.index].clone();
+        if token.token.kind != TokenKind::Eof {
+            self.index += 1;
+        }
+        SyntaxElement::Token(token)
+    }
+
+    fn error(&self, expected: &[TokenKind]) -> ParseError {
+        let token = &self.current().token;
+        if token.kind == TokenKind::Eof {
+            ParseError::UnexpectedEof { span: token.span }
+        } else {
+            ParseError::UnexpectedToken {
+                found: token.kind.clone(),
+                expected: expected.to_vec(),
+                span: token.span,
+            }
+        }
+    }
+
+    fn program(mut self) -> ParseResult<SyntaxNode> {
+        let mut children = Vec::new();
+
+        while *self.current_kind() != TokenKind::Eof {
+            let statement = self.statement()?;
+            let terminated = statement.token(&TokenKind::Semicolon).is_some();
+            children.push(SyntaxElement::Node(statement));
+
+            if !terminated && *self.current_kind() != TokenKind::Eof {
+                let mut expected = vec![TokenKind::Semicolon];
+                expected.extend_from_slice(INFIX_OPERATOR_TOKENS);
+                return Err(self.error(&expected));
+            }
+        }
+
+        children.push(self.bump());
+        Ok(SyntaxNode::new(SyntaxKind::Program, children))
+    }
+
+    fn statement(&mut self) -> ParseResult<SyntaxNode> {
+        let mut children = vec![SyntaxElement::Node(self.expression(Precedence::Lowest)?)];
+        if *self.current_kind() == TokenKind::Semicolon {
+            children.push(self.bump());
+        }
+        Ok(SyntaxNode::new(SyntaxKind::Statement, children))
+    }
+
+    fn expression(&mut self, precedence: Precedence) -> ParseResult<SyntaxNode> {
+        if self.depth == DEFAULT_MAX_DEPTH {
+            if *self.current_kind() == TokenKind::Eof {
+                return Err(self.error(EXPRESSION_START_TOKENS));
+            }
+            return Err(ParseError::TooDeep {
+                limit: DEFAULT_MAX_DEPTH,
+                span: self.current().token.span,
+            });
+        }
+
+        self.depth += 1;
+        let result = self.expression_within_depth(precedence);
+        self.depth -= 1;
+        result
+    }
+
+    fn expression_within_depth(&mut self, precedence: Precedence) -> ParseResult<SyntaxNode> {
+        let mut left = self.prefix()?;
+
+        while precedence < self.operators.infix_precedence(self.current_kind()) {
+            let Some(operator) = self.operators.infix(self.current_kind()) else {
+                // `(` binds like a call but is not an infix operator
+                return Err(self.error(INFIX_OPERATOR_TOKENS));
+            };
+            let op = self.bump();
+            let right = self.expression(operator.right_operand_precedence())?;
+            left = SyntaxNode::new(
+                SyntaxKind::Infix,
+                vec![SyntaxElement::Node(left), op, SyntaxElement::Node(right)],
+            );
+        }
+
+        Ok(left)
+    }
+
+    fn prefix(&mut self) -> ParseResult<SyntaxNode> {
+        match self.current_kind() {
+            TokenKind::Int(value) => {
+                int_literal(*value, self.current().token.span)?;
+                Ok(SyntaxNode::new(SyntaxKind::Literal, vec![self.bump()]))
+            }
+            TokenKind::Float(_) | TokenKind::True | TokenKind::False => {
+                Ok(SyntaxNode::new(SyntaxKind::Literal, vec![self.bump()]))
+            }
+            TokenKind::LeftParen => {
+                let open = self.bump();
+                let inner = self.expression(Precedence::Lowest)?;
+                if *self.current_kind() != TokenKind::RightParen {
+                    let mut expected = vec![TokenKind::RightParen];
+                    expected.extend_from_slice(INFIX_OPERATOR_TOKENS);
+                    return Err(self.error(&expected));
+                }
+                let close = self.bump();
+                Ok(SyntaxNode::new(
+                    SyntaxKind::Grouped,
+                    vec![open, SyntaxElement::Node(inner), close],
+                ))
+            }
+            kind if self.operators.prefix(kind).is_some() => {
+                let negates = *kind == TokenKind::Minus;
+                let op = self.bump();
+                // `-2147483648` is kept as a `-` and a literal; `UnaryNode`
+                // lowers it to `i32::MIN`
+                let next = &self.tokens[(self.index + 1).min(self.tokens.len() - 1)];
+                let operand = if negates
+                    && folds_into_min_int(&self.operators, self.current_kind(), &next.token.kind)
+                {
+                    SyntaxNode::new(SyntaxKind::Literal, vec![self.bump()])
+                } else {
+                    self.expression(Precedence::Unary)?
+                };
+                Ok(SyntaxNode::new(
+                    SyntaxKind::Unary,
+                    vec![op, SyntaxElement::Node(operand)],
+                ))
+            }
+            _ => Err(self.error(EXPRESSION_START_TOKENS)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_lossless() {
+        for input in [
+            "",
+            "   ",
+            "// only a comment\n",
+            "1 + 2",
+            "  ( 1+2 ) *\n\t3 ; // trailing\n\n-true;",
+            "!(1 < 2)   &&   false;\n",
+        ] {
+            assert_eq!(parse_cst(input).unwrap().text(), input);
+        }
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let program = parse_cst("1 + 2 * 3;").unwrap();
+        assert_eq!(program.kind(), SyntaxKind::Program);
+
+        let statement = program.child_nodes().next().unwrap();
+        assert!(statement.token(&TokenKind::Semicolon).is_some());
+
+        // Multiplication binds tighter, so it is the right child of `+`
+        let sum = statement.child_nodes().next().unwrap();
+        assert_eq!(sum.kind(), SyntaxKind::Infix);
+        let kinds: Vec<SyntaxKind> = sum.child_nodes().map(SyntaxNode::kind).collect();
+        assert_eq!(kinds, vec![SyntaxKind::Literal, SyntaxKind::Infix]);
+    }
+
+    #[test]
+    fn test_node_span_excludes_trivia() {
+        let program = parse_cst("  (1)  ").unwrap();
+        let span = program.span().unwrap();
+        assert_eq!((span.start, span.end), (2, 5));
+        assert_eq!(parse_cst("// nothing").unwrap().span(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            parse_cst("(1 + 2"),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            parse_cst("1 2"),
+            Err(ParseError::UnexpectedToken {
+                found: TokenKind::Int(2),
+                ..
+            })
+        ));
+        assert!(matches!(parse_cst("1 @"), Err(ParseError::LexError(_))));
+
+        let deep = format!("{}1", "(".repeat(100_000));
+        assert!(matches!(parse_cst(&deep), Err(ParseError::TooDeep { .. })));
+    }
+}
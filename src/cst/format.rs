@@ -0,0 +1,390 @@
+//! Canonical formatting of soba source
+//!
+//! The formatter rewrites the whitespace of the lossless tree and keeps
+//! everything else: tokens, parentheses and comments. The canonical style:
+//!
+//! - one statement per line, with at most one blank line between them
+//! - one space around infix operators, none after prefix operators or
+//!   inside parentheses
+//! - a comment inside a statement ends its line, and the statement goes on
+//!   at the next line indented by four spaces
+//! - the output ends with a newline unless it is empty
+//!
+//! Formatting formatted code gives the same code back.
+//!
+//! `FormatOptions` changes the indentation of continuation lines, leaves
+//! out the spaces around infix operators, or breaks statements that are
+//! longer than a line before an infix operator.
+
+use super::build::{parse_cst, parse_cst_at};
+use super::tree::{SyntaxElement, SyntaxKind, SyntaxNode};
+use crate::error::ParseResult;
+use crate::lexer::{Trivia, TriviaKind, TriviaToken};
+use crate::source::shebang_len;
+
+/// How `format_source_with` lays out code
+///
+/// The default is the canonical style of `format_source`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Spaces before the lines of a statement after its first
+    pub indent_width: usize,
+    /// One space on each side of infix operators, e.g. `1 + 2` rather
+    /// than `1+2`
+    pub operator_spacing: bool,
+    /// Break a statement before an infix operator when the operator and
+    /// its right operand would not fit on the line; lines with no such
+    /// place stay longer
+    pub max_line_length: Option<usize>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            operator_spacing: true,
+            max_line_length: None,
+        }
+    }
+}
+
+/// Format source text in the canonical style
+///
+/// Fails with the parse error if the input is not a valid program.
+pub fn format_source(input: &str) -> ParseResult<String> {
+    format_source_with(input, &FormatOptions::default())
+}
+
+/// Format source text in the style given by `options`
+pub fn format_source_with(input: &str, options: &FormatOptions) -> ParseResult<String> {
+    Ok(format_tree(&parse_cst(input)?, options))
+}
+
+/// Format the contents of a script file in the canonical style
+///
+/// A leading `#!` line is written back unchanged and the code after it is
+/// formatted; parse error spans count from the start of the file.
+pub fn format_file(input: &str) -> ParseResult<String> {
+    let start = shebang_len(input);
+    if start == 0 {
+        return format_source(input);
+    }
+    let tree = parse_cst_at(&input[start..], start)?;
+    let code = format_tree(&tree, &FormatOptions::default());
+    Ok(format!("{}\n{}", &input[..start], code))
+}
+
+fn format_tree(tree: &SyntaxNode, options: &FormatOptions) -> String {
+    let mut formatter = Formatter {
+        options: *options,
+        ..Formatter::default()
+    };
+    for child in tree.children() {
+        match child {
+            SyntaxElement::Node(statement) => formatter.statement(statement),
+            // Comments at the end of the input belong to the `Eof` token
+            SyntaxElement::Token(eof) => {
+                formatter.leading_comments(&eof.leading);
+            }
+        }
+    }
+    formatter.newline();
+    formatter.out
+}
+
+#[derive(Default)]
+struct Formatter {
+    options: FormatOptions,
+    out: String,
+    /// Whether the current line has anything on it yet
+    line_started: bool,
+    /// A comment ended the current line, so the next token starts a new one
+    break_pending: bool,
+    /// Past the first token of a statement, where new lines are indented
+    in_statement: bool,
+    /// Width of the operator and operand about to be written, where the
+    /// line may break if they do not fit
+    break_width: Option<usize>,
+}
+
+impl Formatter {
+    fn statement(&mut self, statement: &SyntaxNode) {
+        self.newline();
+        self.in_statement = false;
+        for child in statement.children() {
+            self.element(child, false);
+        }
+        self.in_statement = false;
+    }
+
+    fn node(&mut self, node: &SyntaxNode, space_before: bool) {
+        let infix = node.kind() == SyntaxKind::Infix;
+        for (index, child) in node.children().iter().enumerate() {
+            let space = match index {
+                0 => space_before,
+                _ => infix && self.options.operator_spacing,
+            };
+            // The operator, which may start a new line with its right operand
+            if infix && index == 1 {
+                let right = node
+                    .children()
+                    .get(2)
+                    .map_or(0, |right| self.flat_width(right));
+                self.break_width = Some(self.flat_width(child) + usize::from(space) + right);
+            }
+            self.element(child, space);
+        }
+    }
+
+    /// Width of `element` written on one line
+    fn flat_width(&self, element: &SyntaxElement) -> usize {
+        match element {
+            SyntaxElement::Token(token) => token.text.chars().count(),
+            SyntaxElement::Node(node) => {
+                let children = node.children();
+                let spaces = if node.kind() == SyntaxKind::Infix && self.options.operator_spacing {
+                    children.len().saturating_sub(1)
+                } else {
+                    0
+                };
+                children
+                    .iter()
+                    .map(|child| self.flat_width(child))
+                    .sum::<usize>()
+                    + spaces
+            }
+        }
+    }
+
+    fn element(&mut self, element: &SyntaxElement, space_before: bool) {
+        match element {
+            SyntaxElement::Node(node) => self.node(node, space_before),
+            SyntaxElement::Token(token) => self.token(token, space_before),
+        }
+    }
+
+    fn token(&mut self, token: &TriviaToken, space_before: bool) {
+        let newlines = self.leading_comments(&token.leading);
+        self.separate(newlines);
+        self.write(&token.text, space_before);
+        self.in_statement = true;
+        for comment in comments(&token.trailing) {
+            self.write(comment, true);
+            self.break_pending = true;
+        }
+    }
+
+    /// Write the comments before a token, each on its own line, and return
+    /// how many newlines follow the last one
+    fn leading_comments(&mut self, leading: &[Trivia]) -> usize {
+        let mut newlines = 0;
+        for trivia in leading {
+            match trivia.kind {
+                TriviaKind::Whitespace => newlines += trivia.text.matches('\n').count(),
+                TriviaKind::LineComment => {
+                    self.separate(newlines);
+                    newlines = 0;
+                    self.newline();
+                    self.write(trivia.text.trim_end(), false);
+                    self.break_pending = true;
+                }
+            }
+        }
+        newlines
+    }
+
+    /// Leave one blank line where the source had any between statements,
+    /// except at the start of the input
+    fn separate(&mut self, newlines: usize) {
+        if newlines > 1 && !self.in_statement && !self.out.is_empty() {
+            self.newline();
+            self.out.push('\n');
+        }
+    }
+
+    fn write(&mut self, text: &str, space_before: bool) {
+        let width = self.break_width.take();
+        if self.break_pending || self.overflows(width, space_before) {
+            self.newline();
+        }
+        if !self.line_started {
+            if self.in_statement {
+                self.out
+                    .extend(std::iter::repeat_n(' ', self.options.indent_width));
+            }
+        } else if space_before {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+        self.line_started = true;
+    }
+
+    /// Whether `width` more characters after an optional space would make
+    /// the current line longer than the maximum
+    fn overflows(&self, width: Option<usize>, space_before: bool) -> bool {
+        let (Some(width), Some(max)) = (width, self.options.max_line_length) else {
+            return false;
+        };
+        let line = &self.out[self.out.rfind('\n').map_or(0, |end| end + 1)..];
+        self.line_started && line.chars().count() + usize::from(space_before) + width > max
+    }
+
+    /// End the current line, if anything is on it
+    fn newline(&mut self) {
+        if self.line_started {
+            self.out.push('\n');
+            self.line_started = false;
+        }
+        self.break_pending = false;
+    }
+}
+
+fn comments(trivia: &[Trivia]) -> impl Iterator<Item = &str> {
+    trivia
+        .iter()
+        .filter(|trivia| trivia.kind == TriviaKind::LineComment)
+        .map(|trivia| trivia.text.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_util::parse;
+
+    fn assert_formats(input: &str, expected: &str) {
+        assert_eq!(format_source(input).unwrap(), expected, "input: {input:?}");
+    }
+
+    #[test]
+    fn test_spacing() {
+        assert_formats("1+2*  3", "1 + 2 * 3\n");
+        assert_formats("( 1+2 )*-  3 ;", "(1 + 2) * -3;\n");
+        assert_formats("!   (1<=2)&&\n\n\tfalse", "!(1 <= 2) && false\n");
+        assert_formats("- -1", "--1\n");
+    }
+
+    #[test]
+    fn test_statements_and_blank_lines() {
+        assert_formats("1; 2;3", "1;\n2;\n3\n");
+        assert_formats("\n\n1;\n\n\n\n2;\n3;\n\n", "1;\n\n2;\n3;\n");
+        assert_formats("", "");
+        assert_formats("  \n\n ", "");
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_formats(
+            "// header\n\n1 + 2; // sum   \n",
+            "// header\n\n1 + 2; // sum\n",
+        );
+        assert_formats("1; // a\n// b\n2", "1; // a\n// b\n2\n");
+        // A comment inside a statement breaks it across lines
+        assert_formats(
+            "1 + // one\n  2 *\n// two\n3;",
+            "1 + // one\n    2 *\n    // two\n    3;\n",
+        );
+        assert_formats("1;\n\n// the end", "1;\n\n// the end\n");
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent_and_keeps_meaning() {
+        for input in [
+            "1 + 2",
+            "  ( 1+2 ) *\n\t3 ; // trailing\n\n-true;",
+            "// a\n\n\n// b\n1 +\n// c\n(2 // d\n)\n;\n\n\n",
+            "!(1 < 2)   &&   false;\n+3 ** -2 ** 2",
+        ] {
+            let formatted = format_source(input).unwrap();
+            assert_eq!(
+                format_source(&formatted).unwrap(),
+                formatted,
+                "input: {input:?}"
+            );
+            assert_eq!(
+                parse(&formatted).to_source(),
+                parse(input).to_source(),
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let format = |input, options| format_source_with(input, &options).unwrap();
+        let compact = FormatOptions {
+            operator_spacing: false,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format("1 + 2 * -3;  (4 - -5)", compact),
+            "1+2*-3;\n(4--5)\n"
+        );
+
+        let two = FormatOptions {
+            indent_width: 2,
+            ..FormatOptions::default()
+        };
+        assert_eq!(format("1 + // one\n2", two), "1 + // one\n  2\n");
+
+        let narrow = FormatOptions {
+            max_line_length: Some(16),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format("1 + 2 + 3 + 4 + 5;\n123 * (45 + 678) - 9", narrow),
+            "1 + 2 + 3 + 4\n    + 5;\n123 * (45 + 678)\n    - 9\n"
+        );
+        // A line with nowhere to break stays long
+        assert_eq!(format("-(((123456789)))", narrow), "-(((123456789)))\n");
+    }
+
+    #[test]
+    fn test_options_keep_meaning() {
+        let options = [
+            FormatOptions {
+                operator_spacing: false,
+                ..FormatOptions::default()
+            },
+            FormatOptions {
+                indent_width: 1,
+                max_line_length: Some(8),
+                ..FormatOptions::default()
+            },
+        ];
+        for options in options {
+            for input in [
+                "1 - -1 * +2 ** -3;\n!(1 < -2) && true != false",
+                "// a\n1 + (2 // b\n* 3) - 4 / 5 >= 6",
+            ] {
+                let formatted = format_source_with(input, &options).unwrap();
+                assert_eq!(
+                    parse(&formatted).to_source(),
+                    parse(input).to_source(),
+                    "input: {input:?}"
+                );
+                assert_eq!(
+                    format_source_with(&formatted, &options).unwrap(),
+                    formatted,
+                    "input: {input:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(format_source("1 +").is_err());
+        assert!(format_source("1 2").is_err());
+    }
+
+    #[test]
+    fn test_format_file_keeps_the_shebang() {
+        assert_eq!(
+            format_file("#!/usr/bin/env soba\n\n1+2;   3").unwrap(),
+            "#!/usr/bin/env soba\n1 + 2;\n3\n"
+        );
+        assert_eq!(format_file("#!soba").unwrap(), "#!soba\n");
+        assert_eq!(format_file("1+2").unwrap(), "1 + 2\n");
+        assert_eq!(format_file("#!soba\n1 +").unwrap_err().span().start, 10);
+    }
+}
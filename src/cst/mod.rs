@@ -0,0 +1,21 @@
+//! Lossless concrete syntax tree
+//!
+//! The CST keeps every token of the input together with its whitespace and
+//! comments, so `SyntaxNode::text()` always reproduces the source exactly.
+//! Tools that rewrite code (refactorings, the formatter) edit this tree
+//! instead of the AST, which throws trivia and parentheses layout away.
+//!
+//! The `view` types give a typed, AST-like API over the untyped nodes and
+//! can lower the tree into the regular `ast::Program`.
+
+pub mod build;
+pub mod format;
+pub mod tree;
+pub mod view;
+
+pub use build::{parse_cst, parse_cst_at};
+pub use format::{format_file, format_source, format_source_with, FormatOptions};
+pub use tree::{SyntaxElement, SyntaxKind, SyntaxNode};
+pub use view::{
+    ExprNode, GroupedNode, InfixNode, LiteralNode, ProgramNode, StatementNode, UnaryNode,
+};
@@ -0,0 +1,107 @@
+//! Untyped syntax tree nodes
+
+use crate::lexer::{TokenKind, TriviaToken};
+use crate::span::Span;
+
+/// Kinds of interior nodes in the concrete syntax tree
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyntaxKind {
+    /// The whole input; its last child is the `Eof` token
+    Program,
+    /// An expression and its optional `;`
+    Statement,
+    /// A single literal token
+    Literal,
+    /// `left op right`
+    Infix,
+    /// `( inner )`
+    Grouped,
+    /// `op operand`
+    Unary,
+}
+
+/// A child of a syntax node
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(TriviaToken),
+}
+
+/// An interior node of the concrete syntax tree
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxNode {
+    kind: SyntaxKind,
+    children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn new(kind: SyntaxKind, children: Vec<SyntaxElement>) -> Self {
+        Self { kind, children }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    /// Iterate over the child nodes, skipping tokens
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Iterate over the direct child tokens, skipping nodes
+    pub fn child_tokens(&self) -> impl Iterator<Item = &TriviaToken> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    /// Find the first direct child token of the given kind
+    pub fn token(&self, kind: &TokenKind) -> Option<&TriviaToken> {
+        self.child_tokens().find(|token| token.token.kind == *kind)
+    }
+
+    /// Collect every token under this node in source order
+    pub fn descendant_tokens(&self) -> Vec<&TriviaToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a TriviaToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// The exact source text covered by this node, including trivia
+    pub fn text(&self) -> String {
+        self.descendant_tokens()
+            .iter()
+            .map(|token| token.to_source())
+            .collect()
+    }
+
+    /// Span from the first to the last token, excluding surrounding trivia
+    ///
+    /// Returns `None` for nodes with no real tokens, such as an empty program.
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.descendant_tokens();
+        let mut real = tokens
+            .into_iter()
+            .filter(|token| token.token.kind != TokenKind::Eof);
+        let first = real.next()?.token.span;
+        let last = real.next_back().map_or(first, |token| token.token.span);
+        Some(first.merge(last))
+    }
+}
@@ -0,0 +1,294 @@
+//! Typed views over syntax nodes
+//!
+//! Each view wraps a `SyntaxNode` of one kind and offers accessors for its
+//! parts. Views borrow the tree, so they are cheap to create and copy.
+
+use super::tree::{SyntaxKind, SyntaxNode};
+use crate::ast::{BinaryOp, Expr, Program, Statement, UnaryOp};
+use crate::lexer::{TokenKind, TriviaToken};
+
+/// The root of a syntax tree
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramNode<'a>(&'a SyntaxNode);
+
+/// A statement: an expression and its optional `;`
+#[derive(Debug, Clone, Copy)]
+pub struct StatementNode<'a>(&'a SyntaxNode);
+
+/// A literal token
+#[derive(Debug, Clone, Copy)]
+pub struct LiteralNode<'a>(&'a SyntaxNode);
+
+/// A binary expression
+#[derive(Debug, Clone, Copy)]
+pub struct InfixNode<'a>(&'a SyntaxNode);
+
+/// A parenthesized expression
+#[derive(Debug, Clone, Copy)]
+pub struct GroupedNode<'a>(&'a SyntaxNode);
+
+/// A prefix operator applied to an operand
+#[derive(Debug, Clone, Copy)]
+pub struct UnaryNode<'a>(&'a SyntaxNode);
+
+/// Any expression node
+#[derive(Debug, Clone, Copy)]
+pub enum ExprNode<'a> {
+    Literal(LiteralNode<'a>),
+    Infix(InfixNode<'a>),
+    Grouped(GroupedNode<'a>),
+    Unary(UnaryNode<'a>),
+}
+
+/// The n-th child node, which the builder guarantees exists
+fn nth_node(node: &SyntaxNode, n: usize) -> &SyntaxNode {
+    node.child_nodes()
+        .nth(n)
+        .expect("malformed syntax tree: missing child node")
+}
+
+/// The first child token, which the builder guarantees exists
+fn first_token(node: &SyntaxNode) -> &TriviaToken {
+    node.child_tokens()
+        .next()
+        .expect("malformed syntax tree: missing token")
+}
+
+impl<'a> ProgramNode<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        (node.kind() == SyntaxKind::Program).then_some(Self(node))
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    pub fn statements(&self) -> impl Iterator<Item = StatementNode<'a>> {
+        self.0.child_nodes().filter_map(StatementNode::cast)
+    }
+
+    /// Lower the tree into an AST program
+    pub fn to_ast(&self) -> Program {
+        Program::new(self.statements().map(|stmt| stmt.to_ast()).collect())
+    }
+}
+
+impl<'a> StatementNode<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        (node.kind() == SyntaxKind::Statement).then_some(Self(node))
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    pub fn expr(&self) -> ExprNode<'a> {
+        ExprNode::cast(nth_node(self.0, 0))
+            .expect("malformed syntax tree: statement without expression")
+    }
+
+    pub fn semicolon(&self) -> Option<&'a TriviaToken> {
+        self.0.token(&TokenKind::Semicolon)
+    }
+
+    pub fn to_ast(&self) -> Statement {
+        Statement::expr_statement(self.expr().to_ast())
+    }
+}
+
+impl<'a> ExprNode<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        match node.kind() {
+            SyntaxKind::Literal => Some(ExprNode::Literal(LiteralNode(node))),
+            SyntaxKind::Infix => Some(ExprNode::Infix(InfixNode(node))),
+            SyntaxKind::Grouped => Some(ExprNode::Grouped(GroupedNode(node))),
+            SyntaxKind::Unary => Some(ExprNode::Unary(UnaryNode(node))),
+            SyntaxKind::Program | SyntaxKind::Statement => None,
+        }
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        match self {
+            ExprNode::Literal(node) => node.0,
+            ExprNode::Infix(node) => node.0,
+            ExprNode::Grouped(node) => node.0,
+            ExprNode::Unary(node) => node.0,
+        }
+    }
+
+    /// Lower the expression into an AST node
+    pub fn to_ast(&self) -> Expr {
+        match self {
+            ExprNode::Literal(node) => node.to_ast(),
+            ExprNode::Infix(node) => {
+                let left = node.lhs().to_ast();
+                let right = node.rhs().to_ast();
+                let span = left.span().merge(right.span());
+                Expr::InfixExpr {
+                    left: Box::new(left),
+                    op: node.op(),
+                    right: Box::new(right),
+                    span,
+                }
+            }
+            ExprNode::Grouped(node) => Expr::Grouped {
+                inner: Box::new(node.inner().to_ast()),
+                span: node.0.span().expect("grouped expression has tokens"),
+            },
+            ExprNode::Unary(node) => {
+                if let Some(value) = node.folded_int() {
+                    return Expr::Int {
+                        value,
+                        span: node.0.span().expect("unary expression has tokens"),
+                    };
+                }
+                let operand = node.operand().to_ast();
+                Expr::UnaryExpr {
+                    op: node.op(),
+                    span: node.op_token().token.span.merge(operand.span()),
+                    operand: Box::new(operand),
+                }
+            }
+        }
+    }
+}
+
+impl<'a> LiteralNode<'a> {
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    pub fn token(&self) -> &'a TriviaToken {
+        first_token(self.0)
+    }
+
+    pub fn to_ast(&self) -> Expr {
+        let token = &self.token().token;
+        let span = token.span;
+        match token.kind {
+            TokenKind::Int(value) => Expr::Int {
+                value: i32::try_from(value).expect("malformed syntax tree: integer out of range"),
+                span,
+            },
+            TokenKind::Float(value) => Expr::Float { value, span },
+            TokenKind::True => Expr::Bool { value: true, span },
+            TokenKind::False => Expr::Bool { value: false, span },
+            _ => unreachable!("malformed syntax tree: literal node holds '{}'", token.kind),
+        }
+    }
+}
+
+impl<'a> InfixNode<'a> {
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    pub fn lhs(&self) -> ExprNode<'a> {
+        ExprNode::cast(nth_node(self.0, 0)).expect("malformed syntax tree: infix without lhs")
+    }
+
+    pub fn rhs(&self) -> ExprNode<'a> {
+        ExprNode::cast(nth_node(self.0, 1)).expect("malformed syntax tree: infix without rhs")
+    }
+
+    pub fn op_token(&self) -> &'a TriviaToken {
+        first_token(self.0)
+    }
+
+    pub fn op(&self) -> BinaryOp {
+        BinaryOp::from_token(&self.op_token().token.kind)
+            .expect("malformed syntax tree: infix node without operator")
+    }
+}
+
+impl<'a> GroupedNode<'a> {
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    pub fn inner(&self) -> ExprNode<'a> {
+        ExprNode::cast(nth_node(self.0, 0)).expect("malformed syntax tree: empty group")
+    }
+}
+
+impl<'a> UnaryNode<'a> {
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    pub fn op_token(&self) -> &'a TriviaToken {
+        first_token(self.0)
+    }
+
+    pub fn op(&self) -> UnaryOp {
+        UnaryOp::from_token(&self.op_token().token.kind)
+            .expect("malformed syntax tree: unary node without operator")
+    }
+
+    pub fn operand(&self) -> ExprNode<'a> {
+        ExprNode::cast(nth_node(self.0, 0)).expect("malformed syntax tree: unary without operand")
+    }
+
+    /// `i32::MIN` if this is `-2147483648`, which the parser reads as one
+    /// literal because `2147483648` alone is out of range
+    fn folded_int(&self) -> Option<i32> {
+        let ExprNode::Literal(literal) = self.operand() else {
+            return None;
+        };
+        let folds = self.op() == UnaryOp::Minus
+            && literal.token().token.kind == TokenKind::Int(i32::MIN.unsigned_abs());
+        folds.then_some(i32::MIN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cst::parse_cst;
+    use crate::parser::test_util::parse;
+
+    #[test]
+    fn test_lowering_matches_parser() {
+        for input in [
+            "1 + 2 * 3",
+            "(1 + 2) * 3; -4.5; !true || false",
+            "// comment\n  1 <= 2 // trailing\n; +(3)",
+            "-2147483648; 1 - - 2147483648; (-2147483648) ** 2",
+        ] {
+            let tree = parse_cst(input).unwrap();
+            let program = ProgramNode::cast(&tree).unwrap();
+            assert_eq!(program.to_ast(), parse(input), "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let tree = parse_cst("-(1 +  2);").unwrap();
+        let program = ProgramNode::cast(&tree).unwrap();
+        let statement = program.statements().next().unwrap();
+        assert!(statement.semicolon().is_some());
+
+        let ExprNode::Unary(unary) = statement.expr() else {
+            panic!("Expected unary expression");
+        };
+        assert_eq!(unary.op(), UnaryOp::Minus);
+
+        let ExprNode::Grouped(group) = unary.operand() else {
+            panic!("Expected grouped expression");
+        };
+        let ExprNode::Infix(infix) = group.inner() else {
+            panic!("Expected infix expression");
+        };
+        assert_eq!(infix.op(), BinaryOp::Plus);
+        assert_eq!(infix.op_token().trailing[0].text, "  ");
+        assert_eq!(infix.syntax().text(), "1 +  2");
+    }
+
+    #[test]
+    fn test_cast_checks_kind() {
+        let tree = parse_cst("1").unwrap();
+        assert!(ProgramNode::cast(&tree).is_some());
+        assert!(StatementNode::cast(&tree).is_none());
+        assert!(ExprNode::cast(&tree).is_none());
+    }
+}
@@ -0,0 +1,517 @@
+//! Errors and warnings rendered with their source
+//!
+//! A `Diagnostic` is an error or warning with an optional code, span and
+//! hint, plus labels for other places that explain it. A `Renderer` turns
+//! it into the text the command line tools print: a header, the location,
+//! the source line and a caret under the span, then each label's place
+//! underlined with `-` and followed by its message.
+//!
+//! ```text
+//! error[E0001]: Division by zero
+//!  --> main.soba:1:6-11
+//!   |
+//! 1 | 1 + (2 / 0)
+//!   |      ^^^^^
+//!   = hint: make sure the divisor can never be zero
+//! ```
+//!
+//! ```text
+//! error[E0102]: Mismatched parentheses
+//!  --> main.soba:1:7-7
+//!   |
+//! 1 | (1 + 2
+//!   |       ^
+//!   | - unclosed delimiter opened here
+//!   = hint: add a `)` to close the `(`
+//! ```
+//!
+//! A span over several lines is underlined to the end of its first line,
+//! and a line wider than the renderer is cut down to the part around the
+//! span, with `...` where text was left out.
+
+use crate::analysis::{Lint, Severity};
+use crate::error::{hint, ParseError, ParseWarning, SobaError};
+use crate::source::SourceMap;
+use crate::span::{ColumnUnit, Span};
+use crate::testing::Failure;
+use crate::typeck::TypeCheckError;
+use unicode_width::UnicodeWidthChar;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Marks the ends of a shortened source line
+const ELLIPSIS: &str = "...";
+
+/// An error or warning to show to the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable code shown in the header, e.g. `E0001`
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// What the caret points at; without a span only the header and the
+    /// hint are shown
+    pub span: Option<Span>,
+    /// One-line suggestion shown last
+    pub hint: Option<String>,
+    /// Other places that explain the problem, shown after the span
+    pub labels: Vec<Label>,
+}
+
+/// A place a diagnostic points at besides its span, with a short message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message.into())
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message.into())
+    }
+
+    fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            code: None,
+            message,
+            span: None,
+            hint: None,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Set the code and, unless there is a hint already, the hint for it
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        if self.hint.is_none() {
+            self.hint = hint(code).map(str::to_string);
+        }
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+}
+
+impl From<&SobaError> for Diagnostic {
+    /// The message leaves out the kind of error, which the code tells
+    fn from(err: &SobaError) -> Self {
+        let message = match err {
+            SobaError::LexError(e) => e.to_string(),
+            SobaError::ParseError(e) => return e.into(),
+            SobaError::EvalError(e) => e.to_string(),
+            // The first error leads; the others are labels on the same diagnostic
+            SobaError::TypeCheckErrors(errors) => {
+                let diagnostic = Diagnostic::from(&errors[0]);
+                return errors[1..].iter().fold(diagnostic, |diagnostic, error| {
+                    diagnostic.with_label(error.span, format!("type error: {}", error.message))
+                });
+            }
+        };
+        let diagnostic = Diagnostic::error(message).with_code(err.code());
+        match err.span() {
+            Some(span) => diagnostic.with_span(span),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let diagnostic = Diagnostic::error(err.to_string())
+            .with_code(err.code())
+            .with_span(err.span());
+        match err {
+            ParseError::MismatchedParentheses {
+                open: Some(open), ..
+            } => diagnostic.with_label(*open, "unclosed delimiter opened here"),
+            // The hint for the code is about a '(' that is never closed
+            ParseError::MismatchedParentheses { open: None, .. } => {
+                diagnostic.with_hint("remove this `)` or add a `(` before it")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&ParseWarning> for Diagnostic {
+    fn from(warning: &ParseWarning) -> Self {
+        Diagnostic::warning(warning.to_string())
+            .with_code(warning.code())
+            .with_span(warning.span())
+    }
+}
+
+impl From<&TypeCheckError> for Diagnostic {
+    /// A type error found before evaluation has the code of the one
+    /// evaluation would report
+    fn from(err: &TypeCheckError) -> Self {
+        Diagnostic::error(format!("Type error: {}", err.message))
+            .with_code("E0003")
+            .with_span(err.span)
+    }
+}
+
+impl From<&Lint> for Diagnostic {
+    /// The rule name takes the place of the code
+    fn from(lint: &Lint) -> Self {
+        Diagnostic {
+            severity: lint.severity,
+            code: Some(lint.rule.name),
+            message: lint.message.clone(),
+            span: Some(lint.span),
+            hint: None,
+            labels: Vec::new(),
+        }
+    }
+}
+
+impl From<&Failure> for Diagnostic {
+    fn from(failure: &Failure) -> Self {
+        match failure {
+            Failure::NotTrue { value, span } => Diagnostic::error(format!(
+                "Assertion failed: expected true, got {value} : {}",
+                value.type_name()
+            ))
+            .with_span(*span)
+            .with_hint("every statement of a test file must evaluate to true"),
+            Failure::Error(err) => err.into(),
+        }
+    }
+}
+
+/// Renders diagnostics against the sources their spans point into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Renderer {
+    /// Color the output with ANSI escape codes
+    pub color: bool,
+    /// Most terminal cells a line of output takes, gutter included
+    pub width: usize,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            color: false,
+            width: 100,
+        }
+    }
+}
+
+impl Renderer {
+    /// The diagnostic as lines of text, without a final newline
+    pub fn render(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let accent = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let label = match diagnostic.code {
+            Some(code) => format!("{}[{code}]", diagnostic.severity),
+            None => diagnostic.severity.to_string(),
+        };
+        let mut lines = vec![format!(
+            "{}{}",
+            self.paint(accent, &label),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        )];
+
+        let mut gutter = 1;
+        if let Some(span) = diagnostic.span {
+            let excerpt = self.excerpt(span, sources);
+            let labels: Vec<_> = diagnostic
+                .labels
+                .iter()
+                .filter_map(|label| Some((label, self.excerpt(label.span, sources)?)))
+                .collect();
+            if let Some((number, _, _)) = &excerpt {
+                gutter = labels
+                    .iter()
+                    .map(|(_, (number, _, _))| number.len())
+                    .fold(number.len(), usize::max);
+            }
+            let pad = " ".repeat(gutter);
+            lines.push(format!(
+                "{pad}{} {}",
+                self.paint(BLUE, "-->"),
+                sources.location(span)
+            ));
+            if let Some((number, text, caret)) = excerpt {
+                let bar = self.paint(BLUE, "|");
+                lines.push(format!("{pad} {bar}"));
+                let line = |number: &str, text: &str| {
+                    let number = self.paint(BLUE, &format!("{number:>gutter$} |"));
+                    format!("{number} {text}").trim_end().to_string()
+                };
+                lines.push(line(&number, &text));
+                lines.push(format!("{pad} {bar} {}", self.paint(accent, &caret)));
+
+                // A label on the line just shown only adds its marker line
+                let mut shown = (number, text);
+                for (label, (number, text, marker)) in labels {
+                    if (&number, &text) != (&shown.0, &shown.1) {
+                        lines.push(line(&number, &text));
+                    }
+                    let marker = format!("{} {}", marker.replace('^', "-"), label.message);
+                    lines.push(format!("{pad} {bar} {}", self.paint(BLUE, &marker)));
+                    shown = (number, text);
+                }
+            }
+        }
+        if let Some(hint) = &diagnostic.hint {
+            let pad = " ".repeat(gutter);
+            lines.push(format!("{pad} {} {hint}", self.paint(BOLD, "= hint:")));
+        }
+        lines.join("\n")
+    }
+
+    /// The line number, the source line and the caret line under it for
+    /// `span`, if its source is in the map
+    fn excerpt(&self, span: Span, sources: &SourceMap) -> Option<(String, String, String)> {
+        let file = sources.get(span.source)?;
+        let contents = file.contents();
+        let line_start = contents.get(..span.start)?.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = contents[span.start..]
+            .find('\n')
+            .map_or(contents.len(), |i| span.start + i);
+        // A tab takes one cell like any other space, so carets line up
+        let line = contents[line_start..line_end]
+            .trim_end_matches('\r')
+            .replace('\t', " ");
+        let end = span.end.clamp(span.start, line_start + line.len());
+
+        let column = |offset: usize| {
+            line.get(..offset - line_start)
+                .map_or(0, |prefix| ColumnUnit::DisplayWidth.measure(prefix))
+        };
+        let number = file.position(span.start).line.to_string();
+        let available = self.width.saturating_sub(number.len() + 3);
+        let (text, start, end) = clip(&line, column(span.start), column(end), available);
+        let caret = format!(
+            "{}{}",
+            " ".repeat(start),
+            "^".repeat(end.saturating_sub(start).max(1))
+        );
+        Some((number, text, caret))
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Cut `line` down to at most `width` cells around the columns
+/// `start..end`, returning the text that is left and the columns moved to
+/// match it
+fn clip(line: &str, start: usize, end: usize, width: usize) -> (String, usize, usize) {
+    if ColumnUnit::DisplayWidth.measure(line) <= width {
+        return (line.to_string(), start, end);
+    }
+    // Keep a third of the width before the span for context
+    let skip = start.saturating_sub(width / 3);
+    let mut text = String::new();
+    if skip > 0 {
+        text.push_str(ELLIPSIS);
+    }
+    let prefix = text.len();
+    let budget = width.saturating_sub(prefix + ELLIPSIS.len());
+
+    let mut column = 0;
+    let mut first = None;
+    let mut used = 0;
+    for ch in line.chars() {
+        let cells = ch.width().unwrap_or(0);
+        if column >= skip {
+            if used + cells > budget {
+                text.push_str(ELLIPSIS);
+                break;
+            }
+            first.get_or_insert(column);
+            text.push(ch);
+            used += cells;
+        }
+        column += cells;
+    }
+
+    let first = first.unwrap_or(skip);
+    let shift = |column: usize| prefix + column.saturating_sub(first).min(used);
+    (text, shift(start), shift(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EvalError;
+    use crate::eval_program_string;
+
+    fn render(source: &str, diagnostic: impl FnOnce(Span) -> Diagnostic) -> String {
+        let mut sources = SourceMap::new();
+        let id = sources.add("main.soba", source);
+        Renderer::default().render(&diagnostic(Span::single(0).in_source(id)), &sources)
+    }
+
+    #[test]
+    fn test_render_error() {
+        let source = "1;\n1 + (2 / 0)";
+        let err = eval_program_string(source).unwrap_err();
+        let mut sources = SourceMap::new();
+        sources.add("main.soba", source);
+        assert_eq!(
+            Renderer::default().render(&Diagnostic::from(&err), &sources),
+            "error[E0001]: Division by zero\n\
+             \x20--> main.soba:2:6-11\n\
+             \x20 |\n\
+             2 | 1 + (2 / 0)\n\
+             \x20 |      ^^^^^\n\
+             \x20 = hint: make sure the divisor can never be zero"
+        );
+
+        // An empty span at the end of the input still gets a caret
+        let err = eval_program_string("1 +\t").unwrap_err();
+        let mut sources = SourceMap::new();
+        sources.add("main.soba", "1 +\t");
+        let rendered = Renderer::default().render(&Diagnostic::from(&err), &sources);
+        assert!(rendered.contains("1 | 1 +\n  |     ^\n"), "{rendered}");
+    }
+
+    #[test]
+    fn test_render_labels() {
+        let render_error = |source: &str| {
+            let err = eval_program_string(source).unwrap_err();
+            let mut sources = SourceMap::new();
+            sources.add("main.soba", source);
+            Renderer::default().render(&Diagnostic::from(&err), &sources)
+        };
+        assert_eq!(
+            render_error("(1 + 2"),
+            "error[E0102]: Mismatched parentheses\n\
+             \x20--> main.soba:1:7-7\n\
+             \x20 |\n\
+             1 | (1 + 2\n\
+             \x20 |       ^\n\
+             \x20 | - unclosed delimiter opened here\n\
+             \x20 = hint: add a `)` to close the `(`"
+        );
+        // A stray ')' has no label and its own hint
+        assert_eq!(
+            render_error("1 + 2)"),
+            "error[E0102]: Mismatched parentheses\n\
+             \x20--> main.soba:1:6-7\n\
+             \x20 |\n\
+             1 | 1 + 2)\n\
+             \x20 |      ^\n\
+             \x20 = hint: remove this `)` or add a `(` before it"
+        );
+
+        // A label on another line brings its own source line
+        let source = format!("{}(1 +\n2", "\n".repeat(9));
+        let rendered = render_error(&source);
+        assert!(
+            rendered.contains("11 | 2\n   |  ^\n10 | (1 +\n   | - unclosed"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn test_render_type_check_errors() {
+        let source = "-true; 2 < false";
+        let soba = crate::engine::Soba::builder().strict(true).build();
+        let err = soba.eval(source).unwrap_err();
+        let mut sources = SourceMap::new();
+        sources.add("main.soba", source);
+        assert_eq!(
+            Renderer::default().render(&Diagnostic::from(&err), &sources),
+            "error[E0003]: Type error: Cannot negate boolean value\n\
+             \x20--> main.soba:1:1-6\n\
+             \x20 |\n\
+             1 | -true; 2 < false\n\
+             \x20 | ^^^^^\n\
+             \x20 |        --------- type error: Cannot compare int and bool for ordering\n\
+             \x20 = hint: check the operand types: arithmetic needs numbers, only strings \
+             concatenate, and booleans are compared with `==` and `!=`"
+        );
+    }
+
+    #[test]
+    fn test_render_without_span_or_source() {
+        let err = SobaError::from(EvalError::Overflow { span: None });
+        assert_eq!(
+            Renderer::default().render(&Diagnostic::from(&err), &SourceMap::new()),
+            "error[E0002]: Arithmetic overflow\n\
+             \x20 = hint: integers must stay between -2147483648 and 2147483647"
+        );
+        assert_eq!(
+            render("1", |span| Diagnostic::warning("Odd")
+                .with_span(span.merge(Span::new(0, 1)))),
+            "warning: Odd\n --> main.soba:1:1-2\n  |\n1 | 1\n  | ^"
+        );
+    }
+
+    #[test]
+    fn test_long_lines_are_clipped() {
+        let line = format!("{}1 / 0{}", "1 + ".repeat(30), " + 2".repeat(30));
+        let start = line.find("1 / 0").unwrap();
+        let mut sources = SourceMap::new();
+        let id = sources.add("long.soba", line.as_str());
+        let renderer = Renderer {
+            width: 40,
+            ..Renderer::default()
+        };
+        let diagnostic = Diagnostic::error("Division by zero")
+            .with_span(Span::new(start, start + 5).in_source(id));
+        let rendered = renderer.render(&diagnostic, &sources);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[3], "1 | ...1 + 1 + 1 + 1 / 0 + 2 + 2 + 2 ...");
+        assert_eq!(lines[4], "  |                ^^^^^");
+        assert!(lines.iter().all(|line| line.len() <= 40));
+
+        // Columns count terminal cells, so wide characters keep carets aligned
+        let (text, start, end) = clip("ああああ x", 9, 10, 20);
+        assert_eq!((text.as_str(), start, end), ("ああああ x", 9, 10));
+        // A wide character cut in half is left out whole
+        let (text, start, end) = clip("ああああああああああ x", 21, 22, 12);
+        assert_eq!((text.as_str(), start, end), ("...あ x", 6, 7));
+    }
+
+    #[test]
+    fn test_color() {
+        let diagnostic = Diagnostic::warning("Redundant parentheses").with_code("W0001");
+        let rendered = Renderer {
+            color: true,
+            ..Renderer::default()
+        }
+        .render(&diagnostic, &SourceMap::new());
+        assert!(rendered.starts_with("\x1b[1;33mwarning[W0001]\x1b[0m"));
+        assert!(rendered.contains("remove one pair of parentheses"));
+        assert!(!Renderer::default()
+            .render(&diagnostic, &SourceMap::new())
+            .contains('\x1b'));
+    }
+}
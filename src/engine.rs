@@ -0,0 +1,452 @@
+//! Embedding facade
+//!
+//! `Soba` bundles the settings a host application would otherwise thread
+//! through the lexer, parser and evaluator by hand: custom operators, the
+//! parser's nesting limit, an evaluation step limit, cancellation, float
+//! equality and strict type checking. Build one with `Soba::builder()` and
+//! reuse it for every program.
+//!
+//! The builder has no settings for capabilities, builtin functions or
+//! injected variables: soba expressions cannot call functions or reference
+//! names yet, so there is nothing for such settings to configure.
+//!
+//! ```
+//! use soba::{Soba, Value};
+//! use std::num::NonZeroUsize;
+//!
+//! let soba = Soba::builder()
+//!     .max_steps(NonZeroUsize::new(1_000).unwrap())
+//!     .build();
+//! assert_eq!(soba.eval("1 + 2 == 3").unwrap(), Value::Bool(true));
+//! ```
+//!
+//! Hot paths that evaluate the same source repeatedly compile it once into
+//! a `CompiledExpr` and call `eval` on that, skipping the lexer and parser.
+
+use crate::ast::Program;
+use crate::error::{EvalError, SobaError, SobaResult};
+use crate::evaluator::{eval_program_with_float_eq, CancelHandle, EvalStatus, Evaluation};
+use crate::lexer::SobaLexer;
+use crate::operators::OperatorRegistry;
+use crate::parser::{Parser, DEFAULT_MAX_DEPTH};
+use crate::source::shebang_len;
+use crate::typeck::check_program;
+use crate::value::{FloatEq, Value};
+use std::fmt;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+/// A configured interpreter
+#[derive(Clone)]
+pub struct Soba {
+    operators: OperatorRegistry,
+    max_depth: usize,
+    max_steps: Option<NonZeroUsize>,
+    cancel: Option<CancelHandle>,
+    float_eq: FloatEq,
+    strict: bool,
+}
+
+/// Builder for `Soba`
+#[derive(Clone, Default)]
+pub struct SobaBuilder {
+    operators: OperatorRegistry,
+    max_depth: Option<usize>,
+    max_steps: Option<NonZeroUsize>,
+    cancel: Option<CancelHandle>,
+    float_eq: FloatEq,
+    strict: bool,
+}
+
+/// A program compiled once with an interpreter's settings, for evaluating
+/// any number of times
+///
+/// soba expressions cannot reference variables yet, so `eval` takes no
+/// bindings and every call evaluates the same program afresh.
+#[derive(Clone)]
+pub struct CompiledExpr {
+    soba: Soba,
+    program: Program,
+}
+
+/// Error from `Soba::eval_file`
+#[derive(Debug)]
+pub enum FileError {
+    /// The file could not be read
+    Io { path: PathBuf, error: io::Error },
+    /// The file was read but did not run
+    Soba(SobaError),
+}
+
+impl SobaBuilder {
+    /// Make the custom operators in `operators` available to programs
+    pub fn operators(mut self, operators: OperatorRegistry) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    /// Limit how deeply expressions may nest, `DEFAULT_MAX_DEPTH` by default
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Stop programs that run for more than `max_steps` evaluation steps
+    ///
+    /// Programs run without a limit by default. A limit read from a config
+    /// file goes through `NonZeroUsize::new`, so 0 can mean no limit there.
+    pub fn max_steps(mut self, max_steps: NonZeroUsize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Stop programs with `EvalError::Cancelled` once `handle` is cancelled
+    ///
+    /// Keep a clone of the handle to cancel from another thread.
+    pub fn cancel_handle(mut self, handle: CancelHandle) -> Self {
+        self.cancel = Some(handle);
+        self
+    }
+
+    /// Compare floats in `==` and `!=` with `float_eq`,
+    /// `FloatEq::Absolute(f64::EPSILON)` by default
+    pub fn float_eq(mut self, float_eq: FloatEq) -> Self {
+        self.float_eq = float_eq;
+        self
+    }
+
+    /// Type check programs before running them, rejecting type errors even
+    /// in operands that short-circuiting would skip
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn build(self) -> Soba {
+        Soba {
+            operators: self.operators,
+            max_depth: self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            max_steps: self.max_steps,
+            cancel: self.cancel,
+            float_eq: self.float_eq,
+            strict: self.strict,
+        }
+    }
+}
+
+impl Soba {
+    /// Start configuring an interpreter
+    pub fn builder() -> SobaBuilder {
+        SobaBuilder::default()
+    }
+
+    /// An interpreter with the default settings
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Parse `source` into a program that `run` can evaluate any number
+    /// of times
+    ///
+    /// In strict mode the program is also type checked, and every type
+    /// error is reported together in `SobaError::TypeCheckErrors`.
+    pub fn compile(&self, source: &str) -> SobaResult<Program> {
+        self.compile_at(source, 0)
+    }
+
+    /// Like `compile`, for a slice of a larger text that begins at byte
+    /// offset `start`; spans are in the coordinates of the whole text
+    pub fn compile_at(&self, source: &str, start: usize) -> SobaResult<Program> {
+        let mut lexer = SobaLexer::starting_at(source.chars().collect(), start);
+        lexer.set_operators(&self.operators);
+        let mut parser = Parser::with_operators(lexer, &self.operators)?;
+        parser.set_max_depth(self.max_depth);
+        let program = parser.parse_program()?;
+
+        if self.strict {
+            if let Err(errors) = check_program(&program) {
+                return Err(SobaError::TypeCheckErrors(errors));
+            }
+        }
+        Ok(program)
+    }
+
+    /// Evaluate a compiled program
+    pub fn run(&self, program: &Program) -> SobaResult<Value> {
+        if self.max_steps.is_none() && self.cancel.is_none() {
+            return Ok(eval_program_with_float_eq(
+                program,
+                &self.operators,
+                self.float_eq,
+            )?);
+        }
+
+        let limit = self.max_steps.unwrap_or(NonZeroUsize::MAX);
+        let mut evaluation = Evaluation::with_operators(program, limit, &self.operators);
+        if let Some(handle) = &self.cancel {
+            evaluation.set_cancel_handle(handle.clone());
+        }
+        evaluation.set_float_eq(self.float_eq);
+        match evaluation.resume()? {
+            EvalStatus::Complete(value) => Ok(value),
+            EvalStatus::Suspended => Err(EvalError::StepLimit {
+                limit: limit.get(),
+                span: None,
+            }
+            .into()),
+        }
+    }
+
+    /// Compile `source` into a `CompiledExpr` that keeps these settings
+    pub fn compile_expr(&self, source: &str) -> SobaResult<CompiledExpr> {
+        Ok(CompiledExpr {
+            program: self.compile(source)?,
+            soba: self.clone(),
+        })
+    }
+
+    /// Compile and evaluate `source`
+    pub fn eval(&self, source: &str) -> SobaResult<Value> {
+        self.run(&self.compile(source)?)
+    }
+
+    /// Read the file at `path` and evaluate it
+    pub fn eval_file(&self, path: impl AsRef<Path>) -> Result<Value, FileError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| FileError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let start = shebang_len(&source);
+        self.compile_at(&source[start..], start)
+            .and_then(|program| self.run(&program))
+            .map_err(FileError::Soba)
+    }
+}
+
+impl CompiledExpr {
+    /// Compile `source` with the default settings
+    pub fn compile(source: &str) -> SobaResult<Self> {
+        Soba::new().compile_expr(source)
+    }
+
+    /// Evaluate the compiled program
+    pub fn eval(&self) -> SobaResult<Value> {
+        self.soba.run(&self.program)
+    }
+
+    /// The parsed program
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+}
+
+impl Default for Soba {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            FileError::Soba(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io { error, .. } => Some(error),
+            FileError::Soba(err) => Some(err),
+        }
+    }
+}
+
+impl From<SobaError> for FileError {
+    fn from(err: SobaError) -> Self {
+        FileError::Soba(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ParseError;
+    use crate::operators::Associativity;
+    use crate::parser::Precedence;
+
+    #[test]
+    fn test_default_engine() {
+        let soba = Soba::new();
+        assert_eq!(soba.eval("1 + 2; 3 * 4").unwrap(), Value::Float(12.0));
+        assert_eq!(soba.eval("").unwrap(), Value::Int(0));
+        assert_eq!(soba.eval("1 / 0").unwrap_err().code(), "E0001");
+    }
+
+    #[test]
+    fn test_compile_once_run_many() {
+        let soba = Soba::new();
+        let program = soba.compile("2 ** 10").unwrap();
+        assert_eq!(soba.run(&program).unwrap(), Value::Float(1024.0));
+        assert_eq!(soba.run(&program).unwrap(), Value::Float(1024.0));
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix("<>", Precedence::Comparison, Associativity::Left, |l, r| {
+                Ok(Value::Bool(l != r))
+            })
+            .unwrap();
+        let soba = Soba::builder().operators(operators).build();
+        assert_eq!(soba.eval("1 <> 2").unwrap(), Value::Bool(true));
+        assert!(Soba::new().eval("1 <> 2").is_err());
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let soba = Soba::builder().max_depth(4).build();
+        assert!(soba.eval("((1))").is_ok());
+        assert!(matches!(
+            soba.eval("((((((1))))))"),
+            Err(SobaError::ParseError(ParseError::TooDeep { limit: 4, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_compiled_expr() {
+        let compiled = CompiledExpr::compile("1 + 2; 3 * 4 == 12").unwrap();
+        for _ in 0..3 {
+            assert_eq!(compiled.eval().unwrap(), Value::Bool(true));
+        }
+        assert_eq!(compiled.program().statements.len(), 2);
+        assert_eq!(CompiledExpr::compile("1 +").err().unwrap().code(), "E0101");
+
+        // Settings from the interpreter apply to every evaluation
+        let soba = Soba::builder()
+            .max_steps(NonZeroUsize::new(5).unwrap())
+            .build();
+        let limited = soba.compile_expr("1 + 2 + 3 + 4 + 5").unwrap();
+        assert_eq!(limited.eval().unwrap_err().code(), "E0006");
+        assert_eq!(limited.eval().unwrap_err().code(), "E0006");
+    }
+
+    #[test]
+    fn test_max_steps() {
+        let soba = Soba::builder()
+            .max_steps(NonZeroUsize::new(5).unwrap())
+            .build();
+        assert_eq!(soba.eval("1 + 2").unwrap(), Value::Float(3.0));
+        let err = soba.eval("1 + 2 + 3 + 4 + 5").unwrap_err();
+        assert_eq!(err.code(), "E0006");
+        assert_eq!(
+            err.to_string(),
+            "Evaluation error: Step limit exceeded (limit is 5)"
+        );
+        // Errors inside the budget are still reported as themselves
+        assert_eq!(soba.eval("1 / 0").unwrap_err().code(), "E0001");
+    }
+
+    #[test]
+    fn test_cancel_handle() {
+        let handle = CancelHandle::new();
+        let soba = Soba::builder().cancel_handle(handle.clone()).build();
+        assert_eq!(soba.eval("1 + 2").unwrap(), Value::Float(3.0));
+
+        handle.cancel();
+        let err = soba.eval("1 + 2").unwrap_err();
+        assert_eq!(err.code(), "E0007");
+        assert_eq!(err.to_string(), "Evaluation error: Evaluation cancelled");
+
+        handle.reset();
+        assert!(soba.eval("1 + 2").is_ok());
+    }
+
+    #[test]
+    fn test_float_eq() {
+        let sum = "0.1 + 0.2 == 0.3";
+        assert_eq!(Soba::new().eval(sum).unwrap(), Value::Bool(true));
+        let exact = Soba::builder().float_eq(FloatEq::Exact).build();
+        assert_eq!(exact.eval(sum).unwrap(), Value::Bool(false));
+        assert_eq!(
+            exact.eval("0.5 + 0.25 != 0.75").unwrap(),
+            Value::Bool(false)
+        );
+
+        // The default tolerance is too small to matter for large values
+        let large = "1000.1 + 0.2 == 1000.3";
+        assert_eq!(Soba::new().eval(large).unwrap(), Value::Bool(false));
+        let ulps = Soba::builder().float_eq(FloatEq::Ulps(4)).build();
+        assert_eq!(ulps.eval(large).unwrap(), Value::Bool(true));
+
+        // A step limit runs the resumable evaluator, with the same result
+        let limited = Soba::builder()
+            .float_eq(FloatEq::Ulps(4))
+            .max_steps(NonZeroUsize::new(100).unwrap())
+            .build();
+        assert_eq!(limited.eval(large).unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_strict() {
+        let soba = Soba::builder().strict(true).build();
+        assert_eq!(soba.eval("1 < 2").unwrap(), Value::Bool(true));
+        // Short-circuiting skips `-true`, but the type checker does not
+        assert_eq!(
+            Soba::new().eval("false && -true").unwrap(),
+            Value::Bool(false)
+        );
+        let err = soba.eval("false && -true").unwrap_err();
+        assert_eq!(err.code(), "E0003");
+        assert_eq!(err.span().map(|span| span.start), Some(9));
+
+        // Every type error is reported, not just the first
+        let err = soba.eval("-true; 1; 2 < false").unwrap_err();
+        let SobaError::TypeCheckErrors(errors) = &err else {
+            panic!("expected type check errors, got {err:?}");
+        };
+        let starts: Vec<_> = errors.iter().map(|error| error.span.start).collect();
+        assert_eq!(starts, vec![0, 10]);
+        assert_eq!(err.span().map(|span| span.start), Some(0));
+        assert_eq!(
+            err.to_string(),
+            format!("Type check failed\n{}\n{}", errors[0], errors[1])
+        );
+    }
+
+    #[test]
+    fn test_shebang_is_only_skipped_in_files() {
+        assert_eq!(Soba::new().eval("#!1").unwrap_err().code(), "E0201");
+    }
+
+    #[test]
+    fn test_eval_file() {
+        let dir = std::env::temp_dir().join(format!("soba-engine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("program.soba");
+        std::fs::write(&path, "1 + 1 == 2").unwrap();
+
+        let soba = Soba::new();
+        assert_eq!(soba.eval_file(&path).unwrap(), Value::Bool(true));
+        std::fs::write(&path, "#!/usr/bin/env soba\n1 + 1").unwrap();
+        assert_eq!(soba.eval_file(&path).unwrap(), Value::Float(2.0));
+        std::fs::write(&path, "#!/usr/bin/env soba\n1 / 0").unwrap();
+        // Spans count from the start of the file
+        let Err(FileError::Soba(err)) = soba.eval_file(&path) else {
+            panic!("expected an evaluation error");
+        };
+        assert_eq!(err.span().map(|span| span.start), Some(20));
+        std::fs::write(&path, "1 +").unwrap();
+        assert!(matches!(soba.eval_file(&path), Err(FileError::Soba(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let err = soba.eval_file(&path).unwrap_err();
+        assert!(matches!(err, FileError::Io { .. }));
+        assert!(err.to_string().starts_with(&path.display().to_string()));
+    }
+}
@@ -1,6 +1,15 @@
 //! Error types for the Soba programming language
+//!
+//! Every error variant has a stable code, e.g. `E0001` for division by
+//! zero, that `explain` describes at length; parser warnings have `W`
+//! codes. Codes are never reused: a removed variant retires its code.
 
-use std::fmt;
+use crate::lexer::TokenKind;
+use crate::parser::pratt::{EXPRESSION_START_TOKENS, INFIX_OPERATOR_TOKENS};
+use crate::prelude::*;
+use crate::span::Span;
+use core::fmt;
+use core::mem::discriminant;
 
 /// Main error type for Soba operations
 #[derive(Debug, Clone, PartialEq)]
@@ -8,32 +17,220 @@ pub enum SobaError {
     LexError(LexError),
     ParseError(ParseError),
     EvalError(EvalError),
+    /// Type errors that strict mode found before evaluation, in source
+    /// order; never empty
+    TypeCheckErrors(Vec<TypeCheckError>),
 }
 
 /// Lexing errors
 #[derive(Debug, Clone, PartialEq)]
 pub enum LexError {
-    InvalidNumber(String),
-    UnexpectedCharacter(char),
-    UnterminatedString,
+    InvalidNumber { text: String, span: Span },
+    UnexpectedCharacter { ch: char, span: Span },
+    UnterminatedString { span: Span },
 }
 
 /// Parsing errors
 #[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
-    UnexpectedToken(String),
-    UnexpectedEof,
-    MismatchedParentheses,
-    InvalidExpression,
+    /// A token that cannot appear here, with the tokens that could have
+    UnexpectedToken {
+        found: TokenKind,
+        expected: Vec<TokenKind>,
+        span: Span,
+    },
+    /// Input ended where more tokens were needed; the span is empty and
+    /// sits just past the last token
+    UnexpectedEof {
+        span: Span,
+    },
+    /// A '(' that is never closed, or a ')' with no '(' to close
+    ///
+    /// For an unclosed '(', `open` covers it and the span is empty and sits
+    /// where the input ended; for a stray ')', `open` is `None` and the
+    /// span covers the ')'.
+    MismatchedParentheses {
+        open: Option<Span>,
+        span: Span,
+    },
+    InvalidExpression {
+        span: Span,
+    },
+    /// Expressions nested more than `limit` levels deep; the span covers
+    /// the token that went past the limit
+    TooDeep {
+        limit: usize,
+        span: Span,
+    },
+    /// The lexer failed while the parser was reading tokens
+    LexError(LexError),
+}
+
+/// Non-fatal problems the parser noticed in code that parsed fine
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseWarning {
+    /// Parentheses directly around a parenthesized expression, e.g.
+    /// `((1 + 2))`; the span covers the outer pair
+    RedundantParentheses { span: Span },
+    /// `&&` and `||` mixed without parentheses, e.g. `a || b && c`; the
+    /// span covers the whole mixed expression
+    MixedLogicalOperators { span: Span },
 }
 
 /// Evaluation errors
+///
+/// Value operations create these without a span; the evaluator fills it in
+/// with the span of the expression that failed.
 #[derive(Debug, Clone, PartialEq)]
 pub enum EvalError {
-    DivisionByZero,
-    Overflow,
-    TypeError(String),
-    StackOverflow,
+    DivisionByZero { span: Option<Span> },
+    Overflow { span: Option<Span> },
+    TypeError { message: String, span: Option<Span> },
+    StackOverflow { span: Option<Span> },
+    UnknownOperator { symbol: String, span: Option<Span> },
+    StepLimit { limit: usize, span: Option<Span> },
+    Cancelled { span: Option<Span> },
+}
+
+/// A type error found before evaluation
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeCheckError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl SobaError {
+    /// Get the source location of the error, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            SobaError::LexError(e) => Some(e.span()),
+            SobaError::ParseError(e) => Some(e.span()),
+            SobaError::EvalError(e) => e.span(),
+            SobaError::TypeCheckErrors(errors) => errors.first().map(|e| e.span),
+        }
+    }
+
+    /// Stable code of the error, e.g. `E0001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            SobaError::LexError(e) => e.code(),
+            SobaError::ParseError(e) => e.code(),
+            SobaError::EvalError(e) => e.code(),
+            // The code of the error evaluation would report
+            SobaError::TypeCheckErrors(_) => "E0003",
+        }
+    }
+}
+
+impl LexError {
+    /// Get the source location of the error
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::InvalidNumber { span, .. }
+            | LexError::UnexpectedCharacter { span, .. }
+            | LexError::UnterminatedString { span } => *span,
+        }
+    }
+
+    /// Stable code of the error, e.g. `E0201`
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::InvalidNumber { .. } => "E0200",
+            LexError::UnexpectedCharacter { .. } => "E0201",
+            LexError::UnterminatedString { .. } => "E0202",
+        }
+    }
+}
+
+impl ParseError {
+    /// Get the source location of the error
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span }
+            | ParseError::MismatchedParentheses { span, .. }
+            | ParseError::InvalidExpression { span }
+            | ParseError::TooDeep { span, .. } => *span,
+            ParseError::LexError(e) => e.span(),
+        }
+    }
+
+    /// Stable code of the error, e.g. `E0102`; lexer errors keep their own
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "E0100",
+            ParseError::UnexpectedEof { .. } => "E0101",
+            ParseError::MismatchedParentheses { .. } => "E0102",
+            ParseError::InvalidExpression { .. } => "E0103",
+            ParseError::TooDeep { .. } => "E0104",
+            ParseError::LexError(e) => e.code(),
+        }
+    }
+}
+
+impl ParseWarning {
+    /// Get the source location of the warning
+    pub fn span(&self) -> Span {
+        match self {
+            ParseWarning::RedundantParentheses { span }
+            | ParseWarning::MixedLogicalOperators { span } => *span,
+        }
+    }
+
+    /// Stable code of the warning, e.g. `W0001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseWarning::RedundantParentheses { .. } => "W0001",
+            ParseWarning::MixedLogicalOperators { .. } => "W0002",
+        }
+    }
+}
+
+impl EvalError {
+    /// Get the source location of the error, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            EvalError::DivisionByZero { span }
+            | EvalError::Overflow { span }
+            | EvalError::TypeError { span, .. }
+            | EvalError::StackOverflow { span }
+            | EvalError::UnknownOperator { span, .. }
+            | EvalError::StepLimit { span, .. }
+            | EvalError::Cancelled { span } => *span,
+        }
+    }
+
+    /// Stable code of the error, e.g. `E0001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            EvalError::DivisionByZero { .. } => "E0001",
+            EvalError::Overflow { .. } => "E0002",
+            EvalError::TypeError { .. } => "E0003",
+            EvalError::StackOverflow { .. } => "E0004",
+            EvalError::UnknownOperator { .. } => "E0005",
+            EvalError::StepLimit { .. } => "E0006",
+            EvalError::Cancelled { .. } => "E0007",
+        }
+    }
+
+    /// Attach a span unless the error already has one
+    ///
+    /// Errors bubble up from the innermost failing expression, so the first
+    /// span attached is the most precise one.
+    pub fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            EvalError::DivisionByZero { span }
+            | EvalError::Overflow { span }
+            | EvalError::TypeError { span, .. }
+            | EvalError::StackOverflow { span }
+            | EvalError::UnknownOperator { span, .. }
+            | EvalError::StepLimit { span, .. }
+            | EvalError::Cancelled { span } => {
+                span.get_or_insert(new_span);
+            }
+        }
+        self
+    }
 }
 
 impl fmt::Display for SobaError {
@@ -42,16 +239,29 @@ impl fmt::Display for SobaError {
             SobaError::LexError(e) => write!(f, "Lexing error: {e}"),
             SobaError::ParseError(e) => write!(f, "Parse error: {e}"),
             SobaError::EvalError(e) => write!(f, "Evaluation error: {e}"),
+            SobaError::TypeCheckErrors(errors) => {
+                write!(f, "Type check failed")?;
+                for error in errors {
+                    write!(f, "\n{error}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+impl fmt::Display for TypeCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Type error at {}: {}", self.span, self.message)
+    }
+}
+
 impl fmt::Display for LexError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LexError::InvalidNumber(s) => write!(f, "Invalid number: {s}"),
-            LexError::UnexpectedCharacter(c) => write!(f, "Unexpected character: '{c}'"),
-            LexError::UnterminatedString => write!(f, "Unterminated string literal"),
+            LexError::InvalidNumber { text, .. } => write!(f, "Invalid number: {text}"),
+            LexError::UnexpectedCharacter { ch, .. } => write!(f, "Unexpected character: '{ch}'"),
+            LexError::UnterminatedString { .. } => write!(f, "Unterminated string literal"),
         }
     }
 }
@@ -59,10 +269,37 @@ impl fmt::Display for LexError {
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ParseError::UnexpectedToken(token) => write!(f, "Unexpected token: {token}"),
-            ParseError::UnexpectedEof => write!(f, "Unexpected end of input"),
-            ParseError::MismatchedParentheses => write!(f, "Mismatched parentheses"),
-            ParseError::InvalidExpression => write!(f, "Invalid expression"),
+            ParseError::UnexpectedToken {
+                found, expected, ..
+            } => {
+                if expected.is_empty() {
+                    write!(f, "Unexpected token: '{found}'")
+                } else {
+                    write!(
+                        f,
+                        "Unexpected token: expected {}, found '{found}'",
+                        describe_expected(expected)
+                    )
+                }
+            }
+            ParseError::UnexpectedEof { .. } => write!(f, "Unexpected end of input"),
+            ParseError::MismatchedParentheses { .. } => write!(f, "Mismatched parentheses"),
+            ParseError::InvalidExpression { .. } => write!(f, "Invalid expression"),
+            ParseError::TooDeep { limit, .. } => {
+                write!(f, "Expression nested too deeply (limit is {limit})")
+            }
+            ParseError::LexError(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::RedundantParentheses { .. } => write!(f, "Redundant parentheses"),
+            ParseWarning::MixedLogicalOperators { .. } => {
+                write!(f, "'&&' and '||' mixed without parentheses")
+            }
         }
     }
 }
@@ -70,18 +307,24 @@ impl fmt::Display for ParseError {
 impl fmt::Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EvalError::DivisionByZero => write!(f, "Division by zero"),
-            EvalError::Overflow => write!(f, "Arithmetic overflow"),
-            EvalError::TypeError(msg) => write!(f, "Type error: {msg}"),
-            EvalError::StackOverflow => write!(f, "Stack overflow"),
+            EvalError::DivisionByZero { .. } => write!(f, "Division by zero"),
+            EvalError::Overflow { .. } => write!(f, "Arithmetic overflow"),
+            EvalError::TypeError { message, .. } => write!(f, "Type error: {message}"),
+            EvalError::StackOverflow { .. } => write!(f, "Stack overflow"),
+            EvalError::UnknownOperator { symbol, .. } => write!(f, "Unknown operator: '{symbol}'"),
+            EvalError::StepLimit { limit, .. } => {
+                write!(f, "Step limit exceeded (limit is {limit})")
+            }
+            EvalError::Cancelled { .. } => write!(f, "Evaluation cancelled"),
         }
     }
 }
 
-impl std::error::Error for SobaError {}
-impl std::error::Error for LexError {}
-impl std::error::Error for ParseError {}
-impl std::error::Error for EvalError {}
+impl core::error::Error for SobaError {}
+impl core::error::Error for LexError {}
+impl core::error::Error for ParseError {}
+impl core::error::Error for EvalError {}
+impl core::error::Error for TypeCheckError {}
 
 impl From<LexError> for SobaError {
     fn from(err: LexError) -> Self {
@@ -91,7 +334,10 @@ impl From<LexError> for SobaError {
 
 impl From<ParseError> for SobaError {
     fn from(err: ParseError) -> Self {
-        SobaError::ParseError(err)
+        match err {
+            ParseError::LexError(e) => SobaError::LexError(e),
+            other => SobaError::ParseError(other),
+        }
     }
 }
 
@@ -103,17 +349,224 @@ impl From<EvalError> for SobaError {
 
 impl From<LexError> for ParseError {
     fn from(err: LexError) -> Self {
-        match err {
-            LexError::InvalidNumber(s) => {
-                ParseError::UnexpectedToken(format!("invalid number: {s}"))
-            }
-            LexError::UnexpectedCharacter(c) => {
-                ParseError::UnexpectedToken(format!("unexpected character: '{c}'"))
-            }
-            LexError::UnterminatedString => {
-                ParseError::UnexpectedToken("unterminated string".to_string())
-            }
+        ParseError::LexError(err)
+    }
+}
+
+/// Error codes and their explanations, in code order
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0001",
+        "Division by zero.\n\n\
+         The right operand of `/` is zero, e.g. `1 / 0` or `1 / (2 - 2.0)`. \
+         This is an error for float operands too.",
+    ),
+    (
+        "E0002",
+        "Arithmetic overflow.\n\n\
+         An integer operation produced a result outside the 32-bit signed \
+         range, such as negating the smallest integer.",
+    ),
+    (
+        "E0003",
+        "Type error.\n\n\
+         An operator or conversion was given a value of a type it does not \
+         accept. Booleans cannot be negated or ordered, e.g. `-true` or \
+         `true < 1`; compare them with `==` and `!=` instead. Strings and \
+         host values have no numeric value, so arithmetic on them fails \
+         (`Cannot add int and str`), and only two strings can be \
+         concatenated (`Cannot concatenate int and str`). Converting a value \
+         to a type it does not fit, e.g. a string to an int with \
+         `Value::coerce_to`, and passing a host operator an operand it does \
+         not expect are type errors too. In strict mode the type checker \
+         reports these before the program runs.",
+    ),
+    (
+        "E0004",
+        "Stack overflow.\n\n\
+         Evaluation nested too deeply to continue. Split the expression into \
+         several statements.",
+    ),
+    (
+        "E0005",
+        "Unknown operator.\n\n\
+         The program uses a custom operator that the evaluator has no \
+         implementation for. Evaluate it with the `OperatorRegistry` the \
+         operator was registered in.",
+    ),
+    (
+        "E0006",
+        "Step limit exceeded.\n\n\
+         The program took more evaluation steps than the engine allows. Each \
+         literal and operator is one step; the limit is set with \
+         `SobaBuilder::max_steps`.",
+    ),
+    (
+        "E0007",
+        "Evaluation cancelled.\n\n\
+         The host stopped the evaluation through its `CancelHandle`, e.g. \
+         because a user gave up waiting or a request timed out. The program \
+         itself is not at fault.",
+    ),
+    (
+        "E0100",
+        "Unexpected token.\n\n\
+         The parser found a token that cannot appear at this point, e.g. the \
+         second `*` in `1 * * 2` or the `2` in `1 2`. The message lists the \
+         tokens that could have appeared instead.",
+    ),
+    (
+        "E0101",
+        "Unexpected end of input.\n\n\
+         The input ended in the middle of an expression, e.g. `1 +`. Complete \
+         the expression or remove the trailing operator.",
+    ),
+    (
+        "E0102",
+        "Mismatched parentheses.\n\n\
+         A `(` is never closed, e.g. `(1 + 2`, or a `)` has no `(` before it \
+         to close, e.g. `1 + 2)` or `(1))`. For a `(` the error points at \
+         the end of the input and marks the `(` that is missing its `)`; \
+         for a `)` it points at the extra `)`.",
+    ),
+    (
+        "E0103",
+        "Invalid expression.\n\n\
+         The input does not form an expression.",
+    ),
+    (
+        "E0104",
+        "Expression nested too deeply.\n\n\
+         Parentheses or operators nest deeper than the parser's limit, e.g. \
+         thousands of `(` in a row. The limit keeps parsing from overflowing \
+         the stack; raise it with `Parser::set_max_depth` if needed.",
+    ),
+    (
+        "E0200",
+        "Invalid number.\n\n\
+         A number literal cannot be represented, e.g. an integer larger than \
+         `2147483647` or a float too large to be finite. `-2147483648` is \
+         allowed: a `-` directly before `2147483648` makes it one literal.",
+    ),
+    (
+        "E0201",
+        "Unexpected character.\n\n\
+         The input contains a character that does not start any token, e.g. \
+         `@` or `#`.",
+    ),
+    (
+        "E0202",
+        "Unterminated string literal.\n\n\
+         A string literal is missing its closing quote.",
+    ),
+    (
+        "W0001",
+        "Redundant parentheses.\n\n\
+         An expression is wrapped in two pairs of parentheses, e.g. `((1 + 2))`. \
+         One pair means the same thing.",
+    ),
+    (
+        "W0002",
+        "`&&` and `||` mixed without parentheses.\n\n\
+         `&&` binds tighter than `||`, so `a || b && c` means `a || (b && c)`. \
+         Add parentheses to make the intended grouping explicit.",
+    ),
+];
+
+/// Long explanation of an error or warning code such as `E0001`, if it
+/// is one
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map(|(_, explanation)| *explanation)
+}
+
+/// One-line suggestions for fixing each error or warning, shown under
+/// rendered diagnostics
+const HINTS: &[(&str, &str)] = &[
+    ("E0001", "make sure the divisor can never be zero"),
+    (
+        "E0002",
+        "integers must stay between -2147483648 and 2147483647",
+    ),
+    (
+        "E0003",
+        "check the operand types: arithmetic needs numbers, only strings concatenate, \
+         and booleans are compared with `==` and `!=`",
+    ),
+    ("E0004", "split the expression into several statements"),
+    (
+        "E0005",
+        "evaluate the program with the registry that defines the operator",
+    ),
+    (
+        "E0006",
+        "simplify the program or raise the engine's step limit",
+    ),
+    ("E0007", "run the program again to retry"),
+    ("E0100", "look for a missing or doubled operator here"),
+    (
+        "E0101",
+        "complete the expression or remove the trailing operator",
+    ),
+    ("E0102", "add a `)` to close the `(`"),
+    ("E0103", "write an expression such as `1 + 2`"),
+    ("E0104", "split the expression into several statements"),
+    (
+        "E0200",
+        "integers must be at most 2147483647; add `.0` for a float",
+    ),
+    ("E0201", "remove the character; comments start with `//`"),
+    ("E0202", "add the closing quote"),
+    ("W0001", "remove one pair of parentheses"),
+    ("W0002", "add parentheses around the `&&` operation"),
+];
+
+/// One-line hint for fixing the error or warning with `code`, if it is one
+pub fn hint(code: &str) -> Option<&'static str> {
+    HINTS
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map(|(_, hint)| *hint)
+}
+
+/// Describe a set of expected tokens for an error message,
+/// e.g. "')' or an operator"
+fn describe_expected(expected: &[TokenKind]) -> String {
+    let contains_all = |group: &[TokenKind]| {
+        group.iter().all(|kind| {
+            expected
+                .iter()
+                .any(|e| discriminant(e) == discriminant(kind))
+        })
+    };
+    let in_group = |group: &[TokenKind], kind: &TokenKind| {
+        group.iter().any(|g| discriminant(g) == discriminant(kind))
+    };
+
+    let mut items = Vec::new();
+    let has_expression = contains_all(EXPRESSION_START_TOKENS);
+    let has_operator = contains_all(INFIX_OPERATOR_TOKENS);
+    for kind in expected {
+        if (has_expression && in_group(EXPRESSION_START_TOKENS, kind))
+            || (has_operator && in_group(INFIX_OPERATOR_TOKENS, kind))
+        {
+            continue;
         }
+        items.push(format!("'{kind}'"));
+    }
+    if has_expression {
+        items.push("an expression".to_string());
+    }
+    if has_operator {
+        items.push("an operator".to_string());
+    }
+
+    match items.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {last}", rest.join(", ")),
     }
 }
 
@@ -122,3 +575,112 @@ pub type SobaResult<T> = Result<T, SobaError>;
 pub type LexResult<T> = Result<T, LexError>;
 pub type ParseResult<T> = Result<T, ParseError>;
 pub type EvalResult<T> = Result<T, EvalError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval_program_string;
+
+    fn code_of(input: &str) -> &'static str {
+        eval_program_string(input).unwrap_err().code()
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(code_of("1 / 0"), "E0001");
+        assert_eq!(code_of("-true"), "E0003");
+        assert_eq!(code_of("1 * * 2"), "E0100");
+        assert_eq!(code_of("1 +"), "E0101");
+        assert_eq!(code_of("(1 + 2"), "E0102");
+        assert_eq!(code_of("1 + 2)"), "E0102");
+        assert_eq!(code_of("99999999999"), "E0200");
+        assert_eq!(code_of("1 @ 2"), "E0201");
+        // Lexer errors seen by the parser keep their code
+        let err = ParseError::from(LexError::UnterminatedString {
+            span: Span::single(0),
+        });
+        assert_eq!(err.code(), "E0202");
+    }
+
+    #[test]
+    fn test_every_code_is_explained_once() {
+        for pair in EXPLANATIONS.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{} is out of order", pair[1].0);
+        }
+        let span = Some(Span::single(0));
+        let errors: Vec<SobaError> = vec![
+            EvalError::DivisionByZero { span }.into(),
+            EvalError::Overflow { span }.into(),
+            EvalError::TypeError {
+                message: String::new(),
+                span,
+            }
+            .into(),
+            EvalError::StackOverflow { span }.into(),
+            EvalError::UnknownOperator {
+                symbol: String::new(),
+                span,
+            }
+            .into(),
+            EvalError::StepLimit { limit: 0, span }.into(),
+            EvalError::Cancelled { span }.into(),
+            ParseError::UnexpectedToken {
+                found: TokenKind::Eof,
+                expected: Vec::new(),
+                span: Span::single(0),
+            }
+            .into(),
+            ParseError::UnexpectedEof {
+                span: Span::single(0),
+            }
+            .into(),
+            ParseError::MismatchedParentheses {
+                open: Some(Span::single(0)),
+                span: Span::single(0),
+            }
+            .into(),
+            ParseError::InvalidExpression {
+                span: Span::single(0),
+            }
+            .into(),
+            ParseError::TooDeep {
+                limit: 0,
+                span: Span::single(0),
+            }
+            .into(),
+            LexError::InvalidNumber {
+                text: String::new(),
+                span: Span::single(0),
+            }
+            .into(),
+            LexError::UnexpectedCharacter {
+                ch: '@',
+                span: Span::single(0),
+            }
+            .into(),
+            LexError::UnterminatedString {
+                span: Span::single(0),
+            }
+            .into(),
+        ];
+        let warnings = [
+            ParseWarning::RedundantParentheses {
+                span: Span::single(0),
+            },
+            ParseWarning::MixedLogicalOperators {
+                span: Span::single(0),
+            },
+        ];
+        let codes: Vec<&str> = errors
+            .iter()
+            .map(SobaError::code)
+            .chain(warnings.iter().map(ParseWarning::code))
+            .collect();
+        let explained: Vec<&str> = EXPLANATIONS.iter().map(|(code, _)| *code).collect();
+        assert_eq!(codes, explained);
+        let hinted: Vec<&str> = HINTS.iter().map(|(code, _)| *code).collect();
+        assert_eq!(codes, hinted);
+        assert!(explain("E0001").unwrap().starts_with("Division by zero."));
+        assert_eq!(explain("E9999"), None);
+    }
+}
@@ -1,98 +1,238 @@
 //! Expression evaluation
 
-use crate::ast::{BinaryOp, Expr, Program, Statement, UnaryOp};
+use crate::ast::{
+    ArenaExpr, ArenaProgram, BinaryOp, Expr, ExprArena, ExprId, PostfixOp, Program, Statement,
+    UnaryOp,
+};
 use crate::error::EvalResult;
-use crate::value::Value;
+use crate::operators::{OperatorRegistry, NO_OPERATORS};
+use crate::span::Span;
+use crate::value::{FloatEq, Value};
 
 /// Evaluate an expression AST node
+///
+/// Custom operators fail with `EvalError::UnknownOperator`; use
+/// `eval_expr_with_operators` to supply their handlers.
 pub fn eval_expr(expr: &Expr) -> EvalResult<Value> {
+    eval_expr_with_operators(expr, &NO_OPERATORS)
+}
+
+/// Evaluate an expression, running custom operators with `operators`
+pub fn eval_expr_with_operators(expr: &Expr, operators: &OperatorRegistry) -> EvalResult<Value> {
+    eval_expr_in(expr, operators, FloatEq::default())
+}
+
+fn eval_expr_in(expr: &Expr, operators: &OperatorRegistry, float_eq: FloatEq) -> EvalResult<Value> {
     match expr {
         Expr::Int { value, .. } => Ok(Value::Int(*value)),
         Expr::Float { value, .. } => Ok(Value::Float(*value)),
         Expr::Bool { value, .. } => Ok(Value::Bool(*value)),
 
         Expr::InfixExpr {
-            left, op, right, ..
-        } => {
-            match op {
-                // Arithmetic operations - evaluate both sides
-                BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Multiply | BinaryOp::Divide => {
-                    let left_val = eval_expr(left)?;
-                    let right_val = eval_expr(right)?;
-
-                    match op {
-                        BinaryOp::Plus => left_val.add_value(right_val),
-                        BinaryOp::Minus => left_val.subtract_value(right_val),
-                        BinaryOp::Multiply => left_val.multiply_value(right_val),
-                        BinaryOp::Divide => left_val.divide_value(right_val),
-                        _ => unreachable!(),
-                    }
-                }
-                // Logical operations - short-circuit evaluation
-                BinaryOp::LogicalAnd => {
-                    let left_val = eval_expr(left)?;
-                    if !left_val.is_truthy() {
-                        Ok(Value::Bool(false))
-                    } else {
-                        let right_val = eval_expr(right)?;
-                        left_val.logical_and(right_val)
-                    }
+            left,
+            op,
+            right,
+            span,
+        } => match op {
+            // Logical operations - short-circuit evaluation
+            BinaryOp::LogicalAnd => {
+                let left_val = eval_expr_in(left, operators, float_eq)?;
+                if !left_val.is_truthy() {
+                    Ok(Value::Bool(false))
+                } else {
+                    let right_val = eval_expr_in(right, operators, float_eq)?;
+                    left_val.logical_and(right_val)
                 }
-                BinaryOp::LogicalOr => {
-                    let left_val = eval_expr(left)?;
-                    if left_val.is_truthy() {
-                        Ok(Value::Bool(true))
-                    } else {
-                        let right_val = eval_expr(right)?;
-                        left_val.logical_or(right_val)
-                    }
-                }
-                // Comparison operations - evaluate both sides
-                BinaryOp::Equal
-                | BinaryOp::NotEqual
-                | BinaryOp::Less
-                | BinaryOp::Greater
-                | BinaryOp::LessEqual
-                | BinaryOp::GreaterEqual => {
-                    let left_val = eval_expr(left)?;
-                    let right_val = eval_expr(right)?;
-
-                    match op {
-                        BinaryOp::Equal => left_val.equal_to(right_val),
-                        BinaryOp::NotEqual => left_val.not_equal_to(right_val),
-                        BinaryOp::Less => left_val.less_than(right_val),
-                        BinaryOp::Greater => left_val.greater_than(right_val),
-                        BinaryOp::LessEqual => left_val.less_equal(right_val),
-                        BinaryOp::GreaterEqual => left_val.greater_equal(right_val),
-                        _ => unreachable!(),
-                    }
+            }
+            BinaryOp::LogicalOr => {
+                let left_val = eval_expr_in(left, operators, float_eq)?;
+                if left_val.is_truthy() {
+                    Ok(Value::Bool(true))
+                } else {
+                    let right_val = eval_expr_in(right, operators, float_eq)?;
+                    left_val.logical_or(right_val)
                 }
             }
+            // Arithmetic and comparison operations - evaluate both sides
+            _ => {
+                let left_val = eval_expr_in(left, operators, float_eq)?;
+                let right_val = eval_expr_in(right, operators, float_eq)?;
+                apply_binary(operators, float_eq, *op, left_val, right_val, *span)
+            }
+        },
+
+        Expr::Grouped { inner, .. } => eval_expr_in(inner, operators, float_eq),
+
+        Expr::UnaryExpr { op, operand, span } => {
+            let operand = eval_expr_in(operand, operators, float_eq)?;
+            apply_unary(operators, *op, operand, *span)
         }
 
-        Expr::Grouped { inner, .. } => eval_expr(inner),
+        Expr::PostfixExpr { op, operand, span } => {
+            let operand = eval_expr_in(operand, operators, float_eq)?;
+            apply_postfix(operators, *op, operand, *span)
+        }
+    }
+}
+
+/// Evaluate the expression rooted at `id` inside an arena
+pub fn eval_arena_expr(arena: &ExprArena, id: ExprId) -> EvalResult<Value> {
+    eval_arena_expr_in(arena, id, FloatEq::default())
+}
 
-        Expr::UnaryExpr { op, operand, .. } => {
-            let val = eval_expr(operand)?;
-            match op {
-                UnaryOp::Plus => val.positive(),
-                UnaryOp::Minus => val.negate(),
-                UnaryOp::LogicalNot => val.logical_not(),
+fn eval_arena_expr_in(arena: &ExprArena, id: ExprId, float_eq: FloatEq) -> EvalResult<Value> {
+    match &arena[id] {
+        ArenaExpr::Int { value } => Ok(Value::Int(*value)),
+        ArenaExpr::Float { value } => Ok(Value::Float(*value)),
+        ArenaExpr::Bool { value } => Ok(Value::Bool(*value)),
+
+        ArenaExpr::InfixExpr { left, op, right } => match op {
+            BinaryOp::LogicalAnd => {
+                let left_val = eval_arena_expr_in(arena, *left, float_eq)?;
+                if !left_val.is_truthy() {
+                    Ok(Value::Bool(false))
+                } else {
+                    let right_val = eval_arena_expr_in(arena, *right, float_eq)?;
+                    left_val.logical_and(right_val)
+                }
             }
-        }
+            BinaryOp::LogicalOr => {
+                let left_val = eval_arena_expr_in(arena, *left, float_eq)?;
+                if left_val.is_truthy() {
+                    Ok(Value::Bool(true))
+                } else {
+                    let right_val = eval_arena_expr_in(arena, *right, float_eq)?;
+                    left_val.logical_or(right_val)
+                }
+            }
+            _ => {
+                let left_val = eval_arena_expr_in(arena, *left, float_eq)?;
+                let right_val = eval_arena_expr_in(arena, *right, float_eq)?;
+                apply_binary(
+                    &NO_OPERATORS,
+                    float_eq,
+                    *op,
+                    left_val,
+                    right_val,
+                    arena.span(id),
+                )
+            }
+        },
+
+        ArenaExpr::Grouped { inner } => eval_arena_expr_in(arena, *inner, float_eq),
+
+        ArenaExpr::UnaryExpr { op, operand } => apply_unary(
+            &NO_OPERATORS,
+            *op,
+            eval_arena_expr_in(arena, *operand, float_eq)?,
+            arena.span(id),
+        ),
+
+        ArenaExpr::PostfixExpr { op, operand } => apply_postfix(
+            &NO_OPERATORS,
+            *op,
+            eval_arena_expr_in(arena, *operand, float_eq)?,
+            arena.span(id),
+        ),
     }
 }
 
+/// Apply a binary operator to two already-evaluated operands, comparing
+/// floats with `float_eq`
+///
+/// Errors are tagged with `span`, the span of the operator's expression.
+pub(super) fn apply_binary(
+    operators: &OperatorRegistry,
+    float_eq: FloatEq,
+    op: BinaryOp,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> EvalResult<Value> {
+    let result = match op {
+        BinaryOp::Plus => left.add_value(right),
+        BinaryOp::Minus => left.subtract_value(right),
+        BinaryOp::Multiply => left.multiply_value(right),
+        BinaryOp::Divide => left.divide_value(right),
+        BinaryOp::Power => left.power_value(right),
+        BinaryOp::LogicalAnd => left.logical_and(right),
+        BinaryOp::LogicalOr => left.logical_or(right),
+        BinaryOp::Equal => left.equal_to_with(right, float_eq),
+        BinaryOp::NotEqual => left.not_equal_to_with(right, float_eq),
+        BinaryOp::Less => left.less_than(right),
+        BinaryOp::Greater => left.greater_than(right),
+        BinaryOp::LessEqual => left.less_equal(right),
+        BinaryOp::GreaterEqual => left.greater_equal(right),
+        BinaryOp::Custom(custom) => operators.apply_infix(custom, left, right),
+    };
+    result.map_err(|err| err.with_span(span))
+}
+
+/// Apply a unary operator to an already-evaluated operand
+pub(super) fn apply_unary(
+    operators: &OperatorRegistry,
+    op: UnaryOp,
+    operand: Value,
+    span: Span,
+) -> EvalResult<Value> {
+    let result = match op {
+        UnaryOp::Plus => operand.positive(),
+        UnaryOp::Minus => operand.negate(),
+        UnaryOp::LogicalNot => operand.logical_not(),
+        UnaryOp::Custom(custom) => operators.apply_prefix(custom, operand),
+    };
+    result.map_err(|err| err.with_span(span))
+}
+
+/// Apply a postfix operator to an already-evaluated operand
+pub(super) fn apply_postfix(
+    operators: &OperatorRegistry,
+    op: PostfixOp,
+    operand: Value,
+    span: Span,
+) -> EvalResult<Value> {
+    let result = match op {
+        PostfixOp::Custom(custom) => operators.apply_postfix(custom, operand),
+    };
+    result.map_err(|err| err.with_span(span))
+}
+
 /// Evaluate a statement AST node
 pub fn eval_statement(stmt: &Statement) -> EvalResult<Value> {
+    eval_statement_with_operators(stmt, &NO_OPERATORS)
+}
+
+/// Evaluate a statement, running custom operators with `operators`
+pub fn eval_statement_with_operators(
+    stmt: &Statement,
+    operators: &OperatorRegistry,
+) -> EvalResult<Value> {
     match stmt {
-        Statement::ExprStatement { expr, .. } => eval_expr(expr),
+        Statement::ExprStatement { expr, .. } => eval_expr_with_operators(expr, operators),
     }
 }
 
 /// Evaluate a program AST node
 /// Returns the value of the last statement, or a default value for empty programs
 pub fn eval_program(program: &Program) -> EvalResult<Value> {
+    eval_program_with_operators(program, &NO_OPERATORS)
+}
+
+/// Evaluate a program, running custom operators with `operators`
+pub fn eval_program_with_operators(
+    program: &Program,
+    operators: &OperatorRegistry,
+) -> EvalResult<Value> {
+    eval_program_with_float_eq(program, operators, FloatEq::default())
+}
+
+/// Like `eval_program_with_operators`, comparing floats in `==` and `!=`
+/// with `float_eq`
+pub fn eval_program_with_float_eq(
+    program: &Program,
+    operators: &OperatorRegistry,
+    float_eq: FloatEq,
+) -> EvalResult<Value> {
     if program.statements.is_empty() {
         // Return a default value for empty programs
         return Ok(Value::Int(0));
@@ -100,7 +240,27 @@ pub fn eval_program(program: &Program) -> EvalResult<Value> {
 
     let mut last_value = Value::Int(0);
     for stmt in &program.statements {
-        last_value = eval_statement(stmt)?;
+        let Statement::ExprStatement { expr, .. } = stmt;
+        last_value = eval_expr_in(expr, operators, float_eq)?;
+    }
+
+    Ok(last_value)
+}
+
+/// Evaluate an arena program, returning the value of the last statement
+pub fn eval_arena_program(program: &ArenaProgram) -> EvalResult<Value> {
+    eval_arena_program_with_float_eq(program, FloatEq::default())
+}
+
+/// Like `eval_arena_program`, comparing floats in `==` and `!=` with
+/// `float_eq`
+pub fn eval_arena_program_with_float_eq(
+    program: &ArenaProgram,
+    float_eq: FloatEq,
+) -> EvalResult<Value> {
+    let mut last_value = Value::Int(0);
+    for id in &program.statements {
+        last_value = eval_arena_expr_in(&program.arena, *id, float_eq)?;
     }
 
     Ok(last_value)
@@ -110,6 +270,7 @@ pub fn eval_program(program: &Program) -> EvalResult<Value> {
 mod tests {
     use super::*;
     use crate::ast::Expr;
+    use crate::soba_ast;
 
     #[test]
     fn test_eval_integer() {
@@ -118,6 +279,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_eval_float() {
         let expr = Expr::float(3.14);
         assert_eq!(eval_expr(&expr).unwrap(), Value::Float(3.14));
@@ -125,58 +287,31 @@ mod tests {
 
     #[test]
     fn test_eval_addition() {
-        use crate::span::{Position, Span};
-
-        let expr = Expr::InfixExpr {
-            left: Box::new(Expr::int(2)),
-            op: BinaryOp::Plus,
-            right: Box::new(Expr::int(3)),
-            span: Span::single(Position::start()),
-        };
-
+        let expr = soba_ast!(2 + 3);
         assert_eq!(eval_expr(&expr).unwrap(), Value::Float(5.0));
     }
 
     #[test]
     fn test_eval_unary_minus() {
-        use crate::span::{Position, Span};
-
-        let expr = Expr::UnaryExpr {
-            op: UnaryOp::Minus,
-            operand: Box::new(Expr::int(5)),
-            span: Span::single(Position::start()),
-        };
-
+        let expr = soba_ast!(-5);
         assert_eq!(eval_expr(&expr).unwrap(), Value::Int(-5));
     }
 
     #[test]
     fn test_eval_division() {
-        use crate::span::{Position, Span};
-
-        let expr = Expr::InfixExpr {
-            left: Box::new(Expr::int(8)),
-            op: BinaryOp::Divide,
-            right: Box::new(Expr::int(2)),
-            span: Span::single(Position::start()),
-        };
-
+        let expr = soba_ast!(8 / 2);
         assert_eq!(eval_expr(&expr).unwrap(), Value::Float(4.0));
     }
 
     #[test]
     fn test_eval_division_by_zero() {
         use crate::error::EvalError;
-        use crate::span::{Position, Span};
-
-        let expr = Expr::InfixExpr {
-            left: Box::new(Expr::int(5)),
-            op: BinaryOp::Divide,
-            right: Box::new(Expr::int(0)),
-            span: Span::single(Position::start()),
-        };
 
-        assert!(matches!(eval_expr(&expr), Err(EvalError::DivisionByZero)));
+        let expr = soba_ast!(5 / 0);
+        assert!(matches!(
+            eval_expr(&expr),
+            Err(EvalError::DivisionByZero { .. })
+        ));
     }
 
     #[test]
@@ -193,12 +328,12 @@ mod tests {
 
     #[test]
     fn test_eval_logical_not() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::UnaryExpr {
             op: UnaryOp::LogicalNot,
             operand: Box::new(Expr::bool(true)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(false));
@@ -206,13 +341,13 @@ mod tests {
 
     #[test]
     fn test_eval_logical_and_true() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::bool(true)),
             op: BinaryOp::LogicalAnd,
             right: Box::new(Expr::bool(true)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -220,13 +355,13 @@ mod tests {
 
     #[test]
     fn test_eval_logical_and_false() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::bool(false)),
             op: BinaryOp::LogicalAnd,
             right: Box::new(Expr::bool(true)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(false));
@@ -234,13 +369,13 @@ mod tests {
 
     #[test]
     fn test_eval_logical_or_true() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::bool(true)),
             op: BinaryOp::LogicalOr,
             right: Box::new(Expr::bool(false)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -248,13 +383,13 @@ mod tests {
 
     #[test]
     fn test_eval_logical_or_false() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::bool(false)),
             op: BinaryOp::LogicalOr,
             right: Box::new(Expr::bool(false)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(false));
@@ -262,13 +397,13 @@ mod tests {
 
     #[test]
     fn test_eval_comparison_equal() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(5)),
             op: BinaryOp::Equal,
             right: Box::new(Expr::int(5)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -276,13 +411,13 @@ mod tests {
 
     #[test]
     fn test_eval_comparison_not_equal() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(5)),
             op: BinaryOp::NotEqual,
             right: Box::new(Expr::int(3)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -290,13 +425,13 @@ mod tests {
 
     #[test]
     fn test_eval_comparison_less() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(3)),
             op: BinaryOp::Less,
             right: Box::new(Expr::int(5)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -304,13 +439,13 @@ mod tests {
 
     #[test]
     fn test_eval_comparison_greater() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(5)),
             op: BinaryOp::Greater,
             right: Box::new(Expr::int(3)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -318,13 +453,13 @@ mod tests {
 
     #[test]
     fn test_eval_comparison_less_equal() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(3)),
             op: BinaryOp::LessEqual,
             right: Box::new(Expr::int(5)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -332,13 +467,13 @@ mod tests {
 
     #[test]
     fn test_eval_comparison_greater_equal() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(5)),
             op: BinaryOp::GreaterEqual,
             right: Box::new(Expr::int(5)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -346,13 +481,13 @@ mod tests {
 
     #[test]
     fn test_eval_mixed_types_comparison() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         let expr = Expr::InfixExpr {
             left: Box::new(Expr::int(5)),
             op: BinaryOp::Equal,
             right: Box::new(Expr::float(5.0)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         };
 
         assert_eq!(eval_expr(&expr).unwrap(), Value::Bool(true));
@@ -381,28 +516,111 @@ mod tests {
 
     #[test]
     fn test_eval_multiple_statement_program() {
-        use crate::span::{Position, Span};
+        use crate::span::Span;
 
         // Create statements: 1 + 2; 3 * 4; 10;
         let stmt1 = Statement::expr_statement(Expr::InfixExpr {
             left: Box::new(Expr::int(1)),
             op: BinaryOp::Plus,
             right: Box::new(Expr::int(2)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         });
 
         let stmt2 = Statement::expr_statement(Expr::InfixExpr {
             left: Box::new(Expr::int(3)),
             op: BinaryOp::Multiply,
             right: Box::new(Expr::int(4)),
-            span: Span::single(Position::start()),
+            span: Span::single(0),
         });
 
         let stmt3 = Statement::expr_statement(Expr::int(10));
 
         let program = Program::new(vec![stmt1, stmt2, stmt3]);
-        
+
         // Should return the value of the last statement (10)
         assert_eq!(eval_program(&program).unwrap(), Value::Int(10));
     }
+
+    #[test]
+    fn test_eval_arena_matches_tree() {
+        use crate::span::Span;
+
+        // !(1 < 2) || -4 / 2 == -2
+        let expr = Expr::InfixExpr {
+            left: Box::new(Expr::UnaryExpr {
+                op: UnaryOp::LogicalNot,
+                operand: Box::new(Expr::InfixExpr {
+                    left: Box::new(Expr::int(1)),
+                    op: BinaryOp::Less,
+                    right: Box::new(Expr::int(2)),
+                    span: Span::single(0),
+                }),
+                span: Span::single(0),
+            }),
+            op: BinaryOp::LogicalOr,
+            right: Box::new(Expr::InfixExpr {
+                left: Box::new(Expr::InfixExpr {
+                    left: Box::new(Expr::int(-4)),
+                    op: BinaryOp::Divide,
+                    right: Box::new(Expr::int(2)),
+                    span: Span::single(0),
+                }),
+                op: BinaryOp::Equal,
+                right: Box::new(Expr::int(-2)),
+                span: Span::single(0),
+            }),
+            span: Span::single(0),
+        };
+
+        let mut arena = ExprArena::new();
+        let root = arena.alloc_expr(&expr);
+        assert_eq!(
+            eval_arena_expr(&arena, root).unwrap(),
+            eval_expr(&expr).unwrap()
+        );
+        assert_eq!(eval_arena_expr(&arena, root).unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_eval_arena_program() {
+        let program = Program::new(vec![
+            Statement::expr_statement(Expr::int(1)),
+            Statement::expr_statement(Expr::bool(false)),
+        ]);
+        let arena_program = ArenaProgram::from_program(&program);
+        assert_eq!(
+            eval_arena_program(&arena_program).unwrap(),
+            eval_program(&program).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_eval_with_float_eq() {
+        use crate::span::Span;
+
+        // 0.5 == 0.5000000000000001, one ULP apart
+        let program = Program::new(vec![Statement::expr_statement(Expr::InfixExpr {
+            left: Box::new(Expr::float(0.5)),
+            op: BinaryOp::Equal,
+            right: Box::new(Expr::float(0.5000000000000001)),
+            span: Span::single(0),
+        })]);
+        let arena_program = ArenaProgram::from_program(&program);
+        let operators = OperatorRegistry::new();
+
+        for (float_eq, expected) in [
+            (FloatEq::default(), true),
+            (FloatEq::Exact, false),
+            (FloatEq::Ulps(1), true),
+        ] {
+            assert_eq!(
+                eval_program_with_float_eq(&program, &operators, float_eq).unwrap(),
+                Value::Bool(expected)
+            );
+            assert_eq!(
+                eval_arena_program_with_float_eq(&arena_program, float_eq).unwrap(),
+                Value::Bool(expected)
+            );
+        }
+    }
 }
@@ -1,7 +1,13 @@
 //! Evaluation module
 //!
-//! This module contains the expression evaluator.
+//! This module contains the expression evaluator and its resumable variant.
 
 pub mod eval;
+pub mod resumable;
 
-pub use eval::{eval_expr, eval_program, eval_statement};
+pub use eval::{
+    eval_arena_expr, eval_arena_program, eval_arena_program_with_float_eq, eval_expr,
+    eval_expr_with_operators, eval_program, eval_program_with_float_eq,
+    eval_program_with_operators, eval_statement, eval_statement_with_operators,
+};
+pub use resumable::{CancelHandle, EvalStatus, Evaluation};
@@ -0,0 +1,362 @@
+//! Resumable evaluation
+//!
+//! `Evaluation` walks a program with an explicit work stack instead of the
+//! Rust call stack, so it can stop after a fixed number of steps and pick up
+//! where it left off. Hosts can interleave many programs on one thread by
+//! calling `resume()` on each in turn. Another thread can stop an
+//! evaluation through its `CancelHandle`; the evaluation checks it before
+//! every step.
+
+use super::eval::{apply_binary, apply_postfix, apply_unary};
+use crate::ast::{BinaryOp, Expr, PostfixOp, Program, Statement, UnaryOp};
+use crate::error::{EvalError, EvalResult};
+use crate::operators::{OperatorRegistry, NO_OPERATORS};
+use crate::prelude::*;
+use crate::span::Span;
+use crate::value::{FloatEq, Value};
+use alloc::sync::Arc;
+use core::num::NonZeroUsize;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Outcome of a call to `Evaluation::resume`
+#[derive(Debug, PartialEq, Clone)]
+pub enum EvalStatus {
+    /// The program finished with this value
+    Complete(Value),
+    /// The step budget ran out; call `resume` again to continue
+    Suspended,
+}
+
+/// Shared flag that stops an evaluation with `EvalError::Cancelled`
+///
+/// Clones share the flag, so one can be handed to another thread while the
+/// evaluation runs. Cancelling is sticky: every evaluation watching the
+/// handle fails until it is `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the evaluations watching this handle at their next step
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Let evaluations watching this handle run again
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// A pending unit of work on the evaluation stack
+enum Task<'a> {
+    /// Evaluate an expression and push its value
+    Eval(&'a Expr),
+    /// Pop two values and apply a binary operator, reporting errors at the span
+    Binary(BinaryOp, Span),
+    /// Pop the left operand of `&&`/`||` and decide whether to evaluate the right
+    ShortCircuit(BinaryOp, &'a Expr, Span),
+    /// Pop one value and apply a unary operator, reporting errors at the span
+    Unary(UnaryOp, Span),
+    /// Pop one value and apply a postfix operator, reporting errors at the span
+    Postfix(PostfixOp, Span),
+}
+
+/// An in-progress evaluation of a program
+pub struct Evaluation<'a> {
+    statements: &'a [Statement],
+    operators: &'a OperatorRegistry,
+    next_statement: usize,
+    budget: usize,
+    cancel: CancelHandle,
+    float_eq: FloatEq,
+    tasks: Vec<Task<'a>>,
+    values: Vec<Value>,
+    last_value: Value,
+    result: Option<EvalResult<Value>>,
+}
+
+impl<'a> Evaluation<'a> {
+    /// Prepare to evaluate `program`, running at most `budget` steps per `resume`
+    ///
+    /// The budget is never 0, so every `resume` makes progress.
+    pub fn new(program: &'a Program, budget: NonZeroUsize) -> Self {
+        Self::with_operators(program, budget, &NO_OPERATORS)
+    }
+
+    /// Like `new`, running custom operators with the handlers in `operators`
+    pub fn with_operators(
+        program: &'a Program,
+        budget: NonZeroUsize,
+        operators: &'a OperatorRegistry,
+    ) -> Self {
+        Self {
+            statements: &program.statements,
+            operators,
+            next_statement: 0,
+            budget: budget.get(),
+            cancel: CancelHandle::new(),
+            float_eq: FloatEq::default(),
+            tasks: Vec::new(),
+            values: Vec::new(),
+            last_value: Value::Int(0),
+            result: None,
+        }
+    }
+
+    /// Handle that cancels this evaluation, from any thread
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Watch `handle` instead of this evaluation's own, so one handle can
+    /// cancel several evaluations
+    pub fn set_cancel_handle(&mut self, handle: CancelHandle) {
+        self.cancel = handle;
+    }
+
+    /// Compare floats in `==` and `!=` with `float_eq` instead of the
+    /// default `FloatEq::Absolute(f64::EPSILON)`
+    pub fn set_float_eq(&mut self, float_eq: FloatEq) {
+        self.float_eq = float_eq;
+    }
+
+    /// Check whether the evaluation has produced its final result
+    pub fn is_complete(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Run up to the step budget
+    ///
+    /// Once the program has finished, every further call returns the same
+    /// value or error.
+    pub fn resume(&mut self) -> EvalResult<EvalStatus> {
+        if self.result.is_none() {
+            if let Some(result) = self.run(self.budget) {
+                self.result = Some(result);
+            }
+        }
+
+        match &self.result {
+            Some(Ok(value)) => Ok(EvalStatus::Complete(value.clone())),
+            Some(Err(err)) => Err(err.clone()),
+            None => Ok(EvalStatus::Suspended),
+        }
+    }
+
+    /// Run up to `steps` steps, returning the result if the program finished
+    fn run(&mut self, steps: usize) -> Option<EvalResult<Value>> {
+        for _ in 0..steps {
+            if self.cancel.is_cancelled() {
+                return Some(Err(EvalError::Cancelled { span: None }));
+            }
+            if self.tasks.is_empty() {
+                if let Some(value) = self.values.pop() {
+                    self.last_value = value;
+                }
+
+                match self.statements.get(self.next_statement) {
+                    Some(Statement::ExprStatement { expr, .. }) => {
+                        self.next_statement += 1;
+                        self.tasks.push(Task::Eval(expr));
+                    }
+                    None => return Some(Ok(self.last_value.clone())),
+                }
+            }
+
+            if let Err(err) = self.step() {
+                return Some(Err(err));
+            }
+        }
+
+        if self.tasks.is_empty() && self.next_statement == self.statements.len() {
+            let value = self.values.pop().unwrap_or_else(|| self.last_value.clone());
+            return Some(Ok(value));
+        }
+
+        None
+    }
+
+    /// Process the task on top of the stack
+    fn step(&mut self) -> EvalResult<()> {
+        let Some(task) = self.tasks.pop() else {
+            return Ok(());
+        };
+
+        match task {
+            Task::Eval(expr) => match expr {
+                Expr::Int { value, .. } => self.values.push(Value::Int(*value)),
+                Expr::Float { value, .. } => self.values.push(Value::Float(*value)),
+                Expr::Bool { value, .. } => self.values.push(Value::Bool(*value)),
+                Expr::InfixExpr {
+                    left,
+                    op,
+                    right,
+                    span,
+                } => match op {
+                    BinaryOp::LogicalAnd | BinaryOp::LogicalOr => {
+                        self.tasks.push(Task::ShortCircuit(*op, right, *span));
+                        self.tasks.push(Task::Eval(left));
+                    }
+                    _ => {
+                        self.tasks.push(Task::Binary(*op, *span));
+                        self.tasks.push(Task::Eval(right));
+                        self.tasks.push(Task::Eval(left));
+                    }
+                },
+                Expr::Grouped { inner, .. } => self.tasks.push(Task::Eval(inner)),
+                Expr::UnaryExpr { op, operand, span } => {
+                    self.tasks.push(Task::Unary(*op, *span));
+                    self.tasks.push(Task::Eval(operand));
+                }
+                Expr::PostfixExpr { op, operand, span } => {
+                    self.tasks.push(Task::Postfix(*op, *span));
+                    self.tasks.push(Task::Eval(operand));
+                }
+            },
+            Task::Binary(op, span) => {
+                let right = self.pop_value();
+                let left = self.pop_value();
+                self.values.push(apply_binary(
+                    self.operators,
+                    self.float_eq,
+                    op,
+                    left,
+                    right,
+                    span,
+                )?);
+            }
+            Task::ShortCircuit(op, right, span) => {
+                let left = self.pop_value();
+                let truthy = left.is_truthy();
+                match op {
+                    BinaryOp::LogicalAnd if !truthy => self.values.push(Value::Bool(false)),
+                    BinaryOp::LogicalOr if truthy => self.values.push(Value::Bool(true)),
+                    _ => {
+                        self.values.push(left);
+                        self.tasks.push(Task::Binary(op, span));
+                        self.tasks.push(Task::Eval(right));
+                    }
+                }
+            }
+            Task::Unary(op, span) => {
+                let operand = self.pop_value();
+                self.values
+                    .push(apply_unary(self.operators, op, operand, span)?);
+            }
+            Task::Postfix(op, span) => {
+                let operand = self.pop_value();
+                self.values
+                    .push(apply_postfix(self.operators, op, operand, span)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pop_value(&mut self) -> Value {
+        self.values
+            .pop()
+            .expect("evaluation stack underflow: operator task without operands")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::eval_program;
+    use crate::parser::test_util::parse;
+
+    fn budget(steps: usize) -> NonZeroUsize {
+        NonZeroUsize::new(steps).unwrap()
+    }
+
+    fn run_to_completion(evaluation: &mut Evaluation) -> (EvalResult<Value>, usize) {
+        let mut resumes = 0;
+        loop {
+            resumes += 1;
+            match evaluation.resume() {
+                Ok(EvalStatus::Complete(value)) => return (Ok(value), resumes),
+                Ok(EvalStatus::Suspended) => continue,
+                Err(err) => return (Err(err), resumes),
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_budget_suspends_and_matches_eval_program() {
+        let program = parse("1 + 2; (3 * 4) < 20 && !false; -(2 - 7)");
+        let mut evaluation = Evaluation::new(&program, budget(2));
+
+        assert_eq!(evaluation.resume().unwrap(), EvalStatus::Suspended);
+        let (result, resumes) = run_to_completion(&mut evaluation);
+        assert!(resumes > 1);
+        assert_eq!(result.unwrap(), eval_program(&program).unwrap());
+    }
+
+    #[test]
+    fn test_large_budget_completes_in_one_resume() {
+        let program = parse("2 * 3; 10");
+        let mut evaluation = Evaluation::new(&program, budget(1_000));
+        assert_eq!(
+            evaluation.resume().unwrap(),
+            EvalStatus::Complete(Value::Int(10))
+        );
+        assert!(evaluation.is_complete());
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let program = parse("");
+        let mut evaluation = Evaluation::new(&program, budget(1));
+        assert_eq!(
+            evaluation.resume().unwrap(),
+            EvalStatus::Complete(Value::Int(0))
+        );
+    }
+
+    #[test]
+    fn test_short_circuit_skips_right_operand() {
+        // The right side would divide by zero if it were evaluated
+        let program = parse("false && 1 / 0 == 1; true || 1 / 0 == 1");
+        let mut evaluation = Evaluation::new(&program, budget(1));
+        let (result, _) = run_to_completion(&mut evaluation);
+        assert_eq!(result.unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_error_is_sticky() {
+        let program = parse("1; 5 / 0; 2");
+        let mut evaluation = Evaluation::new(&program, budget(3));
+        let (result, _) = run_to_completion(&mut evaluation);
+        assert!(matches!(result, Err(EvalError::DivisionByZero { .. })));
+        assert!(matches!(
+            evaluation.resume(),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cancel_from_another_thread() {
+        let program = parse("1 + 2; 3 * 4");
+        let mut evaluation = Evaluation::new(&program, budget(1));
+        assert_eq!(evaluation.resume().unwrap(), EvalStatus::Suspended);
+
+        let handle = evaluation.cancel_handle();
+        std::thread::spawn(move || handle.cancel()).join().unwrap();
+        assert!(matches!(
+            evaluation.resume(),
+            Err(EvalError::Cancelled { span: None })
+        ));
+        // Like other errors, cancellation is final for this evaluation
+        evaluation.cancel_handle().reset();
+        assert!(evaluation.resume().is_err());
+    }
+}
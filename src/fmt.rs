@@ -0,0 +1,68 @@
+//! Formatting as a library API
+//!
+//! `format_source` is what editors and build tools call: it formats with
+//! the given `FormatOptions` and reports every syntax error it finds as a
+//! `Diagnostic`, not just the first. The layout itself comes from
+//! `cst::format_source_with`, which works on the trivia-preserving tokens,
+//! so comments survive formatting.
+
+pub use crate::cst::FormatOptions;
+
+use crate::cst::format_source_with;
+use crate::diagnostic::Diagnostic;
+use crate::error::ParseError;
+use crate::lexer::SobaLexer;
+use crate::parser::Parser;
+
+/// Format `input` in the style given by `options`
+///
+/// Fails with a diagnostic for each statement that does not parse, or for
+/// the first invalid character.
+pub fn format_source(input: &str, options: &FormatOptions) -> Result<String, Vec<Diagnostic>> {
+    format_source_with(input, options).map_err(|err| syntax_errors(input, err))
+}
+
+/// The errors of the recovering parser, which goes on past the first one
+/// that `err` is
+fn syntax_errors(input: &str, err: ParseError) -> Vec<Diagnostic> {
+    if let ParseError::LexError(_) = err {
+        return vec![(&err).into()];
+    }
+    let lexer = SobaLexer::new(input.chars().collect());
+    let errors = match Parser::new(lexer) {
+        Ok(mut parser) => parser.parse_program_with_recovery().1,
+        Err(err) => vec![err],
+    };
+    if errors.is_empty() {
+        return vec![(&err).into()];
+    }
+    errors.iter().map(Diagnostic::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_source() {
+        let options = FormatOptions {
+            indent_width: 2,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_source("1+ // one\n2;3*4", &options).unwrap(),
+            "1 + // one\n  2;\n3 * 4\n"
+        );
+    }
+
+    #[test]
+    fn test_every_syntax_error_is_reported() {
+        let errors = format_source("1 +;\n2 * ;\n3", &FormatOptions::default()).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|err| err.code == Some("E0100")));
+        assert_eq!(errors[1].span.unwrap().start, 9);
+
+        let errors = format_source("1 @ 2", &FormatOptions::default()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+}
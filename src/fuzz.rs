@@ -0,0 +1,179 @@
+//! Entry points for fuzzing
+//!
+//! Each function takes arbitrary bytes, runs part of the pipeline on them
+//! and returns normally whatever the input is. A panic is a bug: either a
+//! crash reachable from untrusted input or a broken invariant between two
+//! parts of the pipeline that are meant to agree. The cargo-fuzz targets in
+//! `fuzz/` call these functions; input that is not UTF-8 is ignored.
+
+use crate::analysis::analyze_program;
+use crate::ast::{check_round_trip, ArenaProgram};
+use crate::cst::{format_source, parse_cst, ProgramNode};
+use crate::evaluator::{eval_arena_program, eval_program, EvalStatus, Evaluation};
+use crate::lexer::{Lexer, SobaLexer, TokenKind};
+use crate::parser::Parser;
+use crate::typeck::check_program;
+use std::num::NonZeroUsize;
+
+/// Lex `data` with and without error recovery
+///
+/// Checks that a recovering lexer never fails and that its tokens and
+/// trivia reproduce the input exactly.
+pub fn fuzz_lex(data: &[u8]) {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut lexer = SobaLexer::new(input.chars().collect());
+    while let Ok(token) = lexer.next_token() {
+        if token.kind == TokenKind::Eof {
+            break;
+        }
+    }
+
+    let mut lexer = SobaLexer::new(input.chars().collect());
+    lexer.set_error_recovery(true);
+    let tokens = lexer
+        .tokenize_with_trivia()
+        .expect("a recovering lexer never fails");
+    let mut text = String::new();
+    for token in &tokens {
+        text.extend(token.leading.iter().map(|trivia| trivia.text.as_str()));
+        text.push_str(&token.text);
+        text.extend(token.trailing.iter().map(|trivia| trivia.text.as_str()));
+    }
+    assert_eq!(text, input, "tokens do not reproduce the input");
+}
+
+/// Parse `data` into an AST, with and without recovery, and into a CST
+///
+/// Checks that the AST and CST parsers accept the same inputs and build the
+/// same program, that the CST is lossless, that printed programs parse
+/// back unchanged, and that formatting is idempotent and keeps the program.
+pub fn fuzz_parse(data: &[u8]) {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let program = parse(input);
+    let cst = parse_cst(input);
+    let _ = Parser::new(SobaLexer::new(input.chars().collect()))
+        .map(|mut parser| parser.parse_program_with_recovery());
+
+    match (&program, &cst) {
+        (Some(program), Ok(cst)) => {
+            assert_eq!(cst.text(), input, "the CST is not lossless");
+            let lowered = ProgramNode::cast(cst).expect("parse_cst returns a program node");
+            assert!(
+                lowered.to_ast().eq_ignoring_spans(program),
+                "the CST lowers to a different program"
+            );
+
+            if let Err(err) = check_round_trip(program) {
+                panic!("{err}");
+            }
+
+            let formatted = format_source(input).expect("a valid program can be formatted");
+            assert!(
+                parse(&formatted).is_some_and(|formatted| formatted.eq_ignoring_spans(program)),
+                "formatting {input:?} changes the program"
+            );
+            assert_eq!(
+                format_source(&formatted).as_ref(),
+                Ok(&formatted),
+                "formatting {input:?} twice gives a different result"
+            );
+        }
+        (None, Err(_)) => {}
+        (program, cst) => panic!(
+            "the parsers disagree on {input:?}: AST {}, CST {:?}",
+            if program.is_some() { "ok" } else { "failed" },
+            cst.as_ref().err(),
+        ),
+    }
+}
+
+/// Parse `data` and run every analysis and evaluator on it
+///
+/// Checks that the tree-walking, arena and resumable evaluators agree.
+pub fn fuzz_eval(data: &[u8]) {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Some(program) = parse(input) else {
+        return;
+    };
+
+    let _ = check_program(&program);
+    let _ = analyze_program(&program);
+
+    // `Debug` output compares NaN results as equal
+    let expected = format!("{:?}", eval_program(&program));
+    let arena = eval_arena_program(&ArenaProgram::from_program(&program));
+    assert_eq!(
+        format!("{arena:?}"),
+        expected,
+        "the arena evaluator disagrees"
+    );
+
+    let mut evaluation = Evaluation::new(&program, NonZeroUsize::new(3).unwrap());
+    let resumed = loop {
+        match evaluation.resume() {
+            Ok(EvalStatus::Suspended) => continue,
+            Ok(EvalStatus::Complete(value)) => break Ok(value),
+            Err(err) => break Err(err),
+        }
+    };
+    assert_eq!(
+        format!("{resumed:?}"),
+        expected,
+        "the resumable evaluator disagrees"
+    );
+}
+
+fn parse(input: &str) -> Option<crate::ast::Program> {
+    Parser::new(SobaLexer::new(input.chars().collect()))
+        .and_then(|mut parser| parser.parse_program())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small deterministic generator, so the corpus needs no dependency
+    fn corpus() -> Vec<Vec<u8>> {
+        let mut pieces: Vec<&str> =
+            "1 0 2.5 .5 7. true false x + - * / ** ! && || == != < <= > >= ( ) ; @ é = &"
+                .split(' ')
+                .collect();
+        pieces.extend([" ", "\n", "// c\n", "99999999999"]);
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let mut corpus = vec![Vec::new(), vec![0xff, 0xfe], b"((((((".to_vec()];
+        corpus.push("(".repeat(1000).into_bytes());
+        for _ in 0..2000 {
+            let len = next() % 12;
+            let input: String = (0..len)
+                .map(|_| pieces[(next() % pieces.len() as u64) as usize])
+                .collect();
+            corpus.push(input.into_bytes());
+        }
+        corpus
+    }
+
+    #[test]
+    fn test_entry_points_on_generated_inputs() {
+        for input in corpus() {
+            fuzz_lex(&input);
+            fuzz_parse(&input);
+            fuzz_eval(&input);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Classifying source text for syntax highlighting
+//!
+//! `classify` maps every token and comment of a text to a `TokenClass`, so
+//! editor plugins, terminal highlighters and HTML renderers color soba the
+//! same way. It never fails: text the lexer cannot read is classified as
+//! `TokenClass::Error` and classification carries on after it.
+
+use crate::lexer::{SobaLexer, TokenKind, Trivia, TriviaKind, TriviaToken};
+use crate::operators::OperatorRegistry;
+use crate::span::Span;
+
+/// What a piece of source text is, for choosing its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenClass {
+    /// `true`, `false` and keywords registered by an embedder
+    Keyword,
+    /// A word that is not a keyword
+    Identifier,
+    /// An integer or float literal
+    Number,
+    /// A built-in or custom operator
+    Operator,
+    /// Parentheses, braces, brackets, `;`, `,` and `:`
+    Punctuation,
+    /// A `//` comment
+    Comment,
+    /// Text the lexer could not read
+    Error,
+}
+
+impl TokenClass {
+    /// Lowercase name, e.g. for CSS classes
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenClass::Keyword => "keyword",
+            TokenClass::Identifier => "identifier",
+            TokenClass::Number => "number",
+            TokenClass::Operator => "operator",
+            TokenClass::Punctuation => "punctuation",
+            TokenClass::Comment => "comment",
+            TokenClass::Error => "error",
+        }
+    }
+
+    fn of(kind: &TokenKind) -> Option<TokenClass> {
+        let class = match kind {
+            TokenKind::Int(_) | TokenKind::Float(_) => TokenClass::Number,
+            TokenKind::True | TokenKind::False | TokenKind::Keyword(_) => TokenClass::Keyword,
+            TokenKind::Identifier(_) => TokenClass::Identifier,
+            TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Asterisk
+            | TokenKind::Slash
+            | TokenKind::StarStar
+            | TokenKind::Bang
+            | TokenKind::AndAnd
+            | TokenKind::OrOr
+            | TokenKind::Equal
+            | TokenKind::NotEqual
+            | TokenKind::Less
+            | TokenKind::Greater
+            | TokenKind::LessEqual
+            | TokenKind::GreaterEqual
+            | TokenKind::Custom(_) => TokenClass::Operator,
+            TokenKind::LeftParen
+            | TokenKind::RightParen
+            | TokenKind::LeftBrace
+            | TokenKind::RightBrace
+            | TokenKind::LeftBracket
+            | TokenKind::RightBracket
+            | TokenKind::Semicolon
+            | TokenKind::Comma
+            | TokenKind::Colon => TokenClass::Punctuation,
+            TokenKind::Error(_) => TokenClass::Error,
+            TokenKind::Eof => return None,
+        };
+        Some(class)
+    }
+}
+
+/// The class of every token and comment in `input`, in source order
+///
+/// Whitespace is left out, so the spans do not have to cover the input.
+pub fn classify(input: &str) -> Vec<(Span, TokenClass)> {
+    classify_with(SobaLexer::new(input.chars().collect()))
+}
+
+/// Like `classify`, reading the custom operators in `operators` as
+/// operators
+pub fn classify_with_operators(
+    input: &str,
+    operators: &OperatorRegistry,
+) -> Vec<(Span, TokenClass)> {
+    classify_with(SobaLexer::with_operators(
+        input.chars().collect(),
+        operators,
+    ))
+}
+
+fn classify_with(mut lexer: SobaLexer) -> Vec<(Span, TokenClass)> {
+    lexer.set_error_recovery(true);
+    let tokens = lexer
+        .tokenize_with_trivia()
+        .expect("lexing with error recovery never fails");
+    let mut classes = Vec::new();
+    for TriviaToken {
+        token,
+        leading,
+        trailing,
+        ..
+    } in &tokens
+    {
+        classes.extend(comments(leading));
+        if let Some(class) = TokenClass::of(&token.kind) {
+            classes.push((token.span, class));
+        }
+        classes.extend(comments(trailing));
+    }
+    classes
+}
+
+fn comments(trivia: &[Trivia]) -> impl Iterator<Item = (Span, TokenClass)> + '_ {
+    trivia
+        .iter()
+        .filter(|trivia| trivia.kind == TriviaKind::LineComment)
+        .map(|trivia| (trivia.span, TokenClass::Comment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::Associativity;
+    use crate::parser::Precedence;
+    use crate::value::Value;
+
+    /// Each classified piece of `input` with its class name
+    fn pieces(input: &str, classes: Vec<(Span, TokenClass)>) -> Vec<(&str, &str)> {
+        classes
+            .into_iter()
+            .map(|(span, class)| (&input[span.start..span.end], class.name()))
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        let input = "// sum\n(1 + 2.5) >= x; !true // done";
+        assert_eq!(
+            pieces(input, classify(input)),
+            [
+                ("// sum", "comment"),
+                ("(", "punctuation"),
+                ("1", "number"),
+                ("+", "operator"),
+                ("2.5", "number"),
+                (")", "punctuation"),
+                (">=", "operator"),
+                ("x", "identifier"),
+                (";", "punctuation"),
+                ("!", "operator"),
+                ("true", "keyword"),
+                ("// done", "comment"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_do_not_stop_classification() {
+        let input = "1 @ 2";
+        assert_eq!(
+            pieces(input, classify(input)),
+            [("1", "number"), ("@", "error"), ("2", "number")]
+        );
+        assert!(classify("").is_empty());
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Left,
+                |_, _| Ok(Value::Int(0)),
+            )
+            .unwrap();
+        let input = "1 <=> 2";
+        assert_eq!(
+            pieces(input, classify_with_operators(input, &operators)),
+            [("1", "number"), ("<=>", "operator"), ("2", "number")]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Keyword registry
+//!
+//! The lexer reads every word and looks it up in a [`KeywordRegistry`].
+//! Words the registry knows become their keyword token; any other word is
+//! an `Identifier`. Embedders add keywords to reserve words for their own
+//! syntax, and the parser decides what each keyword means.
+
+use super::token::TokenKind;
+use crate::operators::intern;
+use crate::prelude::*;
+use core::fmt;
+
+/// The built-in keywords and the tokens they produce
+const BUILTIN_KEYWORDS: &[(&str, TokenKind)] =
+    &[("true", TokenKind::True), ("false", TokenKind::False)];
+
+/// Errors from registering a keyword
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeywordError {
+    /// The word is empty, or is not an ASCII letter or `_` followed by
+    /// ASCII letters, digits and `_`
+    InvalidWord(String),
+    /// The word is already a keyword
+    AlreadyDefined(String),
+}
+
+impl fmt::Display for KeywordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeywordError::InvalidWord(word) => write!(f, "Invalid keyword '{word}'"),
+            KeywordError::AlreadyDefined(word) => write!(f, "Keyword '{word}' is already defined"),
+        }
+    }
+}
+
+impl core::error::Error for KeywordError {}
+
+/// The words the lexer reads as keywords rather than identifiers
+#[derive(Debug, Clone)]
+pub struct KeywordRegistry {
+    keywords: Vec<(&'static str, TokenKind)>,
+}
+
+impl KeywordRegistry {
+    /// A registry with only the built-in keywords
+    pub fn builtin() -> Self {
+        Self {
+            keywords: BUILTIN_KEYWORDS.to_vec(),
+        }
+    }
+
+    /// Reserve `word` as a keyword, returning the token it lexes to
+    ///
+    /// The token is `TokenKind::Keyword` with the word, for the parser to
+    /// match on.
+    pub fn register(&mut self, word: &str) -> Result<TokenKind, KeywordError> {
+        if !is_word(word) {
+            return Err(KeywordError::InvalidWord(word.to_string()));
+        }
+        if self.lookup(word).is_some() {
+            return Err(KeywordError::AlreadyDefined(word.to_string()));
+        }
+
+        let kind = TokenKind::Keyword(intern(word));
+        self.keywords.push((intern(word), kind.clone()));
+        Ok(kind)
+    }
+
+    /// The token `word` lexes to, if it is a keyword
+    pub fn lookup(&self, word: &str) -> Option<TokenKind> {
+        self.keywords
+            .iter()
+            .find(|(keyword, _)| *keyword == word)
+            .map(|(_, kind)| kind.clone())
+    }
+
+    /// Every keyword, built-in ones first
+    pub fn words(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.keywords.iter().map(|(word, _)| *word)
+    }
+}
+
+impl Default for KeywordRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Whether `text` is spelled like an identifier
+pub fn is_word(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_keywords() {
+        let keywords = KeywordRegistry::builtin();
+        assert_eq!(keywords.lookup("true"), Some(TokenKind::True));
+        assert_eq!(keywords.lookup("false"), Some(TokenKind::False));
+        assert_eq!(keywords.lookup("let"), None);
+        assert_eq!(keywords.words().collect::<Vec<_>>(), ["true", "false"]);
+    }
+
+    #[test]
+    fn test_register() {
+        let mut keywords = KeywordRegistry::builtin();
+        assert_eq!(keywords.register("let"), Ok(TokenKind::Keyword("let")));
+        assert_eq!(keywords.lookup("let"), Some(TokenKind::Keyword("let")));
+        assert_eq!(
+            keywords.register("let"),
+            Err(KeywordError::AlreadyDefined("let".to_string()))
+        );
+        assert_eq!(
+            keywords.register("true"),
+            Err(KeywordError::AlreadyDefined("true".to_string()))
+        );
+        for word in ["", "1st", "a-b", "ä"] {
+            assert_eq!(
+                keywords.register(word),
+                Err(KeywordError::InvalidWord(word.to_string()))
+            );
+        }
+    }
+}
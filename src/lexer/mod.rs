@@ -2,8 +2,10 @@
 //!
 //! This module contains the tokenizer and token definitions.
 
+pub mod keywords;
 pub mod token;
 pub mod tokenizer;
 
-pub use token::{Token, TokenKind};
+pub use keywords::{KeywordError, KeywordRegistry};
+pub use token::{Token, TokenKind, Trivia, TriviaKind, TriviaToken};
 pub use tokenizer::{Lexer, SobaLexer};
@@ -1,5 +1,7 @@
 //! Token definitions for the lexer
 
+use crate::error::LexError;
+use crate::prelude::*;
 use crate::span::Span;
 
 /// A token with position information
@@ -13,19 +15,27 @@ pub struct Token {
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
     // Literals
-    Int(i32),
+    /// Digits only, so never negative. Up to `2147483648`, which fits
+    /// in an `i32` only when the parser folds a `-` in front of it
+    Int(u32),
     Float(f64),
     True,
     False,
 
+    /// A word that is not a keyword
+    Identifier(String),
+    /// A keyword registered by an embedder
+    Keyword(&'static str),
+
     // Operators
     Plus,
     Minus,
     Asterisk,
     Slash,
-    Bang,   // !
-    AndAnd, // &&
-    OrOr,   // ||
+    StarStar, // **
+    Bang,     // !
+    AndAnd,   // &&
+    OrOr,     // ||
 
     // Comparison operators
     Equal,        // ==
@@ -35,13 +45,25 @@ pub enum TokenKind {
     LessEqual,    // <=
     GreaterEqual, // >=
 
+    /// A symbol registered as a custom operator
+    Custom(&'static str),
+
     // Delimiters
     LeftParen,
     RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
     Semicolon,
+    Comma,
+    Colon,
 
     // Special
     Eof,
+    /// Text the lexer could not read, emitted instead of failing when
+    /// error recovery is on; the span covers the skipped text
+    Error(LexError),
 }
 
 impl Token {
@@ -53,22 +75,25 @@ impl Token {
     pub fn simple(kind: TokenKind) -> Self {
         Self {
             kind,
-            span: Span::single(crate::span::Position::start()),
+            span: Span::single(0),
         }
     }
 }
 
-impl std::fmt::Display for TokenKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             TokenKind::Int(i) => write!(f, "{i}"),
             TokenKind::Float(fl) => write!(f, "{fl}"),
             TokenKind::True => write!(f, "true"),
             TokenKind::False => write!(f, "false"),
+            TokenKind::Identifier(name) => write!(f, "{name}"),
+            TokenKind::Keyword(word) => write!(f, "{word}"),
             TokenKind::Plus => write!(f, "+"),
             TokenKind::Minus => write!(f, "-"),
             TokenKind::Asterisk => write!(f, "*"),
             TokenKind::Slash => write!(f, "/"),
+            TokenKind::StarStar => write!(f, "**"),
             TokenKind::Bang => write!(f, "!"),
             TokenKind::AndAnd => write!(f, "&&"),
             TokenKind::OrOr => write!(f, "||"),
@@ -78,16 +103,71 @@ impl std::fmt::Display for TokenKind {
             TokenKind::Greater => write!(f, ">"),
             TokenKind::LessEqual => write!(f, "<="),
             TokenKind::GreaterEqual => write!(f, ">="),
+            TokenKind::Custom(symbol) => write!(f, "{symbol}"),
             TokenKind::LeftParen => write!(f, "("),
             TokenKind::RightParen => write!(f, ")"),
+            TokenKind::LeftBrace => write!(f, "{{"),
+            TokenKind::RightBrace => write!(f, "}}"),
+            TokenKind::LeftBracket => write!(f, "["),
+            TokenKind::RightBracket => write!(f, "]"),
             TokenKind::Semicolon => write!(f, ";"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Colon => write!(f, ":"),
             TokenKind::Eof => write!(f, "EOF"),
+            TokenKind::Error(_) => write!(f, "invalid token"),
         }
     }
 }
 
-impl std::fmt::Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.kind)
     }
 }
+
+/// Kinds of source text that carry no meaning for the parser
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    /// A run of whitespace characters, including newlines
+    Whitespace,
+    /// A `//` comment, up to but not including the end of the line
+    LineComment,
+}
+
+/// Whitespace or a comment, with its original text
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+/// A token together with its source text and surrounding trivia
+///
+/// Trailing trivia runs up to the end of the token's line; everything after
+/// that belongs to the next token's leading trivia. Concatenating the
+/// leading trivia, text and trailing trivia of every token in a stream
+/// reproduces the input exactly.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TriviaToken {
+    pub token: Token,
+    /// The exact source text of the token
+    pub text: String,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl TriviaToken {
+    /// Write the token back out exactly as it appeared in the source
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        for trivia in &self.leading {
+            source.push_str(&trivia.text);
+        }
+        source.push_str(&self.text);
+        for trivia in &self.trailing {
+            source.push_str(&trivia.text);
+        }
+        source
+    }
+}
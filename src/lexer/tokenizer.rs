@@ -1,30 +1,108 @@
 //! Tokenizer implementation
 
-use super::token::{Token, TokenKind};
+use super::keywords::KeywordRegistry;
+use super::token::{Token, TokenKind, Trivia, TriviaKind, TriviaToken};
 use crate::error::{LexError, LexResult};
-use crate::span::{Position, Span};
+use crate::operators::OperatorRegistry;
+use crate::prelude::*;
+use crate::source::SourceId;
+use crate::span::Span;
 
 /// Trait for lexical analysis
 pub trait Lexer {
-    fn next_token(&mut self) -> LexResult<Option<Token>>;
+    /// Read the next token
+    ///
+    /// At the end of input this returns an `Eof` token with an empty span
+    /// at the end offset, and keeps returning it on every later call.
+    fn next_token(&mut self) -> LexResult<Token>;
 }
 
 /// Soba language tokenizer
 pub struct SobaLexer {
     input: Vec<char>,
-    position: Position,
+    /// Byte offset of the current character
+    offset: usize,
     current_index: usize,
+    /// Custom operator symbols, longest first
+    operators: Vec<&'static str>,
+    source: SourceId,
+    keywords: KeywordRegistry,
+    /// Emit `Error` tokens instead of failing on unreadable input
+    recover_errors: bool,
 }
 
 impl SobaLexer {
     pub fn new(input: Vec<char>) -> Self {
         Self {
             input,
-            position: Position::start(),
+            offset: 0,
             current_index: 0,
+            operators: Vec::new(),
+            source: SourceId::default(),
+            keywords: KeywordRegistry::builtin(),
+            recover_errors: false,
         }
     }
 
+    /// Create a lexer that also recognizes the symbols in `operators`
+    pub fn with_operators(input: Vec<char>, operators: &OperatorRegistry) -> Self {
+        let mut lexer = Self::new(input);
+        lexer.set_operators(operators);
+        lexer
+    }
+
+    /// Create a lexer for a slice of a larger source that begins at byte
+    /// offset `start`
+    ///
+    /// Token spans are reported in the coordinates of the whole source.
+    pub fn starting_at(input: Vec<char>, start: usize) -> Self {
+        Self {
+            input,
+            offset: start,
+            current_index: 0,
+            operators: Vec::new(),
+            source: SourceId::default(),
+            keywords: KeywordRegistry::builtin(),
+            recover_errors: false,
+        }
+    }
+
+    /// Set the source that token spans refer to
+    pub fn set_source(&mut self, source: SourceId) {
+        self.source = source;
+    }
+
+    /// Also recognize the symbols in `operators`
+    pub fn set_operators(&mut self, operators: &OperatorRegistry) {
+        let mut symbols = operators.symbols();
+        symbols.sort_by_key(|symbol| core::cmp::Reverse(symbol.chars().count()));
+        self.operators = symbols;
+    }
+
+    /// Read the words in `keywords` as keywords instead of the built-in set
+    pub fn set_keywords(&mut self, keywords: KeywordRegistry) {
+        self.keywords = keywords;
+    }
+
+    /// Turn error recovery on or off
+    ///
+    /// With recovery on, input that cannot be tokenized becomes a
+    /// `TokenKind::Error` token holding the error, and lexing carries on
+    /// after it. `next_token` then never fails, so a whole buffer can be
+    /// tokenized and all of its bad characters reported at once.
+    pub fn set_error_recovery(&mut self, recover_errors: bool) {
+        self.recover_errors = recover_errors;
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(start, end).in_source(self.source)
+    }
+
+    /// Span covering the single character `ch` starting at `start`
+    fn char_span(&self, start: usize, ch: char) -> Span {
+        self.span(start, start + ch.len_utf8())
+    }
+
     fn current_char(&self) -> Option<char> {
         self.input.get(self.current_index).copied()
     }
@@ -35,7 +113,7 @@ impl SobaLexer {
 
     fn advance(&mut self) -> Option<char> {
         if let Some(ch) = self.current_char() {
-            self.position.advance(ch);
+            self.offset += ch.len_utf8();
             self.current_index += 1;
             Some(ch)
         } else {
@@ -43,18 +121,87 @@ impl SobaLexer {
         }
     }
 
+    /// Skip whitespace and comments
     fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.current_char() {
-            if ch.is_whitespace() {
-                self.advance();
-            } else {
-                break;
+        self.read_trivia(false);
+    }
+
+    /// Consume whitespace and comments, returning them as trivia
+    /// With `stop_at_newline`, stops before the first newline so the rest of
+    /// the line can be attached to the preceding token.
+    fn read_trivia(&mut self, stop_at_newline: bool) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
+
+        loop {
+            let start_pos = self.offset;
+            let start_index = self.current_index;
+            let kind = match self.current_char() {
+                Some('\n') if stop_at_newline => break,
+                Some(ch) if ch.is_whitespace() => {
+                    while let Some(ch) = self.current_char() {
+                        if !ch.is_whitespace() || (stop_at_newline && ch == '\n') {
+                            break;
+                        }
+                        self.advance();
+                    }
+                    TriviaKind::Whitespace
+                }
+                Some('/') if self.peek_char() == Some('/') => {
+                    while let Some(ch) = self.current_char() {
+                        if ch == '\n' {
+                            break;
+                        }
+                        self.advance();
+                    }
+                    TriviaKind::LineComment
+                }
+                _ => break,
+            };
+
+            trivia.push(Trivia {
+                kind,
+                text: self.input[start_index..self.current_index].iter().collect(),
+                span: self.span(start_pos, self.offset),
+            });
+        }
+
+        trivia
+    }
+
+    /// Tokenize the whole input, keeping whitespace and comments
+    ///
+    /// The returned stream always ends with an `Eof` token whose leading
+    /// trivia holds whatever follows the last real token.
+    pub fn tokenize_with_trivia(&mut self) -> LexResult<Vec<TriviaToken>> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let leading = self.read_trivia(false);
+            let start_index = self.current_index;
+            let token = self.next_token()?;
+            if token.kind == TokenKind::Eof {
+                tokens.push(TriviaToken {
+                    token,
+                    text: String::new(),
+                    leading,
+                    trailing: Vec::new(),
+                });
+                return Ok(tokens);
             }
+
+            let text = self.input[start_index..self.current_index].iter().collect();
+            let trailing = self.read_trivia(true);
+            tokens.push(TriviaToken {
+                token,
+                text,
+                leading,
+                trailing,
+            });
         }
     }
 
     fn read_number(&mut self) -> LexResult<Token> {
-        let start_pos = self.position;
+        let start_pos = self.offset;
         let mut number_chars = Vec::new();
         let mut has_dot = false;
 
@@ -76,32 +223,40 @@ impl SobaLexer {
             }
         }
 
-        let end_pos = self.position;
-        let span = Span::new(start_pos, end_pos);
+        let end_pos = self.offset;
+        let span = self.span(start_pos, end_pos);
         let number_str: String = number_chars.iter().collect();
 
         if has_dot {
-            number_str
-                .parse::<f64>()
-                .map(|f| Token::new(TokenKind::Float(f), span))
-                .map_err(|_| LexError::InvalidNumber(number_str))
+            // A literal too large for f64 would otherwise become infinity,
+            // which has no literal syntax to print it back as
+            match number_str.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(Token::new(TokenKind::Float(f), span)),
+                _ => Err(LexError::InvalidNumber {
+                    text: number_str,
+                    span,
+                }),
+            }
         } else {
-            number_str
-                .parse::<i32>()
-                .map(|i| Token::new(TokenKind::Int(i), span))
-                .map_err(|_| LexError::InvalidNumber(number_str))
+            match number_str.parse::<u32>() {
+                Ok(i) if i <= i32::MIN.unsigned_abs() => Ok(Token::new(TokenKind::Int(i), span)),
+                _ => Err(LexError::InvalidNumber {
+                    text: number_str,
+                    span,
+                }),
+            }
         }
     }
 
     fn read_single_char_token(&mut self, kind: TokenKind) -> Token {
-        let start_pos = self.position;
+        let start_pos = self.offset;
         self.advance();
-        let end_pos = self.position;
-        Token::new(kind, Span::new(start_pos, end_pos))
+        let end_pos = self.offset;
+        Token::new(kind, self.span(start_pos, end_pos))
     }
 
-    fn read_identifier(&mut self) -> LexResult<Token> {
-        let start_pos = self.position;
+    fn read_identifier(&mut self) -> Token {
+        let start_pos = self.offset;
         let mut identifier_chars = Vec::new();
 
         // Read letters, digits, and underscores
@@ -113,18 +268,34 @@ impl SobaLexer {
             }
         }
 
-        let end_pos = self.position;
-        let span = Span::new(start_pos, end_pos);
+        let end_pos = self.offset;
+        let span = self.span(start_pos, end_pos);
         let identifier: String = identifier_chars.iter().collect();
 
-        // Check for keywords
-        let kind = match identifier.as_str() {
-            "true" => TokenKind::True,
-            "false" => TokenKind::False,
-            _ => return Err(LexError::UnexpectedCharacter(identifier_chars[0])), // For now, only support keywords
-        };
+        let kind = self
+            .keywords
+            .lookup(&identifier)
+            .unwrap_or(TokenKind::Identifier(identifier));
 
-        Ok(Token::new(kind, span))
+        Token::new(kind, span)
+    }
+
+    /// The longest custom operator symbol at the current position, if any
+    fn custom_operator_at_cursor(&self) -> Option<&'static str> {
+        self.operators.iter().copied().find(|symbol| {
+            symbol
+                .chars()
+                .enumerate()
+                .all(|(i, ch)| self.input.get(self.current_index + i) == Some(&ch))
+        })
+    }
+
+    fn read_custom_operator(&mut self, symbol: &'static str) -> Token {
+        let start_pos = self.offset;
+        for _ in symbol.chars() {
+            self.advance();
+        }
+        Token::new(TokenKind::Custom(symbol), self.span(start_pos, self.offset))
     }
 
     fn read_two_char_token(
@@ -133,7 +304,7 @@ impl SobaLexer {
         second_char: char,
         kind: TokenKind,
     ) -> LexResult<Token> {
-        let start_pos = self.position;
+        let start_pos = self.offset;
 
         // Consume first character
         self.advance();
@@ -141,53 +312,59 @@ impl SobaLexer {
         // Check if second character matches
         if self.current_char() == Some(second_char) {
             self.advance(); // consume second character
-            let end_pos = self.position;
-            Ok(Token::new(kind, Span::new(start_pos, end_pos)))
+            let end_pos = self.offset;
+            Ok(Token::new(kind, self.span(start_pos, end_pos)))
         } else {
             // If second character doesn't match, it's an unexpected character
-            Err(LexError::UnexpectedCharacter(first_char))
+            Err(LexError::UnexpectedCharacter {
+                ch: first_char,
+                span: self.span(start_pos, self.offset),
+            })
         }
     }
-}
-
-impl Lexer for SobaLexer {
-    fn next_token(&mut self) -> LexResult<Option<Token>> {
-        self.skip_whitespace();
 
+    /// Read the token at the current character, which is not whitespace
+    fn read_token(&mut self) -> LexResult<Token> {
         match self.current_char() {
-            None => Ok(None), // EOF
+            None => Ok(Token::new(
+                TokenKind::Eof,
+                self.span(self.offset, self.offset),
+            )),
             Some(ch) => {
                 if ch.is_ascii_digit() || ch == '.' {
-                    self.read_number().map(Some)
+                    self.read_number()
                 } else if ch.is_ascii_alphabetic() || ch == '_' {
-                    self.read_identifier().map(Some)
+                    Ok(self.read_identifier())
+                } else if let Some(symbol) = self.custom_operator_at_cursor() {
+                    // Custom symbols never equal or prefix a built-in one,
+                    // so trying them first gives the longest match
+                    Ok(self.read_custom_operator(symbol))
                 } else {
                     let token = match ch {
                         '+' => self.read_single_char_token(TokenKind::Plus),
                         '-' => self.read_single_char_token(TokenKind::Minus),
-                        '*' => self.read_single_char_token(TokenKind::Asterisk),
+                        '*' => {
+                            // Check for **
+                            if self.peek_char() == Some('*') {
+                                return self.read_two_char_token('*', '*', TokenKind::StarStar);
+                            } else {
+                                self.read_single_char_token(TokenKind::Asterisk)
+                            }
+                        }
                         '/' => self.read_single_char_token(TokenKind::Slash),
                         '!' => {
                             // Check for !=
                             if self.peek_char() == Some('=') {
-                                return self
-                                    .read_two_char_token('!', '=', TokenKind::NotEqual)
-                                    .map(Some);
+                                return self.read_two_char_token('!', '=', TokenKind::NotEqual);
                             } else {
                                 self.read_single_char_token(TokenKind::Bang)
                             }
                         }
-                        '=' => {
-                            return self
-                                .read_two_char_token('=', '=', TokenKind::Equal)
-                                .map(Some)
-                        }
+                        '=' => return self.read_two_char_token('=', '=', TokenKind::Equal),
                         '<' => {
                             // Check for <=
                             if self.peek_char() == Some('=') {
-                                return self
-                                    .read_two_char_token('<', '=', TokenKind::LessEqual)
-                                    .map(Some);
+                                return self.read_two_char_token('<', '=', TokenKind::LessEqual);
                             } else {
                                 self.read_single_char_token(TokenKind::Less)
                             }
@@ -195,58 +372,165 @@ impl Lexer for SobaLexer {
                         '>' => {
                             // Check for >=
                             if self.peek_char() == Some('=') {
-                                return self
-                                    .read_two_char_token('>', '=', TokenKind::GreaterEqual)
-                                    .map(Some);
+                                return self.read_two_char_token('>', '=', TokenKind::GreaterEqual);
                             } else {
                                 self.read_single_char_token(TokenKind::Greater)
                             }
                         }
-                        '&' => {
-                            return self
-                                .read_two_char_token('&', '&', TokenKind::AndAnd)
-                                .map(Some)
-                        }
-                        '|' => {
-                            return self
-                                .read_two_char_token('|', '|', TokenKind::OrOr)
-                                .map(Some)
-                        }
+                        '&' => return self.read_two_char_token('&', '&', TokenKind::AndAnd),
+                        '|' => return self.read_two_char_token('|', '|', TokenKind::OrOr),
                         '(' => self.read_single_char_token(TokenKind::LeftParen),
                         ')' => self.read_single_char_token(TokenKind::RightParen),
+                        '{' => self.read_single_char_token(TokenKind::LeftBrace),
+                        '}' => self.read_single_char_token(TokenKind::RightBrace),
+                        '[' => self.read_single_char_token(TokenKind::LeftBracket),
+                        ']' => self.read_single_char_token(TokenKind::RightBracket),
                         ';' => self.read_single_char_token(TokenKind::Semicolon),
-                        _ => return Err(LexError::UnexpectedCharacter(ch)),
+                        ',' => self.read_single_char_token(TokenKind::Comma),
+                        ':' => self.read_single_char_token(TokenKind::Colon),
+                        _ => {
+                            return Err(LexError::UnexpectedCharacter {
+                                ch,
+                                span: self.char_span(self.offset, ch),
+                            })
+                        }
                     };
-                    Ok(Some(token))
+                    Ok(token)
                 }
             }
         }
     }
 }
 
+impl Lexer for SobaLexer {
+    fn next_token(&mut self) -> LexResult<Token> {
+        self.skip_whitespace();
+
+        let start_index = self.current_index;
+        let start_pos = self.offset;
+        match self.read_token() {
+            Err(err) if self.recover_errors => {
+                // Always make progress, even if the error consumed nothing
+                if self.current_index == start_index {
+                    self.advance();
+                }
+                Ok(Token::new(
+                    TokenKind::Error(err),
+                    self.span(start_pos, self.offset),
+                ))
+            }
+            result => result,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::span::LineIndex;
 
     fn tokenize(input: &str) -> LexResult<Vec<Token>> {
         let mut lexer = SobaLexer::new(input.chars().collect());
         let mut tokens = Vec::new();
 
-        while let Some(token) = lexer.next_token()? {
+        loop {
+            let token = lexer.next_token()?;
+            if token.kind == TokenKind::Eof {
+                break;
+            }
             tokens.push(token);
         }
 
         Ok(tokens)
     }
 
+    #[test]
+    fn test_eof_token() {
+        let mut lexer = SobaLexer::new("1  ".chars().collect());
+        assert_eq!(lexer.next_token().unwrap().kind, TokenKind::Int(1));
+        for _ in 0..2 {
+            let token = lexer.next_token().unwrap();
+            assert_eq!(token.kind, TokenKind::Eof);
+            assert_eq!(token.span, Span::single(3));
+        }
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let input = "1 @ 2 + # abc 99999999999";
+        let mut lexer = SobaLexer::new(input.chars().collect());
+        lexer.set_error_recovery(true);
+        let tokens = lexer.tokenize_with_trivia().unwrap();
+
+        let errors: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| match &token.token.kind {
+                TokenKind::Error(err) => Some((err.to_string(), token.text.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("Unexpected character: '@'".to_string(), "@"),
+                ("Unexpected character: '#'".to_string(), "#"),
+                ("Invalid number: 99999999999".to_string(), "99999999999"),
+            ]
+        );
+        assert_eq!(tokens[1].token.span, Span::new(2, 3));
+        assert_eq!(tokens.last().unwrap().token.kind, TokenKind::Eof);
+
+        // Without recovery the first bad character fails
+        let mut lexer = SobaLexer::new(input.chars().collect());
+        assert!(lexer.next_token().is_ok());
+        assert!(matches!(
+            lexer.next_token(),
+            Err(LexError::UnexpectedCharacter { ch: '@', .. })
+        ));
+    }
+
+    #[test]
+    fn test_identifiers_and_keywords() {
+        let tokens = tokenize("true x_1 _ let").unwrap();
+        let kinds: Vec<_> = tokens.into_iter().map(|token| token.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::True,
+                TokenKind::Identifier("x_1".to_string()),
+                TokenKind::Identifier("_".to_string()),
+                TokenKind::Identifier("let".to_string()),
+            ]
+        );
+
+        let mut keywords = KeywordRegistry::builtin();
+        let let_keyword = keywords.register("let").unwrap();
+        let mut lexer = SobaLexer::new("let letter".chars().collect());
+        lexer.set_keywords(keywords);
+        assert_eq!(lexer.next_token().unwrap().kind, let_keyword);
+        let token = lexer.next_token().unwrap();
+        assert_eq!(token.kind, TokenKind::Identifier("letter".to_string()));
+        assert_eq!(token.span, Span::new(4, 10));
+    }
+
     #[test]
     fn test_integers() {
         let tokens = tokenize("123").unwrap();
         assert_eq!(tokens.len(), 1);
         assert_eq!(tokens[0].kind, TokenKind::Int(123));
+
+        // The magnitude of `i32::MIN` lexes; only the parser knows whether
+        // a `-` makes it fit
+        let tokens = tokenize("2147483648").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::Int(2147483648));
+        assert!(matches!(
+            tokenize("2147483649"),
+            Err(LexError::InvalidNumber { .. })
+        ));
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_floats() {
         let tokens = tokenize("3.14").unwrap();
         assert_eq!(tokens.len(), 1);
@@ -255,6 +539,12 @@ mod tests {
         let tokens = tokenize(".5").unwrap();
         assert_eq!(tokens.len(), 1);
         assert_eq!(tokens[0].kind, TokenKind::Float(0.5));
+
+        let huge = format!("{}.0", "9".repeat(400));
+        assert!(matches!(
+            tokenize(&huge),
+            Err(LexError::InvalidNumber { .. })
+        ));
     }
 
     #[test]
@@ -265,6 +555,11 @@ mod tests {
         assert_eq!(tokens[1].kind, TokenKind::Minus);
         assert_eq!(tokens[2].kind, TokenKind::Asterisk);
         assert_eq!(tokens[3].kind, TokenKind::Slash);
+
+        let tokens = tokenize("2**3 * *").unwrap();
+        assert_eq!(tokens[1].kind, TokenKind::StarStar);
+        assert_eq!(tokens[3].kind, TokenKind::Asterisk);
+        assert_eq!(tokens[4].kind, TokenKind::Asterisk);
     }
 
     #[test]
@@ -391,6 +686,42 @@ mod tests {
         assert_eq!(tokens[6].kind, TokenKind::Int(2));
     }
 
+    #[test]
+    fn test_comparison_maximal_munch() {
+        let kinds = |input: &str| -> Vec<TokenKind> {
+            tokenize(input)
+                .unwrap()
+                .into_iter()
+                .map(|token| token.kind)
+                .collect()
+        };
+        assert_eq!(
+            kinds("1!=2"),
+            vec![TokenKind::Int(1), TokenKind::NotEqual, TokenKind::Int(2)]
+        );
+        assert_eq!(kinds("!!="), vec![TokenKind::Bang, TokenKind::NotEqual]);
+        assert_eq!(
+            kinds("!!true"),
+            vec![TokenKind::Bang, TokenKind::Bang, TokenKind::True]
+        );
+        assert_eq!(kinds("<<="), vec![TokenKind::Less, TokenKind::LessEqual]);
+        assert_eq!(
+            kinds(">>="),
+            vec![TokenKind::Greater, TokenKind::GreaterEqual]
+        );
+
+        // A `=` left over after the longest match is not an operator
+        for input in ["===", "!==", "<==", ">=="] {
+            assert!(
+                matches!(
+                    tokenize(input),
+                    Err(LexError::UnexpectedCharacter { ch: '=', .. })
+                ),
+                "{input}"
+            );
+        }
+    }
+
     #[test]
     fn test_invalid_single_equals() {
         let result = tokenize("=");
@@ -404,6 +735,30 @@ mod tests {
         assert_eq!(tokens[0].kind, TokenKind::Semicolon);
     }
 
+    #[test]
+    fn test_punctuation() {
+        let kinds: Vec<_> = tokenize("{a: [1, 2]};")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::LeftBrace,
+                TokenKind::Identifier("a".to_string()),
+                TokenKind::Colon,
+                TokenKind::LeftBracket,
+                TokenKind::Int(1),
+                TokenKind::Comma,
+                TokenKind::Int(2),
+                TokenKind::RightBracket,
+                TokenKind::RightBrace,
+                TokenKind::Semicolon,
+            ]
+        );
+    }
+
     #[test]
     fn test_statement() {
         let tokens = tokenize("2 + 3;").unwrap();
@@ -427,4 +782,93 @@ mod tests {
         assert_eq!(tokens[6].kind, TokenKind::Int(4));
         assert_eq!(tokens[7].kind, TokenKind::Semicolon);
     }
+
+    #[test]
+    fn test_line_comments_are_skipped() {
+        let input = "1 // one\n// two\n+ 2 // end";
+        let tokens = tokenize(input).unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[1].kind, TokenKind::Plus);
+        let position = LineIndex::new(input).position(input, tokens[1].span.start);
+        assert_eq!((position.line, position.column), (3, 1));
+    }
+
+    #[test]
+    fn test_trivia_attachment() {
+        let input = "// header\n1 +  2; // trailing\n\n3";
+        let tokens = SobaLexer::new(input.chars().collect())
+            .tokenize_with_trivia()
+            .unwrap();
+        assert_eq!(tokens.len(), 6);
+
+        let one = &tokens[0];
+        assert_eq!(one.text, "1");
+        assert_eq!(one.leading.len(), 2);
+        assert_eq!(one.leading[0].kind, TriviaKind::LineComment);
+        assert_eq!(one.leading[0].text, "// header");
+        assert_eq!(one.trailing[0].text, " ");
+
+        // The comment after ';' stays on its line; the newlines go to `3`
+        let semicolon = &tokens[3];
+        assert_eq!(semicolon.trailing.len(), 2);
+        assert_eq!(semicolon.trailing[1].text, "// trailing");
+        assert_eq!(tokens[4].leading[0].text, "\n\n");
+
+        let eof = &tokens[5];
+        assert_eq!(eof.token.kind, TokenKind::Eof);
+        assert!(eof.leading.is_empty());
+    }
+
+    #[test]
+    fn test_trivia_round_trip() {
+        let input = "  (1 +\t2.5) // sum\n  * 3;\n// done\n";
+        let tokens = SobaLexer::new(input.chars().collect())
+            .tokenize_with_trivia()
+            .unwrap();
+        let source: String = tokens.iter().map(TriviaToken::to_source).collect();
+        assert_eq!(source, input);
+        assert_eq!(tokens.last().unwrap().leading[1].text, "// done");
+    }
+
+    #[test]
+    fn test_custom_operators_use_longest_match() {
+        use crate::operators::Associativity;
+        use crate::parser::Precedence;
+
+        let mut operators = OperatorRegistry::new();
+        for symbol in ["<=>", "*%"] {
+            operators
+                .register_infix(symbol, Precedence::Product, Associativity::Left, |l, _| {
+                    Ok(l)
+                })
+                .unwrap();
+        }
+
+        let mut lexer = SobaLexer::with_operators("1<=>2 <= 3*%4*5".chars().collect(), &operators);
+        let mut kinds = Vec::new();
+        loop {
+            let token = lexer.next_token().unwrap();
+            if token.kind == TokenKind::Eof {
+                break;
+            }
+            kinds.push(token.kind);
+        }
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Int(1),
+                TokenKind::Custom("<=>"),
+                TokenKind::Int(2),
+                TokenKind::LessEqual,
+                TokenKind::Int(3),
+                TokenKind::Custom("*%"),
+                TokenKind::Int(4),
+                TokenKind::Asterisk,
+                TokenKind::Int(5),
+            ]
+        );
+
+        // Without the registry the symbol is not an operator
+        assert!(tokenize("1 <=> 2").is_ok_and(|tokens| tokens[1].kind == TokenKind::LessEqual));
+    }
 }
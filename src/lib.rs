@@ -1,32 +1,125 @@
 //! Soba Programming Language
+//!
+//! With the default `std` feature off, the core (lexer, parser, AST,
+//! evaluator and values) builds with `no_std` and `alloc`, taking its
+//! float math from the `libm` feature. Tooling such as the linter,
+//! formatter and type checker needs `std`.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("soba needs the `std` feature or, without it, the `libm` feature");
+
+#[cfg(feature = "std")]
+pub mod analysis;
 pub mod ast;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod cst;
+#[cfg(feature = "std")]
+pub mod diagnostic;
+#[cfg(feature = "std")]
+pub mod engine;
 pub mod error;
 pub mod evaluator;
+#[cfg(feature = "std")]
+pub mod fmt;
+#[cfg(feature = "std")]
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod highlight;
 pub mod lexer;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod math;
+pub mod operators;
 pub mod parser;
+pub mod source;
 pub mod span;
+#[cfg(feature = "std")]
+pub mod template;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod typeck;
 pub mod value;
 
+/// The `alloc` items the std prelude provides, for modules that must also
+/// build without it
+mod prelude {
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::format;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec;
+    pub(crate) use alloc::vec::Vec;
+}
+
 // Re-export commonly used types
-pub use ast::{BinaryOp, Expr, Program, Statement, UnaryOp};
-pub use error::{EvalError, LexError, ParseError, SobaError, SobaResult};
-pub use evaluator::{eval_expr, eval_program, eval_statement};
-pub use lexer::{Lexer, SobaLexer, Token, TokenKind};
-pub use parser::{Parser, Precedence};
-pub use span::{Position, Span};
-pub use value::Value;
+pub use ast::{
+    ArenaExpr, ArenaProgram, BinaryOp, Expr, ExprArena, ExprId, Folder, PostfixOp, Program,
+    SideTable, Statement, UnaryOp, Visitor,
+};
+#[cfg(feature = "std")]
+pub use diagnostic::{Diagnostic, Label, Renderer};
+#[cfg(feature = "std")]
+pub use engine::{CompiledExpr, FileError, Soba, SobaBuilder};
+pub use error::{
+    explain, hint, EvalError, LexError, ParseError, ParseResult, ParseWarning, SobaError,
+    SobaResult,
+};
+pub use evaluator::{
+    eval_arena_expr, eval_arena_program, eval_arena_program_with_float_eq, eval_expr,
+    eval_expr_with_operators, eval_program, eval_program_with_float_eq,
+    eval_program_with_operators, eval_statement, CancelHandle, EvalStatus, Evaluation,
+};
+pub use lexer::{KeywordError, KeywordRegistry, Lexer, SobaLexer, Token, TokenKind};
+pub use operators::{Associativity, CustomOp, OperatorError, OperatorRegistry};
+pub use parser::{Parser, Precedence, TextEdit};
+pub use source::{shebang_len, SourceFile, SourceId, SourceMap};
+pub use span::{ColumnUnit, LineIndex, Position, Span};
+pub use value::{ConversionError, FloatEq, FloatFormat, HostObject, Type, Value};
+
+/// Parse a string containing a single expression
+/// The whole input must be one expression; statements and trailing
+/// semicolons are rejected.
+pub fn parse_expr_string(input: &str) -> ParseResult<Expr> {
+    let lexer = SobaLexer::new(input.chars().collect());
+    Parser::new(lexer)?.parse_standalone_expression()
+}
 
+/// Evaluate a string containing a single expression and return the result
+pub fn eval_expr_string(input: &str) -> SobaResult<Value> {
+    let expr = parse_expr_string(input)?;
+    eval_expr(&expr).map_err(SobaError::EvalError)
+}
 
 /// Evaluate a string containing a program (multiple statements) and return the result
+///
+/// Floats compare with `FloatEq::default()`, as in the other `eval_*`
+/// functions without a `float_eq` parameter.
 pub fn eval_program_string(input: &str) -> SobaResult<Value> {
     let lexer = SobaLexer::new(input.chars().collect());
-    let mut parser = Parser::new(lexer).map_err(SobaError::ParseError)?;
+    let mut parser = Parser::new(lexer)?;
 
-    let program = parser.parse_program().map_err(SobaError::ParseError)?;
+    let program = parser.parse_program()?;
     eval_program(&program).map_err(SobaError::EvalError)
 }
 
+/// Evaluate a program that may use the custom operators in `operators`
+pub fn eval_program_string_with_operators(
+    input: &str,
+    operators: &OperatorRegistry,
+) -> SobaResult<Value> {
+    let lexer = SobaLexer::with_operators(input.chars().collect(), operators);
+    let mut parser = Parser::with_operators(lexer, operators)?;
+
+    let program = parser.parse_program()?;
+    eval_program_with_operators(&program, operators).map_err(SobaError::EvalError)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,12 +136,18 @@ mod tests {
 
     #[test]
     fn test_eval_program_string_multiple_with_semicolons() {
-        assert_eq!(eval_program_string("1 + 2; 3 * 4; 10;").unwrap(), Value::Int(10));
+        assert_eq!(
+            eval_program_string("1 + 2; 3 * 4; 10;").unwrap(),
+            Value::Int(10)
+        );
     }
 
     #[test]
     fn test_eval_program_string_multiple_last_without_semicolon() {
-        assert_eq!(eval_program_string("1 + 2; 3 * 4; 10").unwrap(), Value::Int(10));
+        assert_eq!(
+            eval_program_string("1 + 2; 3 * 4; 10").unwrap(),
+            Value::Int(10)
+        );
     }
 
     #[test]
@@ -71,4 +170,104 @@ mod tests {
             Value::Float(6.0)
         );
     }
+
+    #[test]
+    fn test_parse_expr_string() {
+        let expr = parse_expr_string("  (1 + 2) * 3 ").unwrap();
+        assert!(matches!(expr, Expr::InfixExpr { .. }));
+        assert_eq!(expr.to_source(), "(1 + 2) * 3");
+    }
+
+    #[test]
+    fn test_parse_expr_string_rejects_leftover_input() {
+        assert_eq!(
+            parse_expr_string("1 + 2;").unwrap_err().to_string(),
+            "Unexpected token: expected an operator, found ';'"
+        );
+        assert!(parse_expr_string("1; 2").is_err());
+        assert!(matches!(
+            parse_expr_string(""),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+    }
+
+    #[test]
+    fn test_eval_expr_string() {
+        assert_eq!(eval_expr_string("-(2 * 3)").unwrap(), Value::Float(-6.0));
+        assert!(matches!(
+            eval_expr_string("1 / 0"),
+            Err(SobaError::EvalError(EvalError::DivisionByZero { .. }))
+        ));
+        assert!(matches!(eval_expr_string("@"), Err(SobaError::LexError(_))));
+    }
+
+    fn error_span(input: &str) -> Span {
+        eval_program_string(input).unwrap_err().span().unwrap()
+    }
+
+    #[test]
+    fn test_errors_report_source_spans() {
+        // Lexing: the '@' on line 2
+        let input = "1;\n2 @ 3";
+        let start = LineIndex::new(input).position(input, error_span(input).start);
+        assert_eq!((start.line, start.column), (2, 3));
+
+        // Parsing: the unexpected ')'
+        let span = error_span("1 + )");
+        assert_eq!(span.start, 4);
+
+        // Evaluation: the innermost failing expression, `4 / 0`
+        let span = error_span("1 + (4 / 0)");
+        assert_eq!((span.start, span.end), (5, 10));
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(
+            eval_program_string("2 ** 3 ** 2").unwrap(),
+            Value::Float(512.0)
+        );
+        assert_eq!(eval_program_string("-2 ** 2").unwrap(), Value::Float(-4.0));
+        assert_eq!(eval_program_string("2 ** -1").unwrap(), Value::Float(0.5));
+    }
+
+    fn custom_operators() -> OperatorRegistry {
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Left,
+                |l, r| Ok(Value::Int(l.as_f64().total_cmp(&r.as_f64()) as i32)),
+            )
+            .unwrap();
+        operators
+            .register_infix("^^", Precedence::Product, Associativity::Right, |l, r| {
+                Ok(Value::Float(l.as_f64().powf(r.as_f64())))
+            })
+            .unwrap();
+        operators
+            .register_prefix("~~", |v| Ok(Value::Float(v.as_f64().round())))
+            .unwrap();
+        operators
+            .register_postfix("!!", |v| Ok(Value::Float(v.as_f64() * 2.0)))
+            .unwrap();
+        operators
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let operators = custom_operators();
+        let eval = |input| eval_program_string_with_operators(input, &operators);
+
+        assert_eq!(eval("1 + 2 <=> 3").unwrap(), Value::Int(0));
+        assert_eq!(eval("2 ^^ 3 ^^ 2").unwrap(), Value::Float(512.0));
+        assert_eq!(eval("~~2.6 * 2").unwrap(), Value::Float(6.0));
+        assert_eq!(eval("2 <= 3 && 5 <=> 4 == 1").unwrap(), Value::Bool(true));
+        assert_eq!(eval("1 + 3!!!!").unwrap(), Value::Float(13.0));
+        assert_eq!(eval("-(1 - 3)!!").unwrap(), Value::Float(4.0));
+
+        // Without the registry, the symbols are not operators at all
+        assert!(eval_program_string("1 <=> 2").is_err());
+    }
 }
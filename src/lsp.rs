@@ -0,0 +1,533 @@
+//! Language server
+//!
+//! `run` speaks the Language Server Protocol over a pair of streams, as
+//! `soba lsp` does over stdin and stdout. Documents are synced in full on
+//! every change, and the server answers with:
+//!
+//! - diagnostics: parse errors from the recovering parser, then lints and,
+//!   if there are no error lints, the evaluation error
+//! - hover: the value and type of the innermost expression under the cursor
+//! - document symbols: one per statement, named by its canonical source
+//! - formatting: the whole document in the style of `cst::format_source`,
+//!   indenting continuation lines by the client's tab size
+//!
+//! Positions are converted with `LineIndex` counting columns in UTF-16
+//! units, as the protocol asks.
+
+use crate::analysis::{Linter, Severity};
+use crate::ast::{Program, Statement};
+use crate::cst::{format_source_with, FormatOptions};
+use crate::diagnostic::Diagnostic;
+use crate::error::SobaError;
+use crate::evaluator::{eval_expr, eval_program};
+use crate::parser::Parser;
+use crate::source::{SourceFile, SourceMap};
+use crate::span::{ColumnUnit, Span};
+use crate::typeck::infer_type;
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// JSON-RPC error for a request the server does not know
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error for a request after `shutdown`
+const INVALID_REQUEST: i64 = -32600;
+
+/// `SymbolKind.Constant`: statements have no names, only values
+const CONSTANT_SYMBOL: u32 = 14;
+
+/// Serve one client until it sends `exit` or closes `input`
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input)? {
+        if message["method"] == "exit" {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read one `Content-Length` framed message, or None at the end of input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Write `message` with its `Content-Length` header
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+/// The open documents of one client
+#[derive(Debug, Default)]
+pub struct Server {
+    /// Text of each open document by URI
+    documents: HashMap<String, String>,
+    /// Whether `shutdown` was requested, after which requests fail
+    shut_down: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle one message from the client, returning the messages to send
+    /// back: the response to a request and any notifications
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params);
+        };
+
+        let result = match method {
+            _ if self.shut_down => Err((INVALID_REQUEST, "The server is shutting down")),
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "soba", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => {
+                Ok(self.with_document(params, |file| hover(file, &params["position"])))
+            }
+            "textDocument/documentSymbol" => Ok(self.with_document(params, document_symbols)),
+            "textDocument/formatting" => {
+                Ok(self.with_document(params, |file| formatting(file, &params["options"])))
+            }
+            _ => Err((METHOD_NOT_FOUND, "Unknown method")),
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": format!("{message}: {method}") },
+            }),
+        };
+        vec![response]
+    }
+
+    /// Handle a notification, which gets no response
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let changes = params["contentChanges"].as_array();
+                let Some(text) = changes.and_then(|changes| changes.last()?["text"].as_str())
+                else {
+                    return Vec::new();
+                };
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return Vec::new(),
+        }
+        vec![self.publish_diagnostics(&uri)]
+    }
+
+    /// The `publishDiagnostics` notification for the document at `uri`,
+    /// empty once it is closed
+    fn publish_diagnostics(&self, uri: &str) -> Json {
+        let diagnostics: Vec<Json> = match self.documents.get(uri) {
+            Some(text) => {
+                let mut sources = SourceMap::new();
+                let id = sources.add(uri, text.as_str());
+                let file = sources.get(id).unwrap();
+                diagnostics(file)
+                    .iter()
+                    .map(|diagnostic| lsp_diagnostic(file, diagnostic))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    /// Run `f` on the document named in `params`, or give null if it is
+    /// not open
+    fn with_document(&self, params: &Json, f: impl FnOnce(&SourceFile) -> Json) -> Json {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        let Some(text) = self.documents.get(uri) else {
+            return Json::Null;
+        };
+        let mut sources = SourceMap::new();
+        let id = sources.add(uri, text.as_str());
+        f(sources.get(id).unwrap())
+    }
+}
+
+/// The problems in `file`, in the order the command line tools find them
+///
+/// Parse errors come from the recovering parser, so every broken statement
+/// is reported. A program that parses is linted, and evaluated if no lint
+/// is an error, since those would fail evaluation the same way.
+pub fn diagnostics(file: &SourceFile) -> Vec<Diagnostic> {
+    let mut parser = match Parser::new(file.lexer()) {
+        Ok(parser) => parser,
+        Err(err) => return vec![(&err).into()],
+    };
+    let (program, errors) = parser.parse_program_with_recovery();
+    if !errors.is_empty() {
+        return errors.iter().map(Diagnostic::from).collect();
+    }
+
+    let mut found: Vec<Diagnostic> = match Linter::new().lint(file) {
+        Ok(lints) => lints.iter().map(Diagnostic::from).collect(),
+        Err(err) => vec![(&err).into()],
+    };
+    if found
+        .iter()
+        .all(|diagnostic| diagnostic.severity != Severity::Error)
+    {
+        if let Err(err) = eval_program(&program) {
+            found.push((&SobaError::EvalError(err)).into());
+        }
+    }
+    found
+}
+
+/// The contents of a hover over the LSP `position` in `file`: the value and
+/// type of the innermost expression there
+fn hover(file: &SourceFile, position: &Json) -> Json {
+    let Some(program) = parse(file) else {
+        return Json::Null;
+    };
+    let offset = offset(file, position);
+    let Some(expr) = program.node_at(offset) else {
+        return Json::Null;
+    };
+    let value = match eval_expr(expr) {
+        Ok(value) => format!("{value} : {}", value.type_name()),
+        Err(err) => format!("{} : {}", err, infer_type(expr).name()),
+    };
+    json!({
+        "contents": { "kind": "markdown", "value": format!("```soba\n{value}\n```") },
+        "range": range(file, expr.span()),
+    })
+}
+
+/// One symbol per statement of `file`, with the type of its value
+fn document_symbols(file: &SourceFile) -> Json {
+    let Some(program) = parse(file) else {
+        return Json::Array(Vec::new());
+    };
+    let symbols = program
+        .statements
+        .iter()
+        .map(|stmt| {
+            let Statement::ExprStatement { expr, span } = stmt;
+            json!({
+                "name": stmt.to_source(),
+                "detail": infer_type(expr).name(),
+                "kind": CONSTANT_SYMBOL,
+                "range": range(file, *span),
+                "selectionRange": range(file, expr.span()),
+            })
+        })
+        .collect();
+    Json::Array(symbols)
+}
+
+/// An edit replacing `file` with its formatted text, none if it is
+/// formatted already, or null if it does not parse
+fn formatting(file: &SourceFile, options: &Json) -> Json {
+    let defaults = FormatOptions::default();
+    let options = FormatOptions {
+        indent_width: options["tabSize"]
+            .as_u64()
+            .map_or(defaults.indent_width, |size| size as usize),
+        ..defaults
+    };
+    let Ok(formatted) = format_source_with(file.contents(), &options) else {
+        return Json::Null;
+    };
+    if formatted == file.contents() {
+        return json!([]);
+    }
+    let whole = Span::new(0, file.contents().len()).in_source(file.id());
+    json!([{ "range": range(file, whole), "newText": formatted }])
+}
+
+/// The program in `file`, if it parses
+fn parse(file: &SourceFile) -> Option<Program> {
+    Parser::new(file.lexer())
+        .and_then(|mut parser| parser.parse_program())
+        .ok()
+}
+
+/// `diagnostic` as an LSP `Diagnostic` object
+fn lsp_diagnostic(file: &SourceFile, diagnostic: &Diagnostic) -> Json {
+    let span = diagnostic
+        .span
+        .unwrap_or_else(|| Span::single(0).in_source(file.id()));
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    let message = match &diagnostic.hint {
+        Some(hint) => format!("{}\nhint: {hint}", diagnostic.message),
+        None => diagnostic.message.clone(),
+    };
+    json!({
+        "range": range(file, span),
+        "severity": severity,
+        "code": diagnostic.code,
+        "source": "soba",
+        "message": message,
+    })
+}
+
+/// `span` as an LSP `Range`, with 0-based lines and UTF-16 columns
+fn range(file: &SourceFile, span: Span) -> Json {
+    let position = |offset| {
+        let position = file.position_in(offset, ColumnUnit::Utf16);
+        json!({ "line": position.line - 1, "character": position.column - 1 })
+    };
+    json!({ "start": position(span.start), "end": position(span.end) })
+}
+
+/// Byte offset of an LSP `Position` in `file`
+fn offset(file: &SourceFile, position: &Json) -> usize {
+    let number = |key: &str| position[key].as_u64().unwrap_or(0) as usize;
+    file.line_index().offset_in(
+        file.contents(),
+        number("line") + 1,
+        number("character") + 1,
+        ColumnUnit::Utf16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///main.soba";
+
+    fn open(server: &mut Server, text: &str) -> Vec<Json> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "soba", "version": 1, "text": text } },
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, params: Json) -> Json {
+        let mut replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }));
+        assert_eq!(replies.len(), 1);
+        replies.remove(0)
+    }
+
+    #[test]
+    fn test_diagnostics_on_change() {
+        let mut server = Server::new();
+        let published = open(&mut server, "1 +;\n2 * ;\n");
+        let diagnostics = &published[0]["params"]["diagnostics"];
+        // Recovery reports both broken statements
+        assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+        assert_eq!(
+            diagnostics[1]["range"]["start"],
+            json!({ "line": 1, "character": 4 })
+        );
+        assert_eq!(diagnostics[0]["severity"], 1);
+
+        let published = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": "((1)) /\n(2 - 2)" }],
+            },
+        }));
+        let diagnostics = &published[0]["params"]["diagnostics"];
+        let codes: Vec<&Json> = diagnostics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|diagnostic| &diagnostic["code"])
+            .collect();
+        assert_eq!(codes, ["redundant-parentheses", "division-by-zero"]);
+        assert_eq!(diagnostics[0]["severity"], 2);
+
+        let published = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didClose",
+            "params": { "textDocument": { "uri": URI } },
+        }));
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_hover() {
+        let mut server = Server::new();
+        open(&mut server, "(1 + 2) * 3;\n1 < 2");
+        let hover = |server: &mut Server, line, character| {
+            request(
+                server,
+                "textDocument/hover",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": line, "character": character },
+                }),
+            )["result"]
+                .clone()
+        };
+
+        let result = hover(&mut server, 0, 3);
+        assert_eq!(result["contents"]["value"], "```soba\n3 : float\n```");
+        assert_eq!(result["range"]["start"]["character"], 1);
+        assert_eq!(result["range"]["end"]["character"], 6);
+        // On the `*` the whole product is shown
+        let result = hover(&mut server, 0, 8);
+        assert_eq!(result["contents"]["value"], "```soba\n9 : float\n```");
+        let result = hover(&mut server, 1, 2);
+        assert_eq!(result["contents"]["value"], "```soba\ntrue : bool\n```");
+        assert_eq!(hover(&mut server, 5, 0), Json::Null);
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let mut server = Server::new();
+        open(&mut server, "1+2;\n1 < 2");
+        let response = request(
+            &mut server,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        );
+        let symbols = response["result"].as_array().unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0]["name"], "1 + 2;");
+        assert_eq!(symbols[0]["detail"], "float");
+        assert_eq!(symbols[1]["detail"], "bool");
+        assert_eq!(
+            symbols[1]["selectionRange"]["start"],
+            json!({ "line": 1, "character": 0 })
+        );
+    }
+
+    #[test]
+    fn test_formatting() {
+        let mut server = Server::new();
+        let format = |server: &mut Server| {
+            request(
+                server,
+                "textDocument/formatting",
+                json!({ "textDocument": { "uri": URI }, "options": { "tabSize": 2, "insertSpaces": true } }),
+            )["result"]
+                .clone()
+        };
+        open(&mut server, "1+2 // sum\n");
+        let edits = format(&mut server);
+        assert_eq!(edits[0]["newText"], "1 + 2 // sum\n");
+        assert_eq!(
+            edits[0]["range"]["end"],
+            json!({ "line": 1, "character": 0 })
+        );
+
+        open(&mut server, "1 + // one\n2");
+        assert_eq!(format(&mut server)[0]["newText"], "1 + // one\n  2\n");
+
+        open(&mut server, "1 + 2;\n");
+        assert_eq!(format(&mut server), json!([]));
+        open(&mut server, "1 +");
+        assert_eq!(format(&mut server), Json::Null);
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut server = Server::new();
+        let response = request(&mut server, "initialize", json!({ "capabilities": {} }));
+        assert_eq!(response["result"]["capabilities"]["hoverProvider"], true);
+        assert!(server
+            .handle(&json!({ "method": "initialized", "params": {} }))
+            .is_empty());
+
+        let response = request(&mut server, "textDocument/rename", json!({}));
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        assert_eq!(
+            request(&mut server, "shutdown", Json::Null)["result"],
+            Json::Null
+        );
+        let response = request(&mut server, "textDocument/hover", json!({}));
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_run() {
+        let mut input = Vec::new();
+        for message in [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        ] {
+            write_message(&mut input, &message).unwrap();
+        }
+        let mut output = Vec::new();
+        run(input.as_slice(), &mut output).unwrap();
+
+        let mut output = output.as_slice();
+        assert_eq!(read_message(&mut output).unwrap().unwrap()["id"], 1);
+        assert_eq!(read_message(&mut output).unwrap().unwrap()["id"], 2);
+        // Nothing is read after `exit`
+        assert_eq!(read_message(&mut output).unwrap(), None);
+    }
+}
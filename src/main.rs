@@ -1,44 +1,858 @@
 use rustyline::config::Configurer;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
-use soba::eval_program_string;
+use soba::analysis::{Lint, Linter, Severity, LINT_RULES};
+use soba::cst::format_file;
+use soba::testing::{find_test_files, run_file};
+use soba::typeck::check_program;
+use soba::{
+    explain, Diagnostic, Lexer, LineIndex, ParseError, Parser, Program, Renderer, Soba, SobaError,
+    SobaLexer, SobaResult, SourceFile, SourceId, SourceMap, Span, Statement, TokenKind, Value,
+};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+mod config;
+
+use config::ReplConfig;
+
 fn main() -> rustyline::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let subcommand: Option<fn(&[String]) -> Exit> = match args.first().map(String::as_str) {
+        Some("check") => Some(check_files),
+        Some("fmt") => Some(format_files),
+        Some("lint") => Some(lint_files),
+        Some("ast") => Some(dump_ast),
+        Some("tokens") => Some(dump_tokens),
+        Some("lsp") => Some(serve_lsp),
+        Some("test") => Some(run_tests),
+        Some("--explain") => Some(|args| explain_code(args.first().map(String::as_str))),
+        Some("--help" | "-h") => Some(|_| {
+            print!("{}", usage());
+            Exit::Success
+        }),
+        _ => None,
+    };
+    if let Some(run) = subcommand {
+        run(&args[1..]).exit();
+    }
+    if names_script(&args) {
+        run_script(&args).exit();
+    }
+
+    match repl_config(&args) {
+        Some(config) => run_repl(config),
+        None => Exit::Usage.exit(),
+    }
+}
+
+/// Process exit status, telling apart what went wrong
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Exit {
+    Success = 0,
+    /// The input was read but did not pass, e.g. lints or type errors
+    Failure = 1,
+    /// Wrong command line arguments
+    Usage = 2,
+    /// A file could not be read or written
+    Io = 3,
+    Lex = 4,
+    Parse = 5,
+    Eval = 6,
+}
+
+impl Exit {
+    /// The status for failing with `err`
+    fn of(err: &SobaError) -> Exit {
+        match err {
+            SobaError::LexError(_) => Exit::Lex,
+            SobaError::ParseError(err) => Exit::of_parse(err),
+            SobaError::EvalError(_) => Exit::Eval,
+            SobaError::TypeCheckErrors(_) => Exit::Failure,
+        }
+    }
+
+    fn of_parse(err: &ParseError) -> Exit {
+        match err {
+            ParseError::LexError(_) => Exit::Lex,
+            _ => Exit::Parse,
+        }
+    }
+
+    /// Keep the first failure when several things go wrong
+    fn or(self, other: Exit) -> Exit {
+        if self == Exit::Success {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+/// Settings from the config file, if there is one, and `args`
+/// Returns None if the flags are wrong; a broken config file is reported
+/// and ignored
+fn repl_config(args: &[String]) -> Option<ReplConfig> {
+    let mut config = ReplConfig::default();
+    if let Some(path) = ReplConfig::path() {
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            let mut from_file = config.clone();
+            match from_file.merge_toml(&text) {
+                Ok(()) => config = from_file,
+                Err(err) => eprintln!("{}: {err}", path.display()),
+            }
+        }
+    }
+
+    if let Err(err) = config.merge_args(args) {
+        eprintln!("{err}");
+        eprint!("{}", usage());
+        return None;
+    }
+    Some(config)
+}
+
+/// Ways to run soba, as listed by `--help`
+const USAGES: &[(&str, &str)] = &[
+    ("soba [flags]", "Start the REPL"),
+    (
+        "soba [flags] [script flags] <file>",
+        "Run a script and print its final value",
+    ),
+    ("soba check <file>...", "Type check files"),
+    (
+        "soba fmt [--check] <file>...",
+        "Format files, or check that they are formatted",
+    ),
+    (
+        "soba lint [options] <file>...",
+        "Lint files; `soba lint --list-rules` lists the rules",
+    ),
+    (
+        "soba ast <file> [--format tree|sexpr|json]",
+        "Print the parsed program",
+    ),
+    ("soba tokens <file>", "Print the tokens of a file"),
+    (
+        "soba test [dir|file]...",
+        "Run the test files under each path",
+    ),
+    (
+        "soba lsp",
+        "Serve editors over the Language Server Protocol",
+    ),
+    ("soba --explain <code>", "Explain an error code"),
+    ("soba --help, -h", "Show this help"),
+];
+
+/// Flags of `run_script`, which start script mode rather than the REPL
+const SCRIPT_FLAGS: &[(&str, &str)] = &[
+    ("--quiet", "Print nothing when the script succeeds"),
+    (
+        "--output text|json",
+        "Print the outcome as text or as one JSON object",
+    ),
+];
+
+/// The full usage text: every way to run soba and every flag
+fn usage() -> String {
+    let mut text = String::from("Usage:\n");
+    for (usage, description) in USAGES {
+        text.push_str(&format!("  {usage:<44}{description}\n"));
+    }
+    for (heading, flags) in [("Flags", config::FLAGS), ("Script flags", SCRIPT_FLAGS)] {
+        text.push_str(&format!("\n{heading}:\n"));
+        for (flag, description) in flags {
+            text.push_str(&format!("  {flag:<24}{description}\n"));
+        }
+    }
+    text
+}
+
+/// Whether `args` run a script rather than start the REPL: they hold a
+/// script flag or a path, which is anything other than a flag or the
+/// value after one
+fn names_script(args: &[String]) -> bool {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quiet" | "--output" => return true,
+            flag if flag.starts_with('-') => {
+                if config::flag_takes_value(flag) {
+                    args.next();
+                }
+            }
+            _ => return true,
+        }
+    }
+    false
+}
+
+/// How `run_script` reports the outcome of a script
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// The value on stdout, or an error on stderr
+    Text,
+    /// One JSON object on stdout, for programs that run soba
+    Json,
+}
+
+/// Run a script file given as `[flags] [--quiet] [--output text|json] <file>`,
+/// printing its final value unless `--quiet` is given
+///
+/// The REPL flags may come first: `--strict`, `--types` and `--color`
+/// apply to the script as they do to REPL entries, and the others are
+/// accepted and ignored.
+///
+/// With `--output json`, a single object is printed whatever happens:
+/// `{"ok": true, "value": {"type": "int", "value": 5}}` or
+/// `{"ok": false, "error": {"code": "E0001", "message": ..., ...}}`.
+fn run_script(args: &[String]) -> Exit {
+    let usage = || eprint!("{}", usage());
+    let mut quiet = false;
+    let mut format = OutputFormat::Text;
+    let mut paths = Vec::new();
+    let mut repl_flags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quiet" => quiet = true,
+            "--output" => match args.next().map(String::as_str) {
+                Some("text") => format = OutputFormat::Text,
+                Some("json") => format = OutputFormat::Json,
+                _ => {
+                    usage();
+                    return Exit::Usage;
+                }
+            },
+            flag if flag.starts_with('-') => {
+                repl_flags.push(arg.clone());
+                if config::flag_takes_value(flag) {
+                    repl_flags.extend(args.next().cloned());
+                }
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let mut config = ReplConfig::default();
+    if let Err(err) = config.merge_args(&repl_flags) {
+        eprintln!("{err}");
+        usage();
+        return Exit::Usage;
+    }
+    let [path] = paths.as_slice() else {
+        usage();
+        return Exit::Usage;
+    };
+    if format == OutputFormat::Json && !cfg!(feature = "json") {
+        eprintln!("--output json needs soba to be built with the `json` feature");
+        return Exit::Usage;
+    }
+
+    let mut sources = SourceMap::new();
+    let file = match read_file(&mut sources, path) {
+        Ok(id) => sources.get(id).unwrap(),
+        Err(err) => {
+            match format {
+                OutputFormat::Text => eprintln!("{path}: {err}"),
+                OutputFormat::Json => print_json_failure(None, &format!("{path}: {err}"), None),
+            }
+            return Exit::Io;
+        }
+    };
+
+    let soba = Soba::builder().strict(config.strict).build();
+    let result = soba
+        .compile_at(file.code(), file.start())
+        .and_then(|program| soba.run(&program));
+    match (result, format) {
+        (Ok(value), OutputFormat::Text) => {
+            if !quiet && config.types {
+                println!("{value} : {}", value.type_name());
+            } else if !quiet {
+                println!("{value}");
+            }
+            Exit::Success
+        }
+        (Ok(value), OutputFormat::Json) => {
+            print_json_value(&value);
+            Exit::Success
+        }
+        (Err(err), OutputFormat::Text) => {
+            let renderer = renderer(std::io::stderr().is_terminal());
+            let renderer = Renderer {
+                color: config.color && renderer.color,
+                ..renderer
+            };
+            eprintln!("{}", renderer.render(&(&err).into(), &sources));
+            Exit::of(&err)
+        }
+        (Err(err), OutputFormat::Json) => {
+            let location = err.span().map(|span| (span, sources.location(span)));
+            print_json_failure(Some(err.code()), &err.to_string(), location);
+            Exit::of(&err)
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+fn print_json_value(value: &Value) {
+    let output = serde_json::json!({
+        "ok": true,
+        "value": { "type": value.type_name(), "value": value.to_json() },
+    });
+    println!("{output}");
+}
+
+/// Print a failed outcome; `location` is the span of the error and its
+/// description, such as `main.soba:1:3-5`
+#[cfg(feature = "json")]
+fn print_json_failure(code: Option<&str>, message: &str, location: Option<(Span, String)>) {
+    let (span, location) = match location {
+        Some((span, location)) => (
+            serde_json::json!({ "start": span.start, "end": span.end }),
+            serde_json::Value::from(location),
+        ),
+        None => (serde_json::Value::Null, serde_json::Value::Null),
+    };
+    let output = serde_json::json!({
+        "ok": false,
+        "error": { "code": code, "message": message, "location": location, "span": span },
+    });
+    println!("{output}");
+}
+
+#[cfg(not(feature = "json"))]
+fn print_json_value(_: &Value) {
+    unreachable!("--output json is rejected without the json feature")
+}
+
+#[cfg(not(feature = "json"))]
+fn print_json_failure(_: Option<&str>, _: &str, _: Option<(Span, String)>) {
+    unreachable!("--output json is rejected without the json feature")
+}
+
+/// How diagnostics look when printed: colored only on a terminal and
+/// when `NO_COLOR` is not set, and as wide as `COLUMNS` or the terminal
+fn renderer(terminal: bool) -> Renderer {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let width = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(|| terminal_size::terminal_size().map(|(width, _)| usize::from(width.0)))
+        .unwrap_or(Renderer::default().width);
+    Renderer {
+        color: terminal && !no_color,
+        width,
+    }
+}
+
+/// Print a diagnostic for one of `sources` to stderr
+fn print_diagnostic(diagnostic: &Diagnostic, sources: &SourceMap) {
+    let renderer = renderer(std::io::stderr().is_terminal());
+    eprintln!("{}", renderer.render(diagnostic, sources));
+}
+
+/// Read the soba file at `path` into `sources`
+///
+/// A leading `#!` line, as in `#!/usr/bin/env soba`, stays in the contents
+/// for diagnostics, but the file's lexer starts after it.
+fn read_file(sources: &mut SourceMap, path: &str) -> std::io::Result<SourceId> {
+    let source = std::fs::read_to_string(path)?;
+    Ok(sources.add_file(path, source))
+}
+
+/// Type check each file, printing every error found
+fn check_files(paths: &[String]) -> Exit {
+    if paths.is_empty() {
+        eprintln!("Usage: soba check <file>...");
+        return Exit::Usage;
+    }
+
+    let mut exit = Exit::Success;
+    let mut sources = SourceMap::new();
+    for path in paths {
+        let id = match read_file(&mut sources, path) {
+            Ok(id) => id,
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                exit = exit.or(Exit::Io);
+                continue;
+            }
+        };
+
+        let lexer = sources.get(id).unwrap().lexer();
+        let parsed = Parser::new(lexer).and_then(|mut parser| {
+            let program = parser.parse_program()?;
+            Ok((program, parser.take_warnings()))
+        });
+        let program = match parsed {
+            Ok((program, warnings)) => {
+                for warning in &warnings {
+                    print_diagnostic(&warning.into(), &sources);
+                }
+                program
+            }
+            Err(err) => {
+                print_diagnostic(&(&err).into(), &sources);
+                exit = exit.or(Exit::of_parse(&err));
+                continue;
+            }
+        };
+
+        if let Err(errors) = check_program(&program) {
+            for err in &errors {
+                print_diagnostic(&err.into(), &sources);
+            }
+            exit = exit.or(Exit::Failure);
+        }
+    }
+
+    exit
+}
+
+/// Rewrite each file in the canonical style, or with `--check` list the
+/// files that are not formatted
+/// Fails with `Exit::Failure` if `--check` finds unformatted files
+fn format_files(args: &[String]) -> Exit {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if paths.is_empty() {
+        eprintln!("Usage: soba fmt [--check] <file>...");
+        return Exit::Usage;
+    }
+
+    let mut exit = Exit::Success;
+    let mut sources = SourceMap::new();
+    for path in paths {
+        let source = match read_file(&mut sources, path) {
+            Ok(id) => sources.get(id).unwrap().contents(),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                exit = exit.or(Exit::Io);
+                continue;
+            }
+        };
+        let formatted = match format_file(source) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                print_diagnostic(&(&err).into(), &sources);
+                exit = exit.or(Exit::of_parse(&err));
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{path} is not formatted");
+            exit = exit.or(Exit::Failure);
+        } else if let Err(err) = std::fs::write(path, formatted) {
+            eprintln!("{path}: {err}");
+            exit = exit.or(Exit::Io);
+        }
+    }
+
+    exit
+}
+
+/// Run the linter over each file, as
+/// `[--deny warnings] [--select rules] [--ignore rules] [--warn rules]
+/// [--error rules] [--format text|json] <file>...` where rules are
+/// comma-separated; `--list-rules` prints the rules instead
+///
+/// Text output is one `file:line:col: severity[rule]: message` line per
+/// lint; JSON output is an array of lint objects.
+/// Fails with `Exit::Failure` on an error lint, or with `--deny warnings`
+/// on any lint
+fn lint_files(args: &[String]) -> Exit {
+    let usage = || {
+        eprintln!(
+            "Usage: soba lint [--deny warnings] [--select rules] [--ignore rules] \
+             [--warn rules] [--error rules] [--format text|json] <file>..."
+        );
+        eprintln!("       soba lint --list-rules");
+    };
+    let mut linter = Linter::new();
+    let mut deny_warnings = false;
+    let mut format = OutputFormat::Text;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let selected = match arg.as_str() {
+            "--list-rules" => {
+                for rule in LINT_RULES {
+                    println!("{:<26}{:<9}{}", rule.name, rule.severity, rule.description);
+                }
+                return Exit::Success;
+            }
+            "--deny" if args.next().map(String::as_str) == Some("warnings") => {
+                deny_warnings = true;
+                Ok(())
+            }
+            "--select" => linter.select(&rule_names(args.next())),
+            "--ignore" => linter.ignore(&rule_names(args.next())),
+            "--warn" => linter.set_severity(&rule_names(args.next()), Severity::Warning),
+            "--error" => linter.set_severity(&rule_names(args.next()), Severity::Error),
+            "--format" => match args.next().map(String::as_str) {
+                Some("text") => {
+                    format = OutputFormat::Text;
+                    Ok(())
+                }
+                Some("json") => {
+                    format = OutputFormat::Json;
+                    Ok(())
+                }
+                _ => {
+                    usage();
+                    return Exit::Usage;
+                }
+            },
+            _ if arg.starts_with("--") => {
+                usage();
+                return Exit::Usage;
+            }
+            _ => {
+                paths.push(arg);
+                Ok(())
+            }
+        };
+        if let Err(err) = selected {
+            eprintln!("{err}, see `soba lint --list-rules`");
+            return Exit::Usage;
+        }
+    }
+    if paths.is_empty() {
+        usage();
+        return Exit::Usage;
+    }
+    if format == OutputFormat::Json && !cfg!(feature = "json") {
+        eprintln!("--format json needs soba to be built with the `json` feature");
+        return Exit::Usage;
+    }
+
+    let mut exit = Exit::Success;
+    let mut sources = SourceMap::new();
+    let mut found = Vec::new();
+    for path in paths {
+        let id = match read_file(&mut sources, path) {
+            Ok(id) => id,
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                exit = exit.or(Exit::Io);
+                continue;
+            }
+        };
+        match linter.lint(sources.get(id).unwrap()) {
+            Ok(lints) => found.extend(lints),
+            Err(err) => {
+                print_diagnostic(&(&err).into(), &sources);
+                exit = exit.or(Exit::of_parse(&err));
+            }
+        }
+    }
+
+    let failing = |lint: &Lint| lint.severity == Severity::Error || deny_warnings;
+    if found.iter().any(failing) {
+        exit = exit.or(Exit::Failure);
+    }
+    match format {
+        OutputFormat::Text => {
+            for lint in &found {
+                println!("{}: {lint}", sources.location(lint.span));
+            }
+            let failures = found.iter().filter(|lint| failing(lint)).count();
+            if !found.is_empty() {
+                let plural = if found.len() == 1 { "" } else { "s" };
+                eprintln!(
+                    "{} problem{plural}: {failures} failing, {} allowed",
+                    found.len(),
+                    found.len() - failures
+                );
+            }
+        }
+        OutputFormat::Json => print_json_lints(&found, &sources),
+    }
+
+    exit
+}
+
+/// The rules of a comma-separated `--select`, `--ignore`, `--warn` or
+/// `--error` value
+fn rule_names(value: Option<&String>) -> Vec<&str> {
+    value.map_or(Vec::new(), |value| {
+        value.split(',').map(str::trim).collect()
+    })
+}
+
+#[cfg(feature = "json")]
+fn print_json_lints(lints: &[Lint], sources: &SourceMap) {
+    let lints: Vec<serde_json::Value> = lints
+        .iter()
+        .map(|lint| {
+            let file = sources.get(lint.span.source).map(|file| file.name());
+            serde_json::json!({
+                "file": file,
+                "rule": lint.rule.name,
+                "severity": lint.severity.to_string(),
+                "message": lint.message,
+                "location": sources.location(lint.span),
+                "span": { "start": lint.span.start, "end": lint.span.end },
+            })
+        })
+        .collect();
+    println!("{}", serde_json::Value::Array(lints));
+}
+
+#[cfg(not(feature = "json"))]
+fn print_json_lints(_: &[Lint], _: &SourceMap) {
+    unreachable!("--format json is rejected without the json feature")
+}
+
+/// Print the parsed program of a file given as
+/// `<file> [--format tree|sexpr|json]`; see `ast::dump` for the tree and
+/// s-expression formats and `ast::json` for the JSON one
+fn dump_ast(args: &[String]) -> Exit {
+    let usage = || eprintln!("Usage: soba ast <file> [--format tree|sexpr|json]");
+    let mut format = "tree";
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some(name @ ("tree" | "sexpr" | "json")) => format = name,
+                _ => {
+                    usage();
+                    return Exit::Usage;
+                }
+            },
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths.as_slice() else {
+        usage();
+        return Exit::Usage;
+    };
+    if format == "json" && !cfg!(feature = "json") {
+        eprintln!("--format json needs soba to be built with the `json` feature");
+        return Exit::Usage;
+    }
+
+    let mut sources = SourceMap::new();
+    let lexer = match read_file(&mut sources, path) {
+        Ok(id) => sources.get(id).unwrap().lexer(),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return Exit::Io;
+        }
+    };
+    let program = match Parser::new(lexer).and_then(|mut parser| parser.parse_program()) {
+        Ok(program) => program,
+        Err(err) => {
+            print_diagnostic(&(&err).into(), &sources);
+            return Exit::of_parse(&err);
+        }
+    };
+
+    match format {
+        "sexpr" => print!("{}", program.to_sexpr()),
+        "json" => println!("{}", program_json(&program)),
+        _ => print!("{}", program.to_tree()),
+    }
+    Exit::Success
+}
+
+#[cfg(feature = "json")]
+fn program_json(program: &Program) -> String {
+    soba::ast::json::to_json(program)
+}
+
+#[cfg(not(feature = "json"))]
+fn program_json(_: &Program) -> String {
+    unreachable!("--format json is rejected without the json feature")
+}
+
+/// Run the test files under each directory given, or under the current
+/// one; see `soba::testing` for what a test file is
+///
+/// Prints one line per file and the failed assertions with the source
+/// they point at, then a summary.
+/// Fails with `Exit::Failure` if an assertion fails
+fn run_tests(args: &[String]) -> Exit {
+    if args.iter().any(|arg| arg.starts_with("--")) {
+        eprintln!("Usage: soba test [dir|file]...");
+        return Exit::Usage;
+    }
+    let roots = if args.is_empty() {
+        vec![".".to_string()]
+    } else {
+        args.to_vec()
+    };
+
+    let mut exit = Exit::Success;
+    let mut paths = Vec::new();
+    for root in &roots {
+        match find_test_files(root.as_ref()) {
+            Ok(found) => paths.extend(found),
+            Err(err) => {
+                eprintln!("{root}: {err}");
+                exit = exit.or(Exit::Io);
+            }
+        }
+    }
+
+    let (mut passed, mut failed, mut failed_files) = (0, 0, 0);
+    let mut sources = SourceMap::new();
+    for path in &paths {
+        let name = path.display().to_string();
+        let id = match read_file(&mut sources, &name) {
+            Ok(id) => id,
+            Err(err) => {
+                eprintln!("{name}: {err}");
+                exit = exit.or(Exit::Io);
+                continue;
+            }
+        };
+        let report = run_file(sources.get(id).unwrap());
+        passed += report.passed;
+        failed += report.failures.len();
+        if report.is_ok() {
+            println!("{name} ... ok");
+            continue;
+        }
+        failed_files += 1;
+        let total = report.passed + report.failures.len();
+        println!("{name} ... FAILED ({} of {total})", report.failures.len());
+        for failure in &report.failures {
+            print_diagnostic(&failure.into(), &sources);
+        }
+    }
+
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    let plural = if paths.len() == 1 { "" } else { "s" };
+    println!(
+        "test result: {result}. {passed} passed; {failed} failed in {failed_files} of {} file{plural}",
+        paths.len()
+    );
+    if failed > 0 {
+        exit = exit.or(Exit::Failure);
+    }
+    exit
+}
+
+/// Run the language server over stdin and stdout until the client exits
+#[cfg(feature = "lsp")]
+fn serve_lsp(args: &[String]) -> Exit {
+    if !args.is_empty() {
+        eprintln!("Usage: soba lsp");
+        return Exit::Usage;
+    }
+    let stdin = std::io::stdin();
+    match soba::lsp::run(stdin.lock(), std::io::stdout().lock()) {
+        Ok(()) => Exit::Success,
+        Err(err) => {
+            eprintln!("soba lsp: {err}");
+            Exit::Io
+        }
+    }
+}
+
+#[cfg(not(feature = "lsp"))]
+fn serve_lsp(_: &[String]) -> Exit {
+    eprintln!("soba lsp needs soba to be built with the `lsp` feature");
+    Exit::Usage
+}
+
+/// Print the explanation of an error code such as `E0001`
+fn explain_code(code: Option<&str>) -> Exit {
+    let Some(code) = code else {
+        eprintln!("Usage: soba --explain <code>");
+        return Exit::Usage;
+    };
+    match explain(&code.to_ascii_uppercase()) {
+        Some(explanation) => {
+            println!("{explanation}");
+            Exit::Success
+        }
+        None => {
+            eprintln!("{code} is not a Soba error code");
+            Exit::Failure
+        }
+    }
+}
+
+fn run_repl(config: ReplConfig) -> rustyline::Result<()> {
     println!("This is the Soba programming language!");
 
     let mut rl = DefaultEditor::new()?;
 
-    // Set maximum history size to 1000 entries
-    rl.set_max_history_size(1000)?;
+    rl.set_max_history_size(config.history_size)?;
 
-    // Load history from file
-    let history_file = ".soba_history";
-    if rl.load_history(history_file).is_err() {
-        // History file doesn't exist, that's fine
+    let history_file = config.history_path();
+    if let Some(path) = &history_file {
+        match rl.load_history(path) {
+            Ok(()) => {}
+            // No history yet, that's fine
+            Err(ReadlineError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => eprintln!("Could not read history from {}: {err}", path.display()),
+        }
     }
 
+    // Lines of an entry that is still missing its end
+    let mut pending = String::new();
+    let continuation_prompt = config.continuation_prompt();
+    let mut session = Session::new(config);
     loop {
-        let readline = rl.readline(">> ");
+        let prompt = if pending.is_empty() {
+            &session.config.prompt
+        } else {
+            &continuation_prompt
+        };
+        let readline = rl.readline(prompt);
         match readline {
             Ok(line) => {
-                // Add to history
-                let _ = rl.add_history_entry(&line);
+                if pending.is_empty() {
+                    if line.trim() == "exit" {
+                        break;
+                    }
+
+                    if line.trim().is_empty() {
+                        continue;
+                    }
 
-                if line.trim() == "exit" {
-                    break;
+                    if line.trim_start().starts_with(':') {
+                        let _ = rl.add_history_entry(&line);
+                        match run_command(line.trim(), &mut session) {
+                            Flow::Continue => continue,
+                            Flow::Quit => break,
+                        }
+                    }
                 }
 
-                if line.trim().is_empty() {
+                // An empty continuation line gives up and reports the error
+                let give_up = !pending.is_empty() && line.trim().is_empty();
+                pending.push_str(&line);
+                pending.push('\n');
+
+                let result = session.eval(&pending, 0);
+                if is_incomplete(&result) && !give_up {
                     continue;
                 }
 
-                match eval_program_string(&line) {
-                    Ok(result) => {
-                        println!("{result}");
-                    }
-                    Err(err) => {
-                        println!("{err}");
-                    }
-                }
+                // Add the whole entry to history
+                let _ = rl.add_history_entry(pending.trim_end());
+                let mut sources = SourceMap::new();
+                let id = sources.add("<repl>", std::mem::take(&mut pending));
+                session.finish(&sources, id, result);
+            }
+            Err(ReadlineError::Interrupted) if !pending.is_empty() => {
+                // Ctrl-C abandons the unfinished entry
+                pending.clear();
             }
             Err(ReadlineError::Interrupted) => {
                 break;
@@ -53,8 +867,358 @@ fn main() -> rustyline::Result<()> {
         }
     }
 
-    // Save history to file
-    let _ = rl.save_history(history_file);
+    // Append rather than overwrite, so that the entries of other sessions
+    // that ran at the same time are kept
+    if let Some(path) = &history_file {
+        if let Some(dir) = path.parent() {
+            // A directory that cannot be made fails the save below
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(err) = rl.append_history(path) {
+            eprintln!("Could not save history to {}: {err}", path.display());
+        }
+    }
 
     Ok(())
 }
+
+/// What the REPL remembers between entries
+struct Session {
+    config: ReplConfig,
+    /// How errors are shown
+    renderer: Renderer,
+    /// The last complete entry, for commands like `:ast` without input
+    last_input: Option<String>,
+    /// Statements evaluated without errors, as canonical source for `:save`
+    statements: Vec<String>,
+}
+
+impl Session {
+    fn new(config: ReplConfig) -> Self {
+        Self {
+            renderer: Self::renderer(&config),
+            config,
+            last_input: None,
+            statements: Vec::new(),
+        }
+    }
+
+    /// How errors are shown with the settings in `config`
+    fn renderer(config: &ReplConfig) -> Renderer {
+        let renderer = renderer(std::io::stdout().is_terminal());
+        Renderer {
+            color: config.color && renderer.color,
+            ..renderer
+        }
+    }
+
+    /// Run `:set` with `argument`, which is `<setting> on|off`
+    fn set(&mut self, argument: &str) {
+        let Some((name, value)) = argument.split_once(char::is_whitespace) else {
+            println!(
+                "Usage: :set <setting> on|off, where setting is one of {}",
+                config::SWITCHES.join(", ")
+            );
+            return;
+        };
+        match self.config.set(name, value.trim()) {
+            Ok(()) => self.renderer = Self::renderer(&self.config),
+            Err(err) => println!("{err}"),
+        }
+    }
+
+    /// Evaluate `code`, which starts `start` bytes into its source; in
+    /// strict mode it is type checked first and entries with type errors
+    /// never run
+    fn eval(&self, code: &str, start: usize) -> SobaResult<Value> {
+        let soba = self.soba();
+        soba.compile_at(code, start)
+            .and_then(|program| soba.run(&program))
+    }
+
+    /// The engine that entries run on, set up from the settings
+    fn soba(&self) -> Soba {
+        Soba::builder().strict(self.config.strict).build()
+    }
+
+    /// Print `value`, with its type if `types` is on
+    fn print_value(&self, value: &Value) {
+        if self.config.types {
+            println!("{value} : {}", value.type_name());
+        } else {
+            println!("{value}");
+        }
+    }
+
+    /// Print the value of source `id` in `sources`, or why it was rejected,
+    /// where `result` is what `eval` gave
+    fn finish(&mut self, sources: &SourceMap, id: SourceId, result: SobaResult<Value>) {
+        let file = sources.get(id).unwrap();
+        match result {
+            Ok(value) => {
+                self.record(file);
+                self.print_value(&value);
+            }
+            Err(SobaError::TypeCheckErrors(errors)) => {
+                for err in &errors {
+                    self.print_error(&err.into(), sources);
+                }
+            }
+            Err(err) => self.print_error(&(&err).into(), sources),
+        }
+        self.last_input = Some(file.code().to_string());
+    }
+
+    fn print_error(&self, diagnostic: &Diagnostic, sources: &SourceMap) {
+        println!("{}", self.renderer.render(diagnostic, sources));
+    }
+
+    /// Keep the statements of `file`, which evaluated successfully
+    fn record(&mut self, file: &SourceFile) {
+        if let Ok(program) = Parser::new(file.lexer()).and_then(|mut parser| parser.parse_program())
+        {
+            self.statements
+                .extend(program.statements.iter().map(Statement::to_source));
+        }
+    }
+}
+
+/// What the REPL does after a meta-command
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// REPL meta-commands, as listed by `:help`
+const COMMANDS: &[(&str, &str)] = &[
+    (":help", "List the available commands"),
+    (
+        ":tokens [input]",
+        "Show the tokens of the input, or of the last entry",
+    ),
+    (
+        ":ast [input]",
+        "Show the parsed AST of the input, or of the last entry",
+    ),
+    (
+        ":time [-n N] [input]",
+        "Time parsing and evaluating the input, or the last entry, N times",
+    ),
+    (
+        ":save <file>",
+        "Write the statements evaluated without errors to a file",
+    ),
+    (
+        ":load <file>",
+        "Evaluate a file as if its statements were entered",
+    ),
+    (
+        ":set <setting> on|off",
+        "Turn color, strict or types (`5 : int`) on or off",
+    ),
+    (":quit", "Leave the REPL, like `exit`"),
+];
+
+/// Run a `:command` line
+fn run_command(line: &str, session: &mut Session) -> Flow {
+    let last_input = session.last_input.as_deref();
+    let (name, argument) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(name, argument)| (name, argument.trim()));
+    let input = if argument.is_empty() {
+        last_input
+    } else {
+        Some(argument)
+    };
+
+    match (name, input) {
+        (":help", _) => {
+            for (usage, description) in COMMANDS {
+                println!("{usage:<22}{description}");
+            }
+        }
+        (":quit", _) => return Flow::Quit,
+        (":time", _) => time_input(argument, session),
+        (":save" | ":load", _) if argument.is_empty() => {
+            println!("{name} needs a file name, e.g. `{name} session.soba`");
+        }
+        (":save", _) => save_session(argument, session),
+        (":set", _) => session.set(argument),
+        (":load", _) => load_file(argument, session),
+        (":tokens" | ":ast", None) => println!("Nothing to show yet, try `{name} 1 + 2`"),
+        (":tokens", Some(input)) => {
+            print_tokens(input, 0);
+        }
+        (":ast", Some(input)) => print_ast(input),
+        _ => println!("Unknown command {name}, type :help for a list"),
+    }
+    Flow::Continue
+}
+
+/// Write the successful statements of the session to `path`, one per line
+fn save_session(path: &str, session: &Session) {
+    let source: String = session
+        .statements
+        .iter()
+        .map(|stmt| format!("{stmt}\n"))
+        .collect();
+    match std::fs::write(path, source) {
+        Ok(()) => println!("Saved {} statements to {path}", session.statements.len()),
+        Err(err) => println!("{path}: {err}"),
+    }
+}
+
+/// Evaluate the file at `path`, printing its value, and add its statements
+/// to the session if it succeeds
+fn load_file(path: &str, session: &mut Session) {
+    let mut sources = SourceMap::new();
+    let id = match read_file(&mut sources, path) {
+        Ok(id) => id,
+        Err(err) => {
+            println!("{path}: {err}");
+            return;
+        }
+    };
+    let file = sources.get(id).unwrap();
+    let result = session.eval(file.code(), file.start());
+    session.finish(&sources, id, result);
+}
+
+/// Run `:time` with `argument`, which is `[-n N] [input]`, on the same
+/// engine as entries; in strict mode the parse phase includes the type
+/// check
+fn time_input(argument: &str, session: &Session) {
+    let (runs, input) = match argument.strip_prefix("-n") {
+        Some(rest) => {
+            let rest = rest.trim_start();
+            let (count, input) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            match count.parse::<u32>() {
+                Ok(runs) if runs > 0 => (runs, input.trim()),
+                _ => {
+                    println!("-n needs a positive number of runs, e.g. `:time -n 100 1 + 2`");
+                    return;
+                }
+            }
+        }
+        None => (1, argument),
+    };
+    let last_input = session.last_input.as_deref();
+    let Some(input) = Some(input).filter(|input| !input.is_empty()).or(last_input) else {
+        println!("Nothing to time yet, try `:time 1 + 2`");
+        return;
+    };
+
+    let mut sources = SourceMap::new();
+    sources.add("<repl>", input);
+    let soba = session.soba();
+    let mut parse_time = Duration::ZERO;
+    let mut eval_time = Duration::ZERO;
+    let mut result = None;
+    for _ in 0..runs {
+        let start = Instant::now();
+        let program = match soba.compile(input) {
+            Ok(program) => program,
+            Err(err) => {
+                session.print_error(&(&err).into(), &sources);
+                return;
+            }
+        };
+        parse_time += start.elapsed();
+
+        let start = Instant::now();
+        result = Some(soba.run(&program));
+        eval_time += start.elapsed();
+    }
+
+    match result {
+        Some(Ok(value)) => session.print_value(&value),
+        Some(Err(err)) => session.print_error(&(&err).into(), &sources),
+        None => {}
+    }
+    let runs_note = if runs == 1 {
+        String::new()
+    } else {
+        format!(" (mean of {runs} runs)")
+    };
+    println!(
+        "parse: {:?}, eval: {:?}{runs_note}",
+        parse_time / runs,
+        eval_time / runs
+    );
+}
+
+/// Print each token of `input` from byte `start` on with its line and
+/// column, byte range and kind, continuing past invalid characters
+/// Returns false if there were any
+fn print_tokens(input: &str, start: usize) -> bool {
+    let index = LineIndex::new(input);
+    let mut lexer = SobaLexer::starting_at(input[start..].chars().collect(), start);
+    lexer.set_error_recovery(true);
+    let mut ok = true;
+    loop {
+        let token = match lexer.next_token() {
+            Ok(token) if token.kind == TokenKind::Eof => break,
+            Ok(token) => token,
+            Err(err) => {
+                println!("{err}");
+                return false;
+            }
+        };
+        let position = index.position(input, token.span.start).to_string();
+        let span = token.span.to_string();
+        match token.kind {
+            TokenKind::Error(err) => {
+                println!("{position:<8} {span:<10} Error: {err}");
+                ok = false;
+            }
+            kind => println!("{position:<8} {span:<10} {kind:?}"),
+        }
+    }
+    ok
+}
+
+/// Print the tokens of the file given as `<file>`
+/// Fails with `Exit::Lex` if the file has invalid characters
+fn dump_tokens(args: &[String]) -> Exit {
+    let [path] = args else {
+        eprintln!("Usage: soba tokens <file>");
+        return Exit::Usage;
+    };
+    let mut sources = SourceMap::new();
+    match read_file(&mut sources, path) {
+        Ok(id) => {
+            let file = sources.get(id).unwrap();
+            if print_tokens(file.contents(), file.start()) {
+                Exit::Success
+            } else {
+                Exit::Lex
+            }
+        }
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            Exit::Io
+        }
+    }
+}
+
+/// Print the parsed program as an indented tree, one node per line with
+/// its byte range
+fn print_ast(input: &str) {
+    let lexer = SobaLexer::new(input.chars().collect());
+    match Parser::new(lexer).and_then(|mut parser| parser.parse_program()) {
+        Ok(program) => print!("{}", program.to_tree()),
+        Err(err) => println!("Parse error: {err}"),
+    }
+}
+
+/// Whether the input ended in the middle of an expression, e.g. after a
+/// trailing operator or inside an unclosed `(`, so more lines may finish it
+fn is_incomplete(result: &SobaResult<Value>) -> bool {
+    matches!(
+        result,
+        Err(SobaError::ParseError(
+            ParseError::UnexpectedEof { .. }
+                | ParseError::MismatchedParentheses { open: Some(_), .. }
+        ))
+    )
+}
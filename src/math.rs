@@ -0,0 +1,37 @@
+//! Float functions that `core` lacks, from `std` or else `libm`
+
+/// `base` raised to the power `exponent`
+pub(crate) fn powf(base: f64, exponent: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return base.powf(exponent);
+    #[cfg(not(feature = "std"))]
+    return libm::pow(base, exponent);
+}
+
+/// Whether `value` is finite with no fractional part
+pub(crate) fn is_integral(value: f64) -> bool {
+    #[cfg(feature = "std")]
+    return value.fract() == 0.0;
+    #[cfg(not(feature = "std"))]
+    return value.is_finite() && libm::trunc(value) == value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_integral() {
+        assert!(is_integral(3.0));
+        assert!(is_integral(-0.0));
+        assert!(!is_integral(2.5));
+        assert!(!is_integral(f64::INFINITY));
+        assert!(!is_integral(f64::NAN));
+    }
+
+    #[test]
+    fn test_powf() {
+        assert_eq!(powf(2.0, 10.0), 1024.0);
+        assert_eq!(powf(4.0, 0.5), 2.0);
+    }
+}
@@ -0,0 +1,536 @@
+//! Embedder-defined operators
+//!
+//! An [`OperatorRegistry`] holds infix, prefix and postfix operators that
+//! are not part of the core language, each with a handler that computes its
+//! value.
+//! The same registry is handed to the lexer, the parser and the evaluator,
+//! so a notation like `a <=> b` can be added without changing the crate.
+//!
+//! Operator symbols are made of punctuation characters. They may extend a
+//! built-in operator (`<=>` starts with `<=`) but may not be a built-in or
+//! a prefix of one, since the lexer always takes the longest symbol it
+//! knows.
+
+use crate::error::{EvalError, EvalResult};
+use crate::parser::Precedence;
+use crate::prelude::*;
+use crate::value::Value;
+use alloc::sync::Arc;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+/// Characters that may appear in a custom operator symbol
+pub const OPERATOR_CHARS: &[char] = &[
+    '!', '#', '$', '%', '&', '*', '+', '-', '/', ':', '<', '=', '>', '?', '@', '^', '|', '~',
+];
+
+/// Symbols of the built-in operators
+const BUILTIN_SYMBOLS: &[&str] = &[
+    "+", "-", "*", "/", "**", "!", "&&", "||", "==", "!=", "<", ">", "<=", ">=", ":",
+];
+
+/// Which side an operator groups to when chained at equal precedence
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Associativity {
+    /// `a op b op c` is `(a op b) op c`
+    Left,
+    /// `a op b op c` is `a op (b op c)`
+    Right,
+}
+
+/// A custom operator as it appears in the AST
+///
+/// Only the symbol and how it parses are recorded; the handler stays in
+/// the registry, which the evaluator looks the symbol up in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CustomOp {
+    pub symbol: &'static str,
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+}
+
+impl CustomOp {
+    /// Create an operator descriptor, interning `symbol`
+    pub fn new(symbol: &str, precedence: Precedence, associativity: Associativity) -> Self {
+        Self {
+            symbol: intern(symbol),
+            precedence,
+            associativity,
+        }
+    }
+}
+
+impl fmt::Display for CustomOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CustomOp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("CustomOp", 3)?;
+        state.serialize_field("symbol", self.symbol)?;
+        state.serialize_field("precedence", &self.precedence)?;
+        state.serialize_field("associativity", &self.associativity)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CustomOp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Owned {
+            symbol: String,
+            precedence: Precedence,
+            associativity: Associativity,
+        }
+
+        let op = Owned::deserialize(deserializer)?;
+        Ok(CustomOp::new(&op.symbol, op.precedence, op.associativity))
+    }
+}
+
+/// Get a `'static` copy of `symbol`, shared by every caller
+///
+/// Operator symbols live in `Copy` AST nodes, so they are leaked once and
+/// reused; the set of distinct symbols in a program is small. Without
+/// `std` there is no lock to guard the shared set, so every call leaks
+/// its own copy; only registering an operator or keyword calls this.
+#[cfg(feature = "std")]
+pub(crate) fn intern(symbol: &str) -> &'static str {
+    static SYMBOLS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    let mut symbols = SYMBOLS.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(existing) = symbols.iter().find(|s| **s == symbol) {
+        return existing;
+    }
+    let leaked: &'static str = Box::leak(symbol.to_string().into_boxed_str());
+    symbols.push(leaked);
+    leaked
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn intern(symbol: &str) -> &'static str {
+    Box::leak(symbol.to_string().into_boxed_str())
+}
+
+/// Errors from registering an operator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperatorError {
+    /// The symbol is empty or contains a character outside
+    /// [`OPERATOR_CHARS`], or would start a comment
+    InvalidSymbol(String),
+    /// The symbol is a built-in operator or a prefix of one
+    ReservedSymbol(String),
+    /// An operator of the same kind already uses the symbol, or the symbol
+    /// would be both an infix and a postfix operator
+    AlreadyDefined(String),
+    /// Infix operators must bind between `LogicalOr` and `Unary`
+    InvalidPrecedence(Precedence),
+}
+
+impl fmt::Display for OperatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperatorError::InvalidSymbol(symbol) => {
+                write!(f, "Invalid operator symbol '{symbol}'")
+            }
+            OperatorError::ReservedSymbol(symbol) => {
+                write!(
+                    f,
+                    "Operator symbol '{symbol}' clashes with a built-in operator"
+                )
+            }
+            OperatorError::AlreadyDefined(symbol) => {
+                write!(f, "Operator '{symbol}' is already defined")
+            }
+            OperatorError::InvalidPrecedence(precedence) => {
+                write!(
+                    f,
+                    "Invalid precedence for an infix operator: {precedence:?}"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for OperatorError {}
+
+/// A registry with no operators, for evaluation entry points that take none
+pub(crate) static NO_OPERATORS: OperatorRegistry = OperatorRegistry::new();
+
+type InfixHandler = Arc<dyn Fn(Value, Value) -> EvalResult<Value> + Send + Sync>;
+type UnaryHandler = Arc<dyn Fn(Value) -> EvalResult<Value> + Send + Sync>;
+
+/// A set of custom operators and their handlers
+#[derive(Clone, Default)]
+pub struct OperatorRegistry {
+    infix: Vec<(CustomOp, InfixHandler)>,
+    prefix: Vec<(CustomOp, UnaryHandler)>,
+    postfix: Vec<(CustomOp, UnaryHandler)>,
+}
+
+impl OperatorRegistry {
+    /// Create a registry with no operators
+    pub const fn new() -> Self {
+        Self {
+            infix: Vec::new(),
+            prefix: Vec::new(),
+            postfix: Vec::new(),
+        }
+    }
+
+    /// Register an infix operator
+    pub fn register_infix(
+        &mut self,
+        symbol: &str,
+        precedence: Precedence,
+        associativity: Associativity,
+        handler: impl Fn(Value, Value) -> EvalResult<Value> + Send + Sync + 'static,
+    ) -> Result<CustomOp, OperatorError> {
+        validate_symbol(symbol)?;
+        if matches!(precedence, Precedence::Lowest | Precedence::Group) {
+            return Err(OperatorError::InvalidPrecedence(precedence));
+        }
+        if self.infix(symbol).is_some() || self.postfix(symbol).is_some() {
+            return Err(OperatorError::AlreadyDefined(symbol.to_string()));
+        }
+
+        let op = CustomOp::new(symbol, precedence, associativity);
+        self.infix.push((op, Arc::new(handler)));
+        Ok(op)
+    }
+
+    /// Register a prefix operator, which binds like the built-in `-x`
+    pub fn register_prefix(
+        &mut self,
+        symbol: &str,
+        handler: impl Fn(Value) -> EvalResult<Value> + Send + Sync + 'static,
+    ) -> Result<CustomOp, OperatorError> {
+        validate_symbol(symbol)?;
+        if self.prefix(symbol).is_some() {
+            return Err(OperatorError::AlreadyDefined(symbol.to_string()));
+        }
+
+        let op = CustomOp::new(symbol, Precedence::Unary, Associativity::Right);
+        self.prefix.push((op, Arc::new(handler)));
+        Ok(op)
+    }
+
+    /// Register a postfix operator, which binds tighter than any prefix
+    /// operator: `-x op` is `-(x op)`
+    pub fn register_postfix(
+        &mut self,
+        symbol: &str,
+        handler: impl Fn(Value) -> EvalResult<Value> + Send + Sync + 'static,
+    ) -> Result<CustomOp, OperatorError> {
+        validate_symbol(symbol)?;
+        if self.postfix(symbol).is_some() || self.infix(symbol).is_some() {
+            return Err(OperatorError::AlreadyDefined(symbol.to_string()));
+        }
+
+        let op = CustomOp::new(symbol, Precedence::Group, Associativity::Left);
+        self.postfix.push((op, Arc::new(handler)));
+        Ok(op)
+    }
+
+    /// Look up an infix operator by symbol
+    pub fn infix(&self, symbol: &str) -> Option<CustomOp> {
+        self.infix
+            .iter()
+            .map(|(op, _)| *op)
+            .find(|op| op.symbol == symbol)
+    }
+
+    /// Look up a prefix operator by symbol
+    pub fn prefix(&self, symbol: &str) -> Option<CustomOp> {
+        self.prefix
+            .iter()
+            .map(|(op, _)| *op)
+            .find(|op| op.symbol == symbol)
+    }
+
+    /// Look up a postfix operator by symbol
+    pub fn postfix(&self, symbol: &str) -> Option<CustomOp> {
+        self.postfix
+            .iter()
+            .map(|(op, _)| *op)
+            .find(|op| op.symbol == symbol)
+    }
+
+    /// All registered infix operators
+    pub fn infix_operators(&self) -> impl Iterator<Item = CustomOp> + '_ {
+        self.infix.iter().map(|(op, _)| *op)
+    }
+
+    /// All registered prefix operators
+    pub fn prefix_operators(&self) -> impl Iterator<Item = CustomOp> + '_ {
+        self.prefix.iter().map(|(op, _)| *op)
+    }
+
+    /// All registered postfix operators
+    pub fn postfix_operators(&self) -> impl Iterator<Item = CustomOp> + '_ {
+        self.postfix.iter().map(|(op, _)| *op)
+    }
+
+    /// Every registered symbol, each listed once
+    pub fn symbols(&self) -> Vec<&'static str> {
+        let mut symbols: Vec<&'static str> = Vec::new();
+        let all = self
+            .infix_operators()
+            .chain(self.prefix_operators())
+            .chain(self.postfix_operators());
+        for op in all {
+            if !symbols.contains(&op.symbol) {
+                symbols.push(op.symbol);
+            }
+        }
+        symbols
+    }
+
+    /// Run the handler of an infix operator
+    pub fn apply_infix(&self, op: CustomOp, left: Value, right: Value) -> EvalResult<Value> {
+        match self
+            .infix
+            .iter()
+            .find(|(entry, _)| entry.symbol == op.symbol)
+        {
+            Some((_, handler)) => handler(left, right),
+            None => Err(unknown_operator(op)),
+        }
+    }
+
+    /// Run the handler of a prefix operator
+    pub fn apply_prefix(&self, op: CustomOp, operand: Value) -> EvalResult<Value> {
+        match self
+            .prefix
+            .iter()
+            .find(|(entry, _)| entry.symbol == op.symbol)
+        {
+            Some((_, handler)) => handler(operand),
+            None => Err(unknown_operator(op)),
+        }
+    }
+
+    /// Run the handler of a postfix operator
+    pub fn apply_postfix(&self, op: CustomOp, operand: Value) -> EvalResult<Value> {
+        match self
+            .postfix
+            .iter()
+            .find(|(entry, _)| entry.symbol == op.symbol)
+        {
+            Some((_, handler)) => handler(operand),
+            None => Err(unknown_operator(op)),
+        }
+    }
+}
+
+impl fmt::Debug for OperatorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperatorRegistry")
+            .field("infix", &self.infix_operators().collect::<Vec<_>>())
+            .field("prefix", &self.prefix_operators().collect::<Vec<_>>())
+            .field("postfix", &self.postfix_operators().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn unknown_operator(op: CustomOp) -> EvalError {
+    EvalError::UnknownOperator {
+        symbol: op.symbol.to_string(),
+        span: None,
+    }
+}
+
+fn validate_symbol(symbol: &str) -> Result<(), OperatorError> {
+    if symbol.is_empty()
+        || symbol.contains("//")
+        || !symbol.chars().all(|ch| OPERATOR_CHARS.contains(&ch))
+    {
+        return Err(OperatorError::InvalidSymbol(symbol.to_string()));
+    }
+    if BUILTIN_SYMBOLS
+        .iter()
+        .any(|builtin| builtin.starts_with(symbol))
+    {
+        return Err(OperatorError::ReservedSymbol(symbol.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spaceship(left: Value, right: Value) -> EvalResult<Value> {
+        Ok(Value::Int(left.as_f64().total_cmp(&right.as_f64()) as i32))
+    }
+
+    #[test]
+    fn test_register_and_apply_infix() {
+        let mut registry = OperatorRegistry::new();
+        let op = registry
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Left,
+                spaceship,
+            )
+            .unwrap();
+
+        assert_eq!(registry.infix("<=>"), Some(op));
+        assert_eq!(registry.prefix("<=>"), None);
+        assert_eq!(
+            registry.apply_infix(op, Value::Int(1), Value::Int(2)),
+            Ok(Value::Int(-1))
+        );
+    }
+
+    #[test]
+    fn test_register_prefix_alongside_infix() {
+        let mut registry = OperatorRegistry::new();
+        registry
+            .register_infix("%%", Precedence::Product, Associativity::Left, spaceship)
+            .unwrap();
+        let op = registry
+            .register_prefix("%%", |v| Ok(Value::Float(v.as_f64() / 100.0)))
+            .unwrap();
+
+        assert_eq!(op.precedence, Precedence::Unary);
+        assert_eq!(registry.symbols(), vec!["%%"]);
+        assert_eq!(
+            registry.apply_prefix(op, Value::Int(50)),
+            Ok(Value::Float(0.5))
+        );
+    }
+
+    #[test]
+    fn test_rejected_registrations() {
+        let mut registry = OperatorRegistry::new();
+        let mut infix = |symbol, precedence| {
+            registry
+                .register_infix(symbol, precedence, Associativity::Left, spaceship)
+                .map(|_| ())
+        };
+
+        assert_eq!(infix("^^", Precedence::Product), Ok(()));
+        assert_eq!(
+            infix("^^", Precedence::Sum),
+            Err(OperatorError::AlreadyDefined("^^".to_string()))
+        );
+        assert_eq!(
+            infix("**", Precedence::Unary),
+            Err(OperatorError::ReservedSymbol("**".to_string()))
+        );
+        assert_eq!(
+            infix("<=", Precedence::Sum),
+            Err(OperatorError::ReservedSymbol("<=".to_string()))
+        );
+        assert_eq!(
+            infix("&", Precedence::Sum),
+            Err(OperatorError::ReservedSymbol("&".to_string()))
+        );
+        assert_eq!(
+            infix(":", Precedence::Sum),
+            Err(OperatorError::ReservedSymbol(":".to_string()))
+        );
+        assert_eq!(infix("::", Precedence::Sum), Ok(()));
+        assert_eq!(
+            infix("km", Precedence::Sum),
+            Err(OperatorError::InvalidSymbol("km".to_string()))
+        );
+        assert_eq!(
+            infix("%//", Precedence::Sum),
+            Err(OperatorError::InvalidSymbol("%//".to_string()))
+        );
+        assert_eq!(
+            infix("@", Precedence::Lowest),
+            Err(OperatorError::InvalidPrecedence(Precedence::Lowest))
+        );
+    }
+
+    #[test]
+    fn test_register_postfix() {
+        let mut registry = OperatorRegistry::new();
+        let op = registry
+            .register_postfix("!!", |v| Ok(Value::Float(v.as_f64() * 2.0)))
+            .unwrap();
+
+        assert_eq!(op.precedence, Precedence::Group);
+        assert_eq!(registry.postfix("!!"), Some(op));
+        assert_eq!(
+            registry.apply_postfix(op, Value::Int(4)),
+            Ok(Value::Float(8.0))
+        );
+        // A symbol cannot be both infix and postfix
+        assert_eq!(
+            registry.register_infix("!!", Precedence::Sum, Associativity::Left, spaceship),
+            Err(OperatorError::AlreadyDefined("!!".to_string()))
+        );
+        // But a prefix operator may share it
+        assert!(registry.register_prefix("!!", Ok).is_ok());
+    }
+
+    #[test]
+    fn test_unregistered_operator_is_an_error() {
+        let op = CustomOp::new("<=>", Precedence::Comparison, Associativity::Left);
+        let registry = OperatorRegistry::new();
+
+        assert!(matches!(
+            registry.apply_infix(op, Value::Int(1), Value::Int(2)),
+            Err(EvalError::UnknownOperator { symbol, .. }) if symbol == "<=>"
+        ));
+    }
+
+    #[test]
+    fn test_host_objects_pass_through_operators() {
+        #[derive(Debug, PartialEq)]
+        struct Point {
+            x: f64,
+            y: f64,
+        }
+
+        let mut registry = OperatorRegistry::new();
+        registry
+            .register_infix("@", Precedence::Product, Associativity::Left, |x, y| {
+                Ok(Value::host(Point {
+                    x: x.as_f64(),
+                    y: y.as_f64(),
+                }))
+            })
+            .unwrap();
+        registry
+            .register_postfix("#", |point| match point.downcast_ref::<Point>() {
+                Some(point) => Ok(Value::Float(point.x + point.y)),
+                None => Err(EvalError::TypeError {
+                    message: "expected a point".to_string(),
+                    span: None,
+                }),
+            })
+            .unwrap();
+
+        let point = crate::eval_program_string_with_operators("1 @ 2.5", &registry).unwrap();
+        assert_eq!(point.downcast_ref(), Some(&Point { x: 1.0, y: 2.5 }));
+        assert_eq!(point.type_name(), "host");
+        assert!(point.to_string().ends_with("Point>"));
+        assert_eq!(
+            crate::eval_program_string_with_operators("(1 @ 2.5)#", &registry),
+            Ok(Value::Float(3.5))
+        );
+        assert!(crate::eval_program_string_with_operators("(1 @ 2) + 1", &registry).is_err());
+    }
+
+    // Without `std`, `intern` leaks a separate copy per call
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_symbols_are_interned() {
+        let a = CustomOp::new(&String::from("^^"), Precedence::Sum, Associativity::Left);
+        let b = CustomOp::new("^^", Precedence::Sum, Associativity::Left);
+        assert!(core::ptr::eq(a.symbol, b.symbol));
+    }
+}
@@ -0,0 +1,206 @@
+//! Incremental reparsing
+//!
+//! After a small edit, only the statements around the edited text need to
+//! be parsed again. Statements before the edit are kept as they are, and
+//! statements after it are reused with their spans shifted.
+//!
+//! Reuse after the edit relies on two properties of the lexer: no token
+//! or comment spans a newline, and a statement boundary in the old text is
+//! still a boundary once the parser reaches it again. So once parsing has
+//! passed the first newline after the edit and lands on the start of an
+//! old statement, the rest of the old program is still valid.
+
+use super::pratt::Parser;
+use crate::ast::{Expr, Program, Statement};
+use crate::error::ParseResult;
+use crate::lexer::SobaLexer;
+use crate::prelude::*;
+use crate::span::Span;
+use core::ops::Range;
+
+/// A replacement of a byte range of the source text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextEdit {
+    /// Byte range of the old text to replace
+    pub range: Range<usize>,
+    /// Text to insert in its place
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: impl Into<String>) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Apply the edit to `source`
+    ///
+    /// Panics if the range is out of bounds or not on character boundaries.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_string();
+        edited.replace_range(self.range.clone(), &self.replacement);
+        edited
+    }
+}
+
+impl Parser<SobaLexer> {
+    /// Parse the result of applying `edit` to `old_source`, reusing the
+    /// statements of `old_program` that the edit cannot affect
+    ///
+    /// `old_program` must be the result of parsing `old_source`. The result
+    /// is the same as parsing the edited text from scratch.
+    pub fn reparse(
+        old_program: &Program,
+        old_source: &str,
+        edit: &TextEdit,
+    ) -> ParseResult<Program> {
+        let new_source = edit.apply(old_source);
+        let old = &old_program.statements;
+
+        // A statement is untouched if the next one starts before the edit,
+        // since its terminating `;` then lies before the edit too
+        let reused_prefix = old
+            .windows(2)
+            .take_while(|pair| pair[1].span().start <= edit.range.start)
+            .count();
+        let start = old.get(reused_prefix).map_or(0, |stmt| stmt.span().start);
+
+        let inserted_end = edit.range.start + edit.replacement.len();
+        let resync = new_source[inserted_end..]
+            .find('\n')
+            .map(|index| inserted_end + index);
+        let shift = Shift(edit.replacement.len() as isize - edit.range.len() as isize);
+
+        let tail = new_source[start..].chars().collect();
+        let mut lexer = SobaLexer::starting_at(tail, start);
+        lexer.set_source(old_program.span.source);
+        let mut parser = Parser::new(lexer)?;
+        let mut statements = old[..reused_prefix].to_vec();
+
+        while let Some(offset) = parser.current_offset() {
+            if resync.is_some_and(|resync| offset > resync) {
+                let old_offset = (offset as isize - shift.0) as usize;
+                if let Some(index) = old.iter().position(|stmt| stmt.span().start == old_offset) {
+                    statements.extend(old[index..].iter().map(|stmt| shift.statement(stmt)));
+                    return Ok(Program::new(statements));
+                }
+            }
+
+            let (stmt, terminated) = parser.parse_statement()?;
+            statements.push(stmt);
+            if !terminated {
+                break;
+            }
+        }
+
+        Ok(Program::new(statements))
+    }
+}
+
+/// How many bytes offsets after an edit move by
+#[derive(Clone, Copy)]
+struct Shift(isize);
+
+impl Shift {
+    fn offset(self, offset: usize) -> usize {
+        (offset as isize + self.0) as usize
+    }
+
+    fn span(self, span: Span) -> Span {
+        Span::new(self.offset(span.start), self.offset(span.end)).in_source(span.source)
+    }
+
+    fn statement(self, stmt: &Statement) -> Statement {
+        match stmt {
+            Statement::ExprStatement { expr, span } => {
+                let mut expr = expr.clone();
+                self.expr(&mut expr);
+                Statement::ExprStatement {
+                    expr,
+                    span: self.span(*span),
+                }
+            }
+        }
+    }
+
+    fn expr(self, expr: &mut Expr) {
+        match expr {
+            Expr::Int { span, .. } | Expr::Float { span, .. } | Expr::Bool { span, .. } => {
+                *span = self.span(*span);
+            }
+            Expr::InfixExpr {
+                left, right, span, ..
+            } => {
+                self.expr(left);
+                self.expr(right);
+                *span = self.span(*span);
+            }
+            Expr::Grouped { inner, span } => {
+                self.expr(inner);
+                *span = self.span(*span);
+            }
+            Expr::UnaryExpr { operand, span, .. } | Expr::PostfixExpr { operand, span, .. } => {
+                self.expr(operand);
+                *span = self.span(*span);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_util::parse;
+
+    fn assert_reparse_matches(source: &str, edit: TextEdit) {
+        let old_program = parse(source);
+        let reparsed = Parser::reparse(&old_program, source, &edit).unwrap();
+        let expected = parse(&edit.apply(source));
+        assert_eq!(reparsed, expected, "source: {source:?}, edit: {edit:?}");
+    }
+
+    const SOURCE: &str = "1 + 2;\n(3 * 4) - 5;\n-6 < 7; true\n|| false;\n8";
+
+    #[test]
+    fn test_edit_inside_a_statement() {
+        // "3" -> "30"
+        assert_reparse_matches(SOURCE, TextEdit::new(8..9, "30"));
+        // "-6" -> "6"
+        assert_reparse_matches(SOURCE, TextEdit::new(20..21, ""));
+    }
+
+    #[test]
+    fn test_edits_that_add_or_remove_lines() {
+        assert_reparse_matches(SOURCE, TextEdit::new(6..6, "\n\n100;"));
+        assert_reparse_matches(SOURCE, TextEdit::new(20..43, "0;\n"));
+        assert_reparse_matches(SOURCE, TextEdit::new(0..0, "// hello\n"));
+    }
+
+    #[test]
+    fn test_edits_that_change_statement_boundaries() {
+        // Removing a `;` joins two statements
+        assert_reparse_matches("1 +\n2;\n3;\n4", TextEdit::new(5..6, " *"));
+        // A comment hides the rest of its line
+        assert_reparse_matches("1; 2; 3;\n4", TextEdit::new(3..3, "// "));
+        // Removing a `;` before the last statement is an error in both
+        let source = "1;\n2;\n3";
+        let edit = TextEdit::new(4..5, "");
+        assert!(Parser::reparse(&parse(source), source, &edit).is_err());
+    }
+
+    #[test]
+    fn test_appending_and_emptying() {
+        assert_reparse_matches(SOURCE, TextEdit::new(SOURCE.len()..SOURCE.len(), ";\n9"));
+        assert_reparse_matches(SOURCE, TextEdit::new(0..SOURCE.len(), ""));
+        assert_reparse_matches("", TextEdit::new(0..0, "1 + 1"));
+    }
+
+    #[test]
+    fn test_reparse_reports_errors() {
+        let old_program = parse(SOURCE);
+        let result = Parser::reparse(&old_program, SOURCE, &TextEdit::new(8..9, "@"));
+        assert!(result.is_err());
+    }
+}
@@ -1,9 +1,16 @@
 //! Parser module
 //!
-//! This module contains the parser implementation and precedence handling.
+//! This module contains the parser implementation, precedence handling and
+//! the operator table both are driven by.
 
+pub mod incremental;
 pub mod pratt;
 pub mod precedence;
+pub mod table;
+#[cfg(test)]
+pub mod test_util;
 
-pub use pratt::Parser;
+pub use incremental::TextEdit;
+pub use pratt::{Parser, DEFAULT_MAX_DEPTH};
 pub use precedence::Precedence;
+pub use table::{InfixOperator, OperatorTable, PostfixOperator};
@@ -1,15 +1,128 @@
 //! Parser implementation using Pratt parsing
 
 use super::precedence::Precedence;
+use super::table::OperatorTable;
 use crate::ast::{BinaryOp, Expr, Program, Statement, UnaryOp};
-use crate::error::{ParseError, ParseResult};
+use crate::error::{LexError, ParseError, ParseResult, ParseWarning};
 use crate::lexer::{Lexer, Token, TokenKind};
+use crate::operators::OperatorRegistry;
+use crate::prelude::*;
+use crate::span::Span;
+use alloc::collections::VecDeque;
+
+/// Tokens that can begin an expression
+///
+/// Literal payloads are placeholders; only the kind matters.
+pub const EXPRESSION_START_TOKENS: &[TokenKind] = &[
+    TokenKind::Int(0),
+    TokenKind::Float(0.0),
+    TokenKind::True,
+    TokenKind::False,
+    TokenKind::LeftParen,
+    TokenKind::Plus,
+    TokenKind::Minus,
+    TokenKind::Bang,
+];
+
+/// Tokens that can follow an expression as a binary operator
+pub const INFIX_OPERATOR_TOKENS: &[TokenKind] = &[
+    TokenKind::Plus,
+    TokenKind::Minus,
+    TokenKind::Asterisk,
+    TokenKind::Slash,
+    TokenKind::StarStar,
+    TokenKind::AndAnd,
+    TokenKind::OrOr,
+    TokenKind::Equal,
+    TokenKind::NotEqual,
+    TokenKind::Less,
+    TokenKind::Greater,
+    TokenKind::LessEqual,
+    TokenKind::GreaterEqual,
+];
+
+/// How deeply expressions may nest before parsing fails with
+/// `ParseError::TooDeep`
+///
+/// Parsing recurses once per level of parentheses, prefix operators and
+/// right-associative chains, so an unbounded depth lets a hostile input
+/// overflow the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Error for `token` appearing where one of `expected` was required
+///
+/// At the end of input this is `UnexpectedEof`, placed at the `Eof` token,
+/// and at an `Error` token from a recovering lexer it is the lexer's error.
+fn unexpected(token: &Token, expected: &[TokenKind]) -> ParseError {
+    match &token.kind {
+        TokenKind::Eof => ParseError::UnexpectedEof { span: token.span },
+        TokenKind::Error(err) => ParseError::LexError(err.clone()),
+        found => ParseError::UnexpectedToken {
+            found: found.clone(),
+            expected: expected.to_vec(),
+            span: token.span,
+        },
+    }
+}
+
+/// The value of an integer literal
+///
+/// The lexer accepts `2147483648` so that `-2147483648` can be written;
+/// anywhere the `-` is not folded into it, it is out of range.
+pub(crate) fn int_literal(value: u32, span: Span) -> ParseResult<i32> {
+    i32::try_from(value).map_err(|_| {
+        ParseError::LexError(LexError::InvalidNumber {
+            text: value.to_string(),
+            span,
+        })
+    })
+}
+
+/// Whether a `-` before `literal` folds into it to make `i32::MIN`
+///
+/// Only `2147483648` needs folding, and only when `next` does not bind the
+/// literal more tightly than the `-`: `-2147483648 ** 2` still negates
+/// `2147483648 ** 2`, which is out of range.
+pub(crate) fn folds_into_min_int(
+    operators: &OperatorTable,
+    literal: &TokenKind,
+    next: &TokenKind,
+) -> bool {
+    *literal == TokenKind::Int(i32::MIN.unsigned_abs())
+        && operators.infix_precedence(next) <= Precedence::Unary
+}
+
+/// Whether `left op right` puts an unparenthesized `&&` under `||` or
+/// the other way round
+fn mixes_logical_operators(op: BinaryOp, left: &Expr, right: &Expr) -> bool {
+    let other = match op {
+        BinaryOp::LogicalAnd => BinaryOp::LogicalOr,
+        BinaryOp::LogicalOr => BinaryOp::LogicalAnd,
+        _ => return false,
+    };
+    [left, right]
+        .into_iter()
+        .any(|operand| matches!(operand, Expr::InfixExpr { op, .. } if *op == other))
+}
 
 /// Soba language parser
 pub struct Parser<L: Lexer> {
     lexer: L,
-    current_token: Option<Token>,
-    peek_token: Option<Token>,
+    /// The token being parsed; `Eof` once the input is used up
+    current_token: Token,
+    peek_token: Token,
+    /// Tokens after `peek_token` that `peek_nth` has already read
+    lookahead: VecDeque<Token>,
+    /// Set once the lexer has reported an error; the token stream cannot
+    /// be trusted after that, so recovery stops
+    lexer_failed: bool,
+    /// The operators the parser accepts
+    operators: OperatorTable,
+    /// Current expression nesting depth
+    depth: usize,
+    max_depth: usize,
+    /// Warnings about the code parsed so far
+    warnings: Vec<ParseWarning>,
 }
 
 impl<L: Lexer> Parser<L> {
@@ -21,15 +134,94 @@ impl<L: Lexer> Parser<L> {
             lexer,
             current_token,
             peek_token,
+            lookahead: VecDeque::new(),
+            lexer_failed: false,
+            operators: OperatorTable::builtin(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            warnings: Vec::new(),
         })
     }
 
+    /// Create a parser that also accepts the custom operators in `operators`
+    ///
+    /// The lexer must recognize the same symbols, e.g. one made with
+    /// `SobaLexer::with_operators`.
+    pub fn with_operators(lexer: L, operators: &OperatorRegistry) -> ParseResult<Self> {
+        Self::with_operator_table(lexer, OperatorTable::with_custom(operators))
+    }
+
+    /// Create a parser that accepts exactly the operators in `operators`
+    pub fn with_operator_table(lexer: L, operators: OperatorTable) -> ParseResult<Self> {
+        let mut parser = Self::new(lexer)?;
+        parser.operators = operators;
+        Ok(parser)
+    }
+
+    /// Set how deeply expressions may nest, `DEFAULT_MAX_DEPTH` by default
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Warnings about the code parsed so far, in the order they were found
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Remove and return the warnings collected so far
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        core::mem::take(&mut self.warnings)
+    }
+
     fn next_token(&mut self) -> ParseResult<()> {
-        self.current_token = self.peek_token.take();
-        self.peek_token = self.lexer.next_token().map_err(ParseError::from)?;
+        let peek_token = match self.lookahead.pop_front() {
+            Some(token) => token,
+            None if self.peek_token.kind == TokenKind::Eof => self.peek_token.clone(),
+            None => self.read_token()?,
+        };
+        self.current_token = core::mem::replace(&mut self.peek_token, peek_token);
         Ok(())
     }
 
+    fn read_token(&mut self) -> ParseResult<Token> {
+        self.lexer.next_token().map_err(|err| {
+            self.lexer_failed = true;
+            ParseError::from(err)
+        })
+    }
+
+    /// Look `n` tokens past the current one without consuming anything
+    ///
+    /// `peek_nth(0)` is the token right after the current one. Once `n`
+    /// reaches past the end of input the result is the `Eof` token. Tokens
+    /// are read from the lexer as needed, so a lexer error in the looked-at
+    /// range is reported here rather than when the parser reaches it.
+    pub fn peek_nth(&mut self, n: usize) -> ParseResult<&Token> {
+        while self.lookahead.len() < n && self.last_read().kind != TokenKind::Eof {
+            let token = self.read_token()?;
+            self.lookahead.push_back(token);
+        }
+        Ok(match n {
+            0 => &self.peek_token,
+            _ => self.lookahead.get(n - 1).unwrap_or(self.last_read()),
+        })
+    }
+
+    /// The last token read from the lexer
+    fn last_read(&self) -> &Token {
+        self.lookahead.back().unwrap_or(&self.peek_token)
+    }
+
+    /// Whether the current token is the end of input
+    fn at_eof(&self) -> bool {
+        self.current_token.kind == TokenKind::Eof
+    }
+
+    /// Start offset of the current token, or `None` at end of input
+    pub(super) fn current_offset(&self) -> Option<usize> {
+        (!self.at_eof()).then_some(self.current_token.span.start)
+    }
+
     /// Parse a single expression (test-only method)
     /// This method is only available in test builds and is used for testing
     /// individual expression parsing without requiring a full program structure.
@@ -38,23 +230,29 @@ impl<L: Lexer> Parser<L> {
         self.parse_expression_with_precedence(Precedence::Lowest)
     }
 
+    /// Parse an input that consists of exactly one expression
+    /// Unlike `parse_program`, a trailing `;` or any other leftover token is
+    /// an error.
+    pub fn parse_standalone_expression(&mut self) -> ParseResult<Expr> {
+        let expr = self.parse_expression_with_precedence(Precedence::Lowest)?;
+        match self.peek_token.kind {
+            TokenKind::Eof => Ok(expr),
+            TokenKind::RightParen => Err(ParseError::MismatchedParentheses {
+                open: None,
+                span: self.peek_token.span,
+            }),
+            _ => Err(unexpected(&self.peek_token, INFIX_OPERATOR_TOKENS)),
+        }
+    }
+
     pub fn parse_program(&mut self) -> ParseResult<Program> {
         let mut statements = Vec::new();
 
-        while self.current_token.is_some() {
-            let expr = self.parse_expression_with_precedence(Precedence::Lowest)?;
-            let span = expr.span();
-            let stmt = Statement::ExprStatement { expr, span };
+        while !self.at_eof() {
+            let (stmt, terminated) = self.parse_statement()?;
             statements.push(stmt);
 
-            // Check if there's a semicolon
-            if matches!(
-                self.peek_token.as_ref().map(|t| &t.kind),
-                Some(TokenKind::Semicolon)
-            ) {
-                self.next_token()?; // move to semicolon
-                self.next_token()?; // consume semicolon and move to next token
-            } else {
+            if !terminated {
                 // No semicolon - this should be the last statement
                 break;
             }
@@ -63,105 +261,202 @@ impl<L: Lexer> Parser<L> {
         Ok(Program::new(statements))
     }
 
+    /// Parse a program, recovering from errors at statement boundaries
+    ///
+    /// When a statement fails to parse, its error is recorded and parsing
+    /// resumes after the next semicolon. The returned program contains every
+    /// statement that parsed successfully. Lexer errors end parsing, since
+    /// the remaining input cannot be tokenized reliably.
+    pub fn parse_program_with_recovery(&mut self) -> (Program, Vec<ParseError>) {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+
+        while !self.at_eof() {
+            match self.parse_statement() {
+                Ok((stmt, terminated)) => {
+                    statements.push(stmt);
+                    if !terminated {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    errors.push(err);
+                    if self.lexer_failed {
+                        break;
+                    }
+                    if let Err(err) = self.synchronize() {
+                        errors.push(err);
+                        break;
+                    }
+                }
+            }
+        }
+
+        (Program::new(statements), errors)
+    }
+
+    /// Parse one expression statement
+    /// Returns the statement and whether it was terminated by a semicolon,
+    /// leaving the parser on the first token of the next statement if so
+    pub(super) fn parse_statement(&mut self) -> ParseResult<(Statement, bool)> {
+        let expr = self.parse_expression_with_precedence(Precedence::Lowest)?;
+        let span = expr.span();
+        let stmt = Statement::ExprStatement { expr, span };
+
+        // Only the last statement may omit its semicolon
+        match self.peek_token.kind {
+            TokenKind::Semicolon => {
+                self.next_token()?; // move to semicolon
+                self.next_token()?; // consume semicolon and move to next token
+                Ok((stmt, true))
+            }
+            TokenKind::Eof => Ok((stmt, false)),
+            // Every '(' in the statement has been closed by now
+            TokenKind::RightParen => Err(ParseError::MismatchedParentheses {
+                open: None,
+                span: self.peek_token.span,
+            }),
+            _ => {
+                let mut expected = vec![TokenKind::Semicolon];
+                expected.extend_from_slice(INFIX_OPERATOR_TOKENS);
+                Err(unexpected(&self.peek_token, &expected))
+            }
+        }
+    }
+
+    /// Skip tokens up to and including the next semicolon
+    fn synchronize(&mut self) -> ParseResult<()> {
+        while !self.at_eof() {
+            let at_semicolon = self.current_token.kind == TokenKind::Semicolon;
+            self.next_token()?;
+            if at_semicolon {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     fn parse_expression_with_precedence(&mut self, precedence: Precedence) -> ParseResult<Expr> {
+        if self.depth == self.max_depth {
+            return Err(match self.current_token.kind {
+                TokenKind::Eof => unexpected(&self.current_token, EXPRESSION_START_TOKENS),
+                _ => ParseError::TooDeep {
+                    limit: self.max_depth,
+                    span: self.current_token.span,
+                },
+            });
+        }
+
+        self.depth += 1;
+        let result = self.parse_expression_within_depth(precedence);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_expression_within_depth(&mut self, precedence: Precedence) -> ParseResult<Expr> {
         let mut left = self.parse_prefix()?;
 
-        while let Some(ref peek) = self.peek_token {
-            let peek_precedence = Precedence::from_token(&peek.kind);
+        loop {
+            let peek_precedence = self.operators.infix_precedence(&self.peek_token.kind);
             if precedence >= peek_precedence {
                 break;
             }
 
             self.next_token()?;
-            left = self.parse_infix(left)?;
+            left = self.parse_operator_after(left)?;
         }
 
         Ok(left)
     }
 
     fn parse_prefix(&mut self) -> ParseResult<Expr> {
-        match &self.current_token {
-            Some(token) => match &token.kind {
-                TokenKind::Int(value) => Ok(Expr::Int {
-                    value: *value,
-                    span: token.span,
-                }),
-                TokenKind::Float(value) => Ok(Expr::Float {
-                    value: *value,
-                    span: token.span,
-                }),
-                TokenKind::True => Ok(Expr::Bool {
-                    value: true,
-                    span: token.span,
-                }),
-                TokenKind::False => Ok(Expr::Bool {
-                    value: false,
-                    span: token.span,
-                }),
-                TokenKind::LeftParen => self.parse_grouped_expression(),
-                TokenKind::Plus | TokenKind::Minus | TokenKind::Bang => {
-                    self.parse_unary_expression()
-                }
-                _ => Err(ParseError::UnexpectedToken(token.to_string())),
-            },
-            None => Err(ParseError::UnexpectedEof),
+        let token = &self.current_token;
+        match &token.kind {
+            TokenKind::Int(value) => Ok(Expr::Int {
+                value: int_literal(*value, token.span)?,
+                span: token.span,
+            }),
+            TokenKind::Float(value) => Ok(Expr::Float {
+                value: *value,
+                span: token.span,
+            }),
+            TokenKind::True => Ok(Expr::Bool {
+                value: true,
+                span: token.span,
+            }),
+            TokenKind::False => Ok(Expr::Bool {
+                value: false,
+                span: token.span,
+            }),
+            TokenKind::LeftParen => self.parse_grouped_expression(),
+            kind if self.operators.prefix(kind).is_some() => self.parse_unary_expression(),
+            _ => Err(unexpected(token, EXPRESSION_START_TOKENS)),
+        }
+    }
+
+    /// Parse the infix or postfix operator at the current token, applied
+    /// to `left`
+    fn parse_operator_after(&mut self, left: Expr) -> ParseResult<Expr> {
+        match self.operators.postfix(&self.current_token.kind) {
+            Some(operator) => Ok(Expr::PostfixExpr {
+                op: operator.op,
+                span: left.span().merge(self.current_token.span),
+                operand: Box::new(left),
+            }),
+            None => self.parse_infix(left),
         }
     }
 
     fn parse_infix(&mut self, left: Expr) -> ParseResult<Expr> {
-        match &self.current_token {
-            Some(token) => {
-                let op = match token.kind {
-                    TokenKind::Plus => BinaryOp::Plus,
-                    TokenKind::Minus => BinaryOp::Minus,
-                    TokenKind::Asterisk => BinaryOp::Multiply,
-                    TokenKind::Slash => BinaryOp::Divide,
-                    TokenKind::AndAnd => BinaryOp::LogicalAnd,
-                    TokenKind::OrOr => BinaryOp::LogicalOr,
-                    TokenKind::Equal => BinaryOp::Equal,
-                    TokenKind::NotEqual => BinaryOp::NotEqual,
-                    TokenKind::Less => BinaryOp::Less,
-                    TokenKind::Greater => BinaryOp::Greater,
-                    TokenKind::LessEqual => BinaryOp::LessEqual,
-                    TokenKind::GreaterEqual => BinaryOp::GreaterEqual,
-                    _ => return Err(ParseError::UnexpectedToken(token.to_string())),
-                };
-
-                let _op_span = token.span;
-                let precedence = Precedence::from_token(&token.kind);
-
-                self.next_token()?;
-                let right = self.parse_expression_with_precedence(precedence)?;
-
-                let span = left.span().merge(right.span());
-
-                Ok(Expr::InfixExpr {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span,
-                })
-            }
-            None => Err(ParseError::UnexpectedEof),
+        let Some(operator) = self.operators.infix(&self.current_token.kind) else {
+            return Err(unexpected(&self.current_token, INFIX_OPERATOR_TOKENS));
+        };
+
+        self.next_token()?;
+        let right = self.parse_expression_with_precedence(operator.right_operand_precedence())?;
+
+        let span = left.span().merge(right.span());
+        if mixes_logical_operators(operator.op, &left, &right) {
+            self.warnings
+                .push(ParseWarning::MixedLogicalOperators { span });
         }
+
+        Ok(Expr::InfixExpr {
+            left: Box::new(left),
+            op: operator.op,
+            right: Box::new(right),
+            span,
+        })
     }
 
     fn parse_grouped_expression(&mut self) -> ParseResult<Expr> {
-        let start_span = self.current_token.as_ref().unwrap().span;
+        let start_span = self.current_token.span;
 
         self.next_token()?; // consume '('
         let expr = self.parse_expression_with_precedence(Precedence::Lowest)?;
 
-        if !matches!(
-            self.peek_token.as_ref().map(|t| &t.kind),
-            Some(TokenKind::RightParen)
-        ) {
-            return Err(ParseError::MismatchedParentheses);
+        match self.peek_token.kind {
+            TokenKind::RightParen => {}
+            TokenKind::Eof => {
+                return Err(ParseError::MismatchedParentheses {
+                    open: Some(start_span),
+                    span: self.peek_token.span,
+                })
+            }
+            _ => {
+                let mut expected = vec![TokenKind::RightParen];
+                expected.extend_from_slice(INFIX_OPERATOR_TOKENS);
+                return Err(unexpected(&self.peek_token, &expected));
+            }
         }
 
         self.next_token()?; // move to ')'
-        let end_span = self.current_token.as_ref().unwrap().span;
+        let end_span = self.current_token.span;
         let span = start_span.merge(end_span);
+        if matches!(expr, Expr::Grouped { .. }) {
+            self.warnings
+                .push(ParseWarning::RedundantParentheses { span });
+        }
 
         Ok(Expr::Grouped {
             inner: Box::new(expr),
@@ -170,17 +465,26 @@ impl<L: Lexer> Parser<L> {
     }
 
     fn parse_unary_expression(&mut self) -> ParseResult<Expr> {
-        let token = self.current_token.as_ref().unwrap();
-        let op = match token.kind {
-            TokenKind::Plus => UnaryOp::Plus,
-            TokenKind::Minus => UnaryOp::Minus,
-            TokenKind::Bang => UnaryOp::LogicalNot,
-            _ => return Err(ParseError::UnexpectedToken(token.to_string())),
+        let token = &self.current_token;
+        let Some(op) = self.operators.prefix(&token.kind) else {
+            return Err(unexpected(token, EXPRESSION_START_TOKENS));
         };
 
         let op_span = token.span;
 
         self.next_token()?;
+        if op == UnaryOp::Minus
+            && folds_into_min_int(
+                &self.operators,
+                &self.current_token.kind,
+                &self.peek_token.kind,
+            )
+        {
+            return Ok(Expr::Int {
+                value: i32::MIN,
+                span: op_span.merge(self.current_token.span),
+            });
+        }
         let operand = self.parse_expression_with_precedence(Precedence::Unary)?;
 
         let span = op_span.merge(operand.span());
@@ -196,7 +500,11 @@ impl<L: Lexer> Parser<L> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::{BinaryOp, PostfixOp, UnaryOp};
+    use crate::error::LexError;
     use crate::lexer::SobaLexer;
+    use crate::operators::Associativity;
+    use crate::span::Span;
 
     fn parse_expression_string(input: &str) -> ParseResult<Expr> {
         let lexer = SobaLexer::new(input.chars().collect());
@@ -217,6 +525,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_float() {
         let expr = parse_expression_string("3.14").unwrap();
         assert!(matches!(expr, Expr::Float { value, .. } if (value - 3.14).abs() < 1e-10));
@@ -261,6 +570,38 @@ mod tests {
         assert!(matches!(expr, Expr::Grouped { .. }));
     }
 
+    #[test]
+    fn test_warnings() {
+        let warnings = |input: &str| {
+            let mut parser = Parser::new(SobaLexer::new(input.chars().collect())).unwrap();
+            parser.parse_program().unwrap();
+            parser.take_warnings()
+        };
+
+        assert_eq!(
+            warnings("((1 + 2)) * 3;\ntrue || false && true"),
+            vec![
+                ParseWarning::RedundantParentheses {
+                    span: Span::new(0, 9)
+                },
+                ParseWarning::MixedLogicalOperators {
+                    span: Span::new(15, 36)
+                },
+            ]
+        );
+        assert_eq!(
+            warnings("true && false || true"),
+            vec![ParseWarning::MixedLogicalOperators {
+                span: Span::new(0, 21)
+            }]
+        );
+        // Parentheses that make the grouping explicit are fine
+        assert!(
+            warnings("(1 + 2) * (3); true || (false && true); (true || false) && true").is_empty()
+        );
+        assert_eq!(warnings("true || false || true && (true)").len(), 1);
+    }
+
     #[test]
     fn test_parse_unary() {
         let expr = parse_expression_string("-5").unwrap();
@@ -306,6 +647,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_power_associativity() {
+        // 2 ** 3 ** 2 should parse as 2 ** (3 ** 2)
+        let expr = parse_expression_string("2 ** 3 ** 2").unwrap();
+        if let Expr::InfixExpr {
+            left, op, right, ..
+        } = expr
+        {
+            assert_eq!(op, BinaryOp::Power);
+            assert!(matches!(left.as_ref(), Expr::Int { value: 2, .. }));
+            assert!(matches!(
+                right.as_ref(),
+                Expr::InfixExpr {
+                    op: BinaryOp::Power,
+                    ..
+                }
+            ));
+        } else {
+            panic!("Expected infix expression");
+        }
+
+        // -2 ** 2 should parse as -(2 ** 2), and 2 ** -1 still parses
+        let expr = parse_expression_string("-2 ** 2").unwrap();
+        assert!(matches!(
+            expr,
+            Expr::UnaryExpr { op: UnaryOp::Minus, operand, .. }
+                if matches!(*operand, Expr::InfixExpr { op: BinaryOp::Power, .. })
+        ));
+        assert!(parse_expression_string("2 ** -1 * 3").is_ok());
+    }
+
     #[test]
     fn test_parse_boolean_true() {
         let expr = parse_expression_string("true").unwrap();
@@ -502,7 +874,7 @@ mod tests {
     fn test_parse_single_statement() {
         let program = parse_program_string("2 + 3;").unwrap();
         assert_eq!(program.statements.len(), 1);
-        
+
         match &program.statements[0] {
             Statement::ExprStatement { expr, .. } => {
                 assert!(matches!(
@@ -520,7 +892,7 @@ mod tests {
     fn test_parse_multiple_statements() {
         let program = parse_program_string("1 + 2; 3 * 4; 5;").unwrap();
         assert_eq!(program.statements.len(), 3);
-        
+
         // First statement: 1 + 2
         match &program.statements[0] {
             Statement::ExprStatement { expr, .. } => {
@@ -533,7 +905,7 @@ mod tests {
                 ));
             }
         }
-        
+
         // Second statement: 3 * 4
         match &program.statements[1] {
             Statement::ExprStatement { expr, .. } => {
@@ -546,7 +918,7 @@ mod tests {
                 ));
             }
         }
-        
+
         // Third statement: 5
         match &program.statements[2] {
             Statement::ExprStatement { expr, .. } => {
@@ -565,7 +937,7 @@ mod tests {
     fn test_parse_statement_without_semicolon_as_last() {
         let program = parse_program_string("2 + 3").unwrap();
         assert_eq!(program.statements.len(), 1);
-        
+
         match &program.statements[0] {
             Statement::ExprStatement { expr, .. } => {
                 assert!(matches!(
@@ -583,7 +955,7 @@ mod tests {
     fn test_parse_mixed_semicolons() {
         let program = parse_program_string("1 + 2; 3 * 4").unwrap();
         assert_eq!(program.statements.len(), 2);
-        
+
         // First statement: 1 + 2 (with semicolon)
         match &program.statements[0] {
             Statement::ExprStatement { expr, .. } => {
@@ -596,7 +968,7 @@ mod tests {
                 ));
             }
         }
-        
+
         // Second statement: 3 * 4 (without semicolon, last statement)
         match &program.statements[1] {
             Statement::ExprStatement { expr, .. } => {
@@ -610,4 +982,451 @@ mod tests {
             }
         }
     }
+
+    fn parse_error(input: &str) -> ParseError {
+        let lexer = SobaLexer::new(input.chars().collect());
+        Parser::new(lexer).unwrap().parse_program().unwrap_err()
+    }
+
+    #[test]
+    fn test_unexpected_token_lists_expected_tokens() {
+        let err = parse_error("(1 2)");
+        match &err {
+            ParseError::UnexpectedToken {
+                found,
+                expected,
+                span,
+            } => {
+                assert_eq!(*found, TokenKind::Int(2));
+                assert_eq!(expected[0], TokenKind::RightParen);
+                assert_eq!(span.start, 3);
+            }
+            other => panic!("Expected UnexpectedToken, got {other:?}"),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Unexpected token: expected ')' or an operator, found '2'"
+        );
+    }
+
+    #[test]
+    fn test_unexpected_token_expecting_expression() {
+        assert_eq!(
+            parse_error("1 + ;").to_string(),
+            "Unexpected token: expected an expression, found ';'"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_paren_at_end_of_input() {
+        let err = parse_error("(1 + 2");
+        // Points at the end of input and remembers the unclosed '('
+        assert_eq!(
+            err,
+            ParseError::MismatchedParentheses {
+                open: Some(Span::new(0, 1)),
+                span: Span::single(6),
+            }
+        );
+        // The innermost '(' that is still open is the one reported
+        assert!(matches!(
+            parse_error("(1 + (2) * (3"),
+            ParseError::MismatchedParentheses { open, .. } if open == Some(Span::new(11, 12))
+        ));
+    }
+
+    #[test]
+    fn test_unmatched_closing_paren() {
+        for (input, offset) in [("1 + 2)", 5), ("(1))", 3), ("1; (2) * 3)", 10)] {
+            assert_eq!(
+                parse_error(input),
+                ParseError::MismatchedParentheses {
+                    open: None,
+                    span: Span::new(offset, offset + 1)
+                },
+                "{input}"
+            );
+        }
+        // A ')' where an operand belongs is a missing operand instead
+        assert!(matches!(
+            parse_error("1 + )"),
+            ParseError::UnexpectedToken { .. }
+        ));
+    }
+
+    #[test]
+    fn test_min_int_literal() {
+        assert_eq!(
+            parse_expression_string("-2147483648").unwrap(),
+            Expr::Int {
+                value: i32::MIN,
+                span: Span::new(0, 11)
+            }
+        );
+        assert!(matches!(
+            parse_expression_string("1 - - 2147483648").unwrap(),
+            Expr::InfixExpr { right, .. } if matches!(*right, Expr::Int { value: i32::MIN, .. })
+        ));
+        // Smaller literals keep their prefix minus
+        assert!(matches!(
+            parse_expression_string("-5").unwrap(),
+            Expr::UnaryExpr { .. }
+        ));
+
+        // Without a `-` directly in front, the literal is out of range
+        for (input, offset) in [
+            ("2147483648", 0),
+            ("1 - 2147483648", 4),
+            ("-(2147483648)", 2),
+            ("-2147483648 ** 2", 1),
+        ] {
+            assert_eq!(
+                parse_error(input),
+                ParseError::LexError(LexError::InvalidNumber {
+                    text: "2147483648".to_string(),
+                    span: Span::new(offset, offset + 10)
+                }),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_peek_nth() {
+        let lexer = SobaLexer::new("1 + 2 * 3".chars().collect());
+        let mut parser = Parser::new(lexer).unwrap();
+        let kind = |token: &Token| token.kind.clone();
+
+        assert_eq!(kind(parser.peek_nth(2).unwrap()), TokenKind::Asterisk);
+        assert_eq!(kind(parser.peek_nth(0).unwrap()), TokenKind::Plus);
+        assert_eq!(kind(parser.peek_nth(3).unwrap()), TokenKind::Int(3));
+        assert_eq!(kind(parser.peek_nth(4).unwrap()), TokenKind::Eof);
+        assert_eq!(kind(parser.peek_nth(9).unwrap()), TokenKind::Eof);
+
+        // Buffered tokens are still parsed in order
+        let expr = parser.parse_expression().unwrap();
+        assert_eq!(expr, parse_expression_string("1 + 2 * 3").unwrap());
+        assert_eq!(kind(parser.peek_nth(0).unwrap()), TokenKind::Eof);
+
+        let lexer = SobaLexer::new("1 + 2 @".chars().collect());
+        let mut parser = Parser::new(lexer).unwrap();
+        assert_eq!(kind(parser.peek_nth(1).unwrap()), TokenKind::Int(2));
+        assert!(matches!(parser.peek_nth(2), Err(ParseError::LexError(_))));
+    }
+
+    #[test]
+    fn test_error_tokens_from_a_recovering_lexer() {
+        let mut lexer = SobaLexer::new("1 + @; 2 * #; 3".chars().collect());
+        lexer.set_error_recovery(true);
+        let (program, errors) = Parser::new(lexer).unwrap().parse_program_with_recovery();
+
+        // Both bad characters are reported, and parsing goes on past them
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::LexError(LexError::UnexpectedCharacter {
+                    ch: '@',
+                    span: Span::new(4, 5),
+                }),
+                ParseError::LexError(LexError::UnexpectedCharacter {
+                    ch: '#',
+                    span: Span::new(11, 12),
+                }),
+            ]
+        );
+        assert_eq!(program.statements.len(), 1);
+    }
+
+    #[test]
+    fn test_identifiers_are_not_expressions_yet() {
+        assert_eq!(
+            parse_expression_string("1 + x"),
+            Err(ParseError::UnexpectedToken {
+                found: TokenKind::Identifier("x".to_string()),
+                expected: EXPRESSION_START_TOKENS.to_vec(),
+                span: Span::new(4, 5),
+            })
+        );
+    }
+
+    fn parse_with_recovery(input: &str) -> (Program, Vec<ParseError>) {
+        let lexer = SobaLexer::new(input.chars().collect());
+        let mut parser = Parser::new(lexer).unwrap();
+        parser.parse_program_with_recovery()
+    }
+
+    #[test]
+    fn test_recovery_without_errors_matches_parse_program() {
+        let input = "1 + 2; 3 * 4; 5";
+        let (program, errors) = parse_with_recovery(input);
+        assert!(errors.is_empty());
+        assert_eq!(program, parse_program_string(input).unwrap());
+    }
+
+    #[test]
+    fn test_recovery_collects_multiple_errors() {
+        let (program, errors) = parse_with_recovery("1 + ; 2 * 3; (4 5; 6");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            ParseError::UnexpectedToken {
+                found: TokenKind::Semicolon,
+                ..
+            }
+        ));
+        assert!(matches!(
+            errors[1],
+            ParseError::UnexpectedToken {
+                found: TokenKind::Int(5),
+                ..
+            }
+        ));
+
+        // The statements around the errors are kept
+        assert_eq!(program.statements.len(), 2);
+        match &program.statements[1] {
+            Statement::ExprStatement { expr, .. } => {
+                assert!(matches!(expr, Expr::Int { value: 6, .. }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        // Far past the limit, but an error rather than a stack overflow
+        let deep = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(matches!(
+            parse_program_string(&deep),
+            Err(ParseError::TooDeep {
+                limit: DEFAULT_MAX_DEPTH,
+                ..
+            })
+        ));
+        let negations = format!("{}1", "-".repeat(100_000));
+        assert!(matches!(
+            parse_program_string(&negations),
+            Err(ParseError::TooDeep { .. })
+        ));
+
+        // The statement itself is one level, each `(` another
+        let mut parser = Parser::new(SobaLexer::new("((1)); (((2)))".chars().collect())).unwrap();
+        parser.set_max_depth(3);
+        let (program, errors) = parser.parse_program_with_recovery();
+        assert_eq!(program.statements.len(), 1);
+        assert_eq!(
+            errors,
+            vec![ParseError::TooDeep {
+                limit: 3,
+                span: Span::new(10, 11),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_recovery_error_in_last_statement() {
+        let (program, errors) = parse_with_recovery("1; 2 +");
+        // The error sits just past the dangling '+'
+        assert_eq!(
+            errors,
+            vec![ParseError::UnexpectedEof {
+                span: Span::single(6),
+            }]
+        );
+        assert_eq!(program.statements.len(), 1);
+    }
+
+    #[test]
+    fn test_missing_semicolon_between_statements() {
+        let mut expected = vec![TokenKind::Semicolon];
+        expected.extend_from_slice(INFIX_OPERATOR_TOKENS);
+        assert_eq!(
+            parse_program_string("1; 2 3"),
+            Err(ParseError::UnexpectedToken {
+                found: TokenKind::Int(3),
+                expected,
+                span: Span::new(5, 6),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tokens_after_final_statement_are_not_dropped() {
+        let err = parse_program_string("1 + 2 3 * 4").unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::UnexpectedToken {
+                found: TokenKind::Int(3),
+                ..
+            }
+        ));
+        assert_eq!(err.span(), Span::new(6, 7));
+
+        // Recovery skips the rest of the statement instead of keeping `1 + 2`
+        let (program, errors) = parse_with_recovery("1 + 2 3 * 4");
+        assert!(program.statements.is_empty());
+        assert_eq!(errors, vec![err]);
+    }
+
+    #[test]
+    fn test_eof_errors_point_at_end_of_input() {
+        // Trailing whitespace and comments come before the end of input
+        let err = parse_program_string("1 +  // more\n").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::UnexpectedEof {
+                span: Span::single(13)
+            }
+        );
+
+        let err = parse_expression_string("").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::UnexpectedEof {
+                span: Span::single(0)
+            }
+        );
+    }
+
+    #[test]
+    fn test_recovery_stops_at_lexer_error() {
+        let (program, errors) = parse_with_recovery("1; 2 + @; 3; 4 +");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.statements.len(), 1);
+    }
+
+    fn parse_with_operators(input: &str, operators: &OperatorRegistry) -> ParseResult<Expr> {
+        let lexer = SobaLexer::with_operators(input.chars().collect(), operators);
+        Parser::with_operators(lexer, operators)?.parse_expression()
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let mut operators = OperatorRegistry::new();
+        let spaceship = operators
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Left,
+                |l, _| Ok(l),
+            )
+            .unwrap();
+        let power = operators
+            .register_infix("^^", Precedence::Product, Associativity::Right, |l, _| {
+                Ok(l)
+            })
+            .unwrap();
+        let percent = operators.register_prefix("%%", Ok).unwrap();
+
+        let infix = |left: Expr, op, right: Expr| Expr::InfixExpr {
+            span: left.span().merge(right.span()),
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        };
+        let int = |value, offset| Expr::Int {
+            value,
+            span: Span::new(offset, offset + 1),
+        };
+
+        // Comparison precedence: `1 + 2 <=> 3` is `(1 + 2) <=> 3`
+        assert_eq!(
+            parse_with_operators("1+2<=>3", &operators).unwrap(),
+            infix(
+                infix(int(1, 0), BinaryOp::Plus, int(2, 2)),
+                BinaryOp::Custom(spaceship),
+                int(3, 6)
+            )
+        );
+
+        // Right associativity: `1 ^^ 2 ^^ 3` is `1 ^^ (2 ^^ 3)`
+        assert_eq!(
+            parse_with_operators("1^^2^^3", &operators).unwrap(),
+            infix(
+                int(1, 0),
+                BinaryOp::Custom(power),
+                infix(int(2, 3), BinaryOp::Custom(power), int(3, 6))
+            )
+        );
+
+        // Prefix operators bind like unary minus
+        let expr = parse_with_operators("%%1*2", &operators).unwrap();
+        assert!(matches!(
+            expr,
+            Expr::InfixExpr { op: BinaryOp::Multiply, left, .. }
+                if matches!(*left, Expr::UnaryExpr { op: UnaryOp::Custom(op), .. } if op == percent)
+        ));
+    }
+
+    #[test]
+    fn test_postfix_operators() {
+        let mut operators = OperatorRegistry::new();
+        let bang = operators.register_postfix("!!", Ok).unwrap();
+        let percent = operators.register_prefix("%%", Ok).unwrap();
+
+        // `-2!! * 3` is `(-(2!!)) * 3`
+        let expr = parse_with_operators("-2!! * 3", &operators).unwrap();
+        let Expr::InfixExpr { left, .. } = expr else {
+            panic!("expected an infix expression");
+        };
+        let Expr::UnaryExpr { operand, .. } = *left else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(
+            *operand,
+            Expr::PostfixExpr {
+                op: PostfixOp::Custom(bang),
+                operand: Box::new(Expr::Int {
+                    value: 2,
+                    span: Span::new(1, 2),
+                }),
+                span: Span::new(1, 4),
+            }
+        );
+
+        // Postfix operators chain, and apply inside custom prefix operators
+        let expr = parse_with_operators("%%1!!!!", &operators).unwrap();
+        assert!(matches!(
+            expr,
+            Expr::UnaryExpr { op: UnaryOp::Custom(op), operand, .. }
+                if op == percent && matches!(
+                    *operand,
+                    Expr::PostfixExpr { ref operand, .. }
+                        if matches!(**operand, Expr::PostfixExpr { .. })
+                )
+        ));
+    }
+
+    #[test]
+    fn test_custom_operator_needs_registration_with_parser() {
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Left,
+                |l, _| Ok(l),
+            )
+            .unwrap();
+
+        // The token is recognized, but this parser does not know the operator
+        let lexer = SobaLexer::with_operators("1 <=> 2".chars().collect(), &operators);
+        let mut parser = Parser::new(lexer).unwrap();
+        assert_eq!(
+            parser
+                .parse_standalone_expression()
+                .unwrap_err()
+                .span()
+                .start,
+            2
+        );
+
+        // A symbol registered only as infix cannot start an expression
+        assert!(matches!(
+            parse_with_operators("<=> 1", &operators),
+            Err(ParseError::UnexpectedToken {
+                found: TokenKind::Custom("<=>"),
+                ..
+            })
+        ));
+    }
 }
@@ -1,9 +1,12 @@
 //! Operator precedence definitions
 
+use super::table::{infix_precedence_in, InfixOperator, BUILTIN_INFIX};
+use crate::ast::BinaryOp;
 use crate::lexer::TokenKind;
 
 /// Operator precedence levels
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Precedence {
     Lowest,
     LogicalOr,  // ||
@@ -12,25 +15,46 @@ pub enum Precedence {
     Sum,        // + -
     Product,    // * /
     Unary,      // -x +x !x
+    Power,      // **
     Group,      // ()
 }
 
 impl Precedence {
-    /// Get precedence for a token
+    /// Get precedence for a token, using the built-in operator table
     pub fn from_token(token: &TokenKind) -> Precedence {
-        match token {
-            TokenKind::OrOr => Precedence::LogicalOr,
-            TokenKind::AndAnd => Precedence::LogicalAnd,
-            TokenKind::Equal
-            | TokenKind::NotEqual
-            | TokenKind::Less
-            | TokenKind::Greater
-            | TokenKind::LessEqual
-            | TokenKind::GreaterEqual => Precedence::Comparison,
-            TokenKind::Plus | TokenKind::Minus => Precedence::Sum,
-            TokenKind::Asterisk | TokenKind::Slash => Precedence::Product,
-            TokenKind::LeftParen => Precedence::Group,
-            _ => Precedence::Lowest,
+        infix_precedence_in(BUILTIN_INFIX, token)
+    }
+
+    /// Get precedence for a binary operator
+    pub fn from_binary_op(op: BinaryOp) -> Precedence {
+        InfixOperator::of(op).precedence
+    }
+
+    /// The lowest precedence that binds strictly tighter than this one
+    pub fn next_tighter(self) -> Precedence {
+        match self {
+            Precedence::Lowest => Precedence::LogicalOr,
+            Precedence::LogicalOr => Precedence::LogicalAnd,
+            Precedence::LogicalAnd => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Sum,
+            Precedence::Sum => Precedence::Product,
+            Precedence::Product => Precedence::Unary,
+            Precedence::Unary => Precedence::Power,
+            Precedence::Power | Precedence::Group => Precedence::Group,
+        }
+    }
+
+    /// The highest precedence that binds strictly looser than this one
+    pub fn next_looser(self) -> Precedence {
+        match self {
+            Precedence::Lowest | Precedence::LogicalOr => Precedence::Lowest,
+            Precedence::LogicalAnd => Precedence::LogicalOr,
+            Precedence::Comparison => Precedence::LogicalAnd,
+            Precedence::Sum => Precedence::Comparison,
+            Precedence::Product => Precedence::Sum,
+            Precedence::Unary => Precedence::Product,
+            Precedence::Power => Precedence::Unary,
+            Precedence::Group => Precedence::Power,
         }
     }
 
@@ -44,19 +68,20 @@ impl Precedence {
             Precedence::Sum => 4,
             Precedence::Product => 5,
             Precedence::Unary => 6,
-            Precedence::Group => 7,
+            Precedence::Power => 7,
+            Precedence::Group => 8,
         }
     }
 }
 
 impl PartialOrd for Precedence {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.level().cmp(&other.level()))
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Precedence {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.level().cmp(&other.level())
     }
 }
@@ -75,7 +100,8 @@ mod tests {
         assert!(Precedence::Comparison < Precedence::Sum);
         assert!(Precedence::Sum < Precedence::Product);
         assert!(Precedence::Product < Precedence::Unary);
-        assert!(Precedence::Unary < Precedence::Group);
+        assert!(Precedence::Unary < Precedence::Power);
+        assert!(Precedence::Power < Precedence::Group);
     }
 
     #[test]
@@ -122,6 +148,10 @@ mod tests {
             Precedence::from_token(&TokenKind::Slash),
             Precedence::Product
         );
+        assert_eq!(
+            Precedence::from_token(&TokenKind::StarStar),
+            Precedence::Power
+        );
         assert_eq!(
             Precedence::from_token(&TokenKind::LeftParen),
             Precedence::Group
@@ -131,4 +161,21 @@ mod tests {
             Precedence::Lowest
         );
     }
+
+    #[test]
+    fn test_binary_op_matches_token_precedence() {
+        for (token, op) in [
+            (TokenKind::OrOr, BinaryOp::LogicalOr),
+            (TokenKind::AndAnd, BinaryOp::LogicalAnd),
+            (TokenKind::LessEqual, BinaryOp::LessEqual),
+            (TokenKind::Minus, BinaryOp::Minus),
+            (TokenKind::Slash, BinaryOp::Divide),
+            (TokenKind::StarStar, BinaryOp::Power),
+        ] {
+            assert_eq!(
+                Precedence::from_token(&token),
+                Precedence::from_binary_op(op)
+            );
+        }
+    }
 }
@@ -0,0 +1,336 @@
+//! Operator table
+//!
+//! Every operator the parser knows is described once here: the token that
+//! spells it, the AST operator it produces and how tightly it binds. The
+//! parser, the CST builder and `Precedence::from_token` all read the same
+//! table, so adding an operator means adding one row.
+
+use super::precedence::Precedence;
+use crate::ast::{BinaryOp, PostfixOp, UnaryOp};
+use crate::lexer::TokenKind;
+use crate::operators::{Associativity, OperatorRegistry};
+use crate::prelude::*;
+
+/// How an infix operator parses
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct InfixOperator {
+    pub op: BinaryOp,
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+}
+
+impl InfixOperator {
+    pub const fn new(op: BinaryOp, precedence: Precedence, associativity: Associativity) -> Self {
+        Self {
+            op,
+            precedence,
+            associativity,
+        }
+    }
+
+    const fn left(op: BinaryOp, precedence: Precedence) -> Self {
+        Self::new(op, precedence, Associativity::Left)
+    }
+
+    /// Get the parsing rules of any binary operator
+    pub fn of(op: BinaryOp) -> InfixOperator {
+        match op {
+            BinaryOp::Custom(custom) => {
+                InfixOperator::new(op, custom.precedence, custom.associativity)
+            }
+            _ => BUILTIN_INFIX
+                .iter()
+                .map(|(_, operator)| *operator)
+                .find(|operator| operator.op == op)
+                .expect("every built-in operator has a table row"),
+        }
+    }
+
+    /// Precedence to parse the right operand at
+    ///
+    /// A right-associative operator parses its right operand one level
+    /// looser, so an operator of the same precedence chains there instead
+    /// of ending the operand.
+    pub fn right_operand_precedence(self) -> Precedence {
+        match self.associativity {
+            Associativity::Left => self.precedence,
+            Associativity::Right => self.precedence.next_looser(),
+        }
+    }
+}
+
+/// How a postfix operator parses
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PostfixOperator {
+    pub op: PostfixOp,
+    /// Operands binding looser than this need parentheses, e.g. `(-x)!`
+    pub precedence: Precedence,
+}
+
+impl PostfixOperator {
+    /// Get the parsing rules of any postfix operator
+    pub fn of(op: PostfixOp) -> PostfixOperator {
+        match op {
+            PostfixOp::Custom(custom) => PostfixOperator {
+                op,
+                precedence: custom.precedence,
+            },
+        }
+    }
+}
+
+/// The built-in infix operators
+pub const BUILTIN_INFIX: &[(TokenKind, InfixOperator)] = &[
+    (
+        TokenKind::OrOr,
+        InfixOperator::left(BinaryOp::LogicalOr, Precedence::LogicalOr),
+    ),
+    (
+        TokenKind::AndAnd,
+        InfixOperator::left(BinaryOp::LogicalAnd, Precedence::LogicalAnd),
+    ),
+    (
+        TokenKind::Equal,
+        InfixOperator::left(BinaryOp::Equal, Precedence::Comparison),
+    ),
+    (
+        TokenKind::NotEqual,
+        InfixOperator::left(BinaryOp::NotEqual, Precedence::Comparison),
+    ),
+    (
+        TokenKind::Less,
+        InfixOperator::left(BinaryOp::Less, Precedence::Comparison),
+    ),
+    (
+        TokenKind::Greater,
+        InfixOperator::left(BinaryOp::Greater, Precedence::Comparison),
+    ),
+    (
+        TokenKind::LessEqual,
+        InfixOperator::left(BinaryOp::LessEqual, Precedence::Comparison),
+    ),
+    (
+        TokenKind::GreaterEqual,
+        InfixOperator::left(BinaryOp::GreaterEqual, Precedence::Comparison),
+    ),
+    (
+        TokenKind::Plus,
+        InfixOperator::left(BinaryOp::Plus, Precedence::Sum),
+    ),
+    (
+        TokenKind::Minus,
+        InfixOperator::left(BinaryOp::Minus, Precedence::Sum),
+    ),
+    (
+        TokenKind::Asterisk,
+        InfixOperator::left(BinaryOp::Multiply, Precedence::Product),
+    ),
+    (
+        TokenKind::Slash,
+        InfixOperator::left(BinaryOp::Divide, Precedence::Product),
+    ),
+    // Binds tighter than prefix operators on its left, so `-2 ** 2` is
+    // `-(2 ** 2)`, and right to left, so `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+    (
+        TokenKind::StarStar,
+        InfixOperator::new(BinaryOp::Power, Precedence::Power, Associativity::Right),
+    ),
+];
+
+/// The built-in prefix operators, which all bind at `Precedence::Unary`
+pub const BUILTIN_PREFIX: &[(TokenKind, UnaryOp)] = &[
+    (TokenKind::Plus, UnaryOp::Plus),
+    (TokenKind::Minus, UnaryOp::Minus),
+    (TokenKind::Bang, UnaryOp::LogicalNot),
+];
+
+/// The operators a parser accepts, keyed by token
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    infix: Vec<(TokenKind, InfixOperator)>,
+    prefix: Vec<(TokenKind, UnaryOp)>,
+    postfix: Vec<(TokenKind, PostfixOperator)>,
+}
+
+impl OperatorTable {
+    /// A table with only the built-in operators
+    pub fn builtin() -> Self {
+        Self {
+            infix: BUILTIN_INFIX.to_vec(),
+            prefix: BUILTIN_PREFIX.to_vec(),
+            postfix: Vec::new(),
+        }
+    }
+
+    /// The built-in operators plus every operator in `registry`
+    pub fn with_custom(registry: &OperatorRegistry) -> Self {
+        let mut table = Self::builtin();
+        for custom in registry.infix_operators() {
+            table.add_infix(
+                TokenKind::Custom(custom.symbol),
+                InfixOperator::of(BinaryOp::Custom(custom)),
+            );
+        }
+        for custom in registry.prefix_operators() {
+            table.add_prefix(TokenKind::Custom(custom.symbol), UnaryOp::Custom(custom));
+        }
+        for custom in registry.postfix_operators() {
+            table.add_postfix(
+                TokenKind::Custom(custom.symbol),
+                PostfixOperator::of(PostfixOp::Custom(custom)),
+            );
+        }
+        table
+    }
+
+    /// Add an infix operator, replacing any existing infix or postfix
+    /// operator for `token`
+    pub fn add_infix(&mut self, token: TokenKind, operator: InfixOperator) {
+        self.postfix.retain(|(existing, _)| *existing != token);
+        self.infix.retain(|(existing, _)| *existing != token);
+        self.infix.push((token, operator));
+    }
+
+    /// Add a prefix operator, replacing any existing one for `token`
+    pub fn add_prefix(&mut self, token: TokenKind, op: UnaryOp) {
+        self.prefix.retain(|(existing, _)| *existing != token);
+        self.prefix.push((token, op));
+    }
+
+    /// Add a postfix operator, replacing any existing one for `token`
+    ///
+    /// A token is read as either an infix or a postfix operator after an
+    /// operand, so this also removes any infix operator for `token`.
+    pub fn add_postfix(&mut self, token: TokenKind, operator: PostfixOperator) {
+        self.infix.retain(|(existing, _)| *existing != token);
+        self.postfix.retain(|(existing, _)| *existing != token);
+        self.postfix.push((token, operator));
+    }
+
+    /// The infix operator `token` stands for, if any
+    pub fn infix(&self, token: &TokenKind) -> Option<InfixOperator> {
+        lookup(&self.infix, token)
+    }
+
+    /// The prefix operator `token` stands for, if any
+    pub fn prefix(&self, token: &TokenKind) -> Option<UnaryOp> {
+        lookup(&self.prefix, token)
+    }
+
+    /// The postfix operator `token` stands for, if any
+    pub fn postfix(&self, token: &TokenKind) -> Option<PostfixOperator> {
+        lookup(&self.postfix, token)
+    }
+
+    /// Binding power of `token` when it follows an expression, as an infix
+    /// or postfix operator
+    ///
+    /// `(` gets `Group` so that `1 (2)` reaches the infix position and is
+    /// reported, rather than silently ending the expression.
+    pub fn infix_precedence(&self, token: &TokenKind) -> Precedence {
+        match self.postfix(token) {
+            Some(operator) => operator.precedence,
+            None => infix_precedence_in(&self.infix, token),
+        }
+    }
+}
+
+impl Default for OperatorTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+pub(super) fn infix_precedence_in(
+    rows: &[(TokenKind, InfixOperator)],
+    token: &TokenKind,
+) -> Precedence {
+    match (lookup(rows, token), token) {
+        (Some(operator), _) => operator.precedence,
+        (None, TokenKind::LeftParen) => Precedence::Group,
+        (None, _) => Precedence::Lowest,
+    }
+}
+
+pub(crate) fn lookup<T: Copy>(rows: &[(TokenKind, T)], token: &TokenKind) -> Option<T> {
+    rows.iter()
+        .find(|(kind, _)| kind == token)
+        .map(|(_, value)| *value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::pratt::INFIX_OPERATOR_TOKENS;
+
+    #[test]
+    fn test_error_token_list_matches_table() {
+        let table_tokens: Vec<TokenKind> = BUILTIN_INFIX.iter().map(|(t, _)| t.clone()).collect();
+        assert_eq!(table_tokens.len(), INFIX_OPERATOR_TOKENS.len());
+        for token in INFIX_OPERATOR_TOKENS {
+            assert!(table_tokens.contains(token), "{token} is not in the table");
+        }
+    }
+
+    #[test]
+    fn test_custom_operators_extend_the_table() {
+        let mut registry = OperatorRegistry::new();
+        let custom = registry
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Right,
+                |l, _| Ok(l),
+            )
+            .unwrap();
+        let table = OperatorTable::with_custom(&registry);
+
+        let operator = table.infix(&TokenKind::Custom("<=>")).unwrap();
+        assert_eq!(operator.op, BinaryOp::Custom(custom));
+        assert_eq!(operator.right_operand_precedence(), Precedence::LogicalAnd);
+        assert_eq!(table.prefix(&TokenKind::Custom("<=>")), None);
+        assert_eq!(
+            OperatorTable::builtin().infix(&TokenKind::Custom("<=>")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_postfix_replaces_infix() {
+        let mut registry = OperatorRegistry::new();
+        let custom = registry.register_postfix("%", Ok).unwrap();
+        let mut table = OperatorTable::with_custom(&registry);
+        let token = TokenKind::Custom("%");
+
+        assert_eq!(
+            table.postfix(&token).map(|operator| operator.op),
+            Some(PostfixOp::Custom(custom))
+        );
+        assert_eq!(table.infix_precedence(&token), Precedence::Group);
+
+        table.add_infix(
+            token.clone(),
+            InfixOperator::new(
+                BinaryOp::Custom(custom),
+                Precedence::Sum,
+                Associativity::Left,
+            ),
+        );
+        assert_eq!(table.postfix(&token), None);
+        assert_eq!(table.infix_precedence(&token), Precedence::Sum);
+    }
+
+    #[test]
+    fn test_infix_precedence() {
+        let table = OperatorTable::builtin();
+        assert_eq!(
+            table.infix_precedence(&TokenKind::Slash),
+            Precedence::Product
+        );
+        assert_eq!(
+            table.infix_precedence(&TokenKind::LeftParen),
+            Precedence::Group
+        );
+        assert_eq!(table.infix_precedence(&TokenKind::Bang), Precedence::Lowest);
+    }
+}
@@ -0,0 +1,11 @@
+//! Helpers for the unit tests of other modules
+
+use super::Parser;
+use crate::ast::Program;
+use crate::lexer::SobaLexer;
+
+/// Parse `input` as a program, panicking if it is not valid
+pub fn parse(input: &str) -> Program {
+    let lexer = SobaLexer::new(input.chars().collect());
+    Parser::new(lexer).unwrap().parse_program().unwrap()
+}
@@ -0,0 +1,237 @@
+//! Source files and the map that owns them
+//!
+//! Every span records the `SourceId` of the text it came from, so a
+//! diagnostic for a program built from several sources (files, REPL
+//! snippets) can name the right one. A `SourceMap` hands out the ids and
+//! keeps the names and contents for display.
+//!
+//! Script files may begin with a `#!/usr/bin/env soba` line so they can be
+//! run directly on Unix. Sources added with `SourceMap::add_file` keep that
+//! line in their contents, for display, but their lexer starts after it;
+//! everywhere else `#` is an unexpected character.
+
+use crate::lexer::SobaLexer;
+use crate::prelude::*;
+use crate::span::{ColumnUnit, LineIndex, Position, Span};
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+/// Identifies one source text in a `SourceMap`
+///
+/// The default id is the first source added to a map, so spans from code
+/// lexed without a source still resolve in single-source programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceId(u32);
+
+impl SourceId {
+    /// Position of the source in its map, in the order sources were added
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A named source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    id: SourceId,
+    name: String,
+    contents: String,
+    /// Byte offset where lexing starts, past a `#!` line
+    start: usize,
+    /// Built the first time a line or column is needed
+    line_index: OnceLock<LineIndex>,
+}
+
+impl SourceFile {
+    pub fn id(&self) -> SourceId {
+        self.id
+    }
+
+    /// File name or other label shown in diagnostics, e.g. `<repl:3>`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Byte offset of the code in the contents: the length of a skipped
+    /// `#!` line, or 0
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The contents after a skipped `#!` line
+    pub fn code(&self) -> &str {
+        &self.contents[self.start..]
+    }
+
+    pub fn line_index(&self) -> &LineIndex {
+        self.line_index
+            .get_or_init(|| LineIndex::new(&self.contents))
+    }
+
+    /// Line and column of a byte offset into the contents
+    pub fn position(&self, offset: usize) -> Position {
+        self.line_index().position(&self.contents, offset)
+    }
+
+    /// Like `position`, with the column counted in `unit`
+    pub fn position_in(&self, offset: usize, unit: ColumnUnit) -> Position {
+        self.line_index().position_in(&self.contents, offset, unit)
+    }
+
+    /// A lexer over the code whose spans carry this source's id and count
+    /// from the start of the contents
+    pub fn lexer(&self) -> SobaLexer {
+        let mut lexer = SobaLexer::starting_at(self.code().chars().collect(), self.start);
+        lexer.set_source(self.id);
+        lexer
+    }
+}
+
+/// Owns the sources of a program and resolves spans against them
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source and return its id
+    pub fn add(&mut self, name: impl Into<String>, contents: impl Into<String>) -> SourceId {
+        self.push(name.into(), contents.into(), 0)
+    }
+
+    /// Add the contents of a script file, whose lexer skips a leading `#!`
+    /// line, and return its id
+    pub fn add_file(&mut self, name: impl Into<String>, contents: impl Into<String>) -> SourceId {
+        let contents = contents.into();
+        let start = shebang_len(&contents);
+        self.push(name.into(), contents, start)
+    }
+
+    fn push(&mut self, name: String, contents: String, start: usize) -> SourceId {
+        let id = SourceId(self.files.len() as u32);
+        self.files.push(SourceFile {
+            id,
+            name,
+            contents,
+            start,
+            line_index: OnceLock::new(),
+        });
+        id
+    }
+
+    pub fn get(&self, id: SourceId) -> Option<&SourceFile> {
+        self.files.get(id.index())
+    }
+
+    /// All sources, in the order they were added
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// The text `span` covers, if its source is in the map
+    pub fn snippet(&self, span: Span) -> Option<&str> {
+        self.get(span.source)?.contents.get(span.start..span.end)
+    }
+
+    /// `span` prefixed with the name of its source, e.g. `main.soba:2:3-5`
+    pub fn location(&self, span: Span) -> String {
+        match self.get(span.source) {
+            Some(file) => format!(
+                "{}:{}",
+                file.name,
+                file.line_index().describe(&file.contents, span)
+            ),
+            None => format!("<unknown>:{span}"),
+        }
+    }
+}
+
+/// Length in bytes of a `#!` line at the start of `source`, or 0
+///
+/// The newline after it is not counted, so the code that follows keeps
+/// its line numbers.
+pub fn shebang_len(source: &str) -> usize {
+    if source.starts_with("#!") {
+        source.find('\n').unwrap_or(source.len())
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_add_and_get() {
+        let mut sources = SourceMap::new();
+        let main = sources.add("main.soba", "1 + 2");
+        let repl = sources.add("<repl:1>", "3");
+
+        assert_eq!(main, SourceId::default());
+        assert_eq!(repl.index(), 1);
+        assert_eq!(sources.get(repl).unwrap().name(), "<repl:1>");
+        assert_eq!(sources.get(main).unwrap().contents(), "1 + 2");
+        assert_eq!(sources.files().len(), 2);
+        assert!(SourceMap::new().get(main).is_none());
+    }
+
+    #[test]
+    fn test_spans_carry_their_source() {
+        let mut sources = SourceMap::new();
+        sources.add("first.soba", "1");
+        let second = sources.add("second.soba", "1 +\n(2 * 3)");
+
+        let file = sources.get(second).unwrap();
+        let program = Parser::new(file.lexer()).unwrap().parse_program().unwrap();
+        assert_eq!(program.span.source, second);
+        assert_eq!(sources.snippet(program.span), Some("1 +\n(2 * 3)"));
+        assert_eq!(sources.location(program.span), "second.soba:1:1-2:8");
+
+        let bad = sources.add("bad.soba", "1 +");
+        let err = Parser::new(sources.get(bad).unwrap().lexer())
+            .unwrap()
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(sources.location(err.span()), "bad.soba:1:4-4");
+    }
+
+    #[test]
+    fn test_shebang_len() {
+        assert_eq!(shebang_len("#!/usr/bin/env soba\n1"), 19);
+        assert_eq!(shebang_len("#!soba"), 6);
+        assert_eq!(shebang_len(" #!soba\n1"), 0);
+        assert_eq!(shebang_len("1"), 0);
+    }
+
+    #[test]
+    fn test_files_skip_the_shebang() {
+        let mut sources = SourceMap::new();
+        let script = sources.add_file("script.soba", "#!/usr/bin/env soba\n1 +\n2");
+        let file = sources.get(script).unwrap();
+        assert_eq!(file.start(), 19);
+        assert_eq!(file.code(), "\n1 +\n2");
+        assert_eq!(file.contents(), "#!/usr/bin/env soba\n1 +\n2");
+
+        let program = Parser::new(file.lexer()).unwrap().parse_program().unwrap();
+        assert_eq!(sources.location(program.span), "script.soba:2:1-3:2");
+
+        // Other sources lex everything
+        let snippet = sources.add("<repl:1>", "#!1");
+        assert!(Parser::new(sources.get(snippet).unwrap().lexer())
+            .and_then(|mut parser| parser.parse_program())
+            .is_err());
+    }
+}
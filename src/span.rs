@@ -1,13 +1,22 @@
 //! Position and span information for tokens and AST nodes
+//!
+//! Spans store byte offsets only. Lines and columns are needed just for
+//! display, so a `LineIndex` computes them on demand from the source text.
 
-/// Represents a position in the source code
+use crate::prelude::*;
+use crate::source::SourceId;
+use unicode_width::UnicodeWidthChar;
+
+/// A line and column in the source code, computed by a `LineIndex`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     /// Byte offset from the start of the input
     pub offset: usize,
     /// Line number (1-based)
     pub line: usize,
-    /// Column number (1-based)
+    /// Column number (1-based), counted in characters unless computed with
+    /// `LineIndex::position_in`
     pub column: usize,
 }
 
@@ -27,71 +36,231 @@ impl Position {
             column: 1,
         }
     }
-
-    pub fn advance(&mut self, ch: char) {
-        self.offset += ch.len_utf8();
-        if ch == '\n' {
-            self.line += 1;
-            self.column = 1;
-        } else {
-            self.column += 1;
-        }
-    }
 }
 
 /// Represents a span of source code
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
-    /// Start position (inclusive)
-    pub start: Position,
-    /// End position (exclusive)
-    pub end: Position,
+    /// Start byte offset (inclusive)
+    pub start: usize,
+    /// End byte offset (exclusive)
+    pub end: usize,
+    /// Source the offsets refer to
+    pub source: SourceId,
 }
 
 impl Span {
-    pub fn new(start: Position, end: Position) -> Self {
-        Self { start, end }
-    }
-
-    pub fn single(pos: Position) -> Self {
+    pub fn new(start: usize, end: usize) -> Self {
         Self {
-            start: pos,
-            end: pos,
+            start,
+            end,
+            source: SourceId::default(),
         }
     }
 
+    /// Empty span at `offset`
+    pub fn single(offset: usize) -> Self {
+        Self::new(offset, offset)
+    }
+
+    /// The same offsets in `source`
+    pub fn in_source(self, source: SourceId) -> Span {
+        Span { source, ..self }
+    }
+
+    /// Smallest span covering both; both must be from the same source
     pub fn merge(self, other: Span) -> Span {
         Span {
             start: self.start.min(other.start),
             end: self.end.max(other.end),
+            source: self.source,
         }
     }
 
     pub fn len(&self) -> usize {
-        self.end.offset - self.start.offset
+        self.end - self.start
     }
 
     pub fn is_empty(&self) -> bool {
         self.start == self.end
     }
+
+    /// Whether byte `offset` falls inside the span
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
 }
 
-impl std::fmt::Display for Position {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Position {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}:{}", self.line, self.column)
     }
 }
 
-impl std::fmt::Display for Span {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.start.line == self.end.line {
-            write!(
-                f,
-                "{}:{}-{}",
-                self.start.line, self.start.column, self.end.column
-            )
+/// Shows the byte range; use `LineIndex::describe` for lines and columns
+impl core::fmt::Display for Span {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// What a column number counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnUnit {
+    /// Unicode scalar values
+    #[default]
+    Char,
+    /// UTF-8 bytes
+    Utf8,
+    /// UTF-16 code units, as used by the Language Server Protocol
+    Utf16,
+    /// Terminal cells: wide CJK characters and emoji take two, zero-width
+    /// characters none
+    DisplayWidth,
+}
+
+impl ColumnUnit {
+    /// Width of `text` in this unit
+    pub fn measure(self, text: &str) -> usize {
+        match self {
+            ColumnUnit::Char => text.chars().count(),
+            ColumnUnit::Utf8 => text.len(),
+            ColumnUnit::Utf16 => text.encode_utf16().count(),
+            ColumnUnit::DisplayWidth => text.chars().map(|ch| ch.width().unwrap_or(0)).sum(),
+        }
+    }
+}
+
+/// Byte offsets of the line starts of a text, for turning offsets into
+/// lines and columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = core::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    /// Line and column of `offset` in `text`, which must be the text the
+    /// index was built from
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        self.position_in(text, offset, ColumnUnit::Char)
+    }
+
+    /// Like `position`, with the column counted in `unit`
+    pub fn position_in(&self, text: &str, offset: usize, unit: ColumnUnit) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = text
+            .get(line_start..offset)
+            .map_or(offset - line_start, |prefix| unit.measure(prefix));
+        Position::new(offset, line, column + 1)
+    }
+
+    /// Byte offset of a 1-based `line` and `column` counted in `unit`, the
+    /// reverse of `position_in`
+    ///
+    /// A column past the end of its line gives the end of the line, and a
+    /// line past the end of the text gives the end of the text.
+    pub fn offset_in(&self, text: &str, line: usize, column: usize, unit: ColumnUnit) -> usize {
+        let Some(&line_start) = self.line_starts.get(line.max(1) - 1) else {
+            return text.len();
+        };
+        let line_text = text[line_start..].split('\n').next().unwrap_or("");
+        let mut width = 0;
+        for (index, ch) in line_text.char_indices() {
+            if width >= column.saturating_sub(1) {
+                return line_start + index;
+            }
+            width += unit.measure(ch.encode_utf8(&mut [0; 4]));
+        }
+        line_start + line_text.len()
+    }
+
+    /// `span` as `line:column-column`, or `line:column-line:column` when
+    /// it covers several lines
+    pub fn describe(&self, text: &str, span: Span) -> String {
+        self.describe_in(text, span, ColumnUnit::Char)
+    }
+
+    /// Like `describe`, with columns counted in `unit`
+    pub fn describe_in(&self, text: &str, span: Span, unit: ColumnUnit) -> String {
+        let start = self.position_in(text, span.start, unit);
+        let end = self.position_in(text, span.end, unit);
+        if start.line == end.line {
+            format!("{}:{}-{}", start.line, start.column, end.column)
         } else {
-            write!(f, "{}-{}", self.start, self.end)
+            format!("{start}-{end}")
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_index_positions() {
+        let text = "1 +\n\n  é2;\n";
+        let index = LineIndex::new(text);
+        assert_eq!(index.position(text, 0), Position::start());
+        assert_eq!(index.position(text, 3), Position::new(3, 1, 4));
+        assert_eq!(index.position(text, 4), Position::new(4, 2, 1));
+        assert_eq!(index.position(text, 5), Position::new(5, 3, 1));
+        // Columns count characters, not bytes
+        assert_eq!(index.position(text, 9), Position::new(9, 3, 4));
+        assert_eq!(index.position(text, text.len()), Position::new(12, 4, 1));
+    }
+
+    #[test]
+    fn test_column_units() {
+        // "漢" is three UTF-8 bytes, one UTF-16 unit and two cells wide;
+        // "😀" is four bytes, two UTF-16 units and two cells wide
+        let text = "漢😀 +\n1";
+        let index = LineIndex::new(text);
+        let column = |unit| index.position_in(text, 8, unit).column;
+        assert_eq!(column(ColumnUnit::Char), 4);
+        assert_eq!(column(ColumnUnit::Utf8), 9);
+        assert_eq!(column(ColumnUnit::Utf16), 5);
+        assert_eq!(column(ColumnUnit::DisplayWidth), 6);
+        assert_eq!(
+            index.describe_in(text, Span::new(8, 9), ColumnUnit::DisplayWidth),
+            "1:6-7"
+        );
+        // Combining marks take no cells
+        assert_eq!(ColumnUnit::DisplayWidth.measure("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_offset_in() {
+        let text = "漢😀 +\n1";
+        let index = LineIndex::new(text);
+        for unit in [ColumnUnit::Char, ColumnUnit::Utf8, ColumnUnit::Utf16] {
+            for offset in [0, 3, 7, 8, 9, 10] {
+                let position = index.position_in(text, offset, unit);
+                assert_eq!(
+                    index.offset_in(text, position.line, position.column, unit),
+                    offset
+                );
+            }
+        }
+        // Past the end of a line or of the text
+        assert_eq!(index.offset_in(text, 1, 40, ColumnUnit::Char), 9);
+        assert_eq!(index.offset_in(text, 5, 1, ColumnUnit::Char), text.len());
+    }
+
+    #[test]
+    fn test_describe() {
+        let text = "1 +\n(2 * 3)";
+        let index = LineIndex::new(text);
+        assert_eq!(index.describe(text, Span::new(4, 11)), "2:1-8");
+        assert_eq!(index.describe(text, Span::new(0, 11)), "1:1-2:8");
+        assert_eq!(Span::new(4, 11).to_string(), "4..11");
+    }
+}
@@ -0,0 +1,173 @@
+//! Expression interpolation in text
+//!
+//! `interpolate` copies a template through and replaces each `${...}` with
+//! the value of the soba program between the braces, run by a `Soba`
+//! engine. `$${` writes a literal `${`. Programs cannot contain `}`, so
+//! the first one closes the placeholder. Error spans point into the
+//! template.
+//!
+//! Placeholders hold self-contained expressions only. soba has no
+//! variables yet, so there is no context to look names up in and
+//! `${name}` is a parse error.
+//!
+//! ```
+//! use soba::{template, Soba};
+//!
+//! let soba = Soba::new();
+//! let text = template::interpolate("2 ** 10 = ${2 ** 10}", &soba).unwrap();
+//! assert_eq!(text, "2 ** 10 = 1024");
+//! ```
+
+use crate::engine::Soba;
+use crate::error::SobaError;
+use crate::span::Span;
+use std::fmt;
+
+/// Why a template could not be interpolated
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    /// A `${` with no `}` after it; the span covers the `${`
+    Unclosed { span: Span },
+    /// A placeholder with nothing but whitespace inside; the span covers
+    /// the whole placeholder
+    Empty { span: Span },
+    /// The program in a placeholder failed to parse or evaluate
+    Soba(SobaError),
+}
+
+impl TemplateError {
+    /// Get the source location of the error in the template, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TemplateError::Unclosed { span } | TemplateError::Empty { span } => Some(*span),
+            TemplateError::Soba(err) => err.span(),
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed { .. } => write!(f, "Unclosed placeholder: missing '}}'"),
+            TemplateError::Empty { .. } => write!(f, "Empty placeholder"),
+            TemplateError::Soba(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<SobaError> for TemplateError {
+    fn from(err: SobaError) -> Self {
+        TemplateError::Soba(err)
+    }
+}
+
+/// Interpolate `template`, replacing each placeholder with the value of
+/// the expression inside
+///
+/// Interpolation stops at the first placeholder that fails.
+pub fn interpolate(template: &str, soba: &Soba) -> Result<String, TemplateError> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = 0;
+
+    while let Some(found) = template[rest..].find('$') {
+        let dollar = rest + found;
+        output.push_str(&template[rest..dollar]);
+        let after = &template[dollar..];
+
+        if after.starts_with("$${") {
+            output.push_str("${");
+            rest = dollar + 3;
+        } else if after.starts_with("${") {
+            let start = dollar + 2;
+            let Some(length) = template[start..].find('}') else {
+                return Err(TemplateError::Unclosed {
+                    span: Span::new(dollar, start),
+                });
+            };
+            let end = start + length;
+            let source = &template[start..end];
+            if source.trim().is_empty() {
+                return Err(TemplateError::Empty {
+                    span: Span::new(dollar, end + 1),
+                });
+            }
+
+            let program = soba.compile_at(source, start)?;
+            output.push_str(&soba.run(&program)?.to_string());
+            rest = end + 1;
+        } else {
+            output.push('$');
+            rest = dollar + 1;
+        }
+    }
+    output.push_str(&template[rest..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{EvalError, ParseError};
+
+    fn interpolate(template: &str) -> Result<String, TemplateError> {
+        super::interpolate(template, &Soba::new())
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate("").unwrap(), "");
+        assert_eq!(interpolate("no placeholders").unwrap(), "no placeholders");
+        assert_eq!(
+            interpolate("${1 + 2} apples cost ${3 * 0.5}$").unwrap(),
+            "3 apples cost 1.5$"
+        );
+        assert_eq!(interpolate("${1 < 2 && 3 < 4}").unwrap(), "true");
+        // Each placeholder is a program; the last statement is its value
+        assert_eq!(interpolate("[${1; 2}]").unwrap(), "[2]");
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(interpolate("$${1 + 2}").unwrap(), "${1 + 2}");
+        // Read left to right, the first `$` is plain text
+        assert_eq!(interpolate("$$${1 + 2}").unwrap(), "$${1 + 2}");
+        assert_eq!(interpolate("cost: $5").unwrap(), "cost: $5");
+    }
+
+    #[test]
+    fn test_errors_point_into_the_template() {
+        let err = interpolate("total: ${1 / 0}").unwrap_err();
+        assert!(matches!(
+            err,
+            TemplateError::Soba(SobaError::EvalError(EvalError::DivisionByZero { .. }))
+        ));
+        assert_eq!(err.span(), Some(Span::new(9, 14)));
+
+        let err = interpolate("é ${1 +}").unwrap_err();
+        assert!(matches!(
+            err,
+            TemplateError::Soba(SobaError::ParseError(ParseError::UnexpectedEof { .. }))
+        ));
+        assert_eq!(err.span(), Some(Span::single(8)));
+
+        // There are no variables to look names up in
+        let err = interpolate("Hello ${name}").unwrap_err();
+        assert!(matches!(err, TemplateError::Soba(SobaError::ParseError(_))));
+        assert_eq!(err.span(), Some(Span::new(8, 12)));
+
+        assert_eq!(
+            interpolate("a ${1 + 2").unwrap_err(),
+            TemplateError::Unclosed {
+                span: Span::new(2, 4)
+            }
+        );
+        assert_eq!(
+            interpolate("a ${ } b").unwrap_err(),
+            TemplateError::Empty {
+                span: Span::new(2, 6)
+            }
+        );
+    }
+}
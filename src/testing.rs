@@ -0,0 +1,199 @@
+//! Test files
+//!
+//! A test file is a soba file in which every statement is an assertion:
+//! it passes if it evaluates to `true`. Each statement is evaluated on its
+//! own, so one failing assertion does not hide the ones after it, and each
+//! file is run apart from the others. `soba test` finds the `*.soba` files
+//! under a directory with `find_test_files` and runs each with `run_file`.
+//!
+//! ```text
+//! // arithmetic.soba
+//! 1 + 2 == 3;
+//! 2 ** 10 == 1024;
+//! !(1 > 2)
+//! ```
+
+use crate::error::SobaError;
+use crate::evaluator::eval_statement;
+use crate::parser::Parser;
+use crate::source::SourceFile;
+use crate::span::Span;
+use crate::value::Value;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why an assertion failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    /// The statement evaluated to something other than `true`
+    NotTrue { value: Value, span: Span },
+    /// The statement could not be evaluated
+    Error(SobaError),
+}
+
+impl Failure {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Failure::NotTrue { span, .. } => Some(*span),
+            Failure::Error(err) => err.span(),
+        }
+    }
+}
+
+/// What running one test file gave
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileReport {
+    /// Number of assertions that held
+    pub passed: usize,
+    /// The failed assertions and syntax errors, in source order
+    pub failures: Vec<Failure>,
+}
+
+impl FileReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Run the assertions of `file`
+///
+/// Statements that do not parse count as failures; the parser recovers at
+/// the next `;`, so the assertions around them still run.
+pub fn run_file(file: &SourceFile) -> FileReport {
+    let mut report = FileReport::default();
+    let mut parser = match Parser::new(file.lexer()) {
+        Ok(parser) => parser,
+        Err(err) => {
+            report.failures.push(Failure::Error(err.into()));
+            return report;
+        }
+    };
+    let (program, errors) = parser.parse_program_with_recovery();
+    report
+        .failures
+        .extend(errors.into_iter().map(|err| Failure::Error(err.into())));
+
+    for stmt in &program.statements {
+        match eval_statement(stmt) {
+            Ok(Value::Bool(true)) => report.passed += 1,
+            Ok(value) => report.failures.push(Failure::NotTrue {
+                value,
+                span: stmt.span(),
+            }),
+            Err(err) => report.failures.push(Failure::Error(err.into())),
+        }
+    }
+    report
+        .failures
+        .sort_by_key(|failure| failure.span().map(|span| span.start));
+    report
+}
+
+/// The `*.soba` files under `root`, sorted by path; `root` itself if it
+/// is a file
+pub fn find_test_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if root.is_dir() {
+        collect(root, &mut files)?;
+    } else {
+        // Let the caller report a missing file when reading it
+        files.push(root.to_path_buf());
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "soba")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SourceMap;
+
+    fn run(input: &str) -> FileReport {
+        let mut sources = SourceMap::new();
+        let id = sources.add("test.soba", input);
+        run_file(sources.get(id).unwrap())
+    }
+
+    #[test]
+    fn test_assertions() {
+        let report = run("1 + 2 == 3;\n// a comment\n2 ** 10 == 1024;\n!(1 > 2)");
+        assert_eq!(report.passed, 3);
+        assert!(report.is_ok());
+        assert!(run("").is_ok());
+    }
+
+    #[test]
+    fn test_failures() {
+        let report = run("1 < 2;\n1 + 1;\n1 / 0 == 1;\n2 == 2");
+        assert_eq!(report.passed, 2);
+        assert_eq!(
+            report.failures[0],
+            Failure::NotTrue {
+                value: Value::Float(2.0),
+                span: Span::new(7, 12),
+            }
+        );
+        assert!(matches!(
+            report.failures[1],
+            Failure::Error(SobaError::EvalError(_))
+        ));
+    }
+
+    #[test]
+    fn test_syntax_errors_do_not_stop_other_assertions() {
+        let report = run("1 == 1;\n1 +;\n2 == 2");
+        assert_eq!(report.passed, 2);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].span().unwrap().start, 11);
+
+        let report = run("1 @ 2");
+        assert_eq!(report.passed, 0);
+        assert!(matches!(
+            report.failures[..],
+            [Failure::Error(SobaError::LexError(_))]
+        ));
+    }
+
+    #[test]
+    fn test_find_test_files() {
+        let root = std::env::temp_dir().join(format!("soba-test-files-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        for name in ["b.soba", "a.soba", "notes.txt", "nested/c.soba"] {
+            std::fs::write(root.join(name), "true").unwrap();
+        }
+
+        let found = find_test_files(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let names: Vec<_> = found
+            .iter()
+            .map(|path| path.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("a.soba"),
+                PathBuf::from("b.soba"),
+                PathBuf::from("nested/c.soba")
+            ]
+        );
+        assert_eq!(
+            find_test_files(Path::new("one.soba")).unwrap(),
+            [PathBuf::from("one.soba")]
+        );
+    }
+}
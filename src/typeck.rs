@@ -0,0 +1,356 @@
+//! Static type checking
+//!
+//! Infers the type of every expression and reports type errors before
+//! evaluation. The rules mirror the evaluator: arithmetic always produces a
+//! float, comparisons and logical operators produce a bool, and negation
+//! keeps the numeric type of its operand.
+//!
+//! The checker is stricter than evaluation in one respect: it reports type
+//! errors even inside operands that short-circuiting would skip.
+
+use crate::ast::{
+    ArenaExpr, BinaryOp, Expr, ExprArena, PostfixOp, Program, SideTable, Statement, UnaryOp,
+};
+pub use crate::error::TypeCheckError;
+use crate::operators::CustomOp;
+use crate::span::Span;
+use crate::value::Type;
+
+/// An expression annotated with its type
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypedExpr {
+    pub kind: TypedExprKind,
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// Typed expression nodes, mirroring `Expr`
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypedExprKind {
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Infix {
+        left: Box<TypedExpr>,
+        op: BinaryOp,
+        right: Box<TypedExpr>,
+    },
+    Grouped(Box<TypedExpr>),
+    Unary {
+        op: UnaryOp,
+        operand: Box<TypedExpr>,
+    },
+    Postfix {
+        op: PostfixOp,
+        operand: Box<TypedExpr>,
+    },
+}
+
+/// A program whose statements have been type checked
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypedProgram {
+    pub statements: Vec<TypedExpr>,
+    /// Type of the program's result (the last statement, or int when empty)
+    pub ty: Type,
+}
+
+/// Type check a program, returning the typed program or every error found
+pub fn check_program(program: &Program) -> Result<TypedProgram, Vec<TypeCheckError>> {
+    let mut errors = Vec::new();
+    let statements: Vec<TypedExpr> = program
+        .statements
+        .iter()
+        .map(|stmt| match stmt {
+            Statement::ExprStatement { expr, .. } => check(expr, &mut errors),
+        })
+        .collect();
+
+    if errors.is_empty() {
+        let ty = statements.last().map_or(Type::Int, |expr| expr.ty);
+        Ok(TypedProgram { statements, ty })
+    } else {
+        Err(errors)
+    }
+}
+
+/// Type check a single expression
+pub fn check_expr(expr: &Expr) -> Result<TypedExpr, Vec<TypeCheckError>> {
+    let mut errors = Vec::new();
+    let typed = check(expr, &mut errors);
+    if errors.is_empty() {
+        Ok(typed)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Infer the type an expression evaluates to, without reporting errors
+///
+/// Ill-typed expressions get the type they would have if they succeeded.
+/// Custom operators have no static type; they are given `Float` here and
+/// reported as errors by `check_program` and `check_expr`.
+pub fn infer_type(expr: &Expr) -> Type {
+    match expr {
+        Expr::Int { .. } => Type::Int,
+        Expr::Float { .. } => Type::Float,
+        Expr::Bool { .. } => Type::Bool,
+        Expr::Grouped { inner, .. } => infer_type(inner),
+        Expr::UnaryExpr { op, operand, .. } => unary_type(*op, infer_type(operand)),
+        Expr::InfixExpr { op, .. } => binary_type(*op),
+        Expr::PostfixExpr { op, .. } => postfix_type(*op),
+    }
+}
+
+/// Infer the type of every node in an arena, like `infer_type`
+///
+/// Children are allocated before their parents, so one pass in allocation
+/// order sees every operand's type before it is needed.
+pub fn infer_arena_types(arena: &ExprArena) -> SideTable<Type> {
+    let mut types = SideTable::for_arena(arena);
+    for (id, node) in arena.iter() {
+        let ty = match node {
+            ArenaExpr::Int { .. } => Type::Int,
+            ArenaExpr::Float { .. } => Type::Float,
+            ArenaExpr::Bool { .. } => Type::Bool,
+            ArenaExpr::Grouped { inner, .. } => types[*inner],
+            ArenaExpr::UnaryExpr { op, operand, .. } => unary_type(*op, types[*operand]),
+            ArenaExpr::InfixExpr { op, .. } => binary_type(*op),
+            ArenaExpr::PostfixExpr { op, .. } => postfix_type(*op),
+        };
+        types.insert(id, ty);
+    }
+    types
+}
+
+fn unary_type(op: UnaryOp, operand: Type) -> Type {
+    match (op, operand) {
+        (UnaryOp::LogicalNot, _) => Type::Bool,
+        (UnaryOp::Plus, ty) => ty,
+        (UnaryOp::Minus, Type::Float) => Type::Float,
+        (UnaryOp::Minus, _) => Type::Int,
+        (UnaryOp::Custom(_), _) => Type::Float,
+    }
+}
+
+fn binary_type(op: BinaryOp) -> Type {
+    match op {
+        BinaryOp::Plus
+        | BinaryOp::Minus
+        | BinaryOp::Multiply
+        | BinaryOp::Divide
+        | BinaryOp::Power
+        | BinaryOp::Custom(_) => Type::Float,
+        _ => Type::Bool,
+    }
+}
+
+fn postfix_type(op: PostfixOp) -> Type {
+    match op {
+        PostfixOp::Custom(_) => Type::Float,
+    }
+}
+
+fn check(expr: &Expr, errors: &mut Vec<TypeCheckError>) -> TypedExpr {
+    let span = expr.span();
+    let (kind, ty) = match expr {
+        Expr::Int { value, .. } => (TypedExprKind::Int(*value), Type::Int),
+        Expr::Float { value, .. } => (TypedExprKind::Float(*value), Type::Float),
+        Expr::Bool { value, .. } => (TypedExprKind::Bool(*value), Type::Bool),
+        Expr::Grouped { inner, .. } => {
+            let inner = check(inner, errors);
+            let ty = inner.ty;
+            (TypedExprKind::Grouped(Box::new(inner)), ty)
+        }
+        Expr::UnaryExpr { op, operand, .. } => {
+            let operand = check(operand, errors);
+            if let UnaryOp::Custom(custom) = op {
+                errors.push(custom_operator_error(*custom, span));
+            }
+            if *op == UnaryOp::Minus && operand.ty == Type::Bool {
+                errors.push(TypeCheckError {
+                    message: "Cannot negate boolean value".to_string(),
+                    span,
+                });
+            }
+            let ty = unary_type(*op, operand.ty);
+            (
+                TypedExprKind::Unary {
+                    op: *op,
+                    operand: Box::new(operand),
+                },
+                ty,
+            )
+        }
+        Expr::InfixExpr {
+            left, op, right, ..
+        } => {
+            let left = check(left, errors);
+            let right = check(right, errors);
+            if let BinaryOp::Custom(custom) = op {
+                errors.push(custom_operator_error(*custom, span));
+            }
+            let is_ordering = matches!(
+                op,
+                BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEqual | BinaryOp::GreaterEqual
+            );
+            if is_ordering && (left.ty == Type::Bool || right.ty == Type::Bool) {
+                errors.push(TypeCheckError {
+                    message: format!("Cannot compare {} and {} for ordering", left.ty, right.ty),
+                    span,
+                });
+            }
+            (
+                TypedExprKind::Infix {
+                    left: Box::new(left),
+                    op: *op,
+                    right: Box::new(right),
+                },
+                binary_type(*op),
+            )
+        }
+        Expr::PostfixExpr { op, operand, .. } => {
+            let operand = check(operand, errors);
+            let PostfixOp::Custom(custom) = op;
+            errors.push(custom_operator_error(*custom, span));
+            (
+                TypedExprKind::Postfix {
+                    op: *op,
+                    operand: Box::new(operand),
+                },
+                postfix_type(*op),
+            )
+        }
+    };
+
+    TypedExpr { kind, ty, span }
+}
+
+/// Handlers run at evaluation time, so their result types are unknown here
+fn custom_operator_error(op: CustomOp, span: Span) -> TypeCheckError {
+    TypeCheckError {
+        message: format!("Cannot type check custom operator '{op}'"),
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::eval_program;
+    use crate::lexer::SobaLexer;
+    use crate::parser::test_util::parse;
+    use crate::parser::Parser;
+
+    fn program_type(input: &str) -> Type {
+        check_program(&parse(input)).unwrap().ty
+    }
+
+    #[test]
+    fn test_literal_types() {
+        assert_eq!(program_type("1"), Type::Int);
+        assert_eq!(program_type("1.5"), Type::Float);
+        assert_eq!(program_type("true"), Type::Bool);
+        assert_eq!(program_type(""), Type::Int);
+    }
+
+    #[test]
+    fn test_operator_types() {
+        assert_eq!(program_type("1 + 2"), Type::Float);
+        assert_eq!(program_type("-3"), Type::Int);
+        assert_eq!(program_type("-(1.5)"), Type::Float);
+        assert_eq!(program_type("+true"), Type::Bool);
+        assert_eq!(program_type("!1"), Type::Bool);
+        assert_eq!(program_type("1 < 2.5 && true"), Type::Bool);
+    }
+
+    #[test]
+    fn test_inferred_type_matches_evaluation() {
+        for input in ["2 * 3", "-4", "(1 == 1.0) || false", "+2.5", "!0"] {
+            let program = parse(input);
+            let value = eval_program(&program).unwrap();
+            assert_eq!(check_program(&program).unwrap().ty, value.value_type());
+        }
+    }
+
+    #[test]
+    fn test_typed_program_structure() {
+        let typed = check_program(&parse("1 + 2; true")).unwrap();
+        assert_eq!(typed.statements.len(), 2);
+        if let TypedExprKind::Infix { left, right, .. } = &typed.statements[0].kind {
+            assert_eq!(left.ty, Type::Int);
+            assert_eq!(right.ty, Type::Int);
+        } else {
+            panic!("Expected infix expression");
+        }
+        assert_eq!(typed.statements[0].ty, Type::Float);
+    }
+
+    #[test]
+    fn test_negating_bool_is_an_error() {
+        let errors = check_program(&parse("1;\n-true")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span.start, 3);
+        assert_eq!(errors[0].message, "Cannot negate boolean value");
+    }
+
+    #[test]
+    fn test_ordering_bools_is_an_error() {
+        let errors = check_program(&parse("true < 1; 2 >= false")).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].message,
+            "Cannot compare bool and int for ordering"
+        );
+    }
+
+    #[test]
+    fn test_reports_errors_in_short_circuited_operands() {
+        assert!(check_program(&parse("false && true < false")).is_err());
+    }
+
+    #[test]
+    fn test_arena_types_match_infer_type() {
+        use crate::ast::ArenaProgram;
+
+        let program = parse("-(1.5); !(1 < 2); (+3) * 2; ((true))");
+        let arena_program = ArenaProgram::from_program(&program);
+        let types = infer_arena_types(&arena_program.arena);
+        assert_eq!(types.len(), arena_program.arena.len());
+
+        for (stmt, id) in program.statements.iter().zip(&arena_program.statements) {
+            let Statement::ExprStatement { expr, .. } = stmt;
+            assert_eq!(types[*id], infer_type(expr));
+        }
+    }
+
+    #[test]
+    fn test_equality_accepts_any_types() {
+        assert_eq!(program_type("1 == true"), Type::Bool);
+    }
+
+    #[test]
+    fn test_custom_operators_are_reported() {
+        use crate::operators::{Associativity, OperatorRegistry};
+        use crate::parser::Precedence;
+
+        let mut operators = OperatorRegistry::new();
+        operators
+            .register_infix(
+                "<=>",
+                Precedence::Comparison,
+                Associativity::Left,
+                |l, _| Ok(l),
+            )
+            .unwrap();
+        let lexer = SobaLexer::with_operators("1 + (2 <=> 3)".chars().collect(), &operators);
+        let program = Parser::with_operators(lexer, &operators)
+            .unwrap()
+            .parse_program()
+            .unwrap();
+
+        let errors = check_program(&program).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Cannot type check custom operator '<=>'");
+        assert_eq!(errors[0].span.start, 5);
+    }
+}
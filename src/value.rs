@@ -1,38 +1,139 @@
 //! Value system for the Soba programming language
+//!
+//! Values have value semantics: binding, passing or returning a value
+//! behaves as a copy, and no change to one copy is ever visible through
+//! another. Every variant today owns its data outright, so `Clone` is the
+//! copy. Compound values such as arrays and maps must keep this rule when
+//! they are added: store their contents behind an `Rc` so copies are
+//! cheap, and mutate through `Rc::make_mut`, which clones the contents
+//! only when they are shared. There is deliberately no aliasing
+//! `Rc<RefCell<..>>` representation and so no need for a `clone()`
+//! builtin.
 
 use crate::error::{EvalError, EvalResult};
-use std::fmt;
+use crate::math;
+use crate::prelude::*;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
 
 /// Runtime values in Soba
-#[derive(Debug, PartialEq, Clone)]
+///
+/// `==` on `Value` is structural, so values can be keys of a `HashMap`:
+/// `Int(1)` and `Float(1.0)` differ, and floats are equal when they are
+/// the same number, with `0.0` equal to `-0.0` and every NaN equal to
+/// every other. The language's `==` is `Value::equal_to`, which compares
+/// ints with floats by value.
+///
+/// `Ord` sorts any mix of values, e.g. host-side result sets: booleans
+/// first, then numbers, then strings. Booleans sort `false` before `true`,
+/// numbers by value with an int before an equal float and NaN after every
+/// other number, and strings by their UTF-8 bytes. `Value::compare` is
+/// the stricter ordering of the language's `<`.
+#[derive(Debug, Clone)]
 pub enum Value {
     Int(i32),
     Float(f64),
     Bool(bool),
+    /// Text passed in by the host, e.g. from a custom operator
+    Str(String),
+    /// A Rust value owned by the host, passed through soba untouched
+    Host(HostObject),
+}
+
+/// Types of runtime values
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Host,
+}
+
+impl Type {
+    /// Get the name of this type as shown to users
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Str => "str",
+            Type::Host => "host",
+        }
+    }
 }
 
 impl Value {
-    /// Get the type name of this value
-    pub fn type_name(&self) -> &'static str {
+    /// Get the type of this value
+    pub fn value_type(&self) -> Type {
         match self {
-            Value::Int(_) => "int",
-            Value::Float(_) => "float",
-            Value::Bool(_) => "bool",
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::Str(_) => Type::Str,
+            Value::Host(_) => Type::Host,
         }
     }
 
+    /// Get the type name of this value
+    pub fn type_name(&self) -> &'static str {
+        self.value_type().name()
+    }
+
+    /// Convert to another type, following the one set of conversion rules
+    /// that arithmetic, conditions and `as_*` all use
+    ///
+    /// The rules live in `as_int`, `as_f64` and `is_truthy`, which the
+    /// evaluator calls directly; only a failed conversion here builds an
+    /// error message.
+    ///
+    /// - `Int`: floats with no fractional part that fit, and `true`/`false`
+    ///   as 1/0
+    /// - `Float`: ints, and `true`/`false` as 1.0/0.0
+    /// - `Bool`: truthiness; zero, `0.0` and `""` are false, host objects
+    ///   are true
+    /// - `Str`: the displayed text of any value
+    ///
+    /// Other conversions, such as a string to a number, are type errors.
+    pub fn coerce_to(&self, target: Type) -> EvalResult<Value> {
+        let converted = match target {
+            Type::Str => Some(Value::Str(self.to_string())),
+            Type::Int => self.as_int().map(Value::Int),
+            Type::Float => self.number().map(Value::Float),
+            Type::Bool => Some(Value::Bool(self.is_truthy())),
+            Type::Host => match self {
+                Value::Host(object) => Some(Value::Host(object.clone())),
+                _ => None,
+            },
+        };
+        converted.ok_or_else(|| EvalError::TypeError {
+            message: format!(
+                "Cannot convert {} {self} to {}",
+                self.type_name(),
+                target.name()
+            ),
+            span: None,
+        })
+    }
+
     /// Convert to f64 for arithmetic operations
+    ///
+    /// Strings and host objects have no numeric value and give NaN;
+    /// arithmetic on them is a type error before it gets here.
     pub fn as_f64(&self) -> f64 {
+        self.number().unwrap_or(f64::NAN)
+    }
+
+    /// The numeric value of an int, float or bool
+    fn number(&self) -> Option<f64> {
         match self {
-            Value::Int(i) => *i as f64,
-            Value::Float(f) => *f,
-            Value::Bool(b) => {
-                if *b {
-                    1.0
-                } else {
-                    0.0
-                }
-            }
+            Value::Int(i) => Some(f64::from(*i)),
+            Value::Float(f) => Some(*f),
+            Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Value::Str(_) | Value::Host(_) => None,
         }
     }
 
@@ -41,13 +142,70 @@ impl Value {
         match self {
             Value::Int(i) => Some(*i),
             Value::Float(f) => {
-                if f.fract() == 0.0 && *f >= i32::MIN as f64 && *f <= i32::MAX as f64 {
-                    Some(*f as i32)
-                } else {
-                    None
-                }
+                (math::is_integral(*f) && *f >= i32::MIN as f64 && *f <= i32::MAX as f64)
+                    .then_some(*f as i32)
             }
-            Value::Bool(b) => Some(if *b { 1 } else { 0 }),
+            Value::Bool(b) => Some(i32::from(*b)),
+            Value::Str(_) | Value::Host(_) => None,
+        }
+    }
+
+    /// The text of a string value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Wrap a host value so it can be passed through soba
+    pub fn host<T: Any + Send + Sync>(object: T) -> Value {
+        Value::Host(HostObject::new(object))
+    }
+
+    /// The host value inside, if this is a host object holding a `T`
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self {
+            Value::Host(object) => object.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// Approximate number of bytes the value holds, including its heap data
+    ///
+    /// Strings count their allocated capacity. A host object counts the
+    /// shallow size of its Rust value once per copy, since soba cannot see
+    /// inside it or tell which copies share it.
+    pub fn approx_size_bytes(&self) -> usize {
+        let heap = match self {
+            Value::Int(_) | Value::Float(_) | Value::Bool(_) => 0,
+            Value::Str(s) => s.capacity(),
+            Value::Host(object) => object.size,
+        };
+        core::mem::size_of::<Value>() + heap
+    }
+
+    /// Length of a string value in characters
+    pub fn len(&self) -> Option<usize> {
+        self.as_str().map(|s| s.chars().count())
+    }
+
+    /// Whether a string value is empty
+    pub fn is_empty(&self) -> Option<bool> {
+        self.as_str().map(str::is_empty)
+    }
+
+    /// Join two string values
+    pub fn concat(self, other: Value) -> EvalResult<Value> {
+        match (self, other) {
+            (Value::Str(mut a), Value::Str(b)) => {
+                a.push_str(&b);
+                Ok(Value::Str(a))
+            }
+            (a, b) => Err(EvalError::TypeError {
+                message: format!("Cannot concatenate {} and {}", a.type_name(), b.type_name()),
+                span: None,
+            }),
         }
     }
 
@@ -57,42 +215,66 @@ impl Value {
             Value::Int(i) => *i != 0,
             Value::Float(f) => *f != 0.0,
             Value::Bool(b) => *b,
+            Value::Str(s) => !s.is_empty(),
+            Value::Host(_) => true,
         }
     }
 
     // Arithmetic operations
+    /// Adds numbers and concatenates strings
     pub fn add_value(self, other: Value) -> EvalResult<Value> {
+        if matches!(self, Value::Str(_)) || matches!(other, Value::Str(_)) {
+            return self.concat(other);
+        }
+        check_numeric("add", &self, &other)?;
         let result = self.as_f64() + other.as_f64();
         Ok(Value::Float(result))
     }
 
     pub fn subtract_value(self, other: Value) -> EvalResult<Value> {
+        check_numeric("subtract", &self, &other)?;
         let result = self.as_f64() - other.as_f64();
         Ok(Value::Float(result))
     }
 
     pub fn multiply_value(self, other: Value) -> EvalResult<Value> {
+        check_numeric("multiply", &self, &other)?;
         let result = self.as_f64() * other.as_f64();
         Ok(Value::Float(result))
     }
 
     pub fn divide_value(self, other: Value) -> EvalResult<Value> {
+        check_numeric("divide", &self, &other)?;
         let other_val = other.as_f64();
         if other_val == 0.0 {
-            Err(EvalError::DivisionByZero)
+            Err(EvalError::DivisionByZero { span: None })
         } else {
             let result = self.as_f64() / other_val;
             Ok(Value::Float(result))
         }
     }
 
+    pub fn power_value(self, other: Value) -> EvalResult<Value> {
+        check_numeric("raise", &self, &other)?;
+        let result = math::powf(self.as_f64(), other.as_f64());
+        Ok(Value::Float(result))
+    }
+
     pub fn negate(self) -> EvalResult<Value> {
         match self {
-            Value::Int(i) => i.checked_neg().map(Value::Int).ok_or(EvalError::Overflow),
+            Value::Int(i) => i
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or(EvalError::Overflow { span: None }),
             Value::Float(f) => Ok(Value::Float(-f)),
-            Value::Bool(_) => Err(EvalError::TypeError(
-                "Cannot negate boolean value".to_string(),
-            )),
+            Value::Bool(_) => Err(EvalError::TypeError {
+                message: "Cannot negate boolean value".to_string(),
+                span: None,
+            }),
+            Value::Str(_) | Value::Host(_) => Err(EvalError::TypeError {
+                message: format!("Cannot negate {} value", self.type_name()),
+                span: None,
+            }),
         }
     }
 
@@ -123,13 +305,20 @@ impl Value {
 
     // Comparison operations
     pub fn equal_to(self, other: Value) -> EvalResult<Value> {
+        self.equal_to_with(other, FloatEq::default())
+    }
+
+    /// `==` with floats, and ints compared with floats, equal per `float_eq`
+    pub fn equal_to_with(self, other: Value, float_eq: FloatEq) -> EvalResult<Value> {
         let result = match (self, other) {
             (Value::Int(a), Value::Int(b)) => a == b,
-            (Value::Float(a), Value::Float(b)) => (a - b).abs() < f64::EPSILON,
+            (Value::Float(a), Value::Float(b)) => float_eq.eq(a, b),
             (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Host(a), Value::Host(b)) => a == b,
             // Mixed numeric types
-            (Value::Int(a), Value::Float(b)) => (a as f64 - b).abs() < f64::EPSILON,
-            (Value::Float(a), Value::Int(b)) => (a - b as f64).abs() < f64::EPSILON,
+            (Value::Int(a), Value::Float(b)) => float_eq.eq(a as f64, b),
+            (Value::Float(a), Value::Int(b)) => float_eq.eq(a, b as f64),
             // Different types are not equal
             _ => false,
         };
@@ -137,7 +326,12 @@ impl Value {
     }
 
     pub fn not_equal_to(self, other: Value) -> EvalResult<Value> {
-        match self.equal_to(other)? {
+        self.not_equal_to_with(other, FloatEq::default())
+    }
+
+    /// `!=`, the negation of `equal_to_with`
+    pub fn not_equal_to_with(self, other: Value, float_eq: FloatEq) -> EvalResult<Value> {
+        match self.equal_to_with(other, float_eq)? {
             Value::Bool(result) => Ok(Value::Bool(!result)),
             _ => unreachable!(),
         }
@@ -149,11 +343,13 @@ impl Value {
             (Value::Float(a), Value::Float(b)) => a < b,
             (Value::Int(a), Value::Float(b)) => (a as f64) < b,
             (Value::Float(a), Value::Int(b)) => a < (b as f64),
+            (Value::Str(a), Value::Str(b)) => a < b,
             // Boolean comparison not allowed for ordering
             _ => {
-                return Err(EvalError::TypeError(
-                    "Cannot compare these types for ordering".to_string(),
-                ))
+                return Err(EvalError::TypeError {
+                    message: "Cannot compare these types for ordering".to_string(),
+                    span: None,
+                })
             }
         };
         Ok(Value::Bool(result))
@@ -165,11 +361,13 @@ impl Value {
             (Value::Float(a), Value::Float(b)) => a > b,
             (Value::Int(a), Value::Float(b)) => (a as f64) > b,
             (Value::Float(a), Value::Int(b)) => a > (b as f64),
+            (Value::Str(a), Value::Str(b)) => a > b,
             // Boolean comparison not allowed for ordering
             _ => {
-                return Err(EvalError::TypeError(
-                    "Cannot compare these types for ordering".to_string(),
-                ))
+                return Err(EvalError::TypeError {
+                    message: "Cannot compare these types for ordering".to_string(),
+                    span: None,
+                })
             }
         };
         Ok(Value::Bool(result))
@@ -181,11 +379,13 @@ impl Value {
             (Value::Float(a), Value::Float(b)) => a <= b,
             (Value::Int(a), Value::Float(b)) => (a as f64) <= b,
             (Value::Float(a), Value::Int(b)) => a <= (b as f64),
+            (Value::Str(a), Value::Str(b)) => a <= b,
             // Boolean comparison not allowed for ordering
             _ => {
-                return Err(EvalError::TypeError(
-                    "Cannot compare these types for ordering".to_string(),
-                ))
+                return Err(EvalError::TypeError {
+                    message: "Cannot compare these types for ordering".to_string(),
+                    span: None,
+                })
             }
         };
         Ok(Value::Bool(result))
@@ -197,34 +397,261 @@ impl Value {
             (Value::Float(a), Value::Float(b)) => a >= b,
             (Value::Int(a), Value::Float(b)) => (a as f64) >= b,
             (Value::Float(a), Value::Int(b)) => a >= (b as f64),
+            (Value::Str(a), Value::Str(b)) => a >= b,
             // Boolean comparison not allowed for ordering
             _ => {
-                return Err(EvalError::TypeError(
-                    "Cannot compare these types for ordering".to_string(),
-                ))
+                return Err(EvalError::TypeError {
+                    message: "Cannot compare these types for ordering".to_string(),
+                    span: None,
+                })
             }
         };
         Ok(Value::Bool(result))
     }
 }
 
+impl Value {
+    /// Bits that identify a float for `==` and hashing: one pattern for
+    /// both zeros and one for all NaNs
+    fn float_key(f: f64) -> u64 {
+        if f == 0.0 {
+            0.0f64.to_bits()
+        } else if f.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            f.to_bits()
+        }
+    }
+}
+
+impl Value {
+    /// Order two values the way the language's `<` does
+    ///
+    /// Ints and floats compare by value and strings by their UTF-8 bytes.
+    /// Anything else, including booleans and NaN, is a type error.
+    pub fn compare(&self, other: &Value) -> EvalResult<Ordering> {
+        let ordering = match (self, other) {
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                self.as_f64().partial_cmp(&other.as_f64())
+            }
+            _ => {
+                return Err(EvalError::TypeError {
+                    message: format!(
+                        "Cannot order {} and {}",
+                        self.type_name(),
+                        other.type_name()
+                    ),
+                    span: None,
+                })
+            }
+        };
+        ordering.ok_or_else(|| EvalError::TypeError {
+            message: "Cannot order NaN".to_string(),
+            span: None,
+        })
+    }
+
+    /// Position of the value's kind in the `Ord` ordering
+    fn kind_rank(&self) -> u8 {
+        match self {
+            Value::Bool(_) => 0,
+            Value::Int(_) | Value::Float(_) => 1,
+            Value::Str(_) => 2,
+            Value::Host(_) => 3,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => Value::float_key(*a) == Value::float_key(*b),
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Host(a), Value::Host(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            Value::Int(i) => i.hash(state),
+            Value::Float(f) => Value::float_key(*f).hash(state),
+            Value::Bool(b) => b.hash(state),
+            Value::Str(s) => s.hash(state),
+            Value::Host(object) => object.address().hash(state),
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.cmp(b),
+            (Value::Host(a), Value::Host(b)) => a.address().cmp(&b.address()),
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                let (a, b) = (self.as_f64(), other.as_f64());
+                let by_value = match (a.is_nan(), b.is_nan()) {
+                    (false, false) => a.partial_cmp(&b).expect("neither is NaN"),
+                    (a_nan, b_nan) => a_nan.cmp(&b_nan),
+                };
+                // An int sorts before an equal float, matching `==`
+                by_value.then_with(|| {
+                    matches!(self, Value::Float(_)).cmp(&matches!(other, Value::Float(_)))
+                })
+            }
+            _ => self.kind_rank().cmp(&other.kind_rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// How `Value::format_with` writes floats
+///
+/// The default is what `Display` does: the shortest text that reads back
+/// as the same float, with integral floats written like ints, e.g. `5`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FloatFormat {
+    /// Round to at most this many digits after the decimal point, dropping
+    /// trailing zeros
+    pub max_precision: Option<usize>,
+    /// Keep a decimal point on integral floats, e.g. `5.0` rather than `5`
+    pub always_show_decimal: bool,
+    /// Switch to scientific notation, e.g. `1.5e10`, for magnitudes of at
+    /// least this or below its reciprocal
+    pub scientific_threshold: Option<f64>,
+}
+
+impl FloatFormat {
+    fn format(&self, value: f64) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+
+        let magnitude = value.abs();
+        let scientific = self.scientific_threshold.is_some_and(|threshold| {
+            magnitude != 0.0 && (magnitude >= threshold || magnitude < threshold.recip())
+        });
+        if scientific {
+            let text = match self.max_precision {
+                Some(precision) => format!("{value:.precision$e}"),
+                None => format!("{value:e}"),
+            };
+            let (mantissa, exponent) = text.split_once('e').expect("scientific notation");
+            return format!("{}e{exponent}", self.finish(mantissa));
+        }
+
+        // Integral floats in the int range are written like ints
+        if math::is_integral(value) && value >= i32::MIN as f64 && value <= i32::MAX as f64 {
+            return self.finish(&(value as i64).to_string());
+        }
+        match self.max_precision {
+            Some(precision) => self.finish(&format!("{value:.precision$}")),
+            None => self.finish(&value.to_string()),
+        }
+    }
+
+    /// Drop trailing fractional zeros, then add `.0` back if requested
+    fn finish(&self, digits: &str) -> String {
+        let digits = if digits.contains('.') {
+            digits.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            digits
+        };
+        if self.always_show_decimal && !digits.contains('.') {
+            format!("{digits}.0")
+        } else {
+            digits.to_string()
+        }
+    }
+}
+
+impl Value {
+    /// The value as `Display` writes it, with floats written per `format`
+    pub fn format_with(&self, format: &FloatFormat) -> String {
+        match self {
+            Value::Float(f) => format.format(*f),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// When `==` considers two floats equal
+///
+/// The default is `Absolute(f64::EPSILON)`, which suits values near 1 but,
+/// from a magnitude of 2 up, only passes floats that are exactly equal.
+/// NaN equals nothing under any strategy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatEq {
+    /// Equal only when they are the same number, with `0.0 == -0.0`
+    Exact,
+    /// Equal when they differ by less than the tolerance
+    Absolute(f64),
+    /// Equal when at most this many representable floats apart, a
+    /// tolerance relative to their magnitude; floats of opposite signs
+    /// are only equal as zeros
+    Ulps(u64),
+}
+
+impl Default for FloatEq {
+    fn default() -> Self {
+        FloatEq::Absolute(f64::EPSILON)
+    }
+}
+
+impl FloatEq {
+    /// Whether `a` and `b` are equal under this strategy
+    pub fn eq(self, a: f64, b: f64) -> bool {
+        match self {
+            FloatEq::Exact => a == b,
+            FloatEq::Absolute(tolerance) => (a - b).abs() < tolerance,
+            FloatEq::Ulps(max) => {
+                if a == b {
+                    return true;
+                }
+                if a.is_nan() || b.is_nan() || a.is_sign_negative() != b.is_sign_negative() {
+                    return false;
+                }
+                // Same-sign floats are ordered like their bit patterns
+                a.to_bits().abs_diff(b.to_bits()) <= max
+            }
+        }
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Value::Int(i) => write!(f, "{i}"),
-            Value::Float(fl) => {
-                // Display integers as integers even when they're floats
-                if fl.fract() == 0.0 && *fl >= i32::MIN as f64 && *fl <= i32::MAX as f64 {
-                    write!(f, "{}", *fl as i64)
-                } else {
-                    write!(f, "{fl}")
-                }
-            }
+            Value::Float(fl) => write!(f, "{}", FloatFormat::default().format(*fl)),
             Value::Bool(b) => write!(f, "{b}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Host(object) => write!(f, "<{}>", object.type_name()),
         }
     }
 }
 
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 impl From<i32> for Value {
     fn from(i: i32) -> Self {
         Value::Int(i)
@@ -243,6 +670,299 @@ impl From<bool> for Value {
     }
 }
 
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+/// Operators on values, with the semantics of the language's operators
+///
+/// Each returns an `EvalResult` rather than panicking, since a type error
+/// or division by zero is an ordinary outcome: `(a + b)? * c`.
+macro_rules! value_operator {
+    ($trait:ident, $method:ident, $value_method:ident) => {
+        impl core::ops::$trait for Value {
+            type Output = EvalResult<Value>;
+
+            fn $method(self, other: Value) -> EvalResult<Value> {
+                self.$value_method(other)
+            }
+        }
+    };
+}
+
+value_operator!(Add, add, add_value);
+value_operator!(Sub, sub, subtract_value);
+value_operator!(Mul, mul, multiply_value);
+value_operator!(Div, div, divide_value);
+
+impl core::ops::Neg for Value {
+    type Output = EvalResult<Value>;
+
+    fn neg(self) -> EvalResult<Value> {
+        self.negate()
+    }
+}
+
+impl core::ops::Not for Value {
+    type Output = EvalResult<Value>;
+
+    fn not(self) -> EvalResult<Value> {
+        self.logical_not()
+    }
+}
+
+/// Error from converting a `Value` into a Rust type with `TryFrom`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    /// The value that could not be converted
+    pub value: Value,
+    /// Name of the Rust type it was converted to, e.g. `i64`
+    pub target: &'static str,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot convert {} {} to {}",
+            self.value.type_name(),
+            self.value,
+            self.target
+        )
+    }
+}
+
+impl core::error::Error for ConversionError {}
+
+impl ConversionError {
+    fn new(value: Value, target: &'static str) -> Self {
+        Self { value, target }
+    }
+}
+
+/// Ints, and floats with no fractional part that fit
+///
+/// Arithmetic produces floats, so `1 + 2` evaluates to `3.0`; this lets a
+/// host read it back as an integer without matching on the variant.
+impl TryFrom<Value> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(i) => Ok(i64::from(i)),
+            // i64::MAX is not exactly representable, so compare against 2^63
+            Value::Float(f)
+                if math::is_integral(f) && f >= i64::MIN as f64 && f < i64::MAX as f64 =>
+            {
+                Ok(f as i64)
+            }
+            _ => Err(ConversionError::new(value, "i64")),
+        }
+    }
+}
+
+/// Ints, and floats with no fractional part that fit, like `Value::as_int`
+impl TryFrom<Value> for i32 {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(_) | Value::Float(_) => value
+                .as_int()
+                .ok_or_else(|| ConversionError::new(value, "i32")),
+            _ => Err(ConversionError::new(value, "i32")),
+        }
+    }
+}
+
+/// Floats and ints
+impl TryFrom<Value> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(i) => Ok(f64::from(i)),
+            Value::Float(f) => Ok(f),
+            _ => Err(ConversionError::new(value, "f64")),
+        }
+    }
+}
+
+/// Bools only; use `Value::is_truthy` for truthiness
+impl TryFrom<Value> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(ConversionError::new(value, "bool")),
+        }
+    }
+}
+
+/// Strings only; use `to_string` to display any value
+impl TryFrom<Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Str(s) => Ok(s),
+            _ => Err(ConversionError::new(value, "String")),
+        }
+    }
+}
+
+/// Error from `Value::from_json` for JSON with no soba equivalent
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonValueError {
+    /// The JSON value that could not be converted
+    pub json: serde_json::Value,
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for JsonValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = match self.json {
+            serde_json::Value::Null => "JSON null",
+            serde_json::Value::Array(_) => "a JSON array",
+            serde_json::Value::Object(_) => "a JSON object",
+            _ => "this JSON value",
+        };
+        write!(f, "Cannot convert {json} to a soba value")
+    }
+}
+
+#[cfg(feature = "json")]
+impl core::error::Error for JsonValueError {}
+
+#[cfg(feature = "json")]
+impl Value {
+    /// Convert a JSON scalar into a value
+    ///
+    /// Integers that fit in an `Int` become one and other numbers become
+    /// floats. Null, arrays and objects have no soba equivalent.
+    pub fn from_json(json: serde_json::Value) -> Result<Value, JsonValueError> {
+        match &json {
+            serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
+            serde_json::Value::String(s) => Ok(Value::Str(s.clone())),
+            serde_json::Value::Number(n) => {
+                let int = n.as_i64().and_then(|i| i32::try_from(i).ok());
+                match (int, n.as_f64()) {
+                    (Some(i), _) => Ok(Value::Int(i)),
+                    (None, Some(f)) => Ok(Value::Float(f)),
+                    (None, None) => Err(JsonValueError { json }),
+                }
+            }
+            _ => Err(JsonValueError { json }),
+        }
+    }
+
+    /// Convert the value into JSON
+    ///
+    /// JSON has no NaN or infinities, so those floats become null, and so
+    /// do host objects.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Int(i) => serde_json::Value::from(*i),
+            Value::Float(f) => serde_json::Number::from_f64(*f)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Str(s) => serde_json::Value::String(s.clone()),
+            Value::Host(_) => serde_json::Value::Null,
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(match u.choose_index(4)? {
+            0 => Value::Int(u.arbitrary()?),
+            1 => Value::Float(u.arbitrary()?),
+            2 => Value::Bool(u.arbitrary()?),
+            _ => Value::Str(u.arbitrary()?),
+        })
+    }
+}
+
+/// A host value inside a `Value`
+///
+/// Copies share the same object, which soba never looks inside: two host
+/// values are equal only if they are the same object, and they hash and
+/// sort by identity.
+#[derive(Clone)]
+pub struct HostObject {
+    object: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    /// Shallow size of the object, for `Value::approx_size_bytes`
+    size: usize,
+}
+
+impl HostObject {
+    pub fn new<T: Any + Send + Sync>(object: T) -> Self {
+        Self {
+            object: Arc::new(object),
+            type_name: core::any::type_name::<T>(),
+            size: core::mem::size_of::<T>(),
+        }
+    }
+
+    /// Rust type name of the object, as shown by `Display`
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.object.downcast_ref()
+    }
+
+    /// A shared handle to the object, if it is a `T`
+    pub fn downcast<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.object.clone().downcast().ok()
+    }
+
+    fn address(&self) -> usize {
+        Arc::as_ptr(&self.object) as *const () as usize
+    }
+}
+
+impl PartialEq for HostObject {
+    fn eq(&self, other: &HostObject) -> bool {
+        Arc::ptr_eq(&self.object, &other.object)
+    }
+}
+
+impl fmt::Debug for HostObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HostObject").field(&self.type_name).finish()
+    }
+}
+
+/// Type error for arithmetic on an operand with no numeric value
+fn check_numeric(action: &str, left: &Value, right: &Value) -> EvalResult<()> {
+    let numeric = |value: &Value| !matches!(value, Value::Str(_) | Value::Host(_));
+    if numeric(left) && numeric(right) {
+        return Ok(());
+    }
+    Err(EvalError::TypeError {
+        message: format!(
+            "Cannot {action} {} and {}",
+            left.type_name(),
+            right.type_name()
+        ),
+        span: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,10 +991,381 @@ mod tests {
     fn test_division_by_zero() {
         let a = Value::Int(5);
         let b = Value::Int(0);
-        assert!(matches!(a.divide_value(b), Err(EvalError::DivisionByZero)));
+        assert!(matches!(
+            a.divide_value(b),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn test_strings() {
+        let hello = Value::from("hello, ");
+        let world = Value::from(String::from("wörld"));
+        assert_eq!(hello.as_str(), Some("hello, "));
+        assert_eq!(world.len(), Some(5));
+        assert_eq!(Value::from("").is_empty(), Some(true));
+        assert_eq!(Value::Int(1).as_str(), None);
+        assert_eq!(Value::Int(1).len(), None);
+        assert_eq!(world.type_name(), "str");
+
+        let joined = hello.clone().concat(world.clone()).unwrap();
+        assert_eq!(joined, Value::from("hello, wörld"));
+        assert_eq!(hello.clone().add_value(world.clone()).unwrap(), joined);
+        assert_eq!(joined.to_string(), "hello, wörld");
+
+        assert_eq!(
+            hello.clone().equal_to(Value::from("hello, ")).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            hello.clone().equal_to(Value::Int(1)).unwrap(),
+            Value::Bool(false)
+        );
+        assert_eq!(
+            hello.clone().less_than(world.clone()).unwrap(),
+            Value::Bool(true)
+        );
+        assert!(hello.is_truthy());
+        assert!(!Value::from("").is_truthy());
+
+        for result in [
+            hello.clone().add_value(Value::Int(1)),
+            hello.clone().multiply_value(Value::Int(2)),
+            Value::Int(1).subtract_value(hello.clone()),
+            hello.clone().negate(),
+            hello.clone().less_than(Value::Int(1)),
+        ] {
+            assert!(matches!(result, Err(EvalError::TypeError { .. })));
+        }
+        assert!(matches!(
+            Value::Int(1).add_value(hello),
+            Err(EvalError::TypeError { message, .. }) if message == "Cannot concatenate int and str"
+        ));
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(i64::try_from(Value::Int(-3)), Ok(-3));
+        assert_eq!(i64::try_from(Value::Float(3.0)), Ok(3));
+        assert_eq!(
+            i64::try_from(Value::Float(1e18)),
+            Ok(1_000_000_000_000_000_000)
+        );
+        assert_eq!(i32::try_from(Value::Float(7.0)), Ok(7));
+        assert_eq!(f64::try_from(Value::Int(2)), Ok(2.0));
+        assert_eq!(bool::try_from(Value::Bool(true)), Ok(true));
+        assert_eq!(
+            String::try_from(Value::from("soba")),
+            Ok("soba".to_string())
+        );
+
+        let err = i64::try_from(Value::Float(2.5)).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot convert float 2.5 to i64");
+        assert!(i64::try_from(Value::Float(1e19)).is_err());
+        assert!(i64::try_from(Value::Float(f64::NAN)).is_err());
+        assert!(i32::try_from(Value::Float(1e10)).is_err());
+        assert!(i32::try_from(Value::Bool(true)).is_err());
+        assert_eq!(
+            bool::try_from(Value::Int(1)).unwrap_err().to_string(),
+            "Cannot convert int 1 to bool"
+        );
+        assert_eq!(
+            f64::try_from(Value::from("1.5")).unwrap_err(),
+            ConversionError {
+                value: Value::from("1.5"),
+                target: "f64"
+            }
+        );
+        assert!(String::try_from(Value::Int(1)).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_interop() {
+        use serde_json::json;
+
+        let values = [
+            Value::Int(-7),
+            Value::Float(2.5),
+            Value::Bool(true),
+            Value::from("soba"),
+        ];
+        for value in values {
+            assert_eq!(Value::from_json(value.to_json()), Ok(value));
+        }
+
+        assert_eq!(Value::Int(3).to_json(), json!(3));
+        assert_eq!(Value::Float(f64::INFINITY).to_json(), json!(null));
+        assert_eq!(Value::from_json(json!(1.0)), Ok(Value::Float(1.0)));
+        assert_eq!(
+            Value::from_json(json!(3_000_000_000u64)),
+            Ok(Value::Float(3e9))
+        );
+
+        let err = Value::from_json(json!([1, 2])).unwrap_err();
+        assert_eq!(err.json, json!([1, 2]));
+        assert_eq!(
+            err.to_string(),
+            "Cannot convert a JSON array to a soba value"
+        );
+        assert!(Value::from_json(json!(null)).is_err());
+        assert!(Value::from_json(json!({ "a": 1 })).is_err());
+    }
+
+    #[test]
+    fn test_hash_and_eq() {
+        use std::collections::HashMap;
+
+        let mut counts = HashMap::new();
+        for value in [
+            Value::Int(1),
+            Value::Float(1.0),
+            Value::Float(0.0),
+            Value::Float(-0.0),
+            Value::Float(f64::NAN),
+            Value::Float(-f64::NAN),
+            Value::Bool(true),
+            Value::from("1"),
+        ] {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.len(), 6);
+        assert_eq!(counts[&Value::Int(1)], 1);
+        assert_eq!(counts[&Value::Float(1.0)], 1);
+        assert_eq!(counts[&Value::Float(0.0)], 2);
+        assert_eq!(counts[&Value::Float(f64::NAN)], 2);
+        assert_eq!(counts[&Value::from("1")], 1);
+
+        // The language's `==` still compares across numeric types
+        assert_ne!(Value::Int(1), Value::Float(1.0));
+        assert_eq!(
+            Value::Int(1).equal_to(Value::Float(1.0)).unwrap(),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(
+            Value::Int(1).compare(&Value::Float(1.5)),
+            Ok(Ordering::Less)
+        );
+        assert_eq!(
+            Value::Float(2.0).compare(&Value::Int(2)),
+            Ok(Ordering::Equal)
+        );
+        assert_eq!(
+            Value::from("b").compare(&Value::from("a")),
+            Ok(Ordering::Greater)
+        );
+        for (a, b) in [
+            (Value::Bool(false), Value::Bool(true)),
+            (Value::Int(1), Value::from("1")),
+            (Value::Float(f64::NAN), Value::Int(1)),
+        ] {
+            assert!(matches!(a.compare(&b), Err(EvalError::TypeError { .. })));
+        }
+    }
+
+    #[test]
+    fn test_sorting_mixed_values() {
+        let mut values = vec![
+            Value::from("b"),
+            Value::Float(f64::NAN),
+            Value::Float(1.0),
+            Value::Bool(true),
+            Value::Int(1),
+            Value::Float(-0.5),
+            Value::from("a"),
+            Value::Bool(false),
+            Value::Int(-3),
+        ];
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                Value::Bool(false),
+                Value::Bool(true),
+                Value::Int(-3),
+                Value::Float(-0.5),
+                Value::Int(1),
+                Value::Float(1.0),
+                Value::Float(f64::NAN),
+                Value::from("a"),
+                Value::from("b"),
+            ]
+        );
+        assert_eq!(Value::Float(0.0).cmp(&Value::Float(-0.0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_float_format() {
+        let format = |value: f64, format: FloatFormat| Value::Float(value).format_with(&format);
+        let default = FloatFormat::default();
+        assert_eq!(format(5.0, default), "5");
+        assert_eq!(format(-0.0, default), "0");
+        assert_eq!(format(0.1 + 0.2, default), "0.30000000000000004");
+        assert_eq!(format(1e300, default), Value::Float(1e300).to_string());
+
+        let decimal = FloatFormat {
+            always_show_decimal: true,
+            ..default
+        };
+        assert_eq!(format(5.0, decimal), "5.0");
+        assert_eq!(format(2.5, decimal), "2.5");
+        assert_eq!(format(f64::INFINITY, decimal), "inf");
+
+        let rounded = FloatFormat {
+            max_precision: Some(3),
+            ..default
+        };
+        assert_eq!(format(2.0 / 3.0, rounded), "0.667");
+        assert_eq!(format(2.5, rounded), "2.5");
+        assert_eq!(format(1.9999, rounded), "2");
+
+        let scientific = FloatFormat {
+            scientific_threshold: Some(1e6),
+            ..default
+        };
+        assert_eq!(format(1.5e10, scientific), "1.5e10");
+        assert_eq!(format(2.5e-7, scientific), "2.5e-7");
+        assert_eq!(format(123456.0, scientific), "123456");
+        assert_eq!(format(0.0, scientific), "0");
+        let all = FloatFormat {
+            max_precision: Some(2),
+            always_show_decimal: true,
+            scientific_threshold: Some(1e6),
+        };
+        assert_eq!(format(1e7, all), "1.0e7");
+        assert_eq!(format(1.23456e7, all), "1.23e7");
+
+        // Other values are unaffected
+        assert_eq!(Value::Int(5).format_with(&decimal), "5");
+    }
+
+    #[test]
+    fn test_coerce_to() {
+        let coerce = |value: Value, target| value.coerce_to(target);
+        assert_eq!(coerce(Value::Int(3), Type::Float), Ok(Value::Float(3.0)));
+        assert_eq!(coerce(Value::Float(3.0), Type::Int), Ok(Value::Int(3)));
+        assert_eq!(coerce(Value::Bool(true), Type::Int), Ok(Value::Int(1)));
+        assert_eq!(
+            coerce(Value::Float(0.0), Type::Bool),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(coerce(Value::from(""), Type::Bool), Ok(Value::Bool(false)));
+        assert_eq!(coerce(Value::Float(2.5), Type::Str), Ok(Value::from("2.5")));
+        assert_eq!(
+            coerce(Value::Bool(false), Type::Str),
+            Ok(Value::from("false"))
+        );
+
+        let err = coerce(Value::Float(2.5), Type::Int).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Type error: Cannot convert float 2.5 to int"
+        );
+        assert!(coerce(Value::Float(1e10), Type::Int).is_err());
+        assert!(coerce(Value::from("1"), Type::Float).is_err());
+
+        // The `as_*` helpers agree
+        assert_eq!(Value::Bool(true).as_f64(), 1.0);
+        assert_eq!(Value::Float(2.5).as_int(), None);
+        assert!(Value::Float(f64::NAN).is_truthy());
+        for value in [
+            Value::Int(-3),
+            Value::Float(4.0),
+            Value::Float(0.5),
+            Value::Bool(false),
+            Value::from("x"),
+            Value::host(1u8),
+        ] {
+            assert_eq!(
+                value.coerce_to(Type::Int).ok(),
+                value.as_int().map(Value::Int)
+            );
+            assert_eq!(
+                value.coerce_to(Type::Bool),
+                Ok(Value::Bool(value.is_truthy()))
+            );
+            if let Ok(Value::Float(f)) = value.coerce_to(Type::Float) {
+                assert_eq!(f, value.as_f64());
+            } else {
+                assert!(value.as_f64().is_nan());
+            }
+        }
+    }
+
+    #[test]
+    fn test_operator_traits() -> EvalResult<()> {
+        let a = Value::Int(6);
+        let b = Value::Float(1.5);
+        assert_eq!((a.clone() + b.clone())?, Value::Float(7.5));
+        assert_eq!(
+            ((a.clone() - b.clone())? * Value::Int(2))?,
+            Value::Float(9.0)
+        );
+        assert_eq!((a.clone() / Value::Int(4))?, Value::Float(1.5));
+        assert_eq!((-b)?, Value::Float(-1.5));
+        assert_eq!((!Value::Bool(true))?, Value::Bool(false));
+        assert_eq!(
+            (Value::from("so") + Value::from("ba"))?,
+            Value::from("soba")
+        );
+
+        assert!(matches!(
+            a.clone() / Value::Int(0),
+            Err(EvalError::DivisionByZero { .. })
+        ));
+        assert!(matches!(
+            -Value::Bool(true),
+            Err(EvalError::TypeError { .. })
+        ));
+        Ok(())
     }
 
     #[test]
+    fn test_host_objects() {
+        let object = Value::host(vec![1u8, 2, 3]);
+        let copy = object.clone();
+        let other = Value::host(vec![1u8, 2, 3]);
+
+        assert_eq!(object.downcast_ref::<Vec<u8>>(), Some(&vec![1, 2, 3]));
+        assert_eq!(object.downcast_ref::<String>(), None);
+        assert_eq!(object.to_string(), "<alloc::vec::Vec<u8>>");
+        // Copies share the object; equal contents are not enough
+        assert_eq!(object, copy);
+        assert_ne!(object, other);
+        assert_eq!(object.clone().equal_to(copy).unwrap(), Value::Bool(true));
+        assert!(object.is_truthy());
+
+        let Value::Host(host) = &object else {
+            panic!("expected a host object");
+        };
+        assert_eq!(host.downcast::<Vec<u8>>().unwrap().len(), 3);
+        assert!(matches!(
+            object.clone().add_value(Value::Int(1)),
+            Err(EvalError::TypeError { .. })
+        ));
+        assert!(object.coerce_to(Type::Int).is_err());
+        assert_eq!(object.coerce_to(Type::Host), Ok(object.clone()));
+    }
+
+    #[test]
+    fn test_approx_size_bytes() {
+        let base = core::mem::size_of::<Value>();
+        assert_eq!(Value::Int(1).approx_size_bytes(), base);
+        assert_eq!(Value::Float(1.0).approx_size_bytes(), base);
+        assert_eq!(Value::from("soba").approx_size_bytes(), base + 4);
+        let mut text = String::with_capacity(100);
+        text.push('x');
+        assert_eq!(Value::from(text).approx_size_bytes(), base + 100);
+        assert_eq!(Value::host([0u64; 4]).approx_size_bytes(), base + 32);
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn test_display() {
         assert_eq!(Value::Int(42).to_string(), "42");
         assert_eq!(Value::Float(3.14).to_string(), "3.14");
@@ -347,6 +1438,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_equal_to() {
         // Same types
         assert_eq!(
@@ -391,6 +1483,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_float_eq() {
+        let next = |f: f64| f64::from_bits(f.to_bits() + 1);
+        let large = 1e10;
+
+        assert!(FloatEq::default().eq(0.5, next(0.5)));
+        assert!(!FloatEq::default().eq(large, next(large)));
+        assert!(!FloatEq::Exact.eq(1.0, next(1.0)));
+        assert!(FloatEq::Exact.eq(0.0, -0.0));
+        assert!(FloatEq::Absolute(0.5).eq(1.0, 1.25));
+        assert!(FloatEq::Ulps(2).eq(large, next(next(large))));
+        assert!(!FloatEq::Ulps(1).eq(large, next(next(large))));
+        assert!(FloatEq::Ulps(1).eq(-0.0, 0.0));
+        assert!(!FloatEq::Ulps(u64::MAX).eq(-1e-300, 1e-300));
+        for float_eq in [FloatEq::Exact, FloatEq::default(), FloatEq::Ulps(u64::MAX)] {
+            assert!(!float_eq.eq(f64::NAN, f64::NAN), "{float_eq:?}");
+        }
+
+        // Ints compared with floats go through the same strategy
+        assert_eq!(
+            Value::Int(1)
+                .equal_to_with(Value::Float(1.25), FloatEq::Absolute(0.5))
+                .unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            Value::Float(0.1 + 0.2)
+                .not_equal_to_with(Value::Float(0.3), FloatEq::Exact)
+                .unwrap(),
+            Value::Bool(true)
+        );
+    }
+
     #[test]
     fn test_not_equal_to() {
         assert_eq!(
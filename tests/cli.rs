@@ -0,0 +1,84 @@
+//! Runs of the `soba` binary on script files and REPL input
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const SCRIPT: &str = "#!/usr/bin/env soba\n1+2 * 3\n";
+
+/// Write `contents` to a file in the temporary directory, named after the
+/// test so that tests running at the same time do not share one
+fn script(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("soba-cli-{}-{name}.soba", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn soba(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_soba"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_check_skips_the_shebang() {
+    let path = script("check", SCRIPT);
+    let output = soba(&["check", path.to_str().unwrap()], "");
+    assert!(output.status.success(), "{output:?}");
+
+    let path = script("check-error", "#!/usr/bin/env soba\ntrue < 1\n");
+    let output = soba(&["check", path.to_str().unwrap()], "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert!(stderr.contains(":2:"), "{stderr}");
+}
+
+#[test]
+fn test_fmt_keeps_the_shebang() {
+    let path = script("fmt", SCRIPT);
+    let output = soba(&["fmt", "--check", path.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    let output = soba(&["fmt", path.to_str().unwrap()], "");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "#!/usr/bin/env soba\n1 + 2 * 3\n"
+    );
+
+    let output = soba(&["fmt", "--check", path.to_str().unwrap()], "");
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_load_skips_the_shebang() {
+    let path = script("load", SCRIPT);
+    let output = soba(&[], &format!(":load {}\n", path.display()));
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout(&output).contains('7'), "{}", stdout(&output));
+    assert!(!stdout(&output).contains("E0201"), "{}", stdout(&output));
+}
+
+#[test]
+fn test_time_uses_the_repl_settings() {
+    let output = soba(&["--strict", "--types"], ":time true < 1\n:time 1 + 2\n");
+    let stdout = stdout(&output);
+    assert!(stdout.contains("error[E0003]"), "{stdout}");
+    assert!(stdout.contains("3 : float"), "{stdout}");
+}